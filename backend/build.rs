@@ -0,0 +1,81 @@
+//! Embeds `migrations/NNNN_*.sql` into the binary so it never reads migration
+//! files at runtime. Adding a migration is just dropping in the next file.
+//! Also records the target triple for `self-update`, the commit and time of
+//! the build for `/api/build-info`, and generates the gRPC service from
+//! `proto/monitor.proto`.
+
+use std::{
+    env, fs,
+    path::Path,
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+fn main() {
+    println!("cargo:rerun-if-changed=migrations");
+    println!("cargo:rustc-env=BUILD_TARGET={}", env::var("TARGET").unwrap());
+
+    // "unknown" outside a git checkout, as when built from crates.io.
+    let commit = git(&["rev-parse", "--short=12", "HEAD"]).unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=BUILD_COMMIT={commit}");
+    // Run again when another commit is checked out or the branch moves on.
+    let head = git(&["symbolic-ref", "-q", "HEAD"]).unwrap_or_else(|| "HEAD".to_string());
+    for reference in ["HEAD", head.as_str()] {
+        if let Some(path) = git(&["rev-parse", "--git-path", reference]) {
+            println!("cargo:rerun-if-changed={path}");
+        }
+    }
+    // SOURCE_DATE_EPOCH pins it for reproducible builds.
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    let built = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse().ok())
+        .unwrap_or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs());
+    println!("cargo:rustc-env=BUILD_TIMESTAMP={built}");
+
+    println!("cargo:rerun-if-changed=proto");
+    let descriptors = protox::compile(["monitor.proto"], ["proto"]).expect("proto/monitor.proto does not compile");
+    tonic_build::configure()
+        .build_client(false)
+        .build_transport(false)
+        .compile_fds(descriptors)
+        .expect("failed to generate the gRPC service");
+
+    let dir = Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).join("migrations");
+    let mut migrations: Vec<(u32, String, String)> = fs::read_dir(&dir)
+        .expect("migrations directory is missing")
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let stem = path.file_stem()?.to_str()?.to_string();
+            let (number, _) = stem.split_once('_')?;
+            let number: u32 = number.parse().ok()?;
+            (path.extension()? == "sql").then(|| (number, stem, path.display().to_string()))
+        })
+        .collect();
+    migrations.sort();
+
+    for (i, (number, stem, _)) in migrations.iter().enumerate() {
+        assert_eq!(
+            *number as usize,
+            i + 1,
+            "migration {stem} is out of sequence; numbers must be contiguous from 0001"
+        );
+    }
+
+    let entries: String = migrations
+        .iter()
+        .map(|(_, stem, path)| format!("    ({stem:?}, include_str!({path:?})),\n"))
+        .collect();
+    let out = Path::new(&env::var("OUT_DIR").unwrap()).join("migrations.rs");
+    fs::write(
+        out,
+        format!("const MIGRATIONS: &[(&str, &str)] = &[\n{entries}];\n"),
+    )
+    .unwrap();
+}
+
+/// Output of `git args`, trimmed, or `None` if git isn't there or fails.
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
@@ -0,0 +1,698 @@
+//! Async client for the Claude session monitor's HTTP API and `/ws` session
+//! stream, typed with the server's own models.
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), claude_monitor_client::Error> {
+//! use futures::StreamExt;
+//!
+//! let client = claude_monitor_client::Client::new("http://localhost:9147")?;
+//! for session in client.sessions(&Default::default()).await? {
+//!     println!("{} {}", session.project_name, session.status);
+//! }
+//! let mut updates = client.subscribe(&Default::default()).await?;
+//! while let Some(sessions) = updates.next().await {
+//!     println!("{} active sessions", sessions?.len());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use futures::{stream::BoxStream, StreamExt};
+use reqwest::{Method, RequestBuilder, StatusCode, Url};
+use serde::{de::DeserializeOwned, Deserialize};
+use std::fmt;
+use tokio_tungstenite::tungstenite;
+
+pub use claude_monitor_models as models;
+
+use models::{
+    ActivityHeatmap, AlertRule, AlertRuleInput, ApiKey, ApiKeyInput, BackupInfo, BuildInfo, BurnRate, CleanupReport,
+    ConcurrencyStats, CostStats, CreatedApiKey, DailyStatsResponse, DeadLetter, DurationStats, EditorSession,
+    ErrorResponse, Export, HealthResponse, HookEvent, ImportReport, IngestStats, MaintenanceReport, MuteRequest,
+    NotificationPage, OnConflict, ProjectAlias, ProjectAliasInput, ProjectAliasKey, ProjectRollup, ProjectStatsResponse,
+    QuotaStatus, Readiness, SearchResponse, SequencedFrame, SessionEvent, SessionSort, SessionStreamMessage,
+    SessionUpdate, SessionUsage, SessionWithAgents, Settings, SettingsUpdate, SortOrder, StatsSummary, TagsRequest,
+    TimelineEntry, ToolStats, WeeklyQuotaStatus, WsStats,
+};
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The base URL given to [`Client::new`] is not an http(s) URL.
+    InvalidUrl(String),
+    /// The request could not be sent, or its response could not be read.
+    Http(reqwest::Error),
+    /// The server answered with an error status; `message` is the `error`
+    /// field of its body when it has one.
+    Api { status: StatusCode, message: String },
+    /// The session stream could not be opened or broke off.
+    WebSocket(Box<tungstenite::Error>),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidUrl(url) => write!(f, "invalid server URL: {url}"),
+            Self::Http(e) => write!(f, "request failed: {e}"),
+            Self::Api { status, message } => write!(f, "server returned {status}: {message}"),
+            Self::WebSocket(e) => write!(f, "session stream failed: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Http(e) => Some(e),
+            Self::WebSocket(e) => Some(e.as_ref()),
+            Self::InvalidUrl(_) | Self::Api { .. } => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Self::Http(e)
+    }
+}
+
+impl From<tungstenite::Error> for Error {
+    fn from(e: tungstenite::Error) -> Self {
+        Self::WebSocket(Box::new(e))
+    }
+}
+
+/// Which sessions [`Client::subscribe`] streams; the default is every
+/// session that is not hidden.
+#[derive(Debug, Clone, Default)]
+pub struct SessionFilter {
+    /// Only sessions on this host.
+    pub host: Option<String>,
+    /// Only sessions carrying any of these tags.
+    pub tags: Vec<String>,
+    /// Also stream sessions muted with `hide`.
+    pub include_hidden: bool,
+}
+
+/// Which sessions [`Client::sessions`] lists; the default is all of them.
+#[derive(Debug, Clone, Default)]
+pub struct SessionQuery {
+    /// Only sessions on this host.
+    pub host: Option<String>,
+    /// Only sessions carrying any of these tags.
+    pub tags: Vec<String>,
+    /// Only sessions in any of these statuses.
+    pub statuses: Vec<String>,
+    /// Only sessions in this project, by name or path.
+    pub project: Option<String>,
+    /// Only sessions updated at or after this, `YYYY-MM-DD` or RFC 3339.
+    pub since: Option<String>,
+    pub sort: Option<SessionSort>,
+    pub order: Option<SortOrder>,
+    /// Page size for [`Client::sessions_page`], at most 500.
+    pub limit: Option<u32>,
+    /// [`SessionPage::next_cursor`] of the previous page.
+    pub cursor: Option<String>,
+}
+
+/// One page of [`Client::sessions_page`].
+#[derive(Debug, Clone)]
+pub struct SessionPage {
+    pub sessions: Vec<SessionWithAgents>,
+    /// Sessions matching the query, across all pages.
+    pub total: Option<u64>,
+    /// Pass as [`SessionQuery::cursor`] for the next page; `None` on the last.
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Acknowledged {
+    acknowledged: bool,
+}
+
+#[derive(Deserialize)]
+struct Tags {
+    tags: Vec<String>,
+}
+
+/// A monitor server, e.g. `http://localhost:9147`. Cheap to clone.
+#[derive(Debug, Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    base: Url,
+}
+
+impl Client {
+    pub fn new(base_url: &str) -> Result<Self> {
+        Self::with_http(base_url, reqwest::Client::new())
+    }
+
+    /// Use a preconfigured HTTP client, e.g. one with a timeout.
+    pub fn with_http(base_url: &str, http: reqwest::Client) -> Result<Self> {
+        let base = Url::parse(base_url).map_err(|_| Error::InvalidUrl(base_url.to_string()))?;
+        if !matches!(base.scheme(), "http" | "https") || base.cannot_be_a_base() {
+            return Err(Error::InvalidUrl(base_url.to_string()));
+        }
+        Ok(Self { http, base })
+    }
+
+    pub fn base_url(&self) -> &Url {
+        &self.base
+    }
+
+    /// The base URL with `segments` appended, each percent-encoded, so a
+    /// server behind a path prefix works too.
+    fn url(&self, segments: &[&str]) -> Url {
+        let mut url = self.base.clone();
+        url.path_segments_mut()
+            .expect("checked in with_http")
+            .pop_if_empty()
+            .extend(segments);
+        url
+    }
+
+    fn request(&self, method: Method, segments: &[&str]) -> RequestBuilder {
+        self.http.request(method, self.url(segments))
+    }
+
+    async fn send(request: RequestBuilder) -> Result<reqwest::Response> {
+        Self::check(request.send().await?).await
+    }
+
+    /// `response` if its status is a success, otherwise the error it carries.
+    async fn check(response: reqwest::Response) -> Result<reqwest::Response> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let body = response.text().await.unwrap_or_default();
+        let message = match serde_json::from_str::<ErrorResponse>(&body) {
+            Ok(error) => error.error,
+            Err(_) => status.canonical_reason().unwrap_or_default().to_string(),
+        };
+        Err(Error::Api { status, message })
+    }
+
+    async fn json<T: DeserializeOwned>(request: RequestBuilder) -> Result<T> {
+        Ok(Self::send(request).await?.json().await?)
+    }
+
+    pub async fn health(&self) -> Result<HealthResponse> {
+        Self::json(self.request(Method::GET, &["health"])).await
+    }
+
+    /// Version, commit and features of the server's build.
+    pub async fn build_info(&self) -> Result<BuildInfo> {
+        Self::json(self.request(Method::GET, &["api", "build-info"])).await
+    }
+
+    /// The server's readiness checks. A server that isn't ready answers 503
+    /// with the same body, which comes back here as `Ok` too.
+    pub async fn readiness(&self) -> Result<Readiness> {
+        let response = self.request(Method::GET, &["readyz"]).send().await?;
+        if response.status() == StatusCode::SERVICE_UNAVAILABLE {
+            return Ok(response.json().await?);
+        }
+        Ok(Self::check(response).await?.json().await?)
+    }
+
+    /// Report a hook event, as the hook scripts do.
+    pub async fn post_event(&self, event: &HookEvent) -> Result<()> {
+        Self::send(self.request(Method::POST, &["api", "events"]).json(event)).await.map(drop)
+    }
+
+    /// Hook payloads the server couldn't parse, newest first.
+    pub async fn dead_letters(&self, limit: u32) -> Result<Vec<DeadLetter>> {
+        Self::json(self.request(Method::GET, &["api", "dead-letters"]).query(&[("limit", limit)])).await
+    }
+
+    /// Forward a Claude CLI hook's stdin payload unchanged.
+    pub async fn post_hook(&self, input: &serde_json::Value) -> Result<()> {
+        Self::send(self.request(Method::POST, &["api", "hooks"]).json(input)).await.map(drop)
+    }
+
+    fn sessions_request(&self, query: &SessionQuery) -> RequestBuilder {
+        let mut request = self.request(Method::GET, &["api", "sessions"]).query(&[
+            ("host", query.host.as_deref()),
+            ("project", query.project.as_deref()),
+            ("since", query.since.as_deref()),
+            ("cursor", query.cursor.as_deref()),
+        ]);
+        request = request
+            .query(&[("sort", query.sort)])
+            .query(&[("order", query.order)])
+            .query(&[("limit", query.limit)]);
+        if !query.tags.is_empty() {
+            request = request.query(&[("tag", query.tags.join(","))]);
+        }
+        if !query.statuses.is_empty() {
+            request = request.query(&[("status", query.statuses.join(","))]);
+        }
+        request
+    }
+
+    /// Active sessions matching `query`.
+    pub async fn sessions(&self, query: &SessionQuery) -> Result<Vec<SessionWithAgents>> {
+        Self::json(self.sessions_request(query)).await
+    }
+
+    /// One page of active sessions matching `query`, with the cursor for the
+    /// next.
+    pub async fn sessions_page(&self, query: &SessionQuery) -> Result<SessionPage> {
+        let response = Self::send(self.sessions_request(query)).await?;
+        let header = |name: &str| response.headers().get(name).and_then(|v| v.to_str().ok()).map(str::to_string);
+        let total = header("x-total-count").and_then(|t| t.parse().ok());
+        let next_cursor = header("x-next-cursor");
+        Ok(SessionPage { sessions: response.json().await?, total, next_cursor })
+    }
+
+    /// Mark every session completed.
+    pub async fn clear_sessions(&self) -> Result<()> {
+        Self::send(self.request(Method::DELETE, &["api", "sessions"])).await.map(drop)
+    }
+
+    /// Move one session to the trash, dismissing it from overlays.
+    pub async fn dismiss_session(&self, session_id: &str) -> Result<()> {
+        Self::send(self.request(Method::DELETE, &["api", "sessions", session_id])).await.map(drop)
+    }
+
+    /// Dismissed sessions that can still be restored.
+    pub async fn trash(&self) -> Result<Vec<SessionWithAgents>> {
+        Self::json(self.request(Method::GET, &["api", "trash"])).await
+    }
+
+    /// Undo a dismissal.
+    pub async fn restore_session(&self, session_id: &str) -> Result<()> {
+        Self::send(self.request(Method::POST, &["api", "sessions", session_id, "restore"])).await.map(drop)
+    }
+
+    /// Returns the fields that changed.
+    pub async fn update_session(&self, session_id: &str, update: &SessionUpdate) -> Result<SessionUpdate> {
+        Self::json(self.request(Method::PATCH, &["api", "sessions", session_id]).json(update)).await
+    }
+
+    /// Returns whether the session was blocked waiting for the user.
+    pub async fn ack_session(&self, session_id: &str) -> Result<bool> {
+        let ack: Acknowledged = Self::json(self.request(Method::POST, &["api", "sessions", session_id, "ack"])).await?;
+        Ok(ack.acknowledged)
+    }
+
+    pub async fn pin_session(&self, session_id: &str) -> Result<()> {
+        Self::send(self.request(Method::POST, &["api", "sessions", session_id, "pin"])).await.map(drop)
+    }
+
+    pub async fn unpin_session(&self, session_id: &str) -> Result<()> {
+        Self::send(self.request(Method::POST, &["api", "sessions", session_id, "unpin"])).await.map(drop)
+    }
+
+    /// Silence a session's notifications; `hide` also leaves it out of
+    /// session streams that do not ask for hidden sessions.
+    pub async fn mute_session(&self, session_id: &str, hide: bool) -> Result<()> {
+        let request = self.request(Method::POST, &["api", "sessions", session_id, "mute"]).json(&MuteRequest { hide });
+        Self::send(request).await.map(drop)
+    }
+
+    pub async fn unmute_session(&self, session_id: &str) -> Result<()> {
+        Self::send(self.request(Method::POST, &["api", "sessions", session_id, "unmute"])).await.map(drop)
+    }
+
+    /// Returns the session's tags afterwards.
+    pub async fn add_tags(&self, session_id: &str, tags: &[String]) -> Result<Vec<String>> {
+        self.tags(Method::POST, session_id, tags).await
+    }
+
+    /// Returns the session's tags afterwards.
+    pub async fn remove_tags(&self, session_id: &str, tags: &[String]) -> Result<Vec<String>> {
+        self.tags(Method::DELETE, session_id, tags).await
+    }
+
+    async fn tags(&self, method: Method, session_id: &str, tags: &[String]) -> Result<Vec<String>> {
+        let body = TagsRequest { tags: tags.to_vec() };
+        let request = self.request(method, &["api", "sessions", session_id, "tags"]).json(&body);
+        Ok(Self::json::<Tags>(request).await?.tags)
+    }
+
+    pub async fn session_tools(&self, session_id: &str) -> Result<Vec<ToolStats>> {
+        Self::json(self.request(Method::GET, &["api", "sessions", session_id, "tools"])).await
+    }
+
+    /// Status changes, tool calls and notifications, oldest first.
+    pub async fn session_timeline(&self, session_id: &str) -> Result<Vec<TimelineEntry>> {
+        Self::json(self.request(Method::GET, &["api", "sessions", session_id, "timeline"])).await
+    }
+
+    pub async fn session_usage(&self, session_id: &str) -> Result<SessionUsage> {
+        Self::json(self.request(Method::GET, &["api", "sessions", session_id, "usage"])).await
+    }
+
+    /// The session's newest `limit` hook events, optionally from one agent.
+    /// The server caps `limit` at 500.
+    pub async fn session_events(&self, session_id: &str, limit: u32, agent: Option<&str>) -> Result<Vec<SessionEvent>> {
+        let mut request = self
+            .request(Method::GET, &["api", "sessions", session_id, "events"])
+            .query(&[("limit", limit)]);
+        if let Some(agent) = agent {
+            request = request.query(&[("agent", agent)]);
+        }
+        Self::json(request).await
+    }
+
+    /// Events whose tool name or message contains every word of `q`, best
+    /// match first. The server caps `limit` at 100.
+    pub async fn search(&self, q: &str, limit: u32, host: Option<&str>) -> Result<SearchResponse> {
+        let request = self
+            .request(Method::GET, &["api", "search"])
+            .query(&[("q", Some(q)), ("host", host)])
+            .query(&[("limit", limit)]);
+        Self::json(request).await
+    }
+
+    /// Session, agent and event counts, without the sessions themselves.
+    pub async fn stats(&self, host: Option<&str>) -> Result<StatsSummary> {
+        Self::json(self.request(Method::GET, &["api", "stats"]).query(&[("host", host)])).await
+    }
+
+    pub async fn costs(&self, host: Option<&str>) -> Result<CostStats> {
+        Self::json(self.request(Method::GET, &["api", "stats", "costs"]).query(&[("host", host)])).await
+    }
+
+    /// Per-project totals between `from` (inclusive) and `to` (exclusive),
+    /// each `YYYY-MM-DD` or RFC 3339.
+    pub async fn project_stats(
+        &self,
+        from: Option<&str>,
+        to: Option<&str>,
+        host: Option<&str>,
+    ) -> Result<ProjectStatsResponse> {
+        let request = self
+            .request(Method::GET, &["api", "stats", "projects"])
+            .query(&[("from", from), ("to", to), ("host", host)]);
+        Self::json(request).await
+    }
+
+    /// Sessions, active minutes and completions per UTC day between `from`
+    /// (inclusive) and `to` (exclusive).
+    pub async fn daily_stats(
+        &self,
+        from: Option<&str>,
+        to: Option<&str>,
+        host: Option<&str>,
+    ) -> Result<DailyStatsResponse> {
+        let request = self
+            .request(Method::GET, &["api", "stats", "daily"])
+            .query(&[("from", from), ("to", to), ("host", host)]);
+        Self::json(request).await
+    }
+
+    /// Duration percentiles of sessions that completed between `from` and
+    /// `to`, with the `longest` of them.
+    pub async fn duration_stats(
+        &self,
+        from: Option<&str>,
+        to: Option<&str>,
+        longest: u32,
+        host: Option<&str>,
+    ) -> Result<DurationStats> {
+        let request = self
+            .request(Method::GET, &["api", "stats", "durations"])
+            .query(&[("from", from), ("to", to), ("host", host)])
+            .query(&[("longest", longest)]);
+        Self::json(request).await
+    }
+
+    /// How many sessions ran in parallel between `from` and `to`, by default
+    /// over the last 24 hours.
+    pub async fn concurrency_stats(
+        &self,
+        from: Option<&str>,
+        to: Option<&str>,
+        host: Option<&str>,
+    ) -> Result<ConcurrencyStats> {
+        let request = self
+            .request(Method::GET, &["api", "stats", "concurrency"])
+            .query(&[("from", from), ("to", to), ("host", host)]);
+        Self::json(request).await
+    }
+
+    /// Events by day-of-week and hour-of-day between `from` and `to`, in the
+    /// timezone `utc_offset` hours east of UTC.
+    pub async fn heatmap(
+        &self,
+        from: Option<&str>,
+        to: Option<&str>,
+        utc_offset: i32,
+        host: Option<&str>,
+    ) -> Result<ActivityHeatmap> {
+        let request = self
+            .request(Method::GET, &["api", "stats", "heatmap"])
+            .query(&[("from", from), ("to", to), ("host", host)])
+            .query(&[("utc_offset", utc_offset)]);
+        Self::json(request).await
+    }
+
+    /// Tokens and spend per minute over the last `minutes`.
+    pub async fn burn_rate(&self, minutes: u32, host: Option<&str>) -> Result<BurnRate> {
+        let request = self
+            .request(Method::GET, &["api", "stats", "burn-rate"])
+            .query(&[("minutes", minutes)])
+            .query(&[("host", host)]);
+        Self::json(request).await
+    }
+
+    /// Depth and throughput of the server's event write queue.
+    pub async fn ingest_stats(&self) -> Result<IngestStats> {
+        Self::json(self.request(Method::GET, &["api", "stats", "ingest"])).await
+    }
+
+    /// WebSocket clients, their lag and how long broadcasts take to reach them.
+    pub async fn ws_stats(&self) -> Result<WsStats> {
+        Self::json(self.request(Method::GET, &["api", "stats", "ws"])).await
+    }
+
+    /// The server's metrics in the Prometheus text format.
+    pub async fn metrics(&self) -> Result<String> {
+        Ok(Self::send(self.request(Method::GET, &["metrics"])).await?.text().await?)
+    }
+
+    /// Active sessions grouped by project, most urgent first.
+    pub async fn projects(&self, host: Option<&str>) -> Result<Vec<ProjectRollup>> {
+        Self::json(self.request(Method::GET, &["api", "projects"]).query(&[("host", host)])).await
+    }
+
+    pub async fn aliases(&self) -> Result<Vec<ProjectAlias>> {
+        Self::json(self.request(Method::GET, &["api", "projects", "aliases"])).await
+    }
+
+    /// Returns the alias as stored, with its path normalized.
+    pub async fn set_alias(&self, alias: &ProjectAliasInput) -> Result<ProjectAliasInput> {
+        Self::json(self.request(Method::PUT, &["api", "projects", "aliases"]).json(alias)).await
+    }
+
+    pub async fn delete_alias(&self, project_path: &str) -> Result<()> {
+        let key = ProjectAliasKey { project_path: project_path.to_string() };
+        Self::send(self.request(Method::DELETE, &["api", "projects", "aliases"]).json(&key)).await.map(drop)
+    }
+
+    pub async fn quota(&self) -> Result<QuotaStatus> {
+        Self::json(self.request(Method::GET, &["api", "quota"])).await
+    }
+
+    pub async fn weekly_quota(&self) -> Result<WeeklyQuotaStatus> {
+        Self::json(self.request(Method::GET, &["api", "quota", "weekly"])).await
+    }
+
+    pub async fn rules(&self) -> Result<Vec<AlertRule>> {
+        Self::json(self.request(Method::GET, &["api", "rules"])).await
+    }
+
+    pub async fn rule(&self, rule_id: &str) -> Result<AlertRule> {
+        Self::json(self.request(Method::GET, &["api", "rules", rule_id])).await
+    }
+
+    pub async fn create_rule(&self, rule: &AlertRuleInput) -> Result<AlertRule> {
+        Self::json(self.request(Method::POST, &["api", "rules"]).json(rule)).await
+    }
+
+    pub async fn update_rule(&self, rule_id: &str, rule: &AlertRuleInput) -> Result<AlertRule> {
+        Self::json(self.request(Method::PUT, &["api", "rules", rule_id]).json(rule)).await
+    }
+
+    pub async fn delete_rule(&self, rule_id: &str) -> Result<()> {
+        Self::send(self.request(Method::DELETE, &["api", "rules", rule_id])).await.map(drop)
+    }
+
+    pub async fn settings(&self) -> Result<Settings> {
+        Self::json(self.request(Method::GET, &["api", "settings"])).await
+    }
+
+    /// Returns the settings after the update.
+    pub async fn update_settings(&self, update: &SettingsUpdate) -> Result<Settings> {
+        Self::json(self.request(Method::PUT, &["api", "settings"]).json(update)).await
+    }
+
+    /// Notification history, newest first. The server caps `limit` at 500.
+    pub async fn notifications(&self, limit: u32, offset: u32) -> Result<NotificationPage> {
+        let request = self
+            .request(Method::GET, &["api", "notifications"])
+            .query(&[("limit", limit), ("offset", offset)]);
+        Self::json(request).await
+    }
+
+    /// Sessions in the editor workspace at `cwd`.
+    pub async fn editor_sessions(&self, cwd: &str, host: Option<&str>) -> Result<Vec<EditorSession>> {
+        let request = self
+            .request(Method::GET, &["api", "editor", "sessions"])
+            .query(&[("cwd", Some(cwd)), ("host", host)]);
+        Self::json(request).await
+    }
+
+    /// Run a database maintenance pass now. Fails with 409 while one is
+    /// already running.
+    pub async fn run_maintenance(&self) -> Result<MaintenanceReport> {
+        Self::json(self.request(Method::POST, &["api", "admin", "maintenance"])).await
+    }
+
+    /// Remove completed sessions now, those completed over `older_than`
+    /// seconds ago if given, else after the server's configured TTL.
+    pub async fn run_cleanup(&self, older_than: Option<u64>) -> Result<CleanupReport> {
+        let request = self.request(Method::POST, &["api", "admin", "cleanup"]).query(&[("older_than", older_than)]);
+        Self::json(request).await
+    }
+
+    /// Every API key, revoked ones too.
+    pub async fn api_keys(&self) -> Result<Vec<ApiKey>> {
+        Self::json(self.request(Method::GET, &["api", "admin", "keys"])).await
+    }
+
+    /// Create a key for hooks to post with, and with `admin` for the
+    /// `/api/admin` routes too; its `key` is only returned here.
+    pub async fn create_api_key(&self, name: &str, admin: bool) -> Result<CreatedApiKey> {
+        let input = ApiKeyInput { name: name.to_string(), admin };
+        Self::json(self.request(Method::POST, &["api", "admin", "keys"]).json(&input)).await
+    }
+
+    pub async fn revoke_api_key(&self, name: &str) -> Result<()> {
+        Self::send(self.request(Method::DELETE, &["api", "admin", "keys", name])).await.map(drop)
+    }
+
+    /// Everything the server stores, as one document.
+    pub async fn export(&self) -> Result<Export> {
+        Self::json(self.request(Method::GET, &["api", "export"])).await
+    }
+
+    /// Events between `from` (inclusive) and `to` (exclusive), each
+    /// `YYYY-MM-DD` or RFC 3339, as CSV.
+    pub async fn events_csv(&self, from: Option<&str>, to: Option<&str>, host: Option<&str>) -> Result<String> {
+        let request = self
+            .request(Method::GET, &["api", "export", "events.csv"])
+            .query(&[("from", from), ("to", to), ("host", host)]);
+        Ok(Self::send(request).await?.text().await?)
+    }
+
+    /// Sessions completed between `from` and `to`, then those still open
+    /// that were active in that range, as CSV.
+    pub async fn sessions_csv(&self, from: Option<&str>, to: Option<&str>, host: Option<&str>) -> Result<String> {
+        let request = self
+            .request(Method::GET, &["api", "export", "sessions.csv"])
+            .query(&[("from", from), ("to", to), ("host", host)]);
+        Ok(Self::send(request).await?.text().await?)
+    }
+
+    /// Merge an export into the server's database.
+    pub async fn import(&self, export: &Export, on_conflict: OnConflict) -> Result<ImportReport> {
+        let request = self
+            .request(Method::POST, &["api", "import"])
+            .query(&[("on_conflict", on_conflict)])
+            .json(export);
+        Self::json(request).await
+    }
+
+    /// Snapshot the database to a new file on the server.
+    pub async fn backup(&self) -> Result<BackupInfo> {
+        Self::json(self.request(Method::POST, &["api", "admin", "backup"])).await
+    }
+
+    /// The `/ws` URL for `filter`.
+    pub fn stream_url(&self, filter: &SessionFilter) -> Url {
+        let mut url = self.url(&["ws"]);
+        let scheme = if self.base.scheme() == "https" { "wss" } else { "ws" };
+        url.set_scheme(scheme).expect("http(s) URLs convert to ws(s)");
+        {
+            let mut query = url.query_pairs_mut();
+            if filter.include_hidden {
+                query.append_pair("hidden", "true");
+            }
+            if let Some(host) = &filter.host {
+                query.append_pair("host", host);
+            }
+            if !filter.tags.is_empty() {
+                query.append_pair("tag", &filter.tags.join(","));
+            }
+        }
+        if url.query() == Some("") {
+            url.set_query(None);
+        }
+        url
+    }
+
+    /// Follow the session list: the current snapshot, then a new one after
+    /// every change. The stream ends when the server closes the connection;
+    /// reconnecting is left to the caller.
+    pub async fn subscribe(
+        &self,
+        filter: &SessionFilter,
+    ) -> Result<BoxStream<'static, Result<Vec<SessionWithAgents>>>> {
+        let (socket, _) = tokio_tungstenite::connect_async(self.stream_url(filter).as_str()).await?;
+        let snapshots = socket.filter_map(|frame| async move {
+            match frame {
+                Ok(tungstenite::Message::Text(text)) => serde_json::from_str(&text).ok().map(Ok),
+                Ok(_) => None,
+                Err(e) => Some(Err(e.into())),
+            }
+        });
+        Ok(snapshots.boxed())
+    }
+
+    /// Like [`Client::subscribe`], but numbered so a dropped stream can be
+    /// resumed: pass the [`SequencedFrame::seq`] last seen, or 0 at first,
+    /// and fold each frame in with [`SequencedFrame::apply`].
+    pub async fn subscribe_sequenced(
+        &self,
+        filter: &SessionFilter,
+        since_seq: u64,
+    ) -> Result<BoxStream<'static, Result<SequencedFrame>>> {
+        let mut url = self.stream_url(filter);
+        url.query_pairs_mut().append_pair("since_seq", &since_seq.to_string());
+        let (socket, _) = tokio_tungstenite::connect_async(url.as_str()).await?;
+        // Quota frames carry no `type` and are skipped.
+        let frames = socket.filter_map(|frame| async move {
+            match frame {
+                Ok(tungstenite::Message::Text(text)) => serde_json::from_str(&text).ok().map(Ok),
+                Ok(_) => None,
+                Err(e) => Some(Err(e.into())),
+            }
+        });
+        Ok(frames.boxed())
+    }
+
+    /// Follow one session: its current state, then each event recorded for
+    /// it and each change to it. Ends like [`Client::subscribe`].
+    pub async fn subscribe_session(
+        &self,
+        session_id: &str,
+    ) -> Result<BoxStream<'static, Result<SessionStreamMessage>>> {
+        let mut url = self.url(&["ws", "sessions", session_id]);
+        let scheme = if self.base.scheme() == "https" { "wss" } else { "ws" };
+        url.set_scheme(scheme).expect("http(s) URLs convert to ws(s)");
+        let (socket, _) = tokio_tungstenite::connect_async(url.as_str()).await?;
+        let messages = socket.filter_map(|frame| async move {
+            match frame {
+                Ok(tungstenite::Message::Text(text)) => serde_json::from_str(&text).ok().map(Ok),
+                Ok(_) => None,
+                Err(e) => Some(Err(e.into())),
+            }
+        });
+        Ok(messages.boxed())
+    }
+}
@@ -0,0 +1,38 @@
+//! The nested `recentEvents` fields of the GraphQL schema. They live here
+//! because resolvers must be defined alongside their types; events come
+//! from whatever `EventSource` the server puts in the schema's data.
+
+use async_graphql::{ComplexObject, Context, Result};
+use futures::future::BoxFuture;
+use std::sync::Arc;
+
+use crate::{Agent, SessionEvent, SessionWithAgents};
+
+/// Looks up stored events. The schema must carry an `Arc<dyn EventSource>`.
+pub trait EventSource: Send + Sync {
+    /// The latest `limit` events of a session, newest first, optionally only
+    /// one agent's.
+    fn recent_events<'a>(
+        &'a self,
+        session_id: &'a str,
+        agent_name: Option<&'a str>,
+        limit: u32,
+    ) -> BoxFuture<'a, Result<Vec<SessionEvent>>>;
+}
+
+#[ComplexObject]
+impl SessionWithAgents {
+    /// The latest events across all of the session's agents, newest first.
+    async fn recent_events(&self, ctx: &Context<'_>, #[graphql(default = 20)] limit: u32) -> Result<Vec<SessionEvent>> {
+        ctx.data::<Arc<dyn EventSource>>()?.recent_events(&self.session_id, None, limit).await
+    }
+}
+
+#[ComplexObject]
+impl Agent {
+    /// The latest events this agent reported, newest first.
+    async fn recent_events(&self, ctx: &Context<'_>, #[graphql(default = 20)] limit: u32) -> Result<Vec<SessionEvent>> {
+        let events = ctx.data::<Arc<dyn EventSource>>()?;
+        events.recent_events(&self.session_id, Some(&self.agent_name), limit).await
+    }
+}
@@ -0,0 +1,1207 @@
+//! Request and response types of the Claude session monitor's API, shared
+//! by the server and `claude-monitor-client`.
+//!
+//! The `openapi`, `graphql` and `sqlx` features add the derives the server
+//! needs to describe, serve and load these types; clients need none of them.
+
+use chrono::{DateTime, NaiveDate, NaiveTime, Timelike, Utc};
+use std::collections::BTreeMap;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use uuid::Uuid;
+
+#[cfg(feature = "graphql")]
+mod graphql;
+#[cfg(feature = "graphql")]
+pub use graphql::EventSource;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub id: Uuid,
+    pub session_id: String,
+    pub project_path: String,
+    pub project_name: String,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
+#[cfg_attr(feature = "graphql", graphql(complex))]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+pub struct Agent {
+    #[cfg_attr(feature = "sqlx", sqlx(try_from = "String"))]
+    pub id: Uuid,
+    pub session_id: String,
+    pub agent_name: String,
+    pub parent_session_id: Option<String>,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Time spent `active`, replayed from the agent's status transitions.
+    #[serde(default)]
+    #[cfg_attr(feature = "sqlx", sqlx(skip))]
+    pub runtime_seconds: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
+#[cfg_attr(feature = "graphql", graphql(name = "Session", complex))]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+pub struct SessionWithAgents {
+    #[cfg_attr(feature = "sqlx", sqlx(try_from = "String"))]
+    pub id: Uuid,
+    /// Machine the session runs on, as reported by its hooks.
+    pub host: String,
+    pub session_id: String,
+    pub project_name: String,
+    pub project_path: String,
+    pub status: String,
+    /// Human-readable status line from the template catalog, e.g. "Running Bash".
+    #[cfg_attr(feature = "sqlx", sqlx(skip))]
+    pub status_text: String,
+    /// Claude model in use, e.g. "claude-opus-4-1", from hooks or the transcript.
+    pub model: Option<String>,
+    /// Git branch of the project directory, when the hook reports one.
+    pub git_branch: Option<String>,
+    /// Tool the session is running right now; cleared on post_tool_use/stop.
+    pub current_tool: Option<String>,
+    /// Most recent notification/stop message, e.g. the question Claude is asking.
+    pub last_message: Option<String>,
+    /// Latest assistant text and tool call seen in the session transcript.
+    pub last_assistant_text: Option<String>,
+    pub last_tool_call: Option<String>,
+    /// Pinned sessions sort first and stay listed after they complete.
+    pub pinned: bool,
+    /// Muted sessions never notify; hidden ones also stay out of the WS stream.
+    pub muted: bool,
+    pub hidden: bool,
+    /// Free-form labels such as a ticket number, in the order they were added.
+    #[cfg_attr(feature = "sqlx", sqlx(skip))]
+    pub tags: Vec<String>,
+    /// The user's own note about the session.
+    pub notes: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Oldest events dropped to keep the session within `[events] max_per_session`.
+    #[serde(default)]
+    pub pruned_events: i64,
+    /// When it was moved to the trash; only sessions from GET /api/trash have one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "sqlx", sqlx(default))]
+    pub deleted_at: Option<DateTime<Utc>>,
+    #[cfg_attr(feature = "sqlx", sqlx(skip))]
+    pub agents: Vec<Agent>,
+    #[cfg_attr(feature = "sqlx", sqlx(skip))]
+    pub usage: TokenUsage,
+    /// Estimated USD cost of `usage` at the configured per-model prices.
+    #[cfg_attr(feature = "sqlx", sqlx(skip))]
+    pub estimated_cost_usd: f64,
+    /// Aggregator source the session came from; `None` for this monitor's own.
+    #[serde(default)]
+    #[cfg_attr(feature = "sqlx", sqlx(skip))]
+    pub source: Option<String>,
+}
+
+impl SessionWithAgents {
+    /// Whether the session carries any of `tags`; an empty list matches all.
+    pub fn has_any_tag(&self, tags: &[String]) -> bool {
+        tags.is_empty() || tags.iter().any(|t| self.tags.contains(t))
+    }
+}
+
+/// Order of a session list, as `?sort=`. Pinned sessions always come first.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum SessionSort {
+    #[default]
+    CreatedAt,
+    UpdatedAt,
+    /// Alphabetical by status.
+    Status,
+    /// Alphabetical by project name.
+    Project,
+    /// Waiting on input or permission first, then active, then idle; most
+    /// recently updated first within each.
+    Attention,
+}
+
+/// Direction of a [`SessionSort`], as `?order=`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+/// Body for PATCH /api/sessions/:id; omitted fields are left unchanged.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(default)]
+pub struct SessionUpdate {
+    /// An empty note clears it.
+    pub notes: Option<String>,
+}
+
+/// Body for POST and DELETE /api/sessions/:id/tags.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TagsRequest {
+    pub tags: Vec<String>,
+}
+
+/// Optional body for POST /api/sessions/:id/mute.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(default)]
+pub struct MuteRequest {
+    /// Also leave the session out of WS snapshots, unless a client asks for
+    /// hidden sessions with `?hidden=true`.
+    pub hide: bool,
+}
+
+/// Token counts, as reported in transcript `usage` blocks.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+#[serde(default)]
+pub struct TokenUsage {
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cache_creation_input_tokens: i64,
+    pub cache_read_input_tokens: i64,
+}
+
+impl TokenUsage {
+    pub fn add(&self, other: &TokenUsage) -> TokenUsage {
+        TokenUsage {
+            input_tokens: self.input_tokens + other.input_tokens,
+            output_tokens: self.output_tokens + other.output_tokens,
+            cache_creation_input_tokens: self.cache_creation_input_tokens + other.cache_creation_input_tokens,
+            cache_read_input_tokens: self.cache_read_input_tokens + other.cache_read_input_tokens,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+pub struct AgentUsage {
+    pub agent_name: String,
+    #[serde(flatten)]
+    #[cfg_attr(feature = "sqlx", sqlx(flatten))]
+    pub usage: TokenUsage,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ModelCost {
+    pub model: String,
+    #[serde(flatten)]
+    pub usage: TokenUsage,
+    pub cost_usd: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ProjectCost {
+    pub project_name: String,
+    pub sessions: i64,
+    pub cost_usd: f64,
+}
+
+/// Response for GET /api/stats/costs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CostStats {
+    pub total_usd: f64,
+    pub by_model: Vec<ModelCost>,
+    pub by_project: Vec<ProjectCost>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ProjectStats {
+    pub project_name: String,
+    pub sessions: i64,
+    #[serde(flatten)]
+    pub usage: TokenUsage,
+    pub cost_usd: f64,
+    pub active_seconds: i64,
+}
+
+/// Response for GET /api/stats/projects.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ProjectStatsResponse {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub projects: Vec<ProjectStats>,
+}
+
+/// One project's share of GET /api/stats.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ProjectSummary {
+    pub project_name: String,
+    pub sessions: i64,
+    pub agents: i64,
+    pub active_agents: i64,
+}
+
+/// Response for GET /api/stats: counts for a status ribbon, without the
+/// sessions themselves.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct StatsSummary {
+    pub sessions: i64,
+    /// Sessions per status.
+    pub by_status: BTreeMap<String, i64>,
+    /// Agents across all sessions, counting each main agent.
+    pub agents: i64,
+    /// Agents that have not completed.
+    pub active_agents: i64,
+    /// Most sessions first.
+    pub projects: Vec<ProjectSummary>,
+    pub events_last_hour: i64,
+    pub events_per_minute: f64,
+    /// Events in each of the last 60 minutes, oldest first.
+    pub events_by_minute: Vec<i64>,
+}
+
+/// One day of GET /api/stats/daily.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct DailyStats {
+    /// UTC date.
+    pub day: NaiveDate,
+    /// Sessions with any activity that day.
+    pub sessions: i64,
+    pub active_minutes: i64,
+    /// Sessions that ended that day.
+    pub completions: i64,
+}
+
+/// Response for GET /api/stats/daily, one entry per day, oldest first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct DailyStatsResponse {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub days: Vec<DailyStats>,
+}
+
+/// How long one completed session ran.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+pub struct SessionDuration {
+    pub host: String,
+    pub session_id: String,
+    pub project_name: String,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    #[cfg_attr(feature = "sqlx", sqlx(skip))]
+    pub duration_seconds: i64,
+}
+
+/// Response for GET /api/stats/durations. Percentiles are `None` when no
+/// session completed in the range.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct DurationStats {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    /// Sessions that completed in the range.
+    pub sessions: i64,
+    pub p50_seconds: Option<i64>,
+    pub p90_seconds: Option<i64>,
+    pub p99_seconds: Option<i64>,
+    /// Longest first.
+    pub longest: Vec<SessionDuration>,
+}
+
+/// Events in one hour-of-day on one day-of-week of GET /api/stats/heatmap.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct HeatmapCell {
+    /// 0 is Monday.
+    pub weekday: u32,
+    /// 0–23.
+    pub hour: u32,
+    pub events: i64,
+}
+
+/// Response for GET /api/stats/heatmap.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ActivityHeatmap {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    /// Hours east of UTC that weekdays and hours are in.
+    pub utc_offset: i32,
+    pub total_events: i64,
+    /// Busiest cell, for scaling colours.
+    pub max_events: i64,
+    /// All 168 cells, Monday midnight first, then by hour.
+    pub cells: Vec<HeatmapCell>,
+}
+
+/// A stretch of time one session spent active.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+pub struct ActiveInterval {
+    pub host: String,
+    pub session_id: String,
+    pub project_name: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+/// How many sessions were active from `start` to `end`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ConcurrencyLevel {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub sessions: i64,
+}
+
+/// Response for GET /api/stats/concurrency.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ConcurrencyStats {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    /// Most sessions active at once.
+    pub max_concurrent: i64,
+    /// Consecutive, oldest first; stretches with no active session are left out.
+    pub levels: Vec<ConcurrencyLevel>,
+    /// Each session's active stretches, clipped to the range, for a Gantt view.
+    pub intervals: Vec<ActiveInterval>,
+}
+
+/// Response for GET /api/quota, also sent over `/ws?quota=true`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct QuotaStatus {
+    pub window_start: DateTime<Utc>,
+    pub window_end: DateTime<Utc>,
+    /// When the oldest counted message leaves the window; `None` if nothing is counted.
+    pub resets_at: Option<DateTime<Utc>>,
+    #[serde(flatten)]
+    pub usage: TokenUsage,
+    /// Input + output tokens, the figure compared against `token_limit`.
+    pub tokens: i64,
+    pub messages: i64,
+    pub token_limit: Option<i64>,
+    pub message_limit: Option<i64>,
+    pub tokens_remaining: Option<i64>,
+    pub messages_remaining: Option<i64>,
+    /// Share of the tighter configured limit already used; `None` without limits.
+    pub used_fraction: Option<f64>,
+}
+
+/// Response for GET /api/quota/weekly.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct WeeklyQuotaStatus {
+    #[serde(flatten)]
+    pub status: QuotaStatus,
+    /// Averages since the weekly reset.
+    pub tokens_per_hour: f64,
+    pub messages_per_hour: f64,
+    /// When a configured weekly limit runs out at those rates; `None` if no
+    /// limit is set or it lasts until the reset.
+    pub projected_limit_at: Option<DateTime<Utc>>,
+}
+
+/// One session's share of the current burn rate.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SessionBurnRate {
+    pub session_id: String,
+    pub project_name: String,
+    /// Input + output tokens within the window.
+    pub tokens: i64,
+    pub tokens_per_minute: f64,
+    pub cost_usd_per_minute: f64,
+}
+
+/// Response for GET /api/stats/burn-rate.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BurnRate {
+    pub minutes: u32,
+    pub since: DateTime<Utc>,
+    pub tokens: i64,
+    pub tokens_per_minute: f64,
+    pub cost_usd_per_minute: f64,
+    /// Active sessions that used any tokens in the window, heaviest first.
+    pub sessions: Vec<SessionBurnRate>,
+}
+
+/// Response for GET /api/stats/ingest. Counts are since the server started.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct IngestStats {
+    /// Events waiting to be written.
+    pub pending: u64,
+    /// Queue size at which handlers start to wait.
+    pub capacity: u64,
+    pub queued: u64,
+    /// Events whose handler had to wait for room in the queue.
+    pub waited: u64,
+    pub written: u64,
+    /// Events lost to a failed write or a stopped writer.
+    pub failed: u64,
+    /// Old events dropped to keep sessions within `[events] max_per_session`.
+    #[serde(default)]
+    pub pruned: u64,
+    /// Transactions written.
+    pub batches: u64,
+    /// Events in the latest transaction.
+    pub last_batch: u64,
+    /// How long the latest transaction took.
+    pub last_flush_ms: f64,
+    /// Database writes of any kind retried after finding the database locked.
+    pub retried_writes: u64,
+    /// From an event's handler starting to its row being written.
+    #[serde(default)]
+    pub write_latency: LatencySummary,
+    /// From an event's handler starting to the broadcast that carries it
+    /// to clients.
+    #[serde(default)]
+    pub broadcast_latency: LatencySummary,
+}
+
+/// Response for GET /api/stats/ws. Counts are since the server started.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct WsStats {
+    /// Clients connected now, to `/ws` and `/ws/sessions/:session_id`.
+    pub connected: u64,
+    /// Connections accepted.
+    pub connections: u64,
+    /// Times a client fell so far behind that broadcasts were dropped for it.
+    pub lag_events: u64,
+    /// Broadcasts dropped for those clients, the `n` of `Lagged(n)`.
+    pub dropped_messages: u64,
+    /// Broadcasts published.
+    pub broadcasts: u64,
+    /// From a broadcast being published to a client having been sent it.
+    pub fanout: LatencySummary,
+}
+
+/// Distribution of a latency. Percentiles are the upper bound of the
+/// histogram bucket they fall in, as Prometheus estimates them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct LatencySummary {
+    pub count: u64,
+    pub mean_ms: f64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+/// Response for POST /api/admin/maintenance: one pass of query-planner
+/// upkeep and free-space reclamation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct MaintenanceReport {
+    /// Database size in bytes before the pass.
+    pub size_before: u64,
+    pub size_after: u64,
+    /// The pass rewrote the whole database, which SQLite does once to switch
+    /// an older file to incremental vacuum.
+    pub full_vacuum: bool,
+    pub duration_ms: f64,
+}
+
+/// Response for POST /api/admin/cleanup: what a cleanup pass removed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CleanupReport {
+    /// Sessions removed, completed or out of the trash.
+    pub sessions: u64,
+    /// Their events, removed with them unless kept for the storage budget
+    /// to prune.
+    pub events: u64,
+}
+
+/// Response for POST /api/admin/backup.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BackupInfo {
+    /// Where the snapshot was written, on the server.
+    pub path: String,
+    pub size: u64,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Version of the `/api/export` format, raised when it changes incompatibly.
+pub const EXPORT_VERSION: u32 = 1;
+
+/// Response for GET /api/export: everything the monitor stores about
+/// sessions, as rows rather than the computed views the other endpoints give.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Export {
+    /// [`EXPORT_VERSION`] of the server that wrote it.
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub sessions: Vec<ExportedSession>,
+    pub agents: Vec<ExportedAgent>,
+    /// Oldest first.
+    pub events: Vec<ExportedEvent>,
+    pub settings: Vec<ExportedSetting>,
+}
+
+/// One line of GET /api/export?format=ndjson. The `header` line comes first,
+/// then sessions, agents and settings, then events oldest first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ExportRecord {
+    Header { version: u32, exported_at: DateTime<Utc> },
+    Session(ExportedSession),
+    Agent(ExportedAgent),
+    Setting(ExportedSetting),
+    Event(ExportedEvent),
+}
+
+/// A `sessions` row with its tags.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+pub struct ExportedSession {
+    pub id: String,
+    pub host: String,
+    pub session_id: String,
+    pub project_path: String,
+    pub project_name: String,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub current_tool: Option<String>,
+    pub last_message: Option<String>,
+    pub last_assistant_text: Option<String>,
+    pub last_tool_call: Option<String>,
+    pub model: Option<String>,
+    pub pinned: bool,
+    pub muted: bool,
+    pub hidden: bool,
+    pub notes: Option<String>,
+    pub git_branch: Option<String>,
+    /// Set while the session is in the trash.
+    #[serde(default)]
+    pub deleted_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub pruned_events: i64,
+    /// In the order they were added.
+    #[serde(default)]
+    #[cfg_attr(feature = "sqlx", sqlx(skip))]
+    pub tags: Vec<String>,
+}
+
+/// An `agents` row.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+pub struct ExportedAgent {
+    pub id: String,
+    /// Exports from before agents carried a host are from the local one.
+    #[serde(default = "local_host")]
+    pub host: String,
+    pub session_id: String,
+    pub agent_name: String,
+    pub parent_session_id: Option<String>,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// An `events` row.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+pub struct ExportedEvent {
+    pub id: String,
+    pub host: String,
+    pub session_id: String,
+    pub agent_name: Option<String>,
+    pub event_type: String,
+    #[cfg_attr(feature = "sqlx", sqlx(json))]
+    pub payload: serde_json::Value,
+    pub timestamp: DateTime<Utc>,
+    #[serde(default)]
+    pub request_id: Option<String>,
+    #[serde(default)]
+    pub source: Option<String>,
+}
+
+/// A `settings` row.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+pub struct ExportedSetting {
+    pub key: String,
+    pub value: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// What POST /api/import does with a row whose key is already taken, as
+/// `?on_conflict=`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum OnConflict {
+    /// Keep the row already there.
+    #[default]
+    Skip,
+    /// Overwrite it with the imported one; a replaced session's tags are
+    /// replaced too. Events never change once written, so are always skipped.
+    Replace,
+}
+
+/// Rows of one table in an import.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ImportCounts {
+    /// Inserted, or written over an existing row with `replace`.
+    pub imported: u64,
+    /// Left alone because the row was already there.
+    pub skipped: u64,
+}
+
+/// Response for POST /api/import.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ImportReport {
+    pub sessions: ImportCounts,
+    pub agents: ImportCounts,
+    pub events: ImportCounts,
+    pub settings: ImportCounts,
+}
+
+/// Conditions an alert rule matches on. Every condition that is set must
+/// hold; unset ones match anything.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(default)]
+pub struct RuleConditions {
+    /// Session status is one of these.
+    pub status: Option<Vec<String>>,
+    /// The session has been in its current status at least this long.
+    pub min_minutes_in_status: Option<u32>,
+    /// Project name, `*` matching any run of characters.
+    pub project: Option<String>,
+    /// At least this many agents, counting the main one.
+    pub min_agents: Option<usize>,
+    /// Input + output tokens per minute over the last five minutes.
+    pub min_tokens_per_minute: Option<f64>,
+}
+
+/// An alert rule, as stored and as returned by /api/rules.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+pub struct AlertRule {
+    pub id: String,
+    pub name: String,
+    pub enabled: bool,
+    #[cfg_attr(feature = "sqlx", sqlx(json))]
+    pub conditions: RuleConditions,
+    /// Names of the notification sinks to alert, e.g. "slack" or "desktop".
+    #[cfg_attr(feature = "sqlx", sqlx(json))]
+    pub sinks: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Request body for POST /api/rules and PUT /api/rules/:id.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct AlertRuleInput {
+    pub name: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub conditions: RuleConditions,
+    pub sinks: Vec<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn local_host() -> String {
+    "local".to_string()
+}
+
+/// One notification the backend tried to send, as kept for auditing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+pub struct NotificationRecord {
+    pub id: i64,
+    /// Sink name, as alert rules refer to it.
+    pub sink: String,
+    /// "alert", "summary" for held-back alerts, or "digest" for email.
+    pub kind: String,
+    /// The alert rule that routed it; `None` when sink triggers did.
+    pub rule_id: Option<String>,
+    /// Name of that rule, if it still exists.
+    pub rule_name: Option<String>,
+    pub session_id: Option<String>,
+    pub project_name: Option<String>,
+    /// Status the session moved to.
+    pub status: Option<String>,
+    pub message: String,
+    /// "sent", "failed", or "dropped" when the sink skipped it.
+    pub result: String,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Response for GET /api/notifications, newest first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct NotificationPage {
+    pub notifications: Vec<NotificationRecord>,
+    pub total: i64,
+    pub limit: u32,
+    pub offset: u32,
+}
+
+/// A friendly display name for the project at a path.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+pub struct ProjectAlias {
+    pub project_path: String,
+    pub display_name: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// One project's active sessions, from GET /api/projects.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ProjectRollup {
+    pub host: String,
+    pub project_name: String,
+    pub project_path: String,
+    /// The most urgent status among the sessions, e.g. `needs_permission`
+    /// if any session needs permission.
+    pub status: String,
+    pub session_count: usize,
+    /// Agents across all sessions, counting each main agent.
+    pub agent_count: usize,
+    /// Sessions per status.
+    pub status_counts: BTreeMap<String, usize>,
+    pub sessions: Vec<SessionWithAgents>,
+}
+
+/// Body for PUT /api/projects/aliases.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ProjectAliasInput {
+    pub project_path: String,
+    pub display_name: String,
+}
+
+/// Body for DELETE /api/projects/aliases.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ProjectAliasKey {
+    pub project_path: String,
+}
+
+/// Response for GET /api/sessions/:id/usage.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SessionUsage {
+    pub session_id: String,
+    pub totals: TokenUsage,
+    pub agents: Vec<AgentUsage>,
+}
+
+/// Compact session view for editor statusline plugins. Kept deliberately small
+/// and stable so plugins can poll it cheaply.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+pub struct EditorSession {
+    pub host: String,
+    pub session_id: String,
+    pub project_name: String,
+    pub project_path: String,
+    pub status: String,
+    pub active_agents: i64,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Incoming event payload from Claude CLI hooks.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct HookEvent {
+    pub event_type: String,
+    pub session_id: String,
+    /// Hostname of the machine running Claude; "local" when omitted.
+    pub host: Option<String>,
+    pub project_path: Option<String>,
+    pub project_name: Option<String>,
+    pub agent_name: Option<String>,
+    pub parent_session_id: Option<String>,
+    pub needs_input: Option<bool>,
+    pub tool_name: Option<String>,
+    pub transcript_path: Option<String>,
+    pub message: Option<String>,
+    pub tool_use_id: Option<String>,
+    pub model: Option<String>,
+    pub git_branch: Option<String>,
+}
+
+/// One stored hook event, from GET /api/sessions/:id/events and GraphQL.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+pub struct SessionEvent {
+    pub id: String,
+    pub agent_name: Option<String>,
+    pub event_type: String,
+    /// Event details such as `tool_name` and `message`.
+    #[cfg_attr(feature = "sqlx", sqlx(json))]
+    pub payload: serde_json::Value,
+    pub timestamp: DateTime<Utc>,
+    /// `X-Request-Id` of the request that posted the event.
+    pub request_id: Option<String>,
+    /// Name of the API key it was posted with, if keys are in use.
+    pub source: Option<String>,
+}
+
+/// Frame of `/ws/sessions/:session_id`, `{"session": ...}` or `{"event": ...}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionStreamMessage {
+    /// The session as it now stands; `null` once it is no longer active.
+    Session(Option<Box<SessionWithAgents>>),
+    /// An event recorded for the session since the stream opened.
+    Event(SessionEvent),
+}
+
+/// Identifies a session in [`SequencedFrame::Delta`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionKey {
+    pub host: String,
+    pub session_id: String,
+}
+
+/// Frame of `/ws?since_seq=`, tagged by `type`. Every broadcast gets the next
+/// `seq`; a client that reconnects with the last one it saw is sent a delta
+/// per broadcast it missed, or a fresh snapshot if they are no longer kept.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SequencedFrame {
+    /// The whole filtered session list as of broadcast `seq`.
+    Snapshot { seq: u64, sessions: Vec<SessionWithAgents> },
+    /// What broadcast `seq` changed since the one before it.
+    Delta {
+        seq: u64,
+        /// Sessions that are new or differ, in full.
+        changed: Vec<SessionWithAgents>,
+        /// Sessions that ended or no longer match the filter.
+        removed: Vec<SessionKey>,
+    },
+}
+
+impl SequencedFrame {
+    pub fn seq(&self) -> u64 {
+        match self {
+            Self::Snapshot { seq, .. } | Self::Delta { seq, .. } => *seq,
+        }
+    }
+
+    /// Bring `sessions` up to date with this frame. Sessions a delta adds are
+    /// appended; the next snapshot restores the server's order.
+    pub fn apply(self, sessions: &mut Vec<SessionWithAgents>) {
+        match self {
+            Self::Snapshot { sessions: next, .. } => *sessions = next,
+            Self::Delta { changed, removed, .. } => {
+                sessions.retain(|s| !removed.iter().any(|k| k.host == s.host && k.session_id == s.session_id));
+                for session in changed {
+                    match sessions
+                        .iter_mut()
+                        .find(|s| s.host == session.host && s.session_id == session.session_id)
+                    {
+                        Some(existing) => *existing = session,
+                        None => sessions.push(session),
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// One event matching GET /api/search, with the session it belongs to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+pub struct SearchHit {
+    pub host: String,
+    pub session_id: String,
+    pub project_name: String,
+    pub session_status: String,
+    #[cfg_attr(feature = "sqlx", sqlx(flatten))]
+    pub event: SessionEvent,
+    /// The matching text, with matches in `[` `]`.
+    pub snippet: String,
+}
+
+/// Response for GET /api/search, best match first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SearchResponse {
+    pub query: String,
+    pub hits: Vec<SearchHit>,
+}
+
+/// One entry of GET /api/sessions/:id/timeline, tagged by `type`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TimelineEntry {
+    /// The session moved to `status` on an `event_type` event from `agent_name`.
+    Status {
+        at: DateTime<Utc>,
+        status: String,
+        event_type: String,
+        agent_name: String,
+    },
+    /// A tool call; `ended_at` is `None` while it runs.
+    Tool {
+        at: DateTime<Utc>,
+        agent_name: String,
+        tool_name: String,
+        ended_at: Option<DateTime<Utc>>,
+        duration_ms: Option<i64>,
+    },
+    /// A notification sent, or attempted, about the session.
+    Notification {
+        at: DateTime<Utc>,
+        sink: String,
+        kind: String,
+        rule_name: Option<String>,
+        message: String,
+        /// "sent", "failed", or "dropped".
+        result: String,
+    },
+}
+
+impl TimelineEntry {
+    pub fn at(&self) -> DateTime<Utc> {
+        match self {
+            Self::Status { at, .. } | Self::Tool { at, .. } | Self::Notification { at, .. } => *at,
+        }
+    }
+}
+
+/// Per-tool timing stats for a session, derived from pre/post_tool_use pairs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+pub struct ToolStats {
+    pub tool_name: String,
+    pub invocations: i64,
+    pub completed: i64,
+    pub in_flight: i64,
+    pub total_ms: i64,
+    pub avg_ms: Option<f64>,
+    pub min_ms: Option<i64>,
+    pub max_ms: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct HealthResponse {
+    /// "ok", or "degraded" when the database did not answer.
+    pub status: String,
+    pub version: String,
+    /// Seconds since the server started.
+    #[serde(default)]
+    pub uptime_secs: u64,
+    /// Why the database check failed; absent when it passed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub database_error: Option<String>,
+    /// Size of SQLite's write-ahead log in bytes; absent for other databases.
+    pub wal_bytes: Option<u64>,
+    /// Size of the database file in bytes, the WAL aside; absent for
+    /// databases not kept in a file on this machine.
+    pub db_file_bytes: Option<u64>,
+    /// Bytes of the database in use, free pages aside; absent for databases
+    /// that can't tell.
+    pub db_bytes: Option<u64>,
+    /// `[storage] max_mb` in bytes; absent when there's no budget.
+    pub db_budget_bytes: Option<u64>,
+    /// Bytes left before the budget is reached and archived events are pruned.
+    pub db_headroom_bytes: Option<u64>,
+    /// Sessions this server is tracking, by status; remote ones aside.
+    #[serde(default)]
+    pub sessions: BTreeMap<String, u64>,
+    /// Clients connected to `/ws` and `/ws/sessions/:session_id`.
+    #[serde(default)]
+    pub ws_clients: u64,
+    /// When events were last stored; absent until the first of this run.
+    pub last_event_at: Option<DateTime<Utc>>,
+}
+
+/// Response for GET /api/build-info: which build of the server this is.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BuildInfo {
+    pub version: String,
+    /// Abbreviated git commit, or "unknown" when built outside a checkout.
+    pub commit: String,
+    pub built_at: Option<DateTime<Utc>>,
+    /// Target triple, e.g. "aarch64-apple-darwin".
+    pub target: String,
+    /// Cargo features compiled in, such as "postgres".
+    pub features: Vec<String>,
+}
+
+/// Response for GET /readyz, with 200 when every check passed and 503
+/// otherwise.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Readiness {
+    pub ready: bool,
+    /// "ok", or why not, for each of "database", "migrations" and "listener".
+    pub checks: BTreeMap<String, String>,
+}
+
+/// A key hooks post events with, from GET /api/admin/keys. The key itself is
+/// only ever returned by the POST that creates it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+pub struct ApiKey {
+    /// Stored as the `source` of each event posted with it.
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    /// Set once revoked; events sent with it are turned away from then on.
+    pub revoked_at: Option<DateTime<Utc>>,
+    /// Whether it also opens the `/api/admin` routes.
+    pub admin: bool,
+}
+
+/// Body for POST /api/admin/keys.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ApiKeyInput {
+    /// Such as the machine it is for: `laptop`, `dev-server`, `ci`.
+    pub name: String,
+    /// Let it manage keys and use the other `/api/admin` routes.
+    #[serde(default)]
+    pub admin: bool,
+}
+
+/// Response for POST /api/admin/keys.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CreatedApiKey {
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+    /// Sent as `X-Api-Key`; it can't be shown again.
+    pub key: String,
+}
+
+/// A hook payload that couldn't be parsed, kept so the hook can be debugged.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+pub struct DeadLetter {
+    pub id: i64,
+    /// Endpoint it was posted to.
+    pub path: String,
+    /// The body as received, with invalid UTF-8 replaced, cut off past 64 KiB.
+    pub body: String,
+    /// Why it was rejected.
+    pub error: String,
+    pub received_at: DateTime<Utc>,
+}
+
+/// Body of every error response.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ErrorResponse {
+    pub error: String,
+}
+
+/// Response for GET /api/settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Settings {
+    /// Do-not-disturb: hold back every notification until turned off.
+    pub dnd: bool,
+    /// From `[notifications.quiet_hours]` in config.toml.
+    pub quiet_hours: Option<QuietHours>,
+    /// Whether notifications are being held back right now, by either.
+    pub suppressed: bool,
+    /// Seconds between cleanup passes.
+    pub cleanup_interval_secs: u64,
+    /// Seconds a completed session stays listed; 0 keeps them until dismissed.
+    pub completed_ttl_secs: u64,
+}
+
+/// Body for PUT /api/settings; omitted fields are left unchanged.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SettingsUpdate {
+    pub dnd: Option<bool>,
+    /// At least 1.
+    pub cleanup_interval_secs: Option<u64>,
+    pub completed_ttl_secs: Option<u64>,
+}
+
+/// `[notifications.quiet_hours]` section of config.toml, in local time. A
+/// range that wraps past midnight (`23:00`–`08:00`) is fine.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct QuietHours {
+    #[serde(serialize_with = "serialize_hhmm", deserialize_with = "deserialize_hhmm")]
+    #[cfg_attr(feature = "openapi", schema(value_type = String, example = "23:00"))]
+    pub start: NaiveTime,
+    #[serde(serialize_with = "serialize_hhmm", deserialize_with = "deserialize_hhmm")]
+    #[cfg_attr(feature = "openapi", schema(value_type = String, example = "08:00"))]
+    pub end: NaiveTime,
+}
+
+impl QuietHours {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+fn deserialize_hhmm<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveTime, D::Error> {
+    let raw = String::deserialize(deserializer)?;
+    NaiveTime::parse_from_str(&raw, "%H:%M")
+        .map_err(|_| de::Error::custom(format!("invalid time '{raw}': expected HH:MM")))
+}
+
+fn serialize_hhmm<S: Serializer>(time: &NaiveTime, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format!("{:02}:{:02}", time.hour(), time.minute()))
+}
@@ -0,0 +1 @@
+ALTER TABLE sessions ADD COLUMN last_tool_call TEXT;
@@ -0,0 +1 @@
+ALTER TABLE usage ADD COLUMN model TEXT;
@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS notifications (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    sink TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    rule_id TEXT,
+    session_id TEXT,
+    project_name TEXT,
+    status TEXT,
+    message TEXT NOT NULL,
+    result TEXT NOT NULL,
+    error TEXT,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_notifications_created ON notifications(created_at);
@@ -0,0 +1 @@
+ALTER TABLE sessions ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+ALTER TABLE sessions ADD COLUMN muted INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE sessions ADD COLUMN hidden INTEGER NOT NULL DEFAULT 0;
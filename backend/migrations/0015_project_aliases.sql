@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS project_aliases (
+    project_path TEXT PRIMARY KEY,
+    display_name TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
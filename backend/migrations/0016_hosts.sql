@@ -0,0 +1,53 @@
+-- Sessions are unique per (host, session_id). SQLite can't alter a
+-- constraint, so rebuild the table. agents goes first: its foreign key on
+-- sessions(session_id) would no longer point at a unique column, and child
+-- rows are already removed alongside their session.
+CREATE TABLE agents_new (
+    id TEXT PRIMARY KEY,
+    session_id TEXT NOT NULL,
+    agent_name TEXT NOT NULL DEFAULT 'main',
+    parent_session_id TEXT,
+    status TEXT NOT NULL DEFAULT 'active',
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    UNIQUE (session_id, agent_name)
+);
+INSERT INTO agents_new (id, session_id, agent_name, parent_session_id, status, created_at, updated_at)
+SELECT id, session_id, agent_name, parent_session_id, status, created_at, updated_at FROM agents;
+DROP TABLE agents;
+ALTER TABLE agents_new RENAME TO agents;
+CREATE INDEX IF NOT EXISTS idx_agents_session_id ON agents(session_id);
+
+CREATE TABLE sessions_new (
+    id TEXT PRIMARY KEY,
+    host TEXT NOT NULL DEFAULT 'local',
+    session_id TEXT NOT NULL,
+    project_path TEXT NOT NULL DEFAULT '',
+    project_name TEXT NOT NULL DEFAULT 'unknown',
+    status TEXT NOT NULL DEFAULT 'active',
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    current_tool TEXT,
+    last_message TEXT,
+    last_assistant_text TEXT,
+    last_tool_call TEXT,
+    model TEXT,
+    pinned INTEGER NOT NULL DEFAULT 0,
+    muted INTEGER NOT NULL DEFAULT 0,
+    hidden INTEGER NOT NULL DEFAULT 0,
+    notes TEXT,
+    UNIQUE (host, session_id)
+);
+INSERT INTO sessions_new (
+    id, session_id, project_path, project_name, status, created_at, updated_at, current_tool,
+    last_message, last_assistant_text, last_tool_call, model, pinned, muted, hidden, notes
+)
+SELECT id, session_id, project_path, project_name, status, created_at, updated_at, current_tool,
+       last_message, last_assistant_text, last_tool_call, model, pinned, muted, hidden, notes
+FROM sessions;
+DROP TABLE sessions;
+ALTER TABLE sessions_new RENAME TO sessions;
+CREATE INDEX IF NOT EXISTS idx_sessions_status ON sessions(status);
+CREATE INDEX IF NOT EXISTS idx_sessions_session_id ON sessions(session_id);
+
+ALTER TABLE events ADD COLUMN host TEXT NOT NULL DEFAULT 'local';
@@ -0,0 +1,2 @@
+-- Git branch the session's working directory was on, as reported by the hook client.
+ALTER TABLE sessions ADD COLUMN git_branch TEXT;
@@ -0,0 +1,10 @@
+-- Per-day totals of sessions that cleanup has removed, so history outlives
+-- their events. Days are UTC dates, `YYYY-MM-DD`.
+CREATE TABLE IF NOT EXISTS daily_stats (
+    day TEXT NOT NULL,
+    host TEXT NOT NULL DEFAULT 'local',
+    sessions INTEGER NOT NULL DEFAULT 0,
+    active_seconds INTEGER NOT NULL DEFAULT 0,
+    completions INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (day, host)
+);
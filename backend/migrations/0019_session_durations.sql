@@ -0,0 +1,9 @@
+-- Start and end of each session cleanup has removed, for duration stats.
+CREATE TABLE IF NOT EXISTS session_durations (
+    host TEXT NOT NULL DEFAULT 'local',
+    session_id TEXT NOT NULL,
+    project_name TEXT NOT NULL DEFAULT 'unknown',
+    started_at TEXT NOT NULL,
+    ended_at TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_session_durations_ended_at ON session_durations(ended_at);
@@ -0,0 +1,10 @@
+-- Stretches each session cleanup has removed spent active, for concurrency
+-- stats.
+CREATE TABLE IF NOT EXISTS active_intervals (
+    host TEXT NOT NULL DEFAULT 'local',
+    session_id TEXT NOT NULL,
+    project_name TEXT NOT NULL DEFAULT 'unknown',
+    started_at TEXT NOT NULL,
+    ended_at TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_active_intervals_ended_at ON active_intervals(ended_at);
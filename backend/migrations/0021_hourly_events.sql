@@ -0,0 +1,8 @@
+-- Events per UTC hour of sessions that cleanup has removed, for the activity
+-- heatmap. Hours are RFC 3339, `YYYY-MM-DDTHH:00:00Z`.
+CREATE TABLE IF NOT EXISTS hourly_events (
+    hour TEXT NOT NULL,
+    host TEXT NOT NULL DEFAULT 'local',
+    events INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (hour, host)
+);
@@ -0,0 +1,12 @@
+-- Full-text index over event tool names and messages for GET /api/search,
+-- keyed by the event's rowid and kept in step by triggers.
+CREATE VIRTUAL TABLE IF NOT EXISTS events_fts USING fts5(tool_name, message);
+INSERT INTO events_fts (rowid, tool_name, message)
+SELECT rowid, json_extract(payload, '$.tool_name'), json_extract(payload, '$.message') FROM events;
+CREATE TRIGGER IF NOT EXISTS events_fts_insert AFTER INSERT ON events BEGIN
+    INSERT INTO events_fts (rowid, tool_name, message)
+    VALUES (new.rowid, json_extract(new.payload, '$.tool_name'), json_extract(new.payload, '$.message'));
+END;
+CREATE TRIGGER IF NOT EXISTS events_fts_delete AFTER DELETE ON events BEGIN
+    DELETE FROM events_fts WHERE rowid = old.rowid;
+END;
@@ -0,0 +1,4 @@
+-- When DELETE /api/sessions/:id moved the session to the trash, NULL while
+-- it is listed. Trashed sessions are erased once `[trash] retention_hours`
+-- has passed, and until then POST /api/sessions/:id/restore brings them back.
+ALTER TABLE sessions ADD COLUMN deleted_at TEXT;
@@ -0,0 +1,2 @@
+-- Events dropped from the session to keep it within `[events] max_per_session`.
+ALTER TABLE sessions ADD COLUMN pruned_events INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,8 @@
+-- Hook payloads that failed to parse, with the reason, for GET /api/dead-letters.
+CREATE TABLE IF NOT EXISTS dead_letters (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    path TEXT NOT NULL,
+    body TEXT NOT NULL,
+    error TEXT NOT NULL,
+    received_at TEXT NOT NULL
+);
@@ -0,0 +1,2 @@
+-- `X-Request-Id` of the request that posted the event.
+ALTER TABLE events ADD COLUMN request_id TEXT;
@@ -0,0 +1,12 @@
+-- Named keys hooks post events with, for POST /api/admin/keys. Only each
+-- key's SHA-256 digest is kept. A revoked key stays, so its name isn't reused.
+CREATE TABLE IF NOT EXISTS api_keys (
+    name TEXT PRIMARY KEY,
+    digest TEXT NOT NULL UNIQUE,
+    created_at TEXT NOT NULL,
+    revoked_at TEXT,
+    -- Whether it also opens the /api/admin routes.
+    admin INTEGER NOT NULL DEFAULT 0
+);
+-- Name of the API key the event was posted with.
+ALTER TABLE events ADD COLUMN source TEXT;
@@ -0,0 +1,39 @@
+-- Two hosts can share a session id, so the rows belonging to a session carry
+-- its host too. Existing rows take the host of the session with their id.
+CREATE TABLE agents_new (
+    id TEXT PRIMARY KEY,
+    host TEXT NOT NULL DEFAULT 'local',
+    session_id TEXT NOT NULL,
+    agent_name TEXT NOT NULL DEFAULT 'main',
+    parent_session_id TEXT,
+    status TEXT NOT NULL DEFAULT 'active',
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    UNIQUE (host, session_id, agent_name)
+);
+INSERT INTO agents_new (id, host, session_id, agent_name, parent_session_id, status, created_at, updated_at)
+SELECT a.id, COALESCE((SELECT s.host FROM sessions s WHERE s.session_id = a.session_id LIMIT 1), 'local'),
+       a.session_id, a.agent_name, a.parent_session_id, a.status, a.created_at, a.updated_at
+FROM agents a;
+DROP TABLE agents;
+ALTER TABLE agents_new RENAME TO agents;
+CREATE INDEX IF NOT EXISTS idx_agents_session_id ON agents(session_id);
+
+CREATE TABLE session_tags_new (
+    host TEXT NOT NULL DEFAULT 'local',
+    session_id TEXT NOT NULL,
+    tag TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    PRIMARY KEY (host, session_id, tag)
+);
+INSERT INTO session_tags_new (host, session_id, tag, created_at)
+SELECT COALESCE((SELECT s.host FROM sessions s WHERE s.session_id = t.session_id LIMIT 1), 'local'),
+       t.session_id, t.tag, t.created_at
+FROM session_tags t;
+DROP TABLE session_tags;
+ALTER TABLE session_tags_new RENAME TO session_tags;
+
+ALTER TABLE tool_invocations ADD COLUMN host TEXT NOT NULL DEFAULT 'local';
+UPDATE tool_invocations SET host = COALESCE(
+    (SELECT s.host FROM sessions s WHERE s.session_id = tool_invocations.session_id LIMIT 1), 'local'
+);
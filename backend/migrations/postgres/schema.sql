@@ -0,0 +1,217 @@
+-- Postgres schema, applied on every start. Statements must be idempotent.
+-- Mirrors the SQLite schema after every migration, with native types.
+CREATE TABLE IF NOT EXISTS sessions (
+    id TEXT PRIMARY KEY,
+    host TEXT NOT NULL DEFAULT 'local',
+    session_id TEXT NOT NULL,
+    project_path TEXT NOT NULL DEFAULT '',
+    project_name TEXT NOT NULL DEFAULT 'unknown',
+    status TEXT NOT NULL DEFAULT 'active',
+    created_at TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL,
+    current_tool TEXT,
+    last_message TEXT,
+    last_assistant_text TEXT,
+    last_tool_call TEXT,
+    model TEXT,
+    pinned BOOLEAN NOT NULL DEFAULT FALSE,
+    muted BOOLEAN NOT NULL DEFAULT FALSE,
+    hidden BOOLEAN NOT NULL DEFAULT FALSE,
+    notes TEXT,
+    git_branch TEXT,
+    deleted_at TIMESTAMPTZ,
+    pruned_events BIGINT NOT NULL DEFAULT 0,
+    UNIQUE (host, session_id)
+);
+
+CREATE TABLE IF NOT EXISTS agents (
+    id TEXT PRIMARY KEY,
+    host TEXT NOT NULL DEFAULT 'local',
+    session_id TEXT NOT NULL,
+    agent_name TEXT NOT NULL DEFAULT 'main',
+    parent_session_id TEXT,
+    status TEXT NOT NULL DEFAULT 'active',
+    created_at TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL,
+    UNIQUE (host, session_id, agent_name)
+);
+
+-- `seq` plays the part of SQLite's rowid: insertion order, for `/ws` replay.
+-- `search` is the full-text index over tool names and messages.
+CREATE TABLE IF NOT EXISTS events (
+    seq BIGINT GENERATED ALWAYS AS IDENTITY,
+    id TEXT PRIMARY KEY,
+    host TEXT NOT NULL DEFAULT 'local',
+    session_id TEXT NOT NULL,
+    agent_name TEXT,
+    event_type TEXT NOT NULL,
+    payload JSONB NOT NULL DEFAULT '{}',
+    timestamp TIMESTAMPTZ NOT NULL,
+    request_id TEXT,
+    source TEXT,
+    search TSVECTOR GENERATED ALWAYS AS (
+        to_tsvector('simple', coalesce(payload->>'tool_name', '') || ' ' || coalesce(payload->>'message', ''))
+    ) STORED
+);
+
+CREATE TABLE IF NOT EXISTS tool_invocations (
+    id TEXT PRIMARY KEY,
+    host TEXT NOT NULL DEFAULT 'local',
+    session_id TEXT NOT NULL,
+    agent_name TEXT NOT NULL DEFAULT 'main',
+    tool_name TEXT NOT NULL,
+    tool_use_id TEXT,
+    started_at TIMESTAMPTZ NOT NULL,
+    ended_at TIMESTAMPTZ,
+    duration_ms BIGINT
+);
+
+CREATE TABLE IF NOT EXISTS usage (
+    id TEXT PRIMARY KEY,
+    session_id TEXT NOT NULL,
+    agent_name TEXT NOT NULL DEFAULT 'main',
+    message_id TEXT UNIQUE,
+    model TEXT,
+    input_tokens BIGINT NOT NULL DEFAULT 0,
+    output_tokens BIGINT NOT NULL DEFAULT 0,
+    cache_creation_input_tokens BIGINT NOT NULL DEFAULT 0,
+    cache_read_input_tokens BIGINT NOT NULL DEFAULT 0,
+    recorded_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS alert_rules (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    conditions JSONB NOT NULL,
+    sinks JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS settings (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS notifications (
+    id BIGINT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+    sink TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    rule_id TEXT,
+    session_id TEXT,
+    project_name TEXT,
+    status TEXT,
+    message TEXT NOT NULL,
+    result TEXT NOT NULL,
+    error TEXT,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS session_tags (
+    host TEXT NOT NULL DEFAULT 'local',
+    session_id TEXT NOT NULL,
+    tag TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (host, session_id, tag)
+);
+
+CREATE TABLE IF NOT EXISTS project_aliases (
+    project_path TEXT PRIMARY KEY,
+    display_name TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS daily_stats (
+    day DATE NOT NULL,
+    host TEXT NOT NULL DEFAULT 'local',
+    sessions BIGINT NOT NULL DEFAULT 0,
+    active_seconds BIGINT NOT NULL DEFAULT 0,
+    completions BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (day, host)
+);
+
+CREATE TABLE IF NOT EXISTS session_durations (
+    host TEXT NOT NULL DEFAULT 'local',
+    session_id TEXT NOT NULL,
+    project_name TEXT NOT NULL DEFAULT 'unknown',
+    started_at TIMESTAMPTZ NOT NULL,
+    ended_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS active_intervals (
+    host TEXT NOT NULL DEFAULT 'local',
+    session_id TEXT NOT NULL,
+    project_name TEXT NOT NULL DEFAULT 'unknown',
+    started_at TIMESTAMPTZ NOT NULL,
+    ended_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS hourly_events (
+    hour TIMESTAMPTZ NOT NULL,
+    host TEXT NOT NULL DEFAULT 'local',
+    events BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (hour, host)
+);
+
+CREATE TABLE IF NOT EXISTS dead_letters (
+    id BIGINT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+    path TEXT NOT NULL,
+    body TEXT NOT NULL,
+    error TEXT NOT NULL,
+    received_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS api_keys (
+    name TEXT PRIMARY KEY,
+    digest TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL,
+    revoked_at TIMESTAMPTZ,
+    admin BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+-- Columns added after a table was first created, for databases that predate them.
+ALTER TABLE sessions ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+ALTER TABLE sessions ADD COLUMN IF NOT EXISTS pruned_events BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE events ADD COLUMN IF NOT EXISTS source TEXT;
+
+-- The rows belonging to a session carry its host, and agents and tags are
+-- unique per host. Existing rows take the host of the session with their id.
+DO $$
+BEGIN
+    IF NOT EXISTS (
+        SELECT 1 FROM information_schema.columns WHERE table_name = 'agents' AND column_name = 'host'
+    ) THEN
+        ALTER TABLE agents ADD COLUMN host TEXT NOT NULL DEFAULT 'local';
+        UPDATE agents a SET host = s.host FROM sessions s WHERE s.session_id = a.session_id;
+        ALTER TABLE agents DROP CONSTRAINT agents_session_id_agent_name_key;
+        ALTER TABLE agents ADD UNIQUE (host, session_id, agent_name);
+    END IF;
+    IF NOT EXISTS (
+        SELECT 1 FROM information_schema.columns WHERE table_name = 'session_tags' AND column_name = 'host'
+    ) THEN
+        ALTER TABLE session_tags ADD COLUMN host TEXT NOT NULL DEFAULT 'local';
+        UPDATE session_tags t SET host = s.host FROM sessions s WHERE s.session_id = t.session_id;
+        ALTER TABLE session_tags DROP CONSTRAINT session_tags_pkey;
+        ALTER TABLE session_tags ADD PRIMARY KEY (host, session_id, tag);
+    END IF;
+    IF NOT EXISTS (
+        SELECT 1 FROM information_schema.columns WHERE table_name = 'tool_invocations' AND column_name = 'host'
+    ) THEN
+        ALTER TABLE tool_invocations ADD COLUMN host TEXT NOT NULL DEFAULT 'local';
+        UPDATE tool_invocations t SET host = s.host FROM sessions s WHERE s.session_id = t.session_id;
+    END IF;
+END $$;
+
+CREATE INDEX IF NOT EXISTS idx_sessions_status ON sessions(status);
+CREATE INDEX IF NOT EXISTS idx_sessions_session_id ON sessions(session_id);
+CREATE INDEX IF NOT EXISTS idx_agents_session_id ON agents(session_id);
+CREATE INDEX IF NOT EXISTS idx_events_session_id ON events(session_id, seq);
+CREATE INDEX IF NOT EXISTS idx_events_search ON events USING GIN (search);
+CREATE INDEX IF NOT EXISTS idx_tool_invocations_session_id ON tool_invocations(session_id);
+CREATE INDEX IF NOT EXISTS idx_usage_session_id ON usage(session_id);
+CREATE INDEX IF NOT EXISTS idx_notifications_created ON notifications(created_at);
+CREATE INDEX IF NOT EXISTS idx_session_durations_ended_at ON session_durations(ended_at);
+CREATE INDEX IF NOT EXISTS idx_active_intervals_ended_at ON active_intervals(ended_at);
@@ -0,0 +1,130 @@
+//! Aggregator mode: poll other monitors and merge their sessions into this
+//! one's view, for teams with several dev boxes.
+//!
+//! ```toml
+//! [[aggregator.sources]]
+//! name = "devbox"
+//! url = "http://devbox.local:9147"
+//! token = "..."   # if the source has one
+//! ```
+//!
+//! Remote sessions carry `source` set to the source's name. They are read-only
+//! here: acknowledging or pinning one has to happen on its own monitor.
+
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use tracing::{info, warn};
+
+use crate::{api::AppState, auth, models::SessionWithAgents};
+
+/// `[aggregator]` section of config.toml.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AggregatorConfig {
+    pub sources: Vec<SourceConfig>,
+    /// Seconds between polls of each source.
+    pub poll_secs: u64,
+}
+
+impl Default for AggregatorConfig {
+    fn default() -> Self {
+        Self {
+            sources: Vec::new(),
+            poll_secs: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SourceConfig {
+    /// Label the source's sessions are namespaced under.
+    pub name: String,
+    /// Base URL of the remote monitor.
+    pub url: String,
+    /// Token the remote monitor requires, if any.
+    pub token: Option<String>,
+}
+
+/// Latest sessions from each source, keyed by source name.
+#[derive(Clone, Default)]
+pub struct RemoteSessions(Arc<RwLock<BTreeMap<String, Vec<SessionWithAgents>>>>);
+
+impl RemoteSessions {
+    /// Every remote session, in source order.
+    pub fn sessions(&self) -> Vec<SessionWithAgents> {
+        let sources = self.0.read().unwrap_or_else(|e| e.into_inner());
+        sources.values().flatten().cloned().collect()
+    }
+
+    /// Replace one source's sessions. Returns whether anything changed.
+    fn replace(&self, source: &str, sessions: Vec<SessionWithAgents>) -> bool {
+        let mut sources = self.0.write().unwrap_or_else(|e| e.into_inner());
+        let encode = |list: &[SessionWithAgents]| serde_json::to_string(list).ok();
+        let changed = sources.get(source).map(|old| encode(old)) != Some(encode(&sessions));
+        sources.insert(source.to_string(), sessions);
+        changed
+    }
+}
+
+/// Start one poller per configured source.
+pub fn spawn(state: &AppState) {
+    let config = &state.config.aggregator;
+    let interval = Duration::from_secs(config.poll_secs.max(1));
+    for source in &config.sources {
+        info!("Aggregating sessions from '{}' at {}", source.name, source.url);
+        tokio::spawn(poll(state.clone(), source.clone(), interval));
+    }
+}
+
+async fn poll(state: AppState, source: SourceConfig, interval: Duration) {
+    let client = auth::http_client(Duration::from_secs(10), source.token.as_deref(), None);
+    let url = format!("{}/api/sessions", source.url.trim_end_matches('/'));
+    let mut reachable = true;
+    let mut ticker = tokio::time::interval(interval);
+
+    loop {
+        ticker.tick().await;
+        let sessions = match fetch(&client, &url).await {
+            Ok(sessions) => {
+                if !reachable {
+                    info!("Source '{}' is reachable again", source.name);
+                    reachable = true;
+                }
+                sessions
+            }
+            Err(e) => {
+                if reachable {
+                    warn!("Source '{}' unreachable: {e}", source.name);
+                    reachable = false;
+                }
+                // Don't keep showing sessions nobody can vouch for.
+                Vec::new()
+            }
+        };
+        let sessions = sessions
+            .into_iter()
+            // Sessions the remote aggregated itself would echo back and forth.
+            .filter(|s| s.source.is_none())
+            .map(|mut s| {
+                s.source = Some(source.name.clone());
+                s
+            })
+            .collect();
+        if state.remote.replace(&source.name, sessions) {
+            state.broadcast_sessions();
+        }
+    }
+}
+
+async fn fetch(client: &reqwest::Client, url: &str) -> Result<Vec<SessionWithAgents>> {
+    let response = client.get(url).send().await.map_err(|e| anyhow::anyhow!(e.without_url()))?;
+    if !response.status().is_success() {
+        bail!("server returned {}", response.status());
+    }
+    Ok(response.json().await?)
+}
@@ -0,0 +1,165 @@
+//! Plain-text announcements of session state changes, for screen readers, TTS
+//! scripts, and other assistive tooling that can't make sense of JSON blobs.
+//!
+//! Consecutive snapshots are diffed into short sentences such as
+//! "Project api-server now needs permission for Bash", phrased through the
+//! template catalog. Served as SSE at `/api/stream/text` and over WebSocket
+//! with `/ws?format=text`.
+
+use axum::{
+    extract::{Query, State},
+    response::sse::{Event, KeepAlive, Sse},
+};
+use futures::{stream, Stream, StreamExt};
+use serde::Deserialize;
+use std::{collections::HashMap, collections::VecDeque, convert::Infallible};
+use tokio::sync::broadcast;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    api::{AppState, SessionSnapshot},
+    models::SessionWithAgents,
+    templates::Catalog,
+};
+
+/// How much gets announced.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Verbosity {
+    /// Only sessions that now need you (input or permission).
+    Brief,
+    /// Also starts, finished turns, resumed work, and ends.
+    #[default]
+    Normal,
+    /// Also every change of the running tool.
+    Verbose,
+}
+
+/// `[text_stream]` section of config.toml.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct TextStreamConfig {
+    pub verbosity: Verbosity,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TextStreamQuery {
+    /// Defaults to `[text_stream] verbosity` from config.toml.
+    pub verbosity: Option<Verbosity>,
+}
+
+/// Sentences describing how `next` differs from `prev`. Muted sessions are
+/// left out.
+pub fn describe_changes(
+    prev: &[SessionWithAgents],
+    next: &[SessionWithAgents],
+    verbosity: Verbosity,
+    catalog: &Catalog,
+) -> Vec<String> {
+    let before: HashMap<(&str, &str), &SessionWithAgents> =
+        prev.iter().map(|s| ((s.host.as_str(), s.session_id.as_str()), s)).collect();
+    let mut lines = Vec::new();
+
+    for session in next.iter().filter(|s| !s.muted) {
+        let project = session.project_name.as_str();
+        let tool = session.current_tool.as_deref();
+        let old = before.get(&(session.host.as_str(), session.session_id.as_str()));
+
+        let status_changed = old.is_none_or(|o| o.status != session.status);
+        if status_changed {
+            let key = match (old, session.status.as_str()) {
+                (_, "waiting_input") => Some("announce.waiting_input"),
+                (_, "needs_permission") if tool.is_some() => Some("announce.needs_permission_tool"),
+                (_, "needs_permission") => Some("announce.needs_permission"),
+                (None, _) if verbosity >= Verbosity::Normal => Some("announce.started"),
+                (Some(_), "active") if verbosity >= Verbosity::Normal => Some("announce.active"),
+                (Some(_), "idle") if verbosity >= Verbosity::Normal => Some("announce.idle"),
+                // Pinned sessions stay listed once they end.
+                (Some(_), "completed") if verbosity >= Verbosity::Normal => Some("announce.ended"),
+                _ => None,
+            };
+            if let Some(key) = key {
+                lines.push(catalog.render(key, &[("project", project), ("tool", tool.unwrap_or(""))]));
+            }
+        } else if verbosity >= Verbosity::Verbose {
+            if let (Some(tool), Some(old)) = (tool, old) {
+                if old.current_tool.as_deref() != Some(tool) {
+                    lines.push(catalog.render("announce.tool", &[("project", project), ("tool", tool)]));
+                }
+            }
+        }
+    }
+
+    if verbosity >= Verbosity::Normal {
+        let still_here: Vec<(&str, &str)> = next.iter().map(|s| (s.host.as_str(), s.session_id.as_str())).collect();
+        let gone = prev.iter().filter(|s| {
+            !s.muted && s.status != "completed" && !still_here.contains(&(s.host.as_str(), s.session_id.as_str()))
+        });
+        for gone in gone {
+            lines.push(catalog.render("announce.ended", &[("project", &gone.project_name)]));
+        }
+    }
+
+    lines
+}
+
+/// Stream of announcement lines, starting from `initial` as the known state.
+pub fn announcements(
+    state: AppState,
+    initial: SessionSnapshot,
+    verbosity: Verbosity,
+) -> impl Stream<Item = String> {
+    let rx = state.tx.subscribe();
+    stream::unfold(
+        (rx, initial, VecDeque::<String>::new()),
+        move |(mut rx, mut prev, mut pending)| {
+            let templates = state.templates.clone();
+            async move {
+                loop {
+                    if let Some(line) = pending.pop_front() {
+                        return Some((line, (rx, prev, pending)));
+                    }
+                    match rx.recv().await {
+                        Ok(next) => {
+                            pending.extend(describe_changes(&prev, &next, verbosity, &templates));
+                            prev = next;
+                        }
+                        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(broadcast::error::RecvError::Closed) => return None,
+                    }
+                }
+            }
+        },
+    )
+}
+
+/// Current sessions, used as the baseline a new listener diffs against.
+pub async fn baseline(state: &AppState) -> SessionSnapshot {
+    match state.active_sessions().await {
+        Ok(sessions) => sessions.into(),
+        Err(e) => {
+            tracing::warn!("Failed to fetch sessions for text stream: {e}");
+            SessionSnapshot::default()
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/stream/text",
+    tag = "stream",
+    params(TextStreamQuery),
+    responses(
+        (status = 200, description = "One announcement per event", content_type = "text/event-stream", body = String)
+    )
+)]
+pub async fn sse_handler(
+    State(state): State<AppState>,
+    Query(query): Query<TextStreamQuery>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let verbosity = query.verbosity.unwrap_or(state.config.text_stream.verbosity);
+    let initial = baseline(&state).await;
+    let lines = announcements(state, initial, verbosity);
+    Sse::new(lines.map(|line| Ok(Event::default().data(line)))).keep_alive(KeepAlive::default())
+}
@@ -1,54 +1,604 @@
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
     response::IntoResponse,
     Json,
 };
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use serde_json::json;
-use tokio::sync::broadcast;
+use sha2::{Digest, Sha256};
+use std::{
+    borrow::{Borrow, Cow},
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap, VecDeque},
+    ops::Deref,
+    sync::{
+        atomic::{self, AtomicBool},
+        Arc, Mutex, MutexGuard,
+    },
+    time::{Duration, Instant},
+};
+use tokio::sync::{broadcast, Notify};
 use tracing::{info, warn};
+use utoipa::IntoParams;
 
 use crate::{
-    db,
-    models::{HealthResponse, HookEvent},
+    aggregator::RemoteSessions,
+    build_info,
+    cleanup,
+    config::Config,
+    dead_letters::HookJson,
+    ingest::{EventWriter, Origin},
+    keys::{Keys, PresentedKey},
+    models::{
+        ErrorResponse, HealthResponse, HookEvent, MuteRequest, Readiness, SessionEvent, SessionSort, SessionUpdate,
+        SessionUsage, SessionWithAgents, SortOrder, TagsRequest, TimelineEntry, ToolStats,
+    },
+    paths,
+    projects,
+    relay::Relay,
+    request_id::RequestId,
+    stats,
+    store::{SessionPage, Store},
+    templates::Catalog,
+    transcript::TranscriptTailer,
+    ws::WsMetrics,
 };
 
+/// Snapshot of active sessions fanned out to WS clients. Each client serializes
+/// it itself so it can apply its own subscription filter.
+#[derive(Debug, Clone, Default)]
+pub struct SessionSnapshot {
+    /// Position in the broadcast sequence; 0 for lists fetched outside it.
+    pub seq: u64,
+    /// When it was broadcast, for lists that were.
+    pub published: Option<Instant>,
+    sessions: Arc<Vec<SessionWithAgents>>,
+}
+
+impl Deref for SessionSnapshot {
+    type Target = [SessionWithAgents];
+
+    fn deref(&self) -> &Self::Target {
+        &self.sessions
+    }
+}
+
+impl From<Vec<SessionWithAgents>> for SessionSnapshot {
+    fn from(sessions: Vec<SessionWithAgents>) -> Self {
+        Self { seq: 0, published: None, sessions: Arc::new(sessions) }
+    }
+}
+
+/// Broadcasts kept for clients resuming with `?since_seq=`.
+const HISTORY_LEN: usize = 128;
+
+/// The most recent broadcasts, oldest first, for replaying to clients that
+/// reconnect.
+#[derive(Clone)]
+pub struct BroadcastHistory(Arc<Mutex<History>>);
+
+struct History {
+    seq: u64,
+    /// Broadcasts since startup.
+    published: u64,
+    snapshots: VecDeque<SessionSnapshot>,
+}
+
+impl Default for BroadcastHistory {
+    fn default() -> Self {
+        // Count from the clock so numbers a client kept from before a restart
+        // are not mistaken for current ones.
+        let seq = Utc::now().timestamp_millis().max(0) as u64;
+        Self(Arc::new(Mutex::new(History { seq, published: 0, snapshots: VecDeque::new() })))
+    }
+}
+
+impl BroadcastHistory {
+    fn lock(&self) -> MutexGuard<'_, History> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Number `sessions` as the next broadcast, keep it and send it. Sending
+    /// under the lock keeps the channel in the same order as the history.
+    fn publish(&self, tx: &broadcast::Sender<SessionSnapshot>, sessions: Vec<SessionWithAgents>) {
+        let mut history = self.lock();
+        history.seq += 1;
+        history.published += 1;
+        let snapshot =
+            SessionSnapshot { seq: history.seq, published: Some(Instant::now()), sessions: Arc::new(sessions) };
+        if history.snapshots.len() == HISTORY_LEN {
+            history.snapshots.pop_front();
+        }
+        history.snapshots.push_back(snapshot.clone());
+        // Ignore the error: it means no receivers are connected.
+        let _ = tx.send(snapshot);
+    }
+
+    /// Broadcasts published since startup.
+    pub fn published(&self) -> u64 {
+        self.lock().published
+    }
+
+    /// The last broadcast, if any since startup.
+    pub fn latest(&self) -> Option<SessionSnapshot> {
+        self.lock().snapshots.back().cloned()
+    }
+
+    /// Broadcast `seq` followed by every later one, or `None` if `seq` is
+    /// no longer kept.
+    pub fn since(&self, seq: u64) -> Option<Vec<SessionSnapshot>> {
+        let history = self.lock();
+        let first = history.snapshots.front()?.seq;
+        if seq < first || seq > history.seq {
+            return None;
+        }
+        Some(history.snapshots.iter().skip((seq - first) as usize).cloned().collect())
+    }
+}
+
 #[derive(Clone)]
 pub struct AppState {
-    pub pool: sqlx::SqlitePool,
-    pub tx: broadcast::Sender<String>,
+    pub store: Arc<dyn Store>,
+    pub tx: broadcast::Sender<SessionSnapshot>,
+    pub config: Arc<Config>,
+    pub templates: Arc<Catalog>,
+    /// Present when transcript tailing is enabled in config.
+    pub transcripts: Option<TranscriptTailer>,
+    /// Present when `[relay]` is configured.
+    pub relay: Option<Relay>,
+    /// Sessions polled from `[aggregator]` sources.
+    pub remote: RemoteSessions,
+    /// Queue that events are written through.
+    pub events: EventWriter,
+    /// Recent broadcasts, for `/ws?since_seq=`.
+    pub history: BroadcastHistory,
+    pub ws: WsMetrics,
+    /// Cleanup schedule, as changed through `/api/settings`.
+    pub cleanup: cleanup::Schedule,
+    /// API keys events are checked against.
+    pub keys: Keys,
+    pub started: Instant,
+    /// Set once the HTTP listener is bound.
+    pub listening: Arc<AtomicBool>,
+    /// Wakes the task started by `spawn_broadcaster`.
+    broadcast_requested: Arc<Notify>,
 }
 
 impl AppState {
-    pub fn new(pool: sqlx::SqlitePool, tx: broadcast::Sender<String>) -> Self {
-        Self { pool, tx }
+    pub fn new(
+        store: Arc<dyn Store>,
+        tx: broadcast::Sender<SessionSnapshot>,
+        config: Arc<Config>,
+        templates: Arc<Catalog>,
+        transcripts: Option<TranscriptTailer>,
+        relay: Option<Relay>,
+        events: EventWriter,
+    ) -> Self {
+        let cleanup = cleanup::Schedule::new(config.cleanup.clone());
+        Self {
+            store,
+            tx,
+            config,
+            templates,
+            transcripts,
+            relay,
+            events,
+            remote: RemoteSessions::default(),
+            history: BroadcastHistory::default(),
+            ws: WsMetrics::default(),
+            cleanup,
+            keys: Keys::default(),
+            started: Instant::now(),
+            listening: Arc::default(),
+            broadcast_requested: Arc::new(Notify::new()),
+        }
     }
 
-    /// Fetch active sessions and broadcast to all WS clients.
-    pub async fn broadcast_sessions(&self) {
-        match db::get_active_sessions(&self.pool).await {
-            Ok(sessions) => match serde_json::to_string(&sessions) {
-                Ok(json) => {
-                    // Ignore the error: it means no receivers are connected.
-                    let _ = self.tx.send(json);
-                }
-                Err(e) => warn!("Failed to serialize sessions: {e}"),
-            },
+    /// Active sessions as served to clients, with derived fields filled in.
+    pub async fn active_sessions(&self) -> anyhow::Result<Vec<SessionWithAgents>> {
+        let mut sessions = self.store.get_active_sessions().await?;
+        self.fill_derived(&mut sessions).await?;
+        sessions.extend(self.remote.sessions());
+
+        Ok(sessions)
+    }
+
+    /// Apply project aliases and fill in cost and status text.
+    pub async fn fill_derived(&self, sessions: &mut [SessionWithAgents]) -> anyhow::Result<()> {
+        projects::apply_aliases(self.store.as_ref(), sessions).await?;
+
+        let session_ids: Vec<String> = sessions.iter().map(|s| s.session_id.clone()).collect();
+        let mut costs: HashMap<String, f64> = HashMap::new();
+        for row in self.store.get_usage_for_sessions(&session_ids).await? {
+            *costs.entry(row.session_id).or_default() += self.config.pricing.cost(row.model.as_deref(), &row.usage);
+        }
+        for session in sessions {
+            session.estimated_cost_usd = costs.get(&session.session_id).copied().unwrap_or(0.0);
+            session.status_text = self
+                .templates
+                .status_text(&session.status, session.current_tool.as_deref());
+        }
+        Ok(())
+    }
+
+    /// Ask for the active sessions to be broadcast to all WS clients. Calls
+    /// in quick succession are coalesced, see `spawn_broadcaster`.
+    pub fn broadcast_sessions(&self) {
+        self.broadcast_requested.notify_one();
+    }
+
+    /// Fetch active sessions and broadcast them now.
+    #[tracing::instrument(name = "broadcast", level = "debug", skip_all)]
+    async fn publish_sessions(&self) {
+        // Events already written are in the sessions read next.
+        let written = self.events.take_written();
+        match self.active_sessions().await {
+            Ok(sessions) => {
+                self.history.publish(&self.tx, sessions);
+                self.events.broadcast(&written);
+            }
             Err(e) => warn!("Failed to fetch sessions for broadcast: {e}"),
         }
     }
 }
 
-pub async fn health() -> impl IntoResponse {
-    Json(HealthResponse {
-        status: "ok",
-        version: "0.1.0",
-    })
+/// Minimum gap between broadcasts.
+const BROADCAST_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Serve `broadcast_sessions` requests: the first in a burst goes out at
+/// once, the rest fold into one sent `BROADCAST_INTERVAL` later. A request
+/// made while a broadcast is being read leaves a permit behind, so the last
+/// state is always sent.
+pub fn spawn_broadcaster(state: &AppState) {
+    let state = state.clone();
+    tokio::spawn(async move {
+        loop {
+            state.broadcast_requested.notified().await;
+            state.publish_sessions().await;
+            tokio::time::sleep(BROADCAST_INTERVAL).await;
+        }
+    });
+}
+
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "system",
+    responses(
+        (status = 200, body = HealthResponse),
+        (status = 503, description = "The database did not answer; `database_error` says why", body = HealthResponse)
+    )
+)]
+pub async fn health(State(state): State<AppState>) -> impl IntoResponse {
+    // Listing the sessions doubles as the database check.
+    let checked = async {
+        anyhow::Ok((state.store.get_active_sessions().await?, state.store.used_size().await?))
+    };
+    let (sessions, db_bytes, database_error) = match checked.await {
+        Ok((sessions, used)) => (sessions, used, None),
+        Err(e) => {
+            warn!("health error: {e}");
+            (Vec::new(), None, Some(e.to_string()))
+        }
+    };
+    let mut session_counts = BTreeMap::new();
+    for session in &sessions {
+        *session_counts.entry(session.status.clone()).or_insert(0) += 1;
+    }
+    let db_budget_bytes = state.config.storage.budget();
+    let code = if database_error.is_none() { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    let health = HealthResponse {
+        status: if database_error.is_none() { "ok" } else { "degraded" }.to_string(),
+        version: build_info::VERSION.to_string(),
+        uptime_secs: state.started.elapsed().as_secs(),
+        database_error,
+        wal_bytes: state.store.wal_size(),
+        db_file_bytes: state.store.file_size(),
+        db_bytes,
+        db_budget_bytes,
+        db_headroom_bytes: db_budget_bytes.zip(db_bytes).map(|(budget, used)| budget.saturating_sub(used)),
+        sessions: session_counts,
+        ws_clients: state.ws.count() as u64,
+        last_event_at: state.events.last_written(),
+    };
+    (code, Json(health))
+}
+
+/// Liveness: the process is up and answering, whatever state it's in.
+#[utoipa::path(get, path = "/livez", tag = "system", responses((status = 200, description = "Alive", body = String)))]
+pub async fn livez() -> &'static str {
+    "ok"
+}
+
+/// Readiness: the database answers with the current schema and the listener
+/// is bound, so requests can be served.
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "system",
+    responses(
+        (status = 200, description = "Ready", body = Readiness),
+        (status = 503, description = "Not ready; the failing checks say why", body = Readiness)
+    )
+)]
+pub async fn readyz(State(state): State<AppState>) -> impl IntoResponse {
+    let ok = || "ok".to_string();
+    let (database, migrations) = match state.store.pending_migrations().await {
+        Ok(0) => (ok(), ok()),
+        Ok(pending) => (ok(), format!("{pending} pending")),
+        Err(e) => (e.to_string(), "unknown".to_string()),
+    };
+    let listener = if state.listening.load(atomic::Ordering::Relaxed) { ok() } else { "not bound".to_string() };
+    let checks = BTreeMap::from([
+        ("database".to_string(), database),
+        ("migrations".to_string(), migrations),
+        ("listener".to_string(), listener),
+    ]);
+    let ready = checks.values().all(|check| check == "ok");
+    let code = if ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (code, Json(Readiness { ready, checks }))
+}
+
+/// `?host=` accepted by every listing endpoint; unset means all hosts. The
+/// routes acting on one session take it too, for a session id more than one
+/// host has; unset, they act on the session on every host.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct HostFilter {
+    /// Only sessions on this host.
+    pub host: Option<String>,
+}
+
+impl HostFilter {
+    pub fn allows(&self, host: &str) -> bool {
+        self.host.as_deref().is_none_or(|h| h == host)
+    }
+}
+
+/// Host, tag and visibility filter for the GraphQL and gRPC session lists.
+pub struct SessionFilter {
+    pub host: Option<String>,
+    /// Sessions carrying any of these; empty matches all.
+    pub tags: Vec<String>,
+    /// Keep sessions muted with `hide`.
+    pub include_hidden: bool,
+}
+
+impl SessionFilter {
+    pub fn apply(&self, sessions: &[SessionWithAgents]) -> Vec<SessionWithAgents> {
+        sessions
+            .iter()
+            .filter(|s| {
+                (self.include_hidden || !s.hidden)
+                    && s.has_any_tag(&self.tags)
+                    && self.host.as_ref().is_none_or(|h| *h == s.host)
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SessionsQuery {
+    /// Comma-separated tags; sessions carrying any of them are returned.
+    pub tag: Option<String>,
+    /// Comma-separated statuses, e.g. `waiting_input,needs_permission`.
+    pub status: Option<String>,
+    /// Only sessions in this project, by name or path.
+    pub project: Option<String>,
+    /// Only sessions updated at or after this: `YYYY-MM-DD` or RFC 3339.
+    pub since: Option<String>,
+    #[serde(default)]
+    pub sort: SessionSort,
+    /// Defaults to `desc` for `created_at` and `updated_at`, `asc` otherwise.
+    pub order: Option<SortOrder>,
+    /// Page size, at most 500; every matching session when unset.
+    pub limit: Option<usize>,
+    /// Resume after the page that returned this `X-Next-Cursor`.
+    pub cursor: Option<String>,
+}
+
+/// Response headers of a paged GET /api/sessions.
+pub const TOTAL_COUNT_HEADER: &str = "x-total-count";
+pub const NEXT_CURSOR_HEADER: &str = "x-next-cursor";
+
+/// Most sessions one page of GET /api/sessions holds.
+const MAX_SESSIONS_PAGE: usize = 500;
+
+fn default_order(sort: SessionSort) -> SortOrder {
+    match sort {
+        SessionSort::CreatedAt | SessionSort::UpdatedAt => SortOrder::Desc,
+        _ => SortOrder::Asc,
+    }
+}
+
+/// Where a session falls in a sorted list. A `?cursor=` carries the key of
+/// the last session on a page, so the next page starts after it even if
+/// that session has gone since.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SortKey<'a> {
+    pub pinned: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub status: Cow<'a, str>,
+    pub project_name: Cow<'a, str>,
+    pub host: Cow<'a, str>,
+    pub session_id: Cow<'a, str>,
+}
+
+impl<'a> SortKey<'a> {
+    fn of(session: &'a SessionWithAgents) -> Self {
+        Self {
+            pinned: session.pinned,
+            created_at: session.created_at,
+            updated_at: session.updated_at,
+            status: Cow::Borrowed(&session.status),
+            project_name: Cow::Borrowed(&session.project_name),
+            host: Cow::Borrowed(&session.host),
+            session_id: Cow::Borrowed(&session.session_id),
+        }
+    }
+
+    fn cursor(&self) -> String {
+        hex::encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    fn from_cursor(cursor: &str) -> Option<SortKey<'static>> {
+        serde_json::from_slice(&hex::decode(cursor).ok()?).ok()
+    }
+
+    /// Pinned first, then by `sort`; host and session ID break ties so the
+    /// order is total and pages never overlap.
+    fn cmp(&self, other: &SortKey, sort: SessionSort, order: SortOrder) -> Ordering {
+        let key = match sort {
+            SessionSort::CreatedAt => self.created_at.cmp(&other.created_at),
+            SessionSort::UpdatedAt => self.updated_at.cmp(&other.updated_at),
+            SessionSort::Status => self.status.cmp(&other.status),
+            // Folding ASCII only, as the store's query does.
+            SessionSort::Project => (self.project_name.bytes().map(|b| b.to_ascii_lowercase()))
+                .cmp(other.project_name.bytes().map(|b| b.to_ascii_lowercase())),
+            SessionSort::Attention => projects::urgency(&self.status)
+                .cmp(&projects::urgency(&other.status))
+                .then(other.updated_at.cmp(&self.updated_at)),
+        };
+        let key = if order == SortOrder::Desc { key.reverse() } else { key };
+        other
+            .pinned
+            .cmp(&self.pinned)
+            .then(key)
+            .then_with(|| (&self.host, &self.session_id).cmp(&(&other.host, &other.session_id)))
+    }
+}
+
+/// Sort `sessions` by `sort`, pinned ones first. `order` defaults to newest
+/// first for the time sorts and ascending for the rest.
+pub fn sort_sessions<S: Borrow<SessionWithAgents>>(sessions: &mut [S], sort: SessionSort, order: Option<SortOrder>) {
+    let order = order.unwrap_or_else(|| default_order(sort));
+    sessions.sort_by(|a, b| SortKey::of(a.borrow()).cmp(&SortKey::of(b.borrow()), sort, order));
 }
 
-pub async fn get_sessions(State(state): State<AppState>) -> impl IntoResponse {
-    match db::get_active_sessions(&state.pool).await {
-        Ok(sessions) => Json(sessions).into_response(),
+/// Whether an `If-None-Match` header lists `etag`, or is `*`, comparing
+/// weakly as RFC 9110 asks for this header.
+fn etag_matches(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|tag| tag.trim())
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag.trim_start_matches("W/"))
+}
+
+/// Split a comma-separated tag list, dropping empty entries.
+pub fn parse_tags(list: &str) -> Vec<String> {
+    list.split(',').map(str::trim).filter(|t| !t.is_empty()).map(str::to_string).collect()
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/sessions",
+    tag = "sessions",
+    params(SessionsQuery, HostFilter),
+    responses(
+        (status = 200, description = "Active sessions", body = Vec<SessionWithAgents>, headers(
+            ("etag" = String, description = "Hash of the response, for `If-None-Match`"),
+            ("x-total-count" = usize, description = "Sessions matching the filters, across all pages"),
+            ("x-next-cursor" = String, description = "`cursor` for the next page; absent on the last")
+        )),
+        (status = 304, description = "Unchanged since the `If-None-Match` ETag"),
+        (status = 400, description = "Invalid `since` or `cursor`", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn get_sessions(
+    State(state): State<AppState>,
+    Query(query): Query<SessionsQuery>,
+    Query(hosts): Query<HostFilter>,
+    request_headers: HeaderMap,
+) -> impl IntoResponse {
+    let tags = query.tag.as_deref().map(parse_tags).unwrap_or_default();
+    let statuses = query.status.as_deref().map(parse_tags).unwrap_or_default();
+    let since = match query.since.as_deref().map(|s| stats::parse_bound(s, false)).transpose() {
+        Ok(since) => since,
+        Err(e) => return (StatusCode::BAD_REQUEST, Json(json!({"error": e}))).into_response(),
+    };
+    let after = match query.cursor.as_deref().map(SortKey::from_cursor) {
+        Some(None) => return (StatusCode::BAD_REQUEST, Json(json!({"error": "invalid cursor"}))).into_response(),
+        Some(after) => after,
+        None => None,
+    };
+    let order = query.order.unwrap_or_else(|| default_order(query.sort));
+    let limit = query.limit.map_or(usize::MAX, |l| l.clamp(1, MAX_SESSIONS_PAGE));
+    let page = SessionPage {
+        host: hosts.host.as_deref(),
+        tags: &tags,
+        statuses: &statuses,
+        project: query.project.as_deref(),
+        since,
+        sort: query.sort,
+        order,
+        after: after.as_ref(),
+        // One past the page, to tell whether there's a next one.
+        limit: (limit != usize::MAX).then(|| limit + 1),
+    };
+    match state.store.get_session_page(&page).await {
+        Ok((mut sessions, total)) => {
+            if let Err(e) = state.fill_derived(&mut sessions).await {
+                warn!("get_sessions error: {e}");
+                return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response();
+            }
+            // Remote sessions aren't in the store, so they are filtered and paged here.
+            let mut remote = state.remote.sessions();
+            remote.retain(|s| {
+                hosts.allows(&s.host)
+                    && s.has_any_tag(&tags)
+                    && (statuses.is_empty() || statuses.contains(&s.status))
+                    && query.project.as_ref().is_none_or(|p| *p == s.project_name || *p == s.project_path)
+                    && since.is_none_or(|since| s.updated_at >= since)
+            });
+            let total = total as usize + remote.len();
+            if let Some(after) = &after {
+                remote.retain(|s| SortKey::of(s).cmp(after, query.sort, order) == Ordering::Greater);
+            }
+            sessions.extend(remote);
+            sort_sessions(&mut sessions, query.sort, Some(order));
+            let next = (sessions.len() > limit).then(|| SortKey::of(&sessions[limit - 1]).cursor());
+            sessions.truncate(limit);
+
+            let body = match serde_json::to_vec(&sessions) {
+                Ok(body) => body,
+                Err(e) => {
+                    warn!("get_sessions error: {e}");
+                    return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response();
+                }
+            };
+            // The paging headers are part of what a poller has seen, so they go into the tag too.
+            let digest = Sha256::new()
+                .chain_update(&body)
+                .chain_update(total.to_string())
+                .chain_update(next.as_deref().unwrap_or_default())
+                .finalize();
+            // Weak: the same tag covers every content encoding of the body.
+            let etag = format!("W/\"{}\"", &hex::encode(digest)[..32]);
+
+            let mut headers = HeaderMap::new();
+            headers.insert(TOTAL_COUNT_HEADER, HeaderValue::from(total));
+            if let Some(next) = next.and_then(|n| HeaderValue::from_str(&n).ok()) {
+                headers.insert(NEXT_CURSOR_HEADER, next);
+            }
+            headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+            if let Ok(value) = HeaderValue::from_str(&etag) {
+                headers.insert(header::ETAG, value);
+            }
+            if etag_matches(&request_headers, &etag) {
+                return (StatusCode::NOT_MODIFIED, headers).into_response();
+            }
+            headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("application/json"));
+            (headers, body).into_response()
+        }
         Err(e) => {
             warn!("get_sessions error: {e}");
             (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response()
@@ -56,60 +606,117 @@ pub async fn get_sessions(State(state): State<AppState>) -> impl IntoResponse {
     }
 }
 
+/// (session, agent) status an event moves to. post_event handles stop and
+/// session_end on their own paths, but they map here too so stored event
+/// history can be replayed into status intervals.
+pub fn statuses_for_event(event_type: &str, needs_input: bool) -> (&'static str, &'static str) {
+    match event_type {
+        "notification" if needs_input => ("waiting_input", "waiting_input"),
+        "needs_permission" => ("needs_permission", "needs_permission"),
+        "subagent_stop" => ("active", "completed"),
+        "stop" => ("idle", "idle"),
+        "session_end" => ("completed", "completed"),
+        _ => ("active", "active"),
+    }
+}
+
+/// Host recorded for events that don't name one.
+pub const LOCAL_HOST: &str = "local";
+
+#[utoipa::path(
+    post,
+    path = "/api/events",
+    tag = "hooks",
+    request_body = HookEvent,
+    responses(
+        (status = 200, description = "Event recorded"),
+        (status = 401, description = "Missing, unknown or revoked API key", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+#[tracing::instrument(
+    name = "hook_event",
+    skip_all,
+    fields(session_id = %event.session_id, event_type = %event.event_type)
+)]
 pub async fn post_event(
     State(state): State<AppState>,
-    Json(event): Json<HookEvent>,
+    request_id: RequestId,
+    key: PresentedKey,
+    HookJson(event): HookJson<HookEvent>,
 ) -> impl IntoResponse {
-    info!(
-        event_type = %event.event_type,
-        session_id = %event.session_id,
-        "Received hook event"
-    );
+    let source = match state.keys.source(state.store.as_ref(), &key).await {
+        Ok(source) => source,
+        Err(refused) => return refused.into_response(),
+    };
+    let origin = Origin::new(request_id, source);
+    info!("Received hook event");
+    if let Some(relay) = &state.relay {
+        relay.forward(&event);
+    }
 
-    let project_path = event.project_path.as_deref().unwrap_or("");
-    let project_name = event.project_name.as_deref().unwrap_or("unknown");
+    // Canonicalize so the same project reached via a symlink, `~`, or a trailing
+    // slash is stored under one path. The name falls back to the directory name.
+    let project_path = paths::canonicalize(event.project_path.as_deref().unwrap_or(""));
+    let project_name = event
+        .project_name
+        .as_deref()
+        .filter(|name| !name.is_empty())
+        .or_else(|| paths::basename(&project_path))
+        .unwrap_or("unknown")
+        .to_string();
     let agent_name = event.agent_name.as_deref().unwrap_or("main");
+    let host = event.host.as_deref().map(str::trim).filter(|h| !h.is_empty()).unwrap_or(LOCAL_HOST);
     let needs_input = event.needs_input.unwrap_or(false);
+    let preview_message = match event.event_type.as_str() {
+        "notification" | "needs_permission" | "stop" => {
+            event.message.as_deref().filter(|m| !m.trim().is_empty())
+        }
+        _ => None,
+    };
 
     // Handle stop: move 'active' sessions to 'idle' so they stay visible in the overlay.
     // Sessions in 'waiting_input' or 'needs_permission' are left untouched.
     if event.event_type == "stop" {
-        if let Err(e) = db::mark_active_session_idle(&state.pool, &event.session_id).await {
+        if let Err(e) = state.store.mark_active_session_idle(host, &event.session_id).await {
             warn!("mark_active_session_idle error: {e}");
         }
-        if let Err(e) = db::insert_event(&state.pool, &event.session_id, Some(agent_name), &event.event_type, "{}").await {
-            warn!("insert_event error: {e}");
+        if let Err(e) = state.store.clear_current_tool(host, &event.session_id, None).await {
+            warn!("clear_current_tool error: {e}");
         }
-        state.broadcast_sessions().await;
+        if let Some(message) = preview_message {
+            if let Err(e) = state.store.set_last_message(host, &event.session_id, message).await {
+                warn!("set_last_message error: {e}");
+            }
+        }
+        // Keep the message so search can find it.
+        let payload = serde_json::to_string(&serde_json::json!({ "message": event.message }))
+            .unwrap_or_else(|_| "{}".to_string());
+        state.events.insert(host, &event.session_id, Some(agent_name), &event.event_type, &payload, &origin).await;
+        state.broadcast_sessions();
         return StatusCode::OK.into_response();
     }
 
     // Handle session_end: mark session completed so it's removed from the overlay.
     if event.event_type == "session_end" {
-        if let Err(e) = db::mark_session_completed(&state.pool, &event.session_id).await {
+        if let Err(e) = state.store.mark_session_completed(host, &event.session_id).await {
             warn!("mark_session_completed error: {e}");
         }
-        let _ = db::insert_event(&state.pool, &event.session_id, Some(agent_name), &event.event_type, "{}").await;
-        state.broadcast_sessions().await;
+        if let Some(transcripts) = &state.transcripts {
+            transcripts.unwatch(Some(host), &event.session_id);
+        }
+        state.events.insert(host, &event.session_id, Some(agent_name), &event.event_type, "{}", &origin).await;
+        state.broadcast_sessions();
         return StatusCode::OK.into_response();
     }
 
-    let (session_status, agent_status) = match event.event_type.as_str() {
-        "notification" if needs_input => ("waiting_input", "waiting_input"),
-        "needs_permission" => ("needs_permission", "needs_permission"),
-        "subagent_stop" => ("active", "completed"),
-        _ => ("active", "active"),
-    };
+    let (session_status, agent_status) = statuses_for_event(&event.event_type, needs_input);
 
     // Upsert session.
-    if let Err(e) = db::upsert_session(
-        &state.pool,
-        &event.session_id,
-        project_path,
-        project_name,
-        session_status,
-    )
-    .await
+    if let Err(e) = state
+        .store
+        .upsert_session(host, &event.session_id, &project_path, &project_name, session_status)
+        .await
     {
         warn!("upsert_session error: {e}");
         return (
@@ -120,14 +727,10 @@ pub async fn post_event(
     }
 
     // Upsert agent.
-    if let Err(e) = db::upsert_agent(
-        &state.pool,
-        &event.session_id,
-        agent_name,
-        event.parent_session_id.as_deref(),
-        agent_status,
-    )
-    .await
+    if let Err(e) = state
+        .store
+        .upsert_agent(host, &event.session_id, agent_name, event.parent_session_id.as_deref(), agent_status)
+        .await
     {
         warn!("upsert_agent error: {e}");
         return (
@@ -137,41 +740,238 @@ pub async fn post_event(
             .into_response();
     }
 
+    if let Some(message) = preview_message {
+        if let Err(e) = state.store.set_last_message(host, &event.session_id, message).await {
+            warn!("set_last_message error: {e}");
+        }
+    }
+
+    if let Some(model) = event.model.as_deref().filter(|m| !m.is_empty()) {
+        if let Err(e) = state.store.set_session_model(host, &event.session_id, model).await {
+            warn!("set_session_model error: {e}");
+        }
+    }
+
+    if let Some(branch) = event.git_branch.as_deref().filter(|b| !b.is_empty()) {
+        if let Err(e) = state.store.set_session_git_branch(host, &event.session_id, branch).await {
+            warn!("set_session_git_branch error: {e}");
+        }
+    }
+
+    if let (Some(transcripts), Some(path)) = (&state.transcripts, event.transcript_path.as_deref()) {
+        if !path.is_empty() {
+            transcripts.watch(host, &event.session_id, agent_name, path);
+        }
+    }
+
+    // Correlate pre/post tool-use pairs into timed invocations and keep the
+    // session's current tool in sync.
+    if let Some(tool_name) = event.tool_name.as_deref() {
+        let tool_use_id = event.tool_use_id.as_deref();
+        let result = match event.event_type.as_str() {
+            "pre_tool_use" => {
+                if let Err(e) = state.store.set_current_tool(host, &event.session_id, tool_name).await {
+                    warn!("set_current_tool error: {e}");
+                }
+                state.store.start_tool_invocation(host, &event.session_id, agent_name, tool_name, tool_use_id).await
+            }
+            "post_tool_use" => {
+                if let Err(e) = state.store.clear_current_tool(host, &event.session_id, Some(tool_name)).await {
+                    warn!("clear_current_tool error: {e}");
+                }
+                state.store.finish_tool_invocation(host, &event.session_id, agent_name, tool_name, tool_use_id).await
+            }
+            _ => Ok(()),
+        };
+        if let Err(e) = result {
+            warn!("tool invocation tracking error: {e}");
+        }
+    }
+
     // Build event payload.
     let payload = serde_json::to_string(&serde_json::json!({
         "needs_input": event.needs_input,
         "tool_name": event.tool_name,
         "transcript_path": event.transcript_path,
         "message": event.message,
+        "tool_use_id": event.tool_use_id,
     }))
     .unwrap_or_else(|_| "{}".to_string());
 
-    if let Err(e) = db::insert_event(
-        &state.pool,
-        &event.session_id,
-        Some(agent_name),
-        &event.event_type,
-        &payload,
+    state.events.insert(host, &event.session_id, Some(agent_name), &event.event_type, &payload, &origin).await;
+
+    state.broadcast_sessions();
+
+    StatusCode::OK.into_response()
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/sessions/{session_id}/tools",
+    tag = "sessions",
+    params(("session_id" = String, Path, description = "Claude session ID")),
+    responses(
+        (status = 200, description = "Timing per tool", body = Vec<ToolStats>),
+        (status = 500, description = "Database error", body = ErrorResponse)
     )
-    .await
-    {
-        warn!("insert_event error: {e}");
+)]
+pub async fn get_session_tools(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+) -> impl IntoResponse {
+    match state.store.get_tool_stats(&session_id).await {
+        Ok(stats) => Json(stats).into_response(),
+        Err(e) => {
+            warn!("get_session_tools error: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response()
+        }
     }
+}
 
-    state.broadcast_sessions().await;
+/// Status changes, tool calls and notifications, oldest first.
+#[utoipa::path(
+    get,
+    path = "/api/sessions/{session_id}/timeline",
+    tag = "sessions",
+    params(("session_id" = String, Path, description = "Claude session ID")),
+    responses(
+        (status = 200, description = "Oldest first", body = Vec<TimelineEntry>),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn get_session_timeline(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+) -> impl IntoResponse {
+    let (events, tools, notifications) = match tokio::try_join!(
+        state.store.get_session_event_rows(&session_id),
+        state.store.get_tool_timeline(&session_id),
+        state.store.get_session_notifications(&session_id),
+    ) {
+        Ok(results) => results,
+        Err(e) => {
+            warn!("get_session_timeline error: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response();
+        }
+    };
 
-    StatusCode::OK.into_response()
+    let mut entries = Vec::new();
+    let mut status = None;
+    for event in events {
+        let (session_status, _) = statuses_for_event(&event.event_type, event.needs_input);
+        if status == Some(session_status) {
+            continue;
+        }
+        status = Some(session_status);
+        entries.push(TimelineEntry::Status {
+            at: event.timestamp,
+            status: session_status.to_string(),
+            event_type: event.event_type,
+            agent_name: event.agent_name,
+        });
+    }
+    entries.extend(tools);
+    entries.extend(notifications.into_iter().map(|n| TimelineEntry::Notification {
+        at: n.created_at,
+        sink: n.sink,
+        kind: n.kind,
+        rule_name: n.rule_name,
+        message: n.message,
+        result: n.result,
+    }));
+    // Stable, so a status change stays ahead of the tool call that caused it.
+    entries.sort_by_key(TimelineEntry::at);
+
+    Json(entries).into_response()
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/sessions/{session_id}/usage",
+    tag = "sessions",
+    params(("session_id" = String, Path, description = "Claude session ID")),
+    responses((status = 200, body = SessionUsage), (status = 500, description = "Database error", body = ErrorResponse))
+)]
+pub async fn get_session_usage(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+) -> impl IntoResponse {
+    match state.store.get_session_usage(&session_id).await {
+        Ok(usage) => Json(usage).into_response(),
+        Err(e) => {
+            warn!("get_session_usage error: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response()
+        }
+    }
+}
+
+/// Most events one request returns.
+pub const MAX_EVENTS: u32 = 500;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct EventsQuery {
+    /// At most 500.
+    #[serde(default = "default_events_limit")]
+    pub limit: u32,
+    /// Only events from this agent, e.g. `main`.
+    pub agent: Option<String>,
+}
+
+fn default_events_limit() -> u32 {
+    50
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/sessions/{session_id}/events",
+    tag = "sessions",
+    params(("session_id" = String, Path, description = "Claude session ID"), EventsQuery),
+    responses(
+        (status = 200, description = "Newest first", body = Vec<SessionEvent>),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn get_session_events(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+    Query(query): Query<EventsQuery>,
+) -> impl IntoResponse {
+    let limit = query.limit.clamp(1, MAX_EVENTS);
+    match state.store.get_recent_events(&session_id, query.agent.as_deref(), limit).await {
+        Ok(events) => Json(events).into_response(),
+        Err(e) => {
+            warn!("get_session_events error: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response()
+        }
+    }
 }
 
+#[utoipa::path(
+    delete,
+    path = "/api/sessions/{session_id}",
+    tag = "sessions",
+    params(("session_id" = String, Path, description = "Claude session ID"), HostFilter),
+    responses(
+        (status = 200, description = "Session moved to the trash, see GET /api/trash"),
+        (status = 404, description = "No such session", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
 pub async fn delete_session(
     State(state): State<AppState>,
     Path(session_id): Path<String>,
+    Query(hosts): Query<HostFilter>,
 ) -> impl IntoResponse {
-    match db::mark_session_completed(&state.pool, &session_id).await {
-        Ok(()) => {
-            state.broadcast_sessions().await;
+    match state.store.trash_session(hosts.host.as_deref(), &session_id).await {
+        Ok(true) => {
+            if let Some(transcripts) = &state.transcripts {
+                transcripts.unwatch(hosts.host.as_deref(), &session_id);
+            }
+            state.broadcast_sessions();
             StatusCode::OK.into_response()
         }
+        Ok(false) => (StatusCode::NOT_FOUND, Json(json!({"error": "session not found"}))).into_response(),
         Err(e) => {
             warn!("delete_session error: {e}");
             (
@@ -183,10 +983,339 @@ pub async fn delete_session(
     }
 }
 
+/// Mark a blocked session as seen so it stops alerting; shared by the
+/// dashboard and editor plugins.
+#[utoipa::path(
+    post,
+    path = "/api/sessions/{session_id}/ack",
+    tag = "sessions",
+    params(("session_id" = String, Path, description = "Claude session ID"), HostFilter),
+    responses(
+        (
+            status = 200,
+            description = "Whether the session was blocked",
+            body = Object,
+            example = json!({"acknowledged": true})
+        ),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn ack_session(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+    Query(hosts): Query<HostFilter>,
+) -> impl IntoResponse {
+    match state.store.acknowledge_session(hosts.host.as_deref(), &session_id).await {
+        Ok(acknowledged) => {
+            if acknowledged {
+                state.broadcast_sessions();
+            }
+            Json(json!({"acknowledged": acknowledged})).into_response()
+        }
+        Err(e) => {
+            warn!("ack_session error: {e}");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": e.to_string()})),
+            )
+                .into_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/sessions/{session_id}/pin",
+    tag = "sessions",
+    params(("session_id" = String, Path, description = "Claude session ID"), HostFilter),
+    responses(
+        (status = 200, body = Object, example = json!({"pinned": true})),
+        (status = 404, description = "No such session", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn pin_session(
+    state: State<AppState>,
+    session_id: Path<String>,
+    hosts: Query<HostFilter>,
+) -> impl IntoResponse {
+    set_pinned(state, session_id, hosts, true).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/sessions/{session_id}/unpin",
+    tag = "sessions",
+    params(("session_id" = String, Path, description = "Claude session ID"), HostFilter),
+    responses(
+        (status = 200, body = Object, example = json!({"pinned": false})),
+        (status = 404, description = "No such session", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn unpin_session(
+    state: State<AppState>,
+    session_id: Path<String>,
+    hosts: Query<HostFilter>,
+) -> impl IntoResponse {
+    set_pinned(state, session_id, hosts, false).await
+}
+
+async fn set_pinned(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+    Query(hosts): Query<HostFilter>,
+    pinned: bool,
+) -> impl IntoResponse {
+    match state.store.set_session_pinned(hosts.host.as_deref(), &session_id, pinned).await {
+        Ok(true) => {
+            state.broadcast_sessions();
+            Json(json!({"pinned": pinned})).into_response()
+        }
+        Ok(false) => (StatusCode::NOT_FOUND, Json(json!({"error": "session not found"}))).into_response(),
+        Err(e) => {
+            warn!("set_pinned error: {e}");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": e.to_string()})),
+            )
+                .into_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/sessions/{session_id}/mute",
+    tag = "sessions",
+    params(("session_id" = String, Path, description = "Claude session ID"), HostFilter),
+    request_body(content = Option<MuteRequest>, description = "Optional; omit to mute without hiding"),
+    responses(
+        (status = 200, body = Object, example = json!({"muted": true, "hidden": false})),
+        (status = 404, description = "No such session", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn mute_session(
+    state: State<AppState>,
+    session_id: Path<String>,
+    hosts: Query<HostFilter>,
+    body: Option<Json<MuteRequest>>,
+) -> impl IntoResponse {
+    let hide = body.map(|Json(b)| b.hide).unwrap_or_default();
+    set_muted(state, session_id, hosts, true, hide).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/sessions/{session_id}/unmute",
+    tag = "sessions",
+    params(("session_id" = String, Path, description = "Claude session ID"), HostFilter),
+    responses(
+        (status = 200, body = Object, example = json!({"muted": false, "hidden": false})),
+        (status = 404, description = "No such session", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn unmute_session(
+    state: State<AppState>,
+    session_id: Path<String>,
+    hosts: Query<HostFilter>,
+) -> impl IntoResponse {
+    set_muted(state, session_id, hosts, false, false).await
+}
+
+async fn set_muted(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+    Query(hosts): Query<HostFilter>,
+    muted: bool,
+    hidden: bool,
+) -> impl IntoResponse {
+    match state.store.set_session_muted(hosts.host.as_deref(), &session_id, muted, hidden).await {
+        Ok(true) => {
+            state.broadcast_sessions();
+            Json(json!({"muted": muted, "hidden": hidden})).into_response()
+        }
+        Ok(false) => (StatusCode::NOT_FOUND, Json(json!({"error": "session not found"}))).into_response(),
+        Err(e) => {
+            warn!("set_muted error: {e}");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": e.to_string()})),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Longest note accepted, in characters.
+const MAX_NOTES_LEN: usize = 2000;
+
+#[utoipa::path(
+    patch,
+    path = "/api/sessions/{session_id}",
+    tag = "sessions",
+    params(("session_id" = String, Path, description = "Claude session ID"), HostFilter),
+    request_body = SessionUpdate,
+    responses(
+        (status = 200, description = "The updated fields", body = Object, example = json!({"notes": "PR #12"})),
+        (status = 400, description = "Note too long", body = ErrorResponse),
+        (status = 404, description = "No such session", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn update_session(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+    Query(hosts): Query<HostFilter>,
+    Json(update): Json<SessionUpdate>,
+) -> impl IntoResponse {
+    let Some(notes) = update.notes else {
+        return Json(json!({})).into_response();
+    };
+    if notes.chars().count() > MAX_NOTES_LEN {
+        let error = format!("`notes` must be at most {MAX_NOTES_LEN} characters");
+        return (StatusCode::BAD_REQUEST, Json(json!({"error": error}))).into_response();
+    }
+    let notes = Some(notes.trim()).filter(|n| !n.is_empty());
+    match state.store.set_session_notes(hosts.host.as_deref(), &session_id, notes).await {
+        Ok(true) => {
+            state.broadcast_sessions();
+            Json(json!({"notes": notes})).into_response()
+        }
+        Ok(false) => (StatusCode::NOT_FOUND, Json(json!({"error": "session not found"}))).into_response(),
+        Err(e) => {
+            warn!("update_session error: {e}");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": e.to_string()})),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Longest tag accepted, in characters.
+const MAX_TAG_LEN: usize = 64;
+
+fn validate_tags(tags: &[String]) -> Result<Vec<String>, String> {
+    let tags: Vec<String> = tags.iter().map(|t| t.trim().to_string()).collect();
+    if tags.is_empty() {
+        return Err("`tags` must name at least one tag".to_string());
+    }
+    if let Some(bad) = tags.iter().find(|t| t.is_empty() || t.contains(',') || t.chars().count() > MAX_TAG_LEN) {
+        return Err(format!("invalid tag '{bad}': tags are 1-{MAX_TAG_LEN} characters without commas"));
+    }
+    Ok(tags)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/sessions/{session_id}/tags",
+    tag = "sessions",
+    params(("session_id" = String, Path, description = "Claude session ID"), HostFilter),
+    request_body = TagsRequest,
+    responses(
+        (status = 200, description = "The session's tags", body = Object, example = json!({"tags": ["PROJ-12"]})),
+        (status = 400, description = "Invalid tag", body = ErrorResponse),
+        (status = 404, description = "No such session", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn add_tags(
+    state: State<AppState>,
+    session_id: Path<String>,
+    hosts: Query<HostFilter>,
+    Json(body): Json<TagsRequest>,
+) -> impl IntoResponse {
+    update_tags(state, session_id, hosts, body, true).await
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/sessions/{session_id}/tags",
+    tag = "sessions",
+    params(("session_id" = String, Path, description = "Claude session ID"), HostFilter),
+    request_body = TagsRequest,
+    responses(
+        (status = 200, description = "The session's tags", body = Object, example = json!({"tags": []})),
+        (status = 400, description = "Invalid tag", body = ErrorResponse),
+        (status = 404, description = "No such session", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn remove_tags(
+    state: State<AppState>,
+    session_id: Path<String>,
+    hosts: Query<HostFilter>,
+    Json(body): Json<TagsRequest>,
+) -> impl IntoResponse {
+    update_tags(state, session_id, hosts, body, false).await
+}
+
+async fn update_tags(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+    Query(hosts): Query<HostFilter>,
+    body: TagsRequest,
+    add: bool,
+) -> impl IntoResponse {
+    let tags = match validate_tags(&body.tags) {
+        Ok(tags) => tags,
+        Err(e) => return (StatusCode::BAD_REQUEST, Json(json!({"error": e}))).into_response(),
+    };
+    match apply_tags(&state, hosts.host.as_deref(), &session_id, &tags, add).await {
+        Ok(Some(tags)) => {
+            state.broadcast_sessions();
+            Json(json!({"tags": tags})).into_response()
+        }
+        Ok(None) => (StatusCode::NOT_FOUND, Json(json!({"error": "session not found"}))).into_response(),
+        Err(e) => {
+            warn!("update_tags error: {e}");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": e.to_string()})),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Add or remove `tags` on the session on `host`, or every host, and return
+/// its tags afterwards, or `None` if there is no such session.
+async fn apply_tags(
+    state: &AppState,
+    host: Option<&str>,
+    session_id: &str,
+    tags: &[String],
+    add: bool,
+) -> anyhow::Result<Option<Vec<String>>> {
+    if !state.store.session_exists(host, session_id).await? {
+        return Ok(None);
+    }
+    if add {
+        state.store.add_session_tags(host, session_id, tags).await?;
+    } else {
+        state.store.remove_session_tags(host, session_id, tags).await?;
+    }
+    state.store.get_session_tags(host, session_id).await.map(Some)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/sessions",
+    tag = "sessions",
+    responses(
+        (status = 200, description = "Every session marked completed"),
+        (status = 401, description = "Neither the listener's token nor an admin key", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
 pub async fn clear_all_sessions(State(state): State<AppState>) -> impl IntoResponse {
-    match db::clear_all_sessions(&state.pool).await {
+    match state.store.clear_all_sessions().await {
         Ok(()) => {
-            state.broadcast_sessions().await;
+            state.broadcast_sessions();
             StatusCode::OK.into_response()
         }
         Err(e) => {
@@ -199,3 +1328,220 @@ pub async fn clear_all_sessions(State(state): State<AppState>) -> impl IntoRespo
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use claude_monitor_client::{
+        models::{SessionSort, SortOrder, TokenUsage},
+        SessionQuery,
+    };
+    use futures::StreamExt;
+
+    use crate::test_support::{self, event};
+
+    #[tokio::test]
+    async fn events_drive_session_status() {
+        let server = test_support::spawn().await;
+        let client = &server.client;
+
+        let mut pre = event("pre_tool_use", "s1");
+        pre.tool_name = Some("Bash".to_string());
+        client.post_event(&pre).await.unwrap();
+
+        let sessions = client.sessions(&SessionQuery::default()).await.unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].project_name, "demo");
+        assert_eq!(sessions[0].status, "active");
+        assert_eq!(sessions[0].current_tool.as_deref(), Some("Bash"));
+
+        client.post_event(&event("needs_permission", "s1")).await.unwrap();
+        let sessions = client.sessions(&SessionQuery::default()).await.unwrap();
+        assert_eq!(sessions[0].status, "needs_permission");
+
+        // Completed sessions leave the list unless pinned.
+        client.post_event(&event("session_end", "s1")).await.unwrap();
+        assert!(client.sessions(&SessionQuery::default()).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn tags_and_pins_filter_sessions() {
+        let server = test_support::spawn().await;
+        let client = &server.client;
+        client.post_event(&event("user_prompt_submit", "s1")).await.unwrap();
+        client.post_event(&event("user_prompt_submit", "s2")).await.unwrap();
+
+        let tags = client.add_tags("s1", &["review".to_string()]).await.unwrap();
+        assert_eq!(tags, ["review"]);
+        client.pin_session("s2").await.unwrap();
+
+        let query = SessionQuery { tags: vec!["review".to_string()], ..Default::default() };
+        let tagged = client.sessions(&query).await.unwrap();
+        assert_eq!(tagged.len(), 1);
+        assert_eq!(tagged[0].session_id, "s1");
+
+        let sessions = client.sessions(&SessionQuery::default()).await.unwrap();
+        let pinned: Vec<_> = sessions.iter().filter(|s| s.pinned).map(|s| s.session_id.as_str()).collect();
+        assert_eq!(pinned, ["s2"]);
+    }
+
+    #[tokio::test]
+    async fn hosts_sharing_a_session_id_keep_separate_sessions() {
+        let server = test_support::spawn().await;
+        let client = &server.client;
+        let on = |host: &str, event_type: &str| {
+            let mut event = event(event_type, "s1");
+            event.host = Some(host.to_string());
+            event
+        };
+        let mut pre = on("laptop", "pre_tool_use");
+        pre.tool_name = Some("Bash".to_string());
+        client.post_event(&pre).await.unwrap();
+        let mut helper = on("laptop", "user_prompt_submit");
+        helper.agent_name = Some("helper".to_string());
+        client.post_event(&helper).await.unwrap();
+        client.post_event(&on("server", "needs_permission")).await.unwrap();
+
+        let session = |host: &str| {
+            let host = host.to_string();
+            async move {
+                let sessions = client.sessions(&SessionQuery::default()).await.unwrap();
+                sessions.into_iter().find(|s| s.host == host).unwrap()
+            }
+        };
+        let laptop = session("laptop").await;
+        assert_eq!(laptop.status, "active");
+        assert_eq!(laptop.current_tool.as_deref(), Some("Bash"));
+        assert_eq!(laptop.agents.len(), 2);
+        let remote = session("server").await;
+        assert_eq!(remote.status, "needs_permission");
+        assert_eq!(remote.current_tool, None);
+        assert_eq!(remote.agents.len(), 1);
+        server.flushed().await;
+        let stats = client.project_stats(None, None, None).await.unwrap();
+        assert_eq!(stats.projects[0].sessions, 2);
+
+        // Acting on one host leaves the other's session alone.
+        assert!(server.state.store.set_session_pinned(Some("server"), "s1", true).await.unwrap());
+        server.state.store.add_session_tags(Some("laptop"), "s1", &["mine".to_string()]).await.unwrap();
+        client.post_event(&on("laptop", "session_end")).await.unwrap();
+        let sessions = client.sessions(&SessionQuery::default()).await.unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].host, "server");
+        assert_eq!(sessions[0].status, "needs_permission");
+        assert!(sessions[0].pinned);
+        assert!(sessions[0].tags.is_empty());
+    }
+
+    #[tokio::test]
+    async fn pages_cover_every_session_once_in_order() {
+        let server = test_support::spawn().await;
+        let client = &server.client;
+        // Mixed case and beyond ASCII, where case folding differs by collation.
+        let projects = [("s1", "Ärger"), ("s2", "ärger"), ("s3", "Zeta"), ("s4", "alpha"), ("s5", "Éclair")];
+        for (id, project) in projects {
+            let mut prompt = event("user_prompt_submit", id);
+            prompt.project_path = Some(format!("/work/{id}"));
+            prompt.project_name = Some(project.to_string());
+            client.post_event(&prompt).await.unwrap();
+        }
+        client.post_event(&event("needs_permission", "s3")).await.unwrap();
+        client.post_event(&event("stop", "s4")).await.unwrap();
+        client.pin_session("s2").await.unwrap();
+
+        for sort in [
+            SessionSort::UpdatedAt,
+            SessionSort::CreatedAt,
+            SessionSort::Status,
+            SessionSort::Project,
+            SessionSort::Attention,
+        ] {
+            for order in [SortOrder::Asc, SortOrder::Desc] {
+                let query = SessionQuery { sort: Some(sort), order: Some(order), ..Default::default() };
+                let all: Vec<_> =
+                    client.sessions(&query).await.unwrap().into_iter().map(|s| s.session_id).collect();
+                assert_eq!(all.len(), 5);
+                assert_eq!(all[0], "s2", "pinned first");
+
+                let mut paged = Vec::new();
+                let mut query = SessionQuery { limit: Some(2), ..query };
+                loop {
+                    let page = client.sessions_page(&query).await.unwrap();
+                    assert_eq!(page.total, Some(5));
+                    paged.extend(page.sessions.into_iter().map(|s| s.session_id));
+                    match page.next_cursor {
+                        Some(cursor) => query.cursor = Some(cursor),
+                        None => break,
+                    }
+                }
+                assert_eq!(paged, all, "{sort:?} {order:?}");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn sessions_are_costed_by_their_own_usage() {
+        let server = test_support::spawn().await;
+        let client = &server.client;
+        client.post_event(&event("user_prompt_submit", "s1")).await.unwrap();
+        client.post_event(&event("user_prompt_submit", "s2")).await.unwrap();
+        let usage = TokenUsage { input_tokens: 1_000_000, ..Default::default() };
+        let store = &server.state.store;
+        store.record_usage("s1", "main", Some("m1"), Some("claude-sonnet-4"), &usage).await.unwrap();
+        store.record_usage("s1", "main", Some("m2"), Some("claude-sonnet-4"), &usage).await.unwrap();
+
+        let sessions = client.sessions(&SessionQuery::default()).await.unwrap();
+        let cost = |id: &str| sessions.iter().find(|s| s.session_id == id).unwrap().estimated_cost_usd;
+        let expected = server.state.config.pricing.cost(Some("claude-sonnet-4"), &usage) * 2.0;
+        assert!(expected > 0.0);
+        assert_eq!(cost("s1"), expected);
+        assert_eq!(cost("s2"), 0.0);
+    }
+
+    #[tokio::test]
+    async fn events_are_listed_once_written() {
+        let server = test_support::spawn().await;
+        let client = &server.client;
+        let mut pre = event("pre_tool_use", "s1");
+        pre.tool_name = Some("Read".to_string());
+        client.post_event(&pre).await.unwrap();
+        client.post_event(&event("stop", "s1")).await.unwrap();
+        server.flushed().await;
+
+        let events = client.session_events("s1", 10, None).await.unwrap();
+        let types: Vec<_> = events.iter().map(|e| e.event_type.as_str()).collect();
+        assert_eq!(types.len(), 2);
+        assert!(types.contains(&"pre_tool_use") && types.contains(&"stop"));
+    }
+
+    #[tokio::test]
+    async fn health_reports_sessions_and_clients() {
+        let server = test_support::spawn().await;
+        let client = &server.client;
+        assert_eq!(client.health().await.unwrap().last_event_at, None);
+        client.post_event(&event("user_prompt_submit", "s1")).await.unwrap();
+        server.flushed().await;
+        let mut stream = client.subscribe(&Default::default()).await.unwrap();
+        stream.next().await.unwrap().unwrap();
+
+        let health = client.health().await.unwrap();
+        assert_eq!(health.status, "ok");
+        assert_eq!(health.database_error, None);
+        assert_eq!(health.sessions.get("active"), Some(&1));
+        assert_eq!(health.ws_clients, 1);
+        assert!(health.last_event_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn readiness_lists_failing_checks() {
+        let server = test_support::spawn().await;
+        let readiness = server.client.readiness().await.unwrap();
+        assert!(readiness.ready);
+        assert!(readiness.checks.values().all(|check| check == "ok"), "{:?}", readiness.checks);
+
+        server.state.listening.store(false, std::sync::atomic::Ordering::Relaxed);
+        let readiness = server.client.readiness().await.unwrap();
+        assert!(!readiness.ready);
+        assert_eq!(readiness.checks["listener"], "not bound");
+        assert_eq!(readiness.checks["database"], "ok");
+    }
+}
@@ -0,0 +1,190 @@
+//! Bearer tokens for listeners other machines can reach. A request to a
+//! listener with a `token` must carry it as `Authorization: Bearer <token>`,
+//! or as `?token=` where no header can be set, as from a browser's
+//! WebSocket. The probes, `/health`, `/livez` and `/readyz` beneath the
+//! `base_path` if set, and CORS preflights need none; nor does anything
+//! else stand in for it, an admin key included. The `/api/admin` routes
+//! need an admin key on a listener without a token while one is live; see
+//! `keys`.
+
+use axum::{
+    extract::{Query, Request, State},
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    Json, Router,
+};
+use serde::Deserialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::{sync::Arc, time::Duration};
+
+use crate::keys;
+
+/// Compared by digest, so how long a comparison takes says nothing about
+/// the token.
+#[derive(Clone)]
+struct Token(Arc<[u8; 32]>);
+
+impl Token {
+    fn new(token: &str) -> Self {
+        Self(Arc::new(Sha256::digest(token).into()))
+    }
+
+    fn matches(&self, candidate: &str) -> bool {
+        *self.0 == <[u8; 32]>::from(Sha256::digest(candidate))
+    }
+}
+
+/// Marks a request that came with the listener's token.
+#[derive(Clone, Copy)]
+pub struct Authenticated;
+
+/// The token to check, and what the server's routes are beneath.
+#[derive(Clone)]
+struct Guard {
+    token: Token,
+    base_path: Arc<str>,
+}
+
+#[derive(Deserialize)]
+struct TokenQuery {
+    token: String,
+}
+
+/// A client for another monitor, sending `token` and the API key (see
+/// `keys`) with every request.
+pub fn http_client(timeout: Duration, token: Option<&str>, api_key: Option<&str>) -> reqwest::Client {
+    let mut headers = HeaderMap::new();
+    if let Some(mut value) = token.and_then(|token| HeaderValue::try_from(format!("Bearer {token}")).ok()) {
+        value.set_sensitive(true);
+        headers.insert(header::AUTHORIZATION, value);
+    }
+    if let Some(mut value) = api_key.and_then(|key| HeaderValue::try_from(key).ok()) {
+        value.set_sensitive(true);
+        headers.insert(keys::HEADER, value);
+    }
+    reqwest::Client::builder().timeout(timeout).default_headers(headers).build().unwrap_or_default()
+}
+
+/// `router`, served beneath `base_path`, answering only requests that carry
+/// `token`.
+pub fn require(router: Router, token: &str, base_path: &str) -> Router {
+    let guard = Guard { token: Token::new(token), base_path: base_path.into() };
+    router.layer(middleware::from_fn_with_state(guard, check))
+}
+
+/// Routes a health check that can't be given the token may poll.
+const PROBES: [&str; 3] = ["/health", "/livez", "/readyz"];
+
+/// Whether `request` needs no token.
+fn exempt(request: &Request, base_path: &str) -> bool {
+    request.method() == Method::OPTIONS
+        || request.uri().path().strip_prefix(base_path).is_some_and(|path| PROBES.contains(&path))
+}
+
+async fn check(State(guard): State<Guard>, mut request: Request, next: Next) -> Response {
+    if exempt(&request, &guard.base_path) {
+        return next.run(request).await;
+    }
+    let bearer = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::to_string);
+    let given = bearer.or_else(|| Query::<TokenQuery>::try_from_uri(request.uri()).ok().map(|q| q.0.token));
+    if given.is_some_and(|given| guard.token.matches(&given)) {
+        request.extensions_mut().insert(Authenticated);
+        return next.run(request).await;
+    }
+    let error = Json(json!({"error": "missing or wrong token"}));
+    (StatusCode::UNAUTHORIZED, [(header::WWW_AUTHENTICATE, "Bearer")], error).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+    use std::path::PathBuf;
+
+    use crate::{
+        config::Config,
+        listen::{self, BindConfig, ListenConfig},
+        shutdown::Shutdown,
+        test_support::{self, TestServer},
+    };
+
+    /// Serve `server`'s app on another listener, with the token `s3cret`;
+    /// returns its URL and the directory to remove.
+    async fn serve_with_token(server: &TestServer) -> (String, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("claude-monitor-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let bind = BindConfig {
+            address: "127.0.0.1:0".parse().unwrap(),
+            token: Some("s3cret".to_string()),
+            tls_cert: None,
+            tls_key: None,
+        };
+        let base_path = server.state.config.listen.base_path.clone();
+        let config = ListenConfig { bind: vec![bind], base_path, ..Default::default() };
+        let listener = listen::bind(&config, &dir).await.unwrap().remove(0);
+        let url = listener.url();
+        tokio::spawn(listener.serve(crate::router(server.state.clone()), Shutdown::never()));
+        (url, dir)
+    }
+
+    #[tokio::test]
+    async fn listeners_with_a_token_turn_away_requests_without_it() {
+        let server = test_support::spawn().await;
+        let (url, dir) = serve_with_token(&server).await;
+
+        let http = reqwest::Client::new();
+        let sessions = format!("{url}/api/sessions");
+        assert_eq!(http.get(&sessions).send().await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        let wrong = http.get(&sessions).bearer_auth("guess").send().await.unwrap();
+        assert_eq!(wrong.status(), StatusCode::UNAUTHORIZED);
+        assert!(http.get(&sessions).bearer_auth("s3cret").send().await.unwrap().status().is_success());
+        let query = http.get(format!("{sessions}?token=s3cret")).send().await.unwrap();
+        assert!(query.status().is_success());
+        let keys = http.get(format!("{url}/api/admin/keys")).bearer_auth("s3cret").send().await.unwrap();
+        assert!(keys.status().is_success());
+        assert!(http.get(format!("{url}/health")).send().await.unwrap().status().is_success());
+        assert!(http.get(format!("{url}/livez")).send().await.unwrap().status().is_success());
+        // The test server's own listener has no token.
+        server.client.sessions(&Default::default()).await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn an_admin_key_does_not_stand_in_for_the_token() {
+        let server = test_support::spawn().await;
+        let admin = server.client.create_api_key("ops", true).await.unwrap();
+        let (url, dir) = serve_with_token(&server).await;
+
+        let http = reqwest::Client::new();
+        let keys = format!("{url}/api/admin/keys");
+        let as_admin = http.get(&keys).header(crate::keys::HEADER, &admin.key).send().await.unwrap();
+        assert_eq!(as_admin.status(), StatusCode::UNAUTHORIZED);
+        assert!(http.get(&keys).bearer_auth("s3cret").send().await.unwrap().status().is_success());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn probes_need_no_token_beneath_the_base_path() {
+        let listen = ListenConfig { base_path: "/claude".to_string(), ..Default::default() };
+        let server = test_support::spawn_with(Config { listen, ..Default::default() }).await;
+        let (url, dir) = serve_with_token(&server).await;
+
+        let http = reqwest::Client::new();
+        for probe in ["health", "livez", "readyz"] {
+            let response = http.get(format!("{url}/claude/{probe}")).send().await.unwrap();
+            assert!(response.status().is_success(), "{probe}: {}", response.status());
+        }
+        let sessions = http.get(format!("{url}/claude/api/sessions")).send().await.unwrap();
+        assert_eq!(sessions.status(), StatusCode::UNAUTHORIZED);
+        // Only the probes beneath the base path are exempt.
+        let unrouted = http.get(format!("{url}/health")).send().await.unwrap();
+        assert_eq!(unrouted.status(), StatusCode::UNAUTHORIZED);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,177 @@
+//! Snapshots of the database under `~/.claude-monitor/backups`, taken by
+//! `POST /api/admin/backup` and, when enabled, once a day:
+//!
+//! ```toml
+//! [backup]
+//! daily = true
+//! keep = 7   # newest snapshots kept; 0 keeps them all
+//! ```
+//!
+//! Each is a complete SQLite file named `sessions-<timestamp>.db`; restore
+//! one by stopping the server and copying it over `sessions.db`.
+
+use anyhow::{Context, Result};
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::json;
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::{
+    api::AppState,
+    models::{BackupInfo, ErrorResponse},
+    store::Store,
+};
+
+/// `[backup]` section of config.toml.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct BackupConfig {
+    /// Take a snapshot every 24 hours, the first a day after startup.
+    pub daily: bool,
+    /// Newest snapshots kept after each new one; 0 keeps every snapshot.
+    pub keep: usize,
+    /// Directory for snapshots instead of `~/.claude-monitor/backups`.
+    pub dir: Option<PathBuf>,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self { daily: false, keep: 7, dir: None }
+    }
+}
+
+impl BackupConfig {
+    fn dir(&self) -> Result<PathBuf> {
+        if let Some(dir) = &self.dir {
+            return Ok(dir.clone());
+        }
+        let home = dirs::home_dir().context("could not determine home directory")?;
+        Ok(home.join(".claude-monitor").join("backups"))
+    }
+}
+
+/// Held while a snapshot is written, so two never race for the same name.
+static RUNNING: Mutex<()> = Mutex::const_new(());
+
+pub fn spawn(state: &AppState) {
+    if !state.config.backup.daily {
+        return;
+    }
+    let store = state.store.clone();
+    let config = state.config.backup.clone();
+    tokio::spawn(async move {
+        let period = Duration::from_secs(24 * 3600);
+        let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+        loop {
+            interval.tick().await;
+            match run(store.as_ref(), &config).await {
+                Some(Ok(_)) => {}
+                Some(Err(e)) => warn!("backup error: {e:#}"),
+                None => info!("Skipping scheduled backup; one is already running"),
+            }
+        }
+    });
+}
+
+/// Take a snapshot and prune old ones, or `None` when one is in progress.
+async fn run(store: &dyn Store, config: &BackupConfig) -> Option<Result<BackupInfo>> {
+    let _running = RUNNING.try_lock().ok()?;
+    Some(snapshot(store, config).await)
+}
+
+async fn snapshot(store: &dyn Store, config: &BackupConfig) -> Result<BackupInfo> {
+    let dir = config.dir()?;
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .with_context(|| format!("failed to create {}", dir.display()))?;
+    let created_at = Utc::now();
+    let path = dir.join(format!("sessions-{}.db", created_at.format("%Y%m%dT%H%M%SZ")));
+    store.backup(&path).await?;
+    let size = tokio::fs::metadata(&path).await?.len();
+    info!("Backed up the database to {} ({size} bytes)", path.display());
+
+    if config.keep > 0 {
+        if let Err(e) = prune(&dir, config.keep) {
+            warn!("failed to prune old backups: {e:#}");
+        }
+    }
+    Ok(BackupInfo { path: path.display().to_string(), size, created_at })
+}
+
+/// Delete all but the newest `keep` snapshots in `dir`. Timestamped names
+/// sort by age, and files that don't look like snapshots are left alone.
+fn prune(dir: &Path, keep: usize) -> Result<()> {
+    let mut snapshots: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+            name.starts_with("sessions-") && name.ends_with(".db")
+        })
+        .collect();
+    snapshots.sort();
+    let excess = snapshots.len().saturating_sub(keep);
+    for path in &snapshots[..excess] {
+        std::fs::remove_file(path).with_context(|| format!("failed to remove {}", path.display()))?;
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/admin/backup",
+    tag = "admin",
+    responses(
+        (status = 201, body = BackupInfo),
+        (status = 401, description = "Neither the listener's token nor an admin key", body = ErrorResponse),
+        (status = 409, description = "A backup is already running", body = ErrorResponse),
+        (status = 500, description = "Backup failed", body = ErrorResponse)
+    )
+)]
+pub async fn create_backup(State(state): State<AppState>) -> impl IntoResponse {
+    match run(state.store.as_ref(), &state.config.backup).await {
+        Some(Ok(info)) => (StatusCode::CREATED, Json(info)).into_response(),
+        Some(Err(e)) => {
+            warn!("create_backup error: {e:#}");
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": format!("{e:#}")}))).into_response()
+        }
+        None => (StatusCode::CONFLICT, Json(json!({"error": "a backup is already running"}))).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        config::Config,
+        store::{SqliteStore, Store},
+        test_support::{self, event},
+    };
+
+    #[tokio::test]
+    async fn backups_are_complete_and_pruned() {
+        let dir = std::env::temp_dir().join(format!("claude-monitor-test-{}", uuid::Uuid::new_v4()));
+        let mut config = Config::default();
+        config.backup.dir = Some(dir.clone());
+        config.backup.keep = 1;
+        let server = test_support::spawn_with(config).await;
+        server.client.post_event(&event("user_prompt_submit", "s1")).await.unwrap();
+
+        let first = server.client.backup().await.unwrap();
+        // Names have one-second resolution.
+        tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+        let second = server.client.backup().await.unwrap();
+        assert!(!std::path::Path::new(&first.path).exists());
+
+        let restored = SqliteStore::open(second.path.as_ref(), 1000).await.unwrap();
+        let sessions = restored.get_active_sessions().await.unwrap();
+        assert_eq!(sessions.len(), 1);
+
+        drop(restored);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
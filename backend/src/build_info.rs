@@ -0,0 +1,66 @@
+//! `GET /api/build-info`: the version, commit and features of this build,
+//! as recorded by `build.rs`, for bug reports and checking what a server
+//! is running. The same line is logged at startup.
+
+use axum::{response::IntoResponse, Json};
+use chrono::DateTime;
+use std::fmt;
+
+use crate::models::BuildInfo;
+
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Optional Cargo features this build has.
+const FEATURES: &[&str] = &[
+    #[cfg(feature = "postgres")]
+    "postgres",
+    #[cfg(feature = "otlp")]
+    "otlp",
+];
+
+pub fn get() -> BuildInfo {
+    BuildInfo {
+        version: VERSION.to_string(),
+        commit: env!("BUILD_COMMIT").to_string(),
+        built_at: env!("BUILD_TIMESTAMP").parse().ok().and_then(|secs| DateTime::from_timestamp(secs, 0)),
+        target: env!("BUILD_TARGET").to_string(),
+        features: FEATURES.iter().map(|feature| feature.to_string()).collect(),
+    }
+}
+
+/// `BuildInfo` as one line for the log.
+pub struct Summary(pub BuildInfo);
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let info = &self.0;
+        write!(f, "{} ({}, {}", info.version, info.commit, info.target)?;
+        if let Some(built_at) = info.built_at {
+            write!(f, ", built {}", built_at.format("%Y-%m-%d %H:%M UTC"))?;
+        }
+        if !info.features.is_empty() {
+            write!(f, ", features: {}", info.features.join(", "))?;
+        }
+        f.write_str(")")
+    }
+}
+
+#[utoipa::path(get, path = "/api/build-info", tag = "system", responses((status = 200, body = BuildInfo)))]
+pub async fn get_build_info() -> impl IntoResponse {
+    Json(get())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support;
+
+    #[tokio::test]
+    async fn build_info_matches_health() {
+        let server = test_support::spawn().await;
+        let info = server.client.build_info().await.unwrap();
+        assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+        assert!(!info.commit.is_empty());
+        assert!(info.built_at.is_some());
+        assert_eq!(server.client.health().await.unwrap().version, info.version);
+    }
+}
@@ -0,0 +1,264 @@
+//! The cleanup pass: sessions completed `completed_ttl_secs` ago are added
+//! to the stats and removed, as are those past the trash's retention, and
+//! old notifications are pruned. Their events are kept while the storage
+//! budget (see `storage`) can prune them, and go with them otherwise.
+//!
+//! ```toml
+//! [cleanup]
+//! interval_secs = 30
+//! completed_ttl_secs = 60   # 0 keeps completed sessions
+//! ```
+//!
+//! `PUT /api/settings` changes either at runtime; the new values take
+//! effect at once and are kept in the database over the config file's.
+//! `POST /api/admin/cleanup` runs a pass without waiting for the next.
+
+use anyhow::Result;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::Duration;
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Arc;
+use tokio::sync::watch;
+use tracing::warn;
+use utoipa::IntoParams;
+
+use crate::{
+    api::AppState,
+    models::{CleanupReport, ErrorResponse},
+    notifier,
+    store::Store,
+};
+
+/// `[cleanup]` section of config.toml.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct CleanupConfig {
+    /// Seconds between passes.
+    pub interval_secs: u64,
+    /// Seconds a completed session stays listed; 0 keeps them until they
+    /// are dismissed.
+    pub completed_ttl_secs: u64,
+}
+
+impl Default for CleanupConfig {
+    fn default() -> Self {
+        Self { interval_secs: 30, completed_ttl_secs: 60 }
+    }
+}
+
+impl CleanupConfig {
+    pub fn completed_ttl(&self) -> Option<Duration> {
+        (self.completed_ttl_secs > 0).then(|| Duration::seconds(self.completed_ttl_secs as i64))
+    }
+}
+
+/// Settings keys holding the values set through `/api/settings`.
+const INTERVAL_KEY: &str = "cleanup.interval_secs";
+const TTL_KEY: &str = "cleanup.completed_ttl_secs";
+
+/// The schedule in effect, shared by the cleanup task and `/api/settings`.
+#[derive(Clone)]
+pub struct Schedule(Arc<watch::Sender<CleanupConfig>>);
+
+impl Schedule {
+    pub fn new(config: CleanupConfig) -> Self {
+        Self(Arc::new(watch::Sender::new(config)))
+    }
+
+    pub fn current(&self) -> CleanupConfig {
+        self.0.borrow().clone()
+    }
+
+    /// Apply the values set at runtime before a restart.
+    async fn load(&self, store: &dyn Store) {
+        let mut config = self.current();
+        for (key, value) in [(INTERVAL_KEY, &mut config.interval_secs), (TTL_KEY, &mut config.completed_ttl_secs)] {
+            match store.get_setting(key).await {
+                Ok(Some(saved)) => match saved.parse() {
+                    Ok(saved) => *value = saved,
+                    Err(_) => warn!("Ignoring setting {key} = {saved:?}: not a number"),
+                },
+                Ok(None) => {}
+                Err(e) => warn!("Failed to read setting {key}: {e}"),
+            }
+        }
+        self.0.send_replace(config);
+    }
+
+    /// Save new values and switch to them; `None` leaves one unchanged.
+    pub async fn update(
+        &self,
+        store: &dyn Store,
+        interval_secs: Option<u64>,
+        completed_ttl_secs: Option<u64>,
+    ) -> Result<()> {
+        let mut config = self.current();
+        if let Some(interval_secs) = interval_secs {
+            store.set_setting(INTERVAL_KEY, &interval_secs.to_string()).await?;
+            config.interval_secs = interval_secs;
+        }
+        if let Some(completed_ttl_secs) = completed_ttl_secs {
+            store.set_setting(TTL_KEY, &completed_ttl_secs.to_string()).await?;
+            config.completed_ttl_secs = completed_ttl_secs;
+        }
+        self.0.send_if_modified(|current| std::mem::replace(current, config.clone()) != config);
+        Ok(())
+    }
+}
+
+/// Run a pass now and then every `interval_secs`; a changed schedule runs
+/// one at once and counts from there.
+pub fn spawn(state: &AppState) {
+    let state = state.clone();
+    tokio::spawn(async move {
+        state.cleanup.load(state.store.as_ref()).await;
+        let mut changed = state.cleanup.0.subscribe();
+        loop {
+            run(&state).await;
+            let interval = changed.borrow_and_update().interval_secs.max(1);
+            tokio::select! {
+                _ = tokio::time::sleep(std::time::Duration::from_secs(interval)) => {}
+                _ = changed.changed() => {}
+            }
+        }
+    });
+}
+
+async fn run(state: &AppState) {
+    if let Err(e) = remove(state, state.cleanup.current().completed_ttl()).await {
+        warn!("cleanup error: {e}");
+    }
+    if let Err(e) = state.store.prune_notifications(notifier::history::RETENTION).await {
+        warn!("notification cleanup error: {e}");
+    }
+}
+
+/// Remove sessions completed over `completed_ttl` ago and those past the
+/// trash's retention, then tell clients.
+async fn remove(state: &AppState, completed_ttl: Option<Duration>) -> Result<CleanupReport> {
+    let (usage_retention, trash_retention) = (state.config.quota.retention(), state.config.trash.retention());
+    // Events outlive their session while the storage budget can prune them.
+    let keep_events = state.config.storage.budget().is_some() && state.store.used_size().await?.is_some();
+    let report = state
+        .store
+        .cleanup_old_completed(completed_ttl, usage_retention, trash_retention, keep_events)
+        .await?;
+    if let Some(transcripts) = &state.transcripts {
+        let listed = state.store.get_active_sessions().await?;
+        transcripts.retain(listed.into_iter().map(|s| (s.host, s.session_id)).collect());
+    }
+    state.broadcast_sessions();
+    Ok(report)
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CleanupQuery {
+    /// Remove sessions completed over this many seconds ago instead of
+    /// `completed_ttl_secs`; 0 removes every completed session.
+    pub older_than: Option<u64>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/admin/cleanup",
+    tag = "admin",
+    params(CleanupQuery),
+    responses(
+        (status = 200, description = "What the pass removed", body = CleanupReport),
+        (status = 401, description = "Neither the listener's token nor an admin key", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn run_cleanup(State(state): State<AppState>, Query(query): Query<CleanupQuery>) -> impl IntoResponse {
+    let completed_ttl = match query.older_than {
+        Some(secs) => Some(Duration::seconds(secs as i64)),
+        None => state.cleanup.current().completed_ttl(),
+    };
+    match remove(&state, completed_ttl).await {
+        Ok(report) => Json(report).into_response(),
+        Err(e) => {
+            warn!("run_cleanup error: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::TTL_KEY;
+    use crate::{
+        config::Config,
+        models::{CleanupReport, SettingsUpdate},
+        test_support::{self, event},
+    };
+
+    #[tokio::test]
+    async fn completed_ttl_changes_without_a_restart() {
+        let server = test_support::spawn().await;
+        super::spawn(&server.state);
+        let client = &server.client;
+        let zero = SettingsUpdate { cleanup_interval_secs: Some(0), ..Default::default() };
+        assert!(client.update_settings(&zero).await.is_err());
+        client.post_event(&event("user_prompt_submit", "s1")).await.unwrap();
+        client.post_event(&event("session_end", "s1")).await.unwrap();
+        server.flushed().await;
+
+        let keep = SettingsUpdate { completed_ttl_secs: Some(0), ..Default::default() };
+        assert_eq!(client.update_settings(&keep).await.unwrap().completed_ttl_secs, 0);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!client.session_events("s1", 10, None).await.unwrap().is_empty());
+
+        let soon = SettingsUpdate { cleanup_interval_secs: Some(1), completed_ttl_secs: Some(1), dnd: None };
+        let settings = client.update_settings(&soon).await.unwrap();
+        assert_eq!((settings.cleanup_interval_secs, settings.completed_ttl_secs), (1, 1));
+        for _ in 0..50 {
+            if client.session_events("s1", 10, None).await.unwrap().is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert!(client.session_events("s1", 10, None).await.unwrap().is_empty());
+        assert_eq!(server.state.store.get_setting(TTL_KEY).await.unwrap().as_deref(), Some("1"));
+    }
+
+    #[tokio::test]
+    async fn cleanup_runs_on_demand() {
+        let server = test_support::spawn().await;
+        let client = &server.client;
+        client.post_event(&event("user_prompt_submit", "s1")).await.unwrap();
+        client.post_event(&event("session_end", "s1")).await.unwrap();
+        client.post_event(&event("user_prompt_submit", "s2")).await.unwrap();
+        server.flushed().await;
+
+        // The configured minute hasn't passed.
+        assert_eq!(client.run_cleanup(None).await.unwrap(), CleanupReport::default());
+        // s1's events are left for the storage budget to prune.
+        let report = client.run_cleanup(Some(0)).await.unwrap();
+        assert_eq!(report, CleanupReport { sessions: 1, events: 0 });
+        assert!(client.session_events("s1", 10, None).await.unwrap().is_empty());
+        assert_eq!(client.session_events("s2", 10, None).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn cleanup_removes_events_without_a_storage_budget() {
+        let mut config = Config::default();
+        config.storage.max_mb = 0;
+        let server = test_support::spawn_with(config).await;
+        let client = &server.client;
+        client.post_event(&event("user_prompt_submit", "s1")).await.unwrap();
+        client.post_event(&event("session_end", "s1")).await.unwrap();
+        server.flushed().await;
+
+        let report = client.run_cleanup(Some(0)).await.unwrap();
+        assert_eq!(report, CleanupReport { sessions: 1, events: 2 });
+    }
+}
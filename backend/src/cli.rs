@@ -0,0 +1,134 @@
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+
+#[derive(Debug, Parser)]
+#[command(name = "claude-monitor", version, about = "Tracks Claude CLI sessions reported by hooks")]
+pub struct Cli {
+    /// Run even if ~/.claude-monitor was written by a newer version.
+    /// The newer version's data may not be fully understood.
+    #[arg(long)]
+    pub force_downgrade: bool,
+
+    /// Database to use instead of ~/.claude-monitor/sessions.db: a SQLite
+    /// file path, `memory` for one that is discarded on exit, or a
+    /// `postgres://` URL in builds with the `postgres` feature.
+    #[arg(long, value_name = "URL")]
+    pub db: Option<String>,
+
+    /// Port to listen on instead of `[listen] port` from config.toml; unused
+    /// when `[[listen.bind]]` lists addresses.
+    #[arg(long)]
+    pub port: Option<u16>,
+
+    /// How the server logs: `text` to read, or `json`, one object a line
+    /// with the fields of its spans, to ship to Loki or Elasticsearch.
+    #[arg(long, value_enum, default_value_t = LogFormat::Text)]
+    pub log_format: LogFormat,
+
+    /// Runs the server when omitted.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Add hook entries reporting to this server to Claude's settings.json.
+    InstallHooks(InstallHooksArgs),
+    /// Forward one hook event from stdin to the server; run by Claude's hooks.
+    Hook(HookArgs),
+    /// Print a table of the server's active sessions.
+    Status(StatusArgs),
+    /// Follow the server's sessions live in the terminal.
+    Watch(WatchArgs),
+    /// Serve the server's sessions to Claude as MCP tools over stdio.
+    Mcp(McpArgs),
+    /// Replace this binary with the latest release.
+    SelfUpdate(SelfUpdateArgs),
+    /// Start the server at login with systemd or launchd.
+    #[command(subcommand)]
+    Service(ServiceCommand),
+}
+
+#[derive(Debug, Args)]
+pub struct SelfUpdateArgs {
+    /// Only report whether a newer release exists.
+    #[arg(long)]
+    pub check: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ServiceCommand {
+    /// Write the unit or agent definition and start it.
+    Install {
+        /// Print the definition instead of installing it.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Stop the service and remove its definition.
+    Uninstall,
+    /// Show what the service manager reports.
+    Status,
+}
+
+#[derive(Debug, Args)]
+pub struct StatusArgs {
+    /// Server to query.
+    /// Defaults to the local server, at the port in ~/.claude-monitor/port.
+    #[arg(long)]
+    pub server: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct HookArgs {
+    /// Server to post to.
+    /// Defaults to the local server, at the port in ~/.claude-monitor/port.
+    #[arg(long)]
+    pub server: Option<String>,
+
+    /// API key to post with, if the server has them; defaults to
+    /// $CLAUDE_MONITOR_API_KEY.
+    #[arg(long)]
+    pub api_key: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct WatchArgs {
+    /// Server to follow.
+    /// Defaults to the local server, at the port in ~/.claude-monitor/port.
+    #[arg(long)]
+    pub server: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct McpArgs {
+    /// Server the tools query.
+    /// Defaults to the local server, at the port in ~/.claude-monitor/port.
+    #[arg(long)]
+    pub server: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct InstallHooksArgs {
+    /// Print the resulting settings instead of writing them.
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Server the hooks post to. Without it they look up the local server's
+    /// port in ~/.claude-monitor/port each time they run.
+    #[arg(long)]
+    pub server: Option<String>,
+
+    /// API key the hooks post with, from POST /api/admin/keys.
+    #[arg(long)]
+    pub api_key: Option<String>,
+
+    /// Settings file to update; defaults to ~/.claude/settings.json.
+    #[arg(long)]
+    pub settings: Option<PathBuf>,
+}
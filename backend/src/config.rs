@@ -0,0 +1,72 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::{
+    aggregator::AggregatorConfig, announce::TextStreamConfig, backup::BackupConfig, cleanup::CleanupConfig,
+    ingest::EventsConfig, listen::ListenConfig, logging::LoggingConfig, maintenance::MaintenanceConfig,
+    notifier::NotificationConfig, pricing::PricingConfig, quota::QuotaConfig, relay::RelayConfig,
+    storage::StorageConfig, telemetry::TelemetryConfig, templates::TemplateConfig, trash::TrashConfig,
+    ws::WebSocketConfig,
+};
+
+/// User configuration, read from `~/.claude-monitor/config.toml`.
+/// Every field has a default, so a missing file or section is fine.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// The port the server listens on.
+    pub listen: ListenConfig,
+    pub transcripts: TranscriptConfig,
+    pub pricing: PricingConfig,
+    pub templates: TemplateConfig,
+    pub text_stream: TextStreamConfig,
+    pub quota: QuotaConfig,
+    pub notifications: NotificationConfig,
+    /// Forward events to a central monitor when set.
+    pub relay: Option<RelayConfig>,
+    /// Other monitors whose sessions are merged into this one's.
+    pub aggregator: AggregatorConfig,
+    /// Settings for the `/ws` stream.
+    pub websocket: WebSocketConfig,
+    /// Schedule for database upkeep.
+    pub maintenance: MaintenanceConfig,
+    /// Snapshots of the database.
+    pub backup: BackupConfig,
+    /// How long dismissed sessions can be restored.
+    pub trash: TrashConfig,
+    /// How often completed sessions are cleared, and after how long.
+    pub cleanup: CleanupConfig,
+    /// Limits on stored events.
+    pub events: EventsConfig,
+    /// Ceiling on the database's size.
+    pub storage: StorageConfig,
+    /// Log files, in addition to stderr.
+    pub logging: LoggingConfig,
+    /// Span export to an OpenTelemetry collector.
+    pub telemetry: TelemetryConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TranscriptConfig {
+    /// Tail the transcript JSONL files referenced by hook events.
+    pub enabled: bool,
+}
+
+impl Default for TranscriptConfig {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        toml::from_str(&raw).with_context(|| format!("failed to parse {}", path.display()))
+    }
+}
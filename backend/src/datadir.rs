@@ -0,0 +1,94 @@
+//! Versioning for the `~/.claude-monitor` data directory.
+//!
+//! `meta.json` records which schema and app version last wrote the directory,
+//! so rolling back to an older binary fails loudly instead of running old
+//! code against a database it doesn't understand.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+use crate::db;
+
+const META_FILE: &str = "meta.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataDirMeta {
+    pub schema_version: usize,
+    pub app_version: String,
+    pub created_at: DateTime<Utc>,
+}
+
+fn meta_path(dir: &Path) -> PathBuf {
+    dir.join(META_FILE)
+}
+
+pub fn read_meta(dir: &Path) -> Result<Option<DataDirMeta>> {
+    let path = meta_path(dir);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let raw = std::fs::read_to_string(&path).with_context(|| format!("failed to read {}", path.display()))?;
+    let meta = serde_json::from_str(&raw).with_context(|| format!("failed to parse {}", path.display()))?;
+    Ok(Some(meta))
+}
+
+/// Refuse to start against a data directory from a newer schema unless
+/// `force_downgrade` is set. Run before the database is opened.
+pub fn check_compatible(dir: &Path, force_downgrade: bool) -> Result<()> {
+    let Some(meta) = read_meta(dir)? else {
+        return Ok(());
+    };
+
+    let ours = db::schema_version();
+    if meta.schema_version > ours {
+        if !force_downgrade {
+            bail!(
+                "{} was written by claude-monitor {} (schema v{}), but this is {} (schema v{}).\n\
+                 Upgrade claude-monitor, or re-run with --force-downgrade to use it anyway.",
+                dir.display(),
+                meta.app_version,
+                meta.schema_version,
+                env!("CARGO_PKG_VERSION"),
+                ours,
+            );
+        }
+        warn!(
+            "Running against data written by schema v{} with schema v{} (--force-downgrade)",
+            meta.schema_version, ours
+        );
+    } else if version_newer(&meta.app_version, env!("CARGO_PKG_VERSION")) {
+        info!(
+            "Data directory was last used by claude-monitor {}; schema is compatible",
+            meta.app_version
+        );
+    }
+
+    Ok(())
+}
+
+/// Record the current versions after migrations ran, keeping the original creation time.
+pub fn write_meta(dir: &Path) -> Result<()> {
+    let created_at = read_meta(dir).ok().flatten().map(|m| m.created_at).unwrap_or_else(Utc::now);
+    let meta = DataDirMeta {
+        schema_version: db::schema_version(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at,
+    };
+
+    // Write-then-rename so a crash never leaves a truncated meta.json.
+    let path = meta_path(dir);
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_vec_pretty(&meta)?)
+        .with_context(|| format!("failed to write {}", tmp.display()))?;
+    std::fs::rename(&tmp, &path).with_context(|| format!("failed to write {}", path.display()))?;
+    Ok(())
+}
+
+/// Whether dotted version `a` is newer than `b`; non-numeric parts compare as 0.
+pub fn version_newer(a: &str, b: &str) -> bool {
+    let parse = |v: &str| -> Vec<u64> { v.split('.').map(|p| p.parse().unwrap_or(0)).collect() };
+    parse(a) > parse(b)
+}
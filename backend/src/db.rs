@@ -1,61 +1,197 @@
-use anyhow::Result;
-use chrono::Utc;
-use sqlx::{Row, SqlitePool};
+//! The SQLite store. Queries with a fixed text are checked against the schema
+//! at compile time by sqlx's macros, offline from the data in `.sqlx`; run
+//! `scripts/sqlx-prepare.sh` after changing one or adding a migration. Those
+//! built at runtime, PRAGMAs, and rows whose `FromRow` flattens, skips or
+//! decodes JSON fields go through the unchecked functions.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use libsqlite3_sys as sqlite;
+use std::{
+    collections::HashMap,
+    ffi::CStr,
+    future::Future,
+    path::Path,
+    sync::atomic::{AtomicU64, Ordering},
+};
+use sqlx::{sqlite::SqliteConnectOptions, ConnectOptions, Connection, SqliteConnection, SqlitePool};
+use tracing::instrument;
 use uuid::Uuid;
 
-use crate::models::{Agent, SessionWithAgents};
-
-const SCHEMA: &str = r#"
-CREATE TABLE IF NOT EXISTS sessions (
-    id TEXT PRIMARY KEY,
-    session_id TEXT UNIQUE NOT NULL,
-    project_path TEXT NOT NULL DEFAULT '',
-    project_name TEXT NOT NULL DEFAULT 'unknown',
-    status TEXT NOT NULL DEFAULT 'active',
-    created_at TEXT NOT NULL,
-    updated_at TEXT NOT NULL
-);
-
-CREATE TABLE IF NOT EXISTS agents (
-    id TEXT PRIMARY KEY,
-    session_id TEXT NOT NULL,
-    agent_name TEXT NOT NULL DEFAULT 'main',
-    parent_session_id TEXT,
-    status TEXT NOT NULL DEFAULT 'active',
-    created_at TEXT NOT NULL,
-    updated_at TEXT NOT NULL,
-    UNIQUE (session_id, agent_name),
-    FOREIGN KEY (session_id) REFERENCES sessions(session_id)
-);
-
-CREATE TABLE IF NOT EXISTS events (
-    id TEXT PRIMARY KEY,
-    session_id TEXT NOT NULL,
-    agent_name TEXT,
-    event_type TEXT NOT NULL,
-    payload TEXT NOT NULL DEFAULT '{}',
-    timestamp TEXT NOT NULL
-);
-
-CREATE INDEX IF NOT EXISTS idx_sessions_status ON sessions(status);
-CREATE INDEX IF NOT EXISTS idx_agents_session_id ON agents(session_id);
-CREATE INDEX IF NOT EXISTS idx_events_session_id ON events(session_id);
-"#;
+use crate::{
+    models::{
+        ActiveInterval, Agent, AgentUsage, AlertRule, AlertRuleInput, ApiKey, CleanupReport, CompletedSession,
+        DailyActivity, DailyTotals, DeadLetter, EditorSession, EventCountRow, EventRow, Export, ExportedAgent,
+        ExportedEvent, ExportedSession, ExportedSetting, HourlyEventCount, ImportReport, MaintenanceReport,
+        ModelUsageRow, NewEvent, NotificationRecord, OnConflict, ProjectAlias, SearchHit, SessionDuration, SessionEvent,
+        SessionUsage, SessionWithAgents, TimelineEntry, TokenUsage, ToolStats, WindowUsage,
+    },
+    stats,
+    store::{self, AgentRuntime, Dialect, Param, RuntimeEvent, SessionPage},
+};
+
+/// Tries at a write before a lock error is returned.
+const WRITE_ATTEMPTS: u32 = 4;
+/// Wait before the first retry, doubled each time.
+const RETRY_DELAY_MS: u64 = 20;
+
+/// Writes retried after finding the database locked, since startup.
+static RETRIED_WRITES: AtomicU64 = AtomicU64::new(0);
+
+pub fn retried_writes() -> u64 {
+    RETRIED_WRITES.load(Ordering::Relaxed)
+}
+
+/// Run a write, retrying with jittered backoff while SQLite reports the
+/// database busy or locked. The busy timeout already waits for the write
+/// lock; this covers what it can't, such as a transaction whose snapshot
+/// went stale while it waited.
+#[instrument(level = "debug", skip_all)]
+pub async fn retry<T, F, Fut>(mut write: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut delay = RETRY_DELAY_MS;
+    let mut attempt = 1;
+    loop {
+        match write().await {
+            Err(e) if attempt < WRITE_ATTEMPTS && is_busy(&e) => {
+                RETRIED_WRITES.fetch_add(1, Ordering::Relaxed);
+                // Spread out writers that collided so they don't collide again.
+                let jitter = (Uuid::new_v4().as_u128() % u128::from(delay)) as u64;
+                tokio::time::sleep(std::time::Duration::from_millis(delay + jitter)).await;
+                delay *= 2;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// SQLITE_BUSY or SQLITE_LOCKED, including their extended codes.
+fn is_busy(e: &anyhow::Error) -> bool {
+    let Some(sqlx::Error::Database(e)) = e.downcast_ref::<sqlx::Error>() else {
+        return false;
+    };
+    e.code()
+        .and_then(|code| code.parse::<i32>().ok())
+        .is_some_and(|code| matches!(code & 0xff, 5 | 6))
+}
+
+/// SQLITE_CORRUPT or SQLITE_NOTADB, or a failed `integrity_check`.
+pub fn is_corrupt(e: &anyhow::Error) -> bool {
+    if e.downcast_ref::<Corrupt>().is_some() {
+        return true;
+    }
+    let Some(sqlx::Error::Database(e)) = e.downcast_ref::<sqlx::Error>() else {
+        return false;
+    };
+    e.code()
+        .and_then(|code| code.parse::<i32>().ok())
+        .is_some_and(|code| matches!(code & 0xff, 11 | 26))
+}
 
+/// The first problem `PRAGMA integrity_check` found.
+#[derive(Debug)]
+pub struct Corrupt(String);
+
+impl std::fmt::Display for Corrupt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "integrity check failed: {}", self.0)
+    }
+}
+
+impl std::error::Error for Corrupt {}
+
+/// Run `PRAGMA integrity_check`, failing with [`Corrupt`] unless it reports
+/// nothing wrong.
+#[instrument(level = "debug", skip_all)]
+pub async fn integrity_check(pool: &SqlitePool) -> Result<()> {
+    let result: String = sqlx::query_scalar("PRAGMA integrity_check(1)").fetch_one(pool).await?;
+    if result != "ok" {
+        // Problems in a table come after a `*** in database main ***` line.
+        return Err(Corrupt(result.lines().last().unwrap_or_default().to_string()).into());
+    }
+    Ok(())
+}
+
+/// Base schema, applied on every start. Statements are idempotent.
+const SCHEMA: &str = include_str!("../migrations/schema.sql");
+
+// Incremental changes applied on top of SCHEMA, generated by build.rs from
+// `migrations/NNNN_*.sql`. Entry `i` moves the database from
+// `PRAGMA user_version = i` to `i + 1`, so only ever add new files.
+include!(concat!(env!("OUT_DIR"), "/migrations.rs"));
+
+/// Schema version this build migrates databases to.
+pub fn schema_version() -> usize {
+    MIGRATIONS.len()
+}
+
+#[instrument(level = "debug", skip_all)]
+pub async fn pending_migrations(pool: &SqlitePool) -> Result<usize> {
+    let version: i64 = sqlx::query_scalar("PRAGMA user_version").fetch_one(pool).await?;
+    Ok(MIGRATIONS.len().saturating_sub(version as usize))
+}
+
+#[instrument(level = "debug", skip_all)]
 pub async fn init_db(pool: &SqlitePool) -> Result<()> {
+    // One connection throughout: a migration that rebuilds a table must see
+    // its own DROP before the RENAME, which another pooled connection may not.
+    let mut conn = pool.acquire().await?;
+    execute_script(&mut conn, SCHEMA).await?;
+
+    let version: i64 = sqlx::query_scalar("PRAGMA user_version").fetch_one(&mut *conn).await?;
+    if version as usize > MIGRATIONS.len() {
+        tracing::warn!(
+            "Database is at schema v{version}, newer than this build's v{}; skipping migrations",
+            MIGRATIONS.len()
+        );
+    }
+    for (i, (name, migration)) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        tracing::info!("Applying migration {name}");
+        let mut tx = conn.begin().await?;
+        execute_script(&mut tx, migration).await?;
+        // PRAGMA does not accept bound parameters.
+        sqlx::query(&format!("PRAGMA user_version = {}", i + 1))
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+    }
+    Ok(())
+}
+
+async fn execute_script(conn: &mut SqliteConnection, script: &str) -> Result<()> {
     // sqlx::query does not support multiple statements; split and execute each.
-    for statement in SCHEMA.split(';') {
-        let trimmed = statement.trim();
-        if trimmed.is_empty() {
+    let mut statement = String::new();
+    for piece in script.split(';') {
+        statement.push_str(piece);
+        let code: Vec<&str> = statement
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with("--"))
+            .collect();
+        if code.is_empty() {
+            statement.clear();
+            continue;
+        }
+        // A trigger body has statements of its own; run it whole once its END arrives.
+        let trigger = code[0].to_ascii_uppercase().starts_with("CREATE TRIGGER");
+        if trigger && !code[code.len() - 1].eq_ignore_ascii_case("END") {
+            statement.push(';');
             continue;
         }
-        sqlx::query(trimmed).execute(pool).await?;
+        sqlx::query(statement.trim()).execute(&mut *conn).await?;
+        statement.clear();
     }
     Ok(())
 }
 
+#[instrument(level = "debug", skip_all)]
 pub async fn upsert_session(
     pool: &SqlitePool,
+    host: &str,
     session_id: &str,
     project_path: &str,
     project_name: &str,
@@ -64,32 +200,35 @@ pub async fn upsert_session(
     let now = Utc::now().to_rfc3339();
     let id = Uuid::new_v4().to_string();
 
-    sqlx::query(
+    sqlx::query!(
         r#"
-        INSERT INTO sessions (id, session_id, project_path, project_name, status, created_at, updated_at)
-        VALUES (?, ?, ?, ?, ?, ?, ?)
-        ON CONFLICT(session_id) DO UPDATE SET
+        INSERT INTO sessions (id, host, session_id, project_path, project_name, status, created_at, updated_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+        ON CONFLICT(host, session_id) DO UPDATE SET
             project_path = excluded.project_path,
             project_name = excluded.project_name,
             status = excluded.status,
             updated_at = excluded.updated_at
         "#,
+        id,
+        host,
+        session_id,
+        project_path,
+        project_name,
+        status,
+        now,
+        now,
     )
-    .bind(&id)
-    .bind(session_id)
-    .bind(project_path)
-    .bind(project_name)
-    .bind(status)
-    .bind(&now)
-    .bind(&now)
     .execute(pool)
     .await?;
 
     Ok(())
 }
 
+#[instrument(level = "debug", skip_all)]
 pub async fn upsert_agent(
     pool: &SqlitePool,
+    host: &str,
     session_id: &str,
     agent_name: &str,
     parent_session_id: Option<&str>,
@@ -98,264 +237,2142 @@ pub async fn upsert_agent(
     let now = Utc::now().to_rfc3339();
     let id = Uuid::new_v4().to_string();
 
-    sqlx::query(
+    sqlx::query!(
         r#"
-        INSERT INTO agents (id, session_id, agent_name, parent_session_id, status, created_at, updated_at)
-        VALUES (?, ?, ?, ?, ?, ?, ?)
-        ON CONFLICT(session_id, agent_name) DO UPDATE SET
+        INSERT INTO agents (id, host, session_id, agent_name, parent_session_id, status, created_at, updated_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+        ON CONFLICT(host, session_id, agent_name) DO UPDATE SET
             parent_session_id = excluded.parent_session_id,
             status = excluded.status,
             updated_at = excluded.updated_at
         "#,
+        id,
+        host,
+        session_id,
+        agent_name,
+        parent_session_id,
+        status,
+        now,
+        now,
     )
-    .bind(&id)
-    .bind(session_id)
-    .bind(agent_name)
-    .bind(parent_session_id)
-    .bind(status)
-    .bind(&now)
-    .bind(&now)
     .execute(pool)
     .await?;
 
     Ok(())
 }
 
-pub async fn insert_event(
+/// Insert queued events in one transaction, in order.
+#[instrument(level = "debug", skip_all)]
+pub async fn insert_events(pool: &SqlitePool, events: &[NewEvent]) -> Result<()> {
+    let mut conn = pool.acquire().await?;
+    // IMMEDIATE takes the write lock up front. A deferred transaction would
+    // fail with SQLITE_BUSY if another write landed between its first read
+    // and its first write, instead of waiting out the busy timeout.
+    sqlx::query("BEGIN IMMEDIATE").execute(&mut *conn).await?;
+    let result = insert_event_rows(&mut conn, events).await;
+    let end = if result.is_ok() { "COMMIT" } else { "ROLLBACK" };
+    sqlx::query(end).execute(&mut *conn).await?;
+    result
+}
+
+async fn insert_event_rows(conn: &mut SqliteConnection, events: &[NewEvent]) -> Result<()> {
+    for event in events {
+        let timestamp = event.timestamp.to_rfc3339();
+        sqlx::query!(
+            r#"
+            INSERT INTO events (id, host, session_id, agent_name, event_type, payload, timestamp, request_id, source)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+            event.id,
+            event.host,
+            event.session_id,
+            event.agent_name,
+            event.event_type,
+            event.payload,
+            timestamp,
+            event.request_id,
+            event.source,
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+#[instrument(level = "debug", skip_all)]
+pub async fn cap_session_events(pool: &SqlitePool, sessions: &[(String, String)], keep: u32) -> Result<u64> {
+    let mut pruned = 0;
+    for (host, session_id) in sessions {
+        // The newest event past the ones kept, if there are more than `keep`.
+        let last: Option<i64> = sqlx::query_scalar!(
+            r#"
+            SELECT rowid AS "rowid!" FROM events
+            WHERE host = ? AND session_id = ?
+            ORDER BY rowid DESC
+            LIMIT 1 OFFSET ?
+            "#,
+            host,
+            session_id,
+            keep,
+        )
+        .fetch_optional(pool)
+        .await?;
+        let Some(last) = last else { continue };
+
+        let mut tx = pool.begin().await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO hourly_events (hour, host, events)
+            SELECT strftime('%Y-%m-%dT%H:00:00Z', timestamp) AS hour, host, COUNT(*) FROM events
+            WHERE host = ? AND session_id = ? AND rowid <= ?
+            GROUP BY hour, host
+            ON CONFLICT(hour, host) DO UPDATE SET events = events + excluded.events
+            "#,
+            host,
+            session_id,
+            last,
+        )
+        .execute(&mut *tx)
+        .await?;
+        let deleted = sqlx::query!(
+            "DELETE FROM events WHERE host = ? AND session_id = ? AND rowid <= ?",
+            host,
+            session_id,
+            last,
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected() as i64;
+        sqlx::query!(
+            "UPDATE sessions SET pruned_events = pruned_events + ? WHERE host = ? AND session_id = ?",
+            deleted,
+            host,
+            session_id,
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        pruned += deleted as u64;
+    }
+    Ok(pruned)
+}
+
+/// The session's latest `limit` events, newest first, optionally only those
+/// of one agent. Events cleanup kept after removing the session aren't
+/// listed.
+#[instrument(level = "debug", skip_all)]
+pub async fn get_recent_events(
     pool: &SqlitePool,
     session_id: &str,
     agent_name: Option<&str>,
-    event_type: &str,
-    payload: &str,
-) -> Result<()> {
-    let id = Uuid::new_v4().to_string();
-    let now = Utc::now().to_rfc3339();
-
-    sqlx::query(
+    limit: u32,
+) -> Result<Vec<SessionEvent>> {
+    let events = sqlx::query_as(
         r#"
-        INSERT INTO events (id, session_id, agent_name, event_type, payload, timestamp)
-        VALUES (?, ?, ?, ?, ?, ?)
+        SELECT id, agent_name, event_type, payload, timestamp, request_id, source
+        FROM events
+        WHERE session_id = ? AND (? IS NULL OR agent_name = ?)
+        AND (host, session_id) IN (SELECT host, session_id FROM sessions)
+        ORDER BY timestamp DESC
+        LIMIT ?
         "#,
     )
-    .bind(&id)
     .bind(session_id)
     .bind(agent_name)
-    .bind(event_type)
-    .bind(payload)
-    .bind(&now)
-    .execute(pool)
+    .bind(agent_name)
+    .bind(limit)
+    .fetch_all(pool)
     .await?;
+    Ok(events)
+}
 
-    Ok(())
+/// Rowid of the newest event, 0 if there are none; the starting point for
+/// `get_events_after`.
+#[instrument(level = "debug", skip_all)]
+pub async fn latest_event_seq(pool: &SqlitePool) -> Result<i64> {
+    let seq: Option<i64> = sqlx::query_scalar!("SELECT MAX(rowid) FROM events").fetch_one(pool).await?;
+    Ok(seq.unwrap_or(0))
 }
 
-pub async fn get_active_sessions(pool: &SqlitePool) -> Result<Vec<SessionWithAgents>> {
-    let rows = sqlx::query(
+/// The session's events inserted after rowid `after`, oldest first, each with
+/// its rowid.
+#[instrument(level = "debug", skip_all)]
+pub async fn get_events_after(pool: &SqlitePool, session_id: &str, after: i64) -> Result<Vec<(i64, SessionEvent)>> {
+    let rows: Vec<EventAfterRow> = sqlx::query_as(
         r#"
-        SELECT id, session_id, project_path, project_name, status, created_at, updated_at
-        FROM sessions
-        WHERE status != 'completed'
-        ORDER BY created_at DESC
+        SELECT rowid, id, agent_name, event_type, payload, timestamp, request_id, source
+        FROM events
+        WHERE session_id = ? AND rowid > ?
+        ORDER BY rowid
         "#,
     )
+    .bind(session_id)
+    .bind(after)
     .fetch_all(pool)
     .await?;
 
-    let mut sessions = Vec::with_capacity(rows.len());
-    for row in rows {
-        let session_id: String = row.get("session_id");
-        let agents = get_agents_for_session(pool, &session_id).await?;
-
-        let id_str: String = row.get("id");
-        let created_at_str: String = row.get("created_at");
-        let updated_at_str: String = row.get("updated_at");
-
-        let session = SessionWithAgents {
-            id: id_str.parse().unwrap_or_else(|_| Uuid::new_v4()),
-            session_id,
-            project_name: row.get("project_name"),
-            project_path: row.get("project_path"),
-            status: row.get("status"),
-            created_at: created_at_str.parse().unwrap_or_else(|_| Utc::now()),
-            updated_at: updated_at_str.parse().unwrap_or_else(|_| Utc::now()),
-            agents,
-        };
-        sessions.push(session);
-    }
+    Ok(rows.into_iter().map(|row| (row.rowid, row.event)).collect())
+}
 
-    Ok(sessions)
+#[derive(sqlx::FromRow)]
+struct EventAfterRow {
+    rowid: i64,
+    #[sqlx(flatten)]
+    event: SessionEvent,
 }
 
-async fn get_agents_for_session(pool: &SqlitePool, session_id: &str) -> Result<Vec<Agent>> {
-    let rows = sqlx::query(
+/// Events whose tool name or message matches the FTS5 `query`, best match
+/// first, with their sessions.
+#[instrument(level = "debug", skip_all)]
+pub async fn search_events(pool: &SqlitePool, query: &str, host: Option<&str>, limit: u32) -> Result<Vec<SearchHit>> {
+    let hits = sqlx::query_as(
         r#"
-        SELECT id, session_id, agent_name, parent_session_id, status, created_at, updated_at
-        FROM agents
-        WHERE session_id = ?
-        ORDER BY created_at ASC
+        SELECT e.id, e.host, e.session_id, s.project_name, s.status AS session_status, e.agent_name, e.event_type,
+               e.payload,
+               e.timestamp, e.request_id, e.source, snippet(events_fts, -1, '[', ']', '…', 12) AS snippet
+        FROM events_fts
+        JOIN events e ON e.rowid = events_fts.rowid
+        JOIN sessions s ON s.host = e.host AND s.session_id = e.session_id
+        WHERE events_fts MATCH ? AND (? IS NULL OR e.host = ?)
+        ORDER BY rank
+        LIMIT ?
         "#,
     )
-    .bind(session_id)
+    .bind(query)
+    .bind(host)
+    .bind(host)
+    .bind(limit)
     .fetch_all(pool)
     .await?;
 
-    let agents = rows
-        .iter()
-        .map(|row| {
-            let id_str: String = row.get("id");
-            let created_at_str: String = row.get("created_at");
-            let updated_at_str: String = row.get("updated_at");
-
-            Agent {
-                id: id_str.parse().unwrap_or_else(|_| Uuid::new_v4()),
-                session_id: row.get("session_id"),
-                agent_name: row.get("agent_name"),
-                parent_session_id: row.get("parent_session_id"),
-                status: row.get("status"),
-                created_at: created_at_str.parse().unwrap_or_else(|_| Utc::now()),
-                updated_at: updated_at_str.parse().unwrap_or_else(|_| Utc::now()),
-            }
-        })
-        .collect();
-
-    Ok(agents)
+    Ok(hits)
 }
 
-pub async fn mark_session_completed(pool: &SqlitePool, session_id: &str) -> Result<()> {
+/// Open a tool invocation on pre_tool_use. It stays open (ended_at NULL) until
+/// the matching post_tool_use arrives.
+#[instrument(level = "debug", skip_all)]
+pub async fn start_tool_invocation(
+    pool: &SqlitePool,
+    host: &str,
+    session_id: &str,
+    agent_name: &str,
+    tool_name: &str,
+    tool_use_id: Option<&str>,
+) -> Result<()> {
+    let id = Uuid::new_v4().to_string();
     let now = Utc::now().to_rfc3339();
 
-    sqlx::query(
+    sqlx::query!(
         r#"
-        UPDATE sessions SET status = 'completed', updated_at = ?
-        WHERE session_id = ?
+        INSERT INTO tool_invocations (id, host, session_id, agent_name, tool_name, tool_use_id, started_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?)
         "#,
+        id,
+        host,
+        session_id,
+        agent_name,
+        tool_name,
+        tool_use_id,
+        now,
     )
-    .bind(&now)
-    .bind(session_id)
     .execute(pool)
     .await?;
 
-    sqlx::query(
+    Ok(())
+}
+
+/// Close the open invocation matching a post_tool_use and record its duration.
+/// Correlates on tool_use_id when the hook provides one, otherwise on the most
+/// recent open invocation of the same tool for this session/agent.
+#[instrument(level = "debug", skip_all)]
+pub async fn finish_tool_invocation(
+    pool: &SqlitePool,
+    host: &str,
+    session_id: &str,
+    agent_name: &str,
+    tool_name: &str,
+    tool_use_id: Option<&str>,
+) -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+
+    sqlx::query!(
         r#"
-        UPDATE agents SET status = 'completed', updated_at = ?
-        WHERE session_id = ?
+        UPDATE tool_invocations
+        SET ended_at = ?,
+            duration_ms = CAST((julianday(?) - julianday(started_at)) * 86400000 AS INTEGER)
+        WHERE id = (
+            SELECT id FROM tool_invocations
+            WHERE host = ? AND session_id = ? AND agent_name = ? AND ended_at IS NULL
+            AND (tool_use_id = ? OR (? IS NULL AND tool_name = ?))
+            ORDER BY started_at DESC
+            LIMIT 1
+        )
         "#,
+        now,
+        now,
+        host,
+        session_id,
+        agent_name,
+        tool_use_id,
+        tool_use_id,
+        tool_name,
     )
-    .bind(&now)
-    .bind(session_id)
     .execute(pool)
     .await?;
 
     Ok(())
 }
 
-/// Move 'active' → 'idle' when Claude finishes a turn.
-/// Idle sessions stay visible until the user explicitly clears them.
-/// 'waiting_input' and 'needs_permission' sessions are left untouched.
-pub async fn mark_active_session_idle(pool: &SqlitePool, session_id: &str) -> Result<()> {
-    let now = Utc::now().to_rfc3339();
+/// Record the tool a session is running right now (set on pre_tool_use).
+#[instrument(level = "debug", skip_all)]
+pub async fn set_current_tool(pool: &SqlitePool, host: &str, session_id: &str, tool_name: &str) -> Result<()> {
+    sqlx::query!("UPDATE sessions SET current_tool = ? WHERE host = ? AND session_id = ?", tool_name, host, session_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
 
-    sqlx::query(
+/// Clear the session's current tool. With `tool_name`, only clears if that tool
+/// is still the current one, so a late post_tool_use doesn't wipe a newer tool.
+#[instrument(level = "debug", skip_all)]
+pub async fn clear_current_tool(
+    pool: &SqlitePool,
+    host: &str,
+    session_id: &str,
+    tool_name: Option<&str>,
+) -> Result<()> {
+    sqlx::query!(
         r#"
-        UPDATE sessions SET status = 'idle', updated_at = ?
-        WHERE session_id = ? AND status = 'active'
+        UPDATE sessions SET current_tool = NULL
+        WHERE host = ? AND session_id = ? AND (? IS NULL OR current_tool = ?)
         "#,
+        host,
+        session_id,
+        tool_name,
+        tool_name,
     )
-    .bind(&now)
-    .bind(session_id)
     .execute(pool)
     .await?;
+    Ok(())
+}
+
+/// Record the model a session reports through its hooks.
+#[instrument(level = "debug", skip_all)]
+pub async fn set_session_model(pool: &SqlitePool, host: &str, session_id: &str, model: &str) -> Result<()> {
+    sqlx::query!("UPDATE sessions SET model = ? WHERE host = ? AND session_id = ?", model, host, session_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+#[instrument(level = "debug", skip_all)]
+pub async fn set_session_git_branch(pool: &SqlitePool, host: &str, session_id: &str, branch: &str) -> Result<()> {
+    sqlx::query!("UPDATE sessions SET git_branch = ? WHERE host = ? AND session_id = ?", branch, host, session_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Keep the latest notification/stop message so clients can preview what Claude said.
+#[instrument(level = "debug", skip_all)]
+pub async fn set_last_message(pool: &SqlitePool, host: &str, session_id: &str, message: &str) -> Result<()> {
+    sqlx::query!("UPDATE sessions SET last_message = ? WHERE host = ? AND session_id = ?", message, host, session_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
 
-    sqlx::query(
+/// Store what the transcript tailer last saw. `None` keeps the previous value.
+#[instrument(level = "debug", skip_all)]
+pub async fn set_transcript_summary(
+    pool: &SqlitePool,
+    host: &str,
+    session_id: &str,
+    assistant_text: Option<&str>,
+    tool_call: Option<&str>,
+    model: Option<&str>,
+) -> Result<()> {
+    sqlx::query!(
         r#"
-        UPDATE agents SET status = 'idle', updated_at = ?
-        WHERE session_id = ? AND status = 'active'
+        UPDATE sessions SET
+            last_assistant_text = COALESCE(?, last_assistant_text),
+            last_tool_call = COALESCE(?, last_tool_call),
+            model = COALESCE(?, model)
+        WHERE host = ? AND session_id = ?
         "#,
+        assistant_text,
+        tool_call,
+        model,
+        host,
+        session_id,
     )
-    .bind(&now)
-    .bind(session_id)
     .execute(pool)
     .await?;
-
     Ok(())
 }
 
-/// Mark a session completed ONLY if it is currently 'active'.
-/// Sessions in 'waiting_input' or 'needs_permission' state are left untouched
-/// so they remain visible in the overlay until the user acknowledges them.
-pub async fn mark_active_session_completed(pool: &SqlitePool, session_id: &str) -> Result<()> {
+/// Record one assistant message's token usage. Transcripts repeat the same
+/// message across lines (one per content block), so rows are keyed on
+/// message_id and re-reads keep the largest counts instead of double counting.
+#[instrument(level = "debug", skip_all)]
+pub async fn record_usage(
+    pool: &SqlitePool,
+    session_id: &str,
+    agent_name: &str,
+    message_id: Option<&str>,
+    model: Option<&str>,
+    usage: &TokenUsage,
+) -> Result<()> {
+    let id = Uuid::new_v4().to_string();
     let now = Utc::now().to_rfc3339();
 
-    sqlx::query(
+    sqlx::query!(
         r#"
-        UPDATE sessions SET status = 'completed', updated_at = ?
-        WHERE session_id = ? AND status = 'active'
+        INSERT INTO usage (id, session_id, agent_name, message_id, model, input_tokens, output_tokens,
+                           cache_creation_input_tokens, cache_read_input_tokens, recorded_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        ON CONFLICT(message_id) DO UPDATE SET
+            model = COALESCE(excluded.model, model),
+            input_tokens = MAX(input_tokens, excluded.input_tokens),
+            output_tokens = MAX(output_tokens, excluded.output_tokens),
+            cache_creation_input_tokens = MAX(cache_creation_input_tokens, excluded.cache_creation_input_tokens),
+            cache_read_input_tokens = MAX(cache_read_input_tokens, excluded.cache_read_input_tokens)
         "#,
+        id,
+        session_id,
+        agent_name,
+        message_id,
+        model,
+        usage.input_tokens,
+        usage.output_tokens,
+        usage.cache_creation_input_tokens,
+        usage.cache_read_input_tokens,
+        now,
     )
-    .bind(&now)
-    .bind(session_id)
     .execute(pool)
     .await?;
 
-    // Only complete agents for the session if the session was actually moved to completed.
-    sqlx::query(
+    Ok(())
+}
+
+/// Token totals for a session, overall and broken down by agent.
+#[instrument(level = "debug", skip_all)]
+pub async fn get_session_usage(pool: &SqlitePool, session_id: &str) -> Result<SessionUsage> {
+    let agents: Vec<AgentUsage> = sqlx::query_as(
         r#"
-        UPDATE agents SET status = 'completed', updated_at = ?
-        WHERE session_id = ? AND status = 'active'
+        SELECT agent_name,
+               SUM(input_tokens) AS input_tokens,
+               SUM(output_tokens) AS output_tokens,
+               SUM(cache_creation_input_tokens) AS cache_creation_input_tokens,
+               SUM(cache_read_input_tokens) AS cache_read_input_tokens
+        FROM usage
+        WHERE session_id = ?
+        GROUP BY agent_name
+        ORDER BY agent_name
         "#,
     )
-    .bind(&now)
     .bind(session_id)
-    .execute(pool)
+    .fetch_all(pool)
     .await?;
+    Ok(store::usage_totals(session_id, agents))
+}
 
-    Ok(())
+/// Token usage grouped by session and model, for cost estimation. Covers every
+/// session still in the database, optionally limited to usage recorded within
+/// `[from, to)`, newest activity first.
+#[instrument(level = "debug", skip_all)]
+pub async fn get_usage_by_session_model(
+    pool: &SqlitePool,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+) -> Result<Vec<ModelUsageRow>> {
+    let from = from.map(|t| t.to_rfc3339());
+    let to = to.map(|t| t.to_rfc3339());
+
+    let rows = sqlx::query_as(
+        r#"
+        SELECT s.host, u.session_id, s.project_name, u.model,
+               SUM(u.input_tokens) AS input_tokens,
+               SUM(u.output_tokens) AS output_tokens,
+               SUM(u.cache_creation_input_tokens) AS cache_creation_input_tokens,
+               SUM(u.cache_read_input_tokens) AS cache_read_input_tokens
+        FROM usage u
+        JOIN sessions s ON s.session_id = u.session_id
+        WHERE (? IS NULL OR julianday(u.recorded_at) >= julianday(?))
+        AND (? IS NULL OR julianday(u.recorded_at) < julianday(?))
+        GROUP BY u.session_id, u.model
+        ORDER BY MAX(u.recorded_at) DESC
+        "#,
+    )
+    .bind(&from)
+    .bind(&from)
+    .bind(&to)
+    .bind(&to)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
 }
 
-/// Delete all rows from sessions, agents, events — but keep the tables intact.
-pub async fn clear_all_sessions(pool: &SqlitePool) -> Result<()> {
-    // Order matters: agents and events reference sessions via session_id.
-    sqlx::query("DELETE FROM events").execute(pool).await?;
-    sqlx::query("DELETE FROM agents").execute(pool).await?;
-    sqlx::query("DELETE FROM sessions").execute(pool).await?;
-    Ok(())
+/// Usage of the sessions with these IDs, grouped by session and model.
+#[instrument(level = "debug", skip_all)]
+pub async fn get_usage_for_sessions(pool: &SqlitePool, session_ids: &[String]) -> Result<Vec<ModelUsageRow>> {
+    if session_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    // One JSON array rather than a placeholder per ID.
+    let rows = sqlx::query_as(
+        r#"
+        SELECT s.host, u.session_id, s.project_name, u.model,
+               SUM(u.input_tokens) AS input_tokens,
+               SUM(u.output_tokens) AS output_tokens,
+               SUM(u.cache_creation_input_tokens) AS cache_creation_input_tokens,
+               SUM(u.cache_read_input_tokens) AS cache_read_input_tokens
+        FROM usage u
+        JOIN sessions s ON s.session_id = u.session_id
+        WHERE u.session_id IN (SELECT value FROM json_each(?))
+        GROUP BY u.session_id, u.model
+        "#,
+    )
+    .bind(serde_json::to_string(session_ids)?)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
 }
 
-pub async fn cleanup_old_completed(pool: &SqlitePool) -> Result<()> {
-    // RFC3339 strings stored in SQLite are sortable; sqlite's datetime() understands ISO-8601.
-    sqlx::query(
+/// Usage recorded at or after `since` by sessions that haven't completed,
+/// grouped by session and model.
+#[instrument(level = "debug", skip_all)]
+pub async fn get_active_usage_since(pool: &SqlitePool, since: DateTime<Utc>) -> Result<Vec<ModelUsageRow>> {
+    let rows = sqlx::query_as(
         r#"
-        DELETE FROM agents WHERE session_id IN (
-            SELECT session_id FROM sessions
-            WHERE status = 'completed'
-            AND datetime(updated_at) <= datetime('now', '-60 seconds')
+        SELECT s.host, u.session_id, s.project_name, u.model,
+               SUM(u.input_tokens) AS input_tokens,
+               SUM(u.output_tokens) AS output_tokens,
+               SUM(u.cache_creation_input_tokens) AS cache_creation_input_tokens,
+               SUM(u.cache_read_input_tokens) AS cache_read_input_tokens
+        FROM usage u
+        JOIN sessions s ON s.session_id = u.session_id
+        WHERE s.status != 'completed'
+        AND julianday(u.recorded_at) >= julianday(?)
+        GROUP BY u.session_id, u.model
+        "#,
+    )
+    .bind(since.to_rfc3339())
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
+
+/// Event history for every session with at least one event in `[from, to)`,
+/// ordered per session by time. Includes each session's events before `from`
+/// so its status at the start of the range can be replayed.
+#[instrument(level = "debug", skip_all)]
+pub async fn get_session_events_in_range(
+    pool: &SqlitePool,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+) -> Result<Vec<EventRow>> {
+    let from = from.map(|t| t.to_rfc3339());
+    let to = to.map(|t| t.to_rfc3339());
+
+    let rows = sqlx::query_as!(
+        EventRow,
+        r#"
+        SELECT e.host, e.session_id, s.project_name, e.event_type,
+               COALESCE(e.agent_name, 'main') AS "agent_name!: String",
+               COALESCE(json_extract(e.payload, '$.needs_input'), 0) AS "needs_input!: bool",
+               e.timestamp AS "timestamp: DateTime<Utc>"
+        FROM events e
+        JOIN sessions s ON s.host = e.host AND s.session_id = e.session_id
+        WHERE (? IS NULL OR julianday(e.timestamp) < julianday(?))
+        AND (e.host, e.session_id) IN (
+            SELECT host, session_id FROM events
+            WHERE (? IS NULL OR julianday(timestamp) >= julianday(?))
+            AND (? IS NULL OR julianday(timestamp) < julianday(?))
         )
+        ORDER BY e.host, e.session_id, e.timestamp
         "#,
+        to,
+        to,
+        from,
+        from,
+        to,
+        to,
     )
-    .execute(pool)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
+
+/// One session's event history, oldest first.
+#[instrument(level = "debug", skip_all)]
+pub async fn get_session_event_rows(pool: &SqlitePool, session_id: &str) -> Result<Vec<EventRow>> {
+    let rows = sqlx::query_as!(
+        EventRow,
+        r#"
+        SELECT e.host, e.session_id, s.project_name, e.event_type,
+               COALESCE(e.agent_name, 'main') AS "agent_name!: String",
+               COALESCE(json_extract(e.payload, '$.needs_input'), 0) AS "needs_input!: bool",
+               e.timestamp AS "timestamp: DateTime<Utc>"
+        FROM events e
+        JOIN sessions s ON s.session_id = e.session_id
+        WHERE e.session_id = ?
+        ORDER BY e.timestamp
+        "#,
+        session_id,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
+
+/// One session's tool calls as timeline entries, oldest first.
+#[instrument(level = "debug", skip_all)]
+pub async fn get_tool_timeline(pool: &SqlitePool, session_id: &str) -> Result<Vec<TimelineEntry>> {
+    let calls = sqlx::query_as!(
+        ToolCallRow,
+        r#"
+        SELECT agent_name, tool_name, started_at AS "started_at: DateTime<Utc>",
+               ended_at AS "ended_at: DateTime<Utc>", duration_ms
+        FROM tool_invocations
+        WHERE session_id = ?
+        ORDER BY started_at
+        "#,
+        session_id,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(calls
+        .into_iter()
+        .map(|call| TimelineEntry::Tool {
+            at: call.started_at,
+            agent_name: call.agent_name,
+            tool_name: call.tool_name,
+            ended_at: call.ended_at,
+            duration_ms: call.duration_ms,
+        })
+        .collect())
+}
+
+struct ToolCallRow {
+    agent_name: String,
+    tool_name: String,
+    started_at: DateTime<Utc>,
+    ended_at: Option<DateTime<Utc>>,
+    duration_ms: Option<i64>,
+}
+
+/// Completed sessions still in the database, with when they completed.
+#[instrument(level = "debug", skip_all)]
+pub async fn get_completed_sessions(pool: &SqlitePool) -> Result<Vec<CompletedSession>> {
+    let sessions = sqlx::query_as!(
+        CompletedSession,
+        r#"SELECT host, session_id, updated_at AS "ended_at: DateTime<Utc>" FROM sessions WHERE status = 'completed'"#,
+    )
+    .fetch_all(pool)
     .await?;
+    Ok(sessions)
+}
 
-    sqlx::query(
+/// Completed sessions that ended within `[from, to)`, both those archived by
+/// `cleanup_old_completed` and those not yet cleaned up.
+#[instrument(level = "debug", skip_all)]
+pub async fn get_session_durations(
+    pool: &SqlitePool,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+) -> Result<Vec<SessionDuration>> {
+    let from = from.map(|t| t.to_rfc3339());
+    let to = to.map(|t| t.to_rfc3339());
+
+    let durations: Vec<SessionDuration> = sqlx::query_as(
         r#"
-        DELETE FROM events WHERE session_id IN (
-            SELECT session_id FROM sessions
+        SELECT host, session_id, project_name, started_at, ended_at FROM (
+            SELECT host, session_id, project_name, started_at, ended_at FROM session_durations
+            UNION ALL
+            SELECT host, session_id, project_name, created_at, updated_at FROM sessions
             WHERE status = 'completed'
-            AND datetime(updated_at) <= datetime('now', '-60 seconds')
         )
+        WHERE (? IS NULL OR julianday(ended_at) >= julianday(?))
+        AND (? IS NULL OR julianday(ended_at) < julianday(?))
         "#,
     )
-    .execute(pool)
+    .bind(&from)
+    .bind(&from)
+    .bind(&to)
+    .bind(&to)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(durations
+        .into_iter()
+        .map(|d| SessionDuration { duration_seconds: (d.ended_at - d.started_at).num_seconds().max(0), ..d })
+        .collect())
+}
+
+/// Events per UTC hour and host for hours starting within `[from, to)`,
+/// both those archived by `cleanup_old_completed` and those still in the
+/// database.
+#[instrument(level = "debug", skip_all)]
+pub async fn get_hourly_event_counts(
+    pool: &SqlitePool,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+) -> Result<Vec<HourlyEventCount>> {
+    let from = from.map(|t| t.to_rfc3339());
+    let to = to.map(|t| t.to_rfc3339());
+
+    let counts = sqlx::query_as!(
+        HourlyEventCount,
+        r#"
+        SELECT hour AS "hour!: DateTime<Utc>", host AS "host!", SUM(events) AS "events!: i64" FROM (
+            SELECT hour, host, events FROM hourly_events
+            UNION ALL
+            SELECT strftime('%Y-%m-%dT%H:00:00Z', timestamp), host, 1 FROM events
+        )
+        WHERE (? IS NULL OR julianday(hour) >= julianday(?))
+        AND (? IS NULL OR julianday(hour) < julianday(?))
+        GROUP BY hour, host
+        "#,
+        from,
+        from,
+        to,
+        to,
+    )
+    .fetch_all(pool)
     .await?;
 
-    sqlx::query(
+    Ok(counts)
+}
+
+/// Active stretches archived by `cleanup_old_completed` that overlap
+/// `[from, to)`.
+#[instrument(level = "debug", skip_all)]
+pub async fn get_archived_intervals(
+    pool: &SqlitePool,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<ActiveInterval>> {
+    let (from, to) = (from.to_rfc3339(), to.to_rfc3339());
+    let intervals = sqlx::query_as!(
+        ActiveInterval,
         r#"
-        DELETE FROM sessions
-        WHERE status = 'completed'
-        AND datetime(updated_at) <= datetime('now', '-60 seconds')
+        SELECT host, session_id, project_name, started_at AS "start: DateTime<Utc>", ended_at AS "end: DateTime<Utc>"
+        FROM active_intervals
+        WHERE julianday(ended_at) > julianday(?) AND julianday(started_at) < julianday(?)
+        ORDER BY started_at
         "#,
+        from,
+        to,
     )
-    .execute(pool)
+    .fetch_all(pool)
     .await?;
 
-    Ok(())
+    Ok(intervals)
+}
+
+/// Daily totals archived by `cleanup_old_completed`.
+#[instrument(level = "debug", skip_all)]
+pub async fn get_daily_stats(pool: &SqlitePool) -> Result<DailyActivity> {
+    let rows = sqlx::query!(
+        r#"SELECT day AS "day: NaiveDate", host, sessions, active_seconds, completions FROM daily_stats"#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let totals = DailyTotals {
+                sessions: row.sessions,
+                active_seconds: row.active_seconds,
+                completions: row.completions,
+            };
+            ((row.day, row.host), totals)
+        })
+        .collect())
+}
+
+/// Events recorded at or after `since`, counted per host and minute.
+#[instrument(level = "debug", skip_all)]
+pub async fn get_event_counts_since(pool: &SqlitePool, since: DateTime<Utc>) -> Result<Vec<EventCountRow>> {
+    let since = since.to_rfc3339();
+    let counts = sqlx::query_as!(
+        EventCountRow,
+        r#"
+        SELECT host AS "host!", minute AS "minute!: i64", COUNT(*) AS "events!: i64" FROM (
+            SELECT host, CAST((julianday(timestamp) - julianday(?)) * 1440 AS INTEGER) AS minute
+            FROM events
+            WHERE julianday(timestamp) >= julianday(?)
+        )
+        GROUP BY host, minute
+        "#,
+        since,
+        since,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(counts)
+}
+
+/// Usage recorded at or after `since`, including rows kept for sessions that
+/// have since been cleaned up.
+#[instrument(level = "debug", skip_all)]
+pub async fn get_usage_since(pool: &SqlitePool, since: DateTime<Utc>) -> Result<WindowUsage> {
+    let usage = sqlx::query_as(
+        r#"
+        SELECT COALESCE(SUM(input_tokens), 0) AS input_tokens,
+               COALESCE(SUM(output_tokens), 0) AS output_tokens,
+               COALESCE(SUM(cache_creation_input_tokens), 0) AS cache_creation_input_tokens,
+               COALESCE(SUM(cache_read_input_tokens), 0) AS cache_read_input_tokens,
+               COUNT(*) AS messages,
+               MIN(recorded_at) AS oldest
+        FROM usage
+        WHERE julianday(recorded_at) >= julianday(?)
+        "#,
+    )
+    .bind(since.to_rfc3339())
+    .fetch_one(pool)
+    .await?;
+    Ok(usage)
+}
+
+#[instrument(level = "debug", skip_all)]
+pub async fn get_tool_stats(pool: &SqlitePool, session_id: &str) -> Result<Vec<ToolStats>> {
+    let stats = sqlx::query_as!(
+        ToolStats,
+        r#"
+        SELECT tool_name AS "tool_name!",
+               COUNT(*) AS "invocations!: i64",
+               COUNT(duration_ms) AS "completed!: i64",
+               COUNT(*) - COUNT(duration_ms) AS "in_flight!: i64",
+               COALESCE(SUM(duration_ms), 0) AS "total_ms!: i64",
+               AVG(duration_ms) AS "avg_ms: f64",
+               MIN(duration_ms) AS "min_ms: i64",
+               MAX(duration_ms) AS "max_ms: i64"
+        FROM tool_invocations
+        WHERE session_id = ?
+        GROUP BY tool_name
+        ORDER BY COALESCE(SUM(duration_ms), 0) DESC
+        "#,
+        session_id,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(stats)
+}
+
+#[instrument(level = "debug", skip_all)]
+pub async fn get_active_sessions(pool: &SqlitePool) -> Result<Vec<SessionWithAgents>> {
+    let mut sessions: Vec<SessionWithAgents> = sqlx::query_as(
+        r#"
+        SELECT id, host, session_id, project_path, project_name, status, model, git_branch, current_tool,
+               last_message, last_assistant_text, last_tool_call, pinned, muted, hidden, notes, created_at, updated_at,
+               pruned_events
+        FROM sessions
+        WHERE (status != 'completed' OR pinned = 1) AND deleted_at IS NULL
+        ORDER BY pinned DESC, created_at DESC
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+    fill_sessions(pool, &mut sessions).await?;
+    Ok(sessions)
+}
+
+/// How SQLite writes a [`SessionPage`]'s comparisons. Times are RFC 3339
+/// text in UTC, as `to_rfc3339` writes them, which sorts as the times do;
+/// text already compares byte by byte.
+const DIALECT: Dialect = Dialect { placeholder: |_| "?".to_string(), bytewise: str::to_string };
+
+fn bind_params<'q, O>(
+    mut query: sqlx::query::QueryAs<'q, sqlx::Sqlite, O, sqlx::sqlite::SqliteArguments<'q>>,
+    params: &'q [Param],
+) -> sqlx::query::QueryAs<'q, sqlx::Sqlite, O, sqlx::sqlite::SqliteArguments<'q>> {
+    for param in params {
+        query = match param {
+            Param::Text(text) => query.bind(text.as_str()),
+            Param::Time(time) => query.bind(time.to_rfc3339()),
+            Param::Bool(flag) => query.bind(*flag),
+            Param::Int(int) => query.bind(*int),
+        };
+    }
+    query
+}
+
+#[instrument(level = "debug", skip_all)]
+pub async fn get_session_page(pool: &SqlitePool, page: &SessionPage<'_>) -> Result<(Vec<SessionWithAgents>, u64)> {
+    let sql = page.sql(&DIALECT);
+    let select = format!(
+        r#"
+        SELECT s.id, s.host, s.session_id, s.project_path, s.project_name, s.status, s.model, s.git_branch,
+               s.current_tool, s.last_message, s.last_assistant_text, s.last_tool_call, s.pinned, s.muted, s.hidden,
+               s.notes, s.created_at, s.updated_at, s.pruned_events
+        {}{}
+        ORDER BY {}{}
+        "#,
+        sql.from, sql.after, sql.order_by, sql.limit
+    );
+    let query = bind_params(sqlx::query_as(&select), &sql.params);
+    let mut sessions: Vec<SessionWithAgents> = bind_params(query, &sql.after_params).fetch_all(pool).await?;
+    fill_sessions(pool, &mut sessions).await?;
+    let count = format!("SELECT COUNT(*) {}", sql.from);
+    let (total,): (i64,) = bind_params(sqlx::query_as(&count), &sql.params).fetch_one(pool).await?;
+    Ok((sessions, total as u64))
+}
+
+#[instrument(level = "debug", skip_all)]
+pub async fn get_trash(pool: &SqlitePool) -> Result<Vec<SessionWithAgents>> {
+    let mut sessions: Vec<SessionWithAgents> = sqlx::query_as(
+        r#"
+        SELECT id, host, session_id, project_path, project_name, status, model, git_branch, current_tool,
+               last_message, last_assistant_text, last_tool_call, pinned, muted, hidden, notes, created_at, updated_at,
+               pruned_events, deleted_at
+        FROM sessions
+        WHERE deleted_at IS NOT NULL
+        ORDER BY deleted_at DESC
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+    fill_sessions(pool, &mut sessions).await?;
+    Ok(sessions)
+}
+
+/// Attach each session's agents, usage and tags.
+async fn fill_sessions(pool: &SqlitePool, sessions: &mut [SessionWithAgents]) -> Result<()> {
+    for session in sessions {
+        session.agents = get_agents_for_session(pool, &session.host, &session.session_id).await?;
+        session.usage = get_session_usage(pool, &session.session_id).await?.totals;
+        session.tags = get_session_tags(pool, Some(&session.host), &session.session_id).await?;
+    }
+    Ok(())
+}
+
+/// Non-completed sessions whose project_path is `workspace` or lies beneath it.
+#[instrument(level = "debug", skip_all)]
+pub async fn get_workspace_sessions(pool: &SqlitePool, workspace: &str) -> Result<Vec<EditorSession>> {
+    let (workspace, prefix) = store::workspace_prefix(workspace);
+    let sessions = sqlx::query_as!(
+        EditorSession,
+        r#"
+        SELECT s.host, s.session_id, s.project_name, s.project_path, s.status,
+               s.updated_at AS "updated_at: DateTime<Utc>",
+               (SELECT COUNT(*) FROM agents a
+                WHERE a.host = s.host AND a.session_id = s.session_id AND a.status != 'completed')
+               AS "active_agents!: i64"
+        FROM sessions s
+        WHERE s.status != 'completed' AND s.deleted_at IS NULL
+        AND (s.project_path = ? OR substr(s.project_path, 1, length(?)) = ?)
+        ORDER BY s.updated_at DESC
+        "#,
+        workspace,
+        prefix,
+        prefix,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(sessions)
+}
+
+async fn get_agents_for_session(pool: &SqlitePool, host: &str, session_id: &str) -> Result<Vec<Agent>> {
+    let mut agents: Vec<Agent> = sqlx::query_as(
+        r#"
+        SELECT id, session_id, agent_name, parent_session_id, status, created_at, updated_at
+        FROM agents
+        WHERE host = ? AND session_id = ?
+        ORDER BY created_at ASC
+        "#,
+    )
+    .bind(host)
+    .bind(session_id)
+    .fetch_all(pool)
+    .await?;
+    let runtimes = get_agent_runtimes(pool, host, session_id).await?;
+
+    for agent in &mut agents {
+        agent.runtime_seconds = runtimes
+            .get(&agent.agent_name)
+            .map_or(0, |runtime| runtime.seconds(&agent.status, agent.updated_at));
+    }
+
+    Ok(agents)
+}
+
+async fn get_agent_runtimes(pool: &SqlitePool, host: &str, session_id: &str) -> Result<HashMap<String, AgentRuntime>> {
+    let events = sqlx::query_as!(
+        RuntimeEvent,
+        r#"
+        SELECT COALESCE(agent_name, 'main') AS "agent_name!: String", event_type,
+               COALESCE(json_extract(payload, '$.needs_input'), 0) AS "needs_input!: bool",
+               timestamp AS "timestamp: DateTime<Utc>"
+        FROM events
+        WHERE host = ? AND session_id = ?
+        ORDER BY timestamp
+        "#,
+        host,
+        session_id,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(store::agent_runtimes(events))
+}
+
+/// Set or clear a session's note, on `host` or every host with that session
+/// id. Returns whether the session exists.
+#[instrument(level = "debug", skip_all)]
+pub async fn set_session_notes(
+    pool: &SqlitePool,
+    host: Option<&str>,
+    session_id: &str,
+    notes: Option<&str>,
+) -> Result<bool> {
+    let result = sqlx::query!(
+        "UPDATE sessions SET notes = ? WHERE session_id = ? AND (? IS NULL OR host = ?)",
+        notes,
+        session_id,
+        host,
+        host,
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+#[instrument(level = "debug", skip_all)]
+/// The tags of the session on `host`, or of every session with that id.
+#[instrument(level = "debug", skip_all)]
+pub async fn get_session_tags(pool: &SqlitePool, host: Option<&str>, session_id: &str) -> Result<Vec<String>> {
+    let tags = sqlx::query_scalar!(
+        r#"
+        SELECT tag AS "tag!" FROM session_tags
+        WHERE session_id = ? AND (? IS NULL OR host = ?)
+        GROUP BY tag
+        ORDER BY MIN(created_at), tag
+        "#,
+        session_id,
+        host,
+        host,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(tags)
+}
+
+/// Add tags to the session on `host`, or every session with that id,
+/// ignoring ones it already has.
+#[instrument(level = "debug", skip_all)]
+pub async fn add_session_tags(pool: &SqlitePool, host: Option<&str>, session_id: &str, tags: &[String]) -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+    for tag in tags {
+        sqlx::query!(
+            r#"
+            INSERT OR IGNORE INTO session_tags (host, session_id, tag, created_at)
+            SELECT host, session_id, ?, ? FROM sessions
+            WHERE session_id = ? AND (? IS NULL OR host = ?)
+            "#,
+            tag,
+            now,
+            session_id,
+            host,
+            host,
+        )
+        .execute(pool)
+        .await?;
+    }
+    Ok(())
+}
+
+#[instrument(level = "debug", skip_all)]
+pub async fn remove_session_tags(
+    pool: &SqlitePool,
+    host: Option<&str>,
+    session_id: &str,
+    tags: &[String],
+) -> Result<()> {
+    for tag in tags {
+        sqlx::query!(
+            "DELETE FROM session_tags WHERE session_id = ? AND (? IS NULL OR host = ?) AND tag = ?",
+            session_id,
+            host,
+            host,
+            tag,
+        )
+        .execute(pool)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Whether a session by that id exists on `host`, or on any host.
+#[instrument(level = "debug", skip_all)]
+pub async fn session_exists(pool: &SqlitePool, host: Option<&str>, session_id: &str) -> Result<bool> {
+    let row = sqlx::query!(
+        "SELECT 1 AS found FROM sessions WHERE session_id = ? AND (? IS NULL OR host = ?)",
+        session_id,
+        host,
+        host,
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.is_some())
+}
+
+#[instrument(level = "debug", skip_all)]
+pub async fn mark_session_completed(pool: &SqlitePool, host: &str, session_id: &str) -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE sessions SET status = 'completed', updated_at = ?
+        WHERE host = ? AND session_id = ?
+        "#,
+        now,
+        host,
+        session_id,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE agents SET status = 'completed', updated_at = ?
+        WHERE host = ? AND session_id = ?
+        "#,
+        now,
+        host,
+        session_id,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Move 'active' → 'idle' when Claude finishes a turn.
+/// Idle sessions stay visible until the user explicitly clears them.
+/// 'waiting_input' and 'needs_permission' sessions are left untouched.
+#[instrument(level = "debug", skip_all)]
+pub async fn mark_active_session_idle(pool: &SqlitePool, host: &str, session_id: &str) -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE sessions SET status = 'idle', updated_at = ?
+        WHERE host = ? AND session_id = ? AND status = 'active'
+        "#,
+        now,
+        host,
+        session_id,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE agents SET status = 'idle', updated_at = ?
+        WHERE host = ? AND session_id = ? AND status = 'active'
+        "#,
+        now,
+        host,
+        session_id,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Acknowledge a session that is blocked on the user: 'waiting_input' or
+/// 'needs_permission' → 'acknowledged', which no notifier alerts on. The next
+/// hook event moves it on as usual. Applies to the session on `host`, or
+/// every session with that id. Returns whether a session was transitioned.
+#[instrument(level = "debug", skip_all)]
+pub async fn acknowledge_session(pool: &SqlitePool, host: Option<&str>, session_id: &str) -> Result<bool> {
+    let now = Utc::now().to_rfc3339();
+
+    let result = sqlx::query!(
+        r#"
+        UPDATE sessions SET status = 'acknowledged', updated_at = ?
+        WHERE session_id = ? AND (? IS NULL OR host = ?) AND status IN ('waiting_input', 'needs_permission')
+        "#,
+        now,
+        session_id,
+        host,
+        host,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE agents SET status = 'acknowledged', updated_at = ?
+        WHERE session_id = ? AND (? IS NULL OR host = ?) AND status IN ('waiting_input', 'needs_permission')
+        "#,
+        now,
+        session_id,
+        host,
+        host,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Set or clear a session's pin, on `host` or every host with that session
+/// id. Returns whether the session exists.
+#[instrument(level = "debug", skip_all)]
+pub async fn set_session_pinned(pool: &SqlitePool, host: Option<&str>, session_id: &str, pinned: bool) -> Result<bool> {
+    let result = sqlx::query!(
+        "UPDATE sessions SET pinned = ? WHERE session_id = ? AND (? IS NULL OR host = ?)",
+        pinned,
+        session_id,
+        host,
+        host,
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Move a session, on `host` or every host with that id, to the trash,
+/// keeping the original time if it is already there. Returns whether the
+/// session exists.
+#[instrument(level = "debug", skip_all)]
+pub async fn trash_session(pool: &SqlitePool, host: Option<&str>, session_id: &str) -> Result<bool> {
+    let now = Utc::now().to_rfc3339();
+    let result = sqlx::query!(
+        "UPDATE sessions SET deleted_at = COALESCE(deleted_at, ?) WHERE session_id = ? AND (? IS NULL OR host = ?)",
+        now,
+        session_id,
+        host,
+        host,
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Take a session, on `host` or every host with that id, out of the trash.
+/// Returns whether it was in it.
+#[instrument(level = "debug", skip_all)]
+pub async fn restore_session(pool: &SqlitePool, host: Option<&str>, session_id: &str) -> Result<bool> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE sessions SET deleted_at = NULL
+        WHERE session_id = ? AND (? IS NULL OR host = ?) AND deleted_at IS NOT NULL
+        "#,
+        session_id,
+        host,
+        host,
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Mute or unmute a session, on `host` or every host with that id; `hidden`
+/// only applies while muted. Returns whether the session exists.
+#[instrument(level = "debug", skip_all)]
+pub async fn set_session_muted(
+    pool: &SqlitePool,
+    host: Option<&str>,
+    session_id: &str,
+    muted: bool,
+    hidden: bool,
+) -> Result<bool> {
+    let hidden = muted && hidden;
+    let result = sqlx::query!(
+        "UPDATE sessions SET muted = ?, hidden = ? WHERE session_id = ? AND (? IS NULL OR host = ?)",
+        muted,
+        hidden,
+        session_id,
+        host,
+        host,
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+#[instrument(level = "debug", skip_all)]
+pub async fn get_alert_rules(pool: &SqlitePool) -> Result<Vec<AlertRule>> {
+    let rules = sqlx::query_as(
+        r#"
+        SELECT id, name, enabled, conditions, sinks, created_at, updated_at
+        FROM alert_rules
+        ORDER BY created_at
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rules)
+}
+
+#[instrument(level = "debug", skip_all)]
+pub async fn get_alert_rule(pool: &SqlitePool, id: &str) -> Result<Option<AlertRule>> {
+    let rule = sqlx::query_as(
+        r#"
+        SELECT id, name, enabled, conditions, sinks, created_at, updated_at
+        FROM alert_rules
+        WHERE id = ?
+        "#,
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(rule)
+}
+
+#[instrument(level = "debug", skip_all)]
+pub async fn insert_alert_rule(pool: &SqlitePool, rule: &AlertRuleInput) -> Result<AlertRule> {
+    let id = Uuid::new_v4().to_string();
+    let now = Utc::now().to_rfc3339();
+    let conditions = serde_json::to_string(&rule.conditions)?;
+    let sinks = serde_json::to_string(&rule.sinks)?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO alert_rules (id, name, enabled, conditions, sinks, created_at, updated_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?)
+        "#,
+        id,
+        rule.name,
+        rule.enabled,
+        conditions,
+        sinks,
+        now,
+        now,
+    )
+    .execute(pool)
+    .await?;
+
+    get_alert_rule(pool, &id).await?.context("inserted rule not found")
+}
+
+/// Replace a rule's fields. Returns `None` if no rule has that id.
+#[instrument(level = "debug", skip_all)]
+pub async fn update_alert_rule(pool: &SqlitePool, id: &str, rule: &AlertRuleInput) -> Result<Option<AlertRule>> {
+    let now = Utc::now().to_rfc3339();
+    let conditions = serde_json::to_string(&rule.conditions)?;
+    let sinks = serde_json::to_string(&rule.sinks)?;
+
+    let result = sqlx::query!(
+        r#"
+        UPDATE alert_rules SET name = ?, enabled = ?, conditions = ?, sinks = ?, updated_at = ?
+        WHERE id = ?
+        "#,
+        rule.name,
+        rule.enabled,
+        conditions,
+        sinks,
+        now,
+        id,
+    )
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Ok(None);
+    }
+    get_alert_rule(pool, id).await
+}
+
+#[instrument(level = "debug", skip_all)]
+pub async fn delete_alert_rule(pool: &SqlitePool, id: &str) -> Result<bool> {
+    let result = sqlx::query!("DELETE FROM alert_rules WHERE id = ?", id).execute(pool).await?;
+    Ok(result.rows_affected() > 0)
+}
+
+#[instrument(level = "debug", skip_all)]
+pub async fn get_project_aliases(pool: &SqlitePool) -> Result<Vec<ProjectAlias>> {
+    let aliases = sqlx::query_as!(
+        ProjectAlias,
+        r#"
+        SELECT project_path AS "project_path!", display_name,
+               created_at AS "created_at: DateTime<Utc>", updated_at AS "updated_at: DateTime<Utc>"
+        FROM project_aliases
+        ORDER BY display_name
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(aliases)
+}
+
+/// Create or rename the alias for `project_path`, which must already be normalized.
+#[instrument(level = "debug", skip_all)]
+pub async fn set_project_alias(pool: &SqlitePool, project_path: &str, display_name: &str) -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+
+    sqlx::query!(
+        r#"
+        INSERT INTO project_aliases (project_path, display_name, created_at, updated_at)
+        VALUES (?, ?, ?, ?)
+        ON CONFLICT(project_path) DO UPDATE SET
+            display_name = excluded.display_name,
+            updated_at = excluded.updated_at
+        "#,
+        project_path,
+        display_name,
+        now,
+        now,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+#[instrument(level = "debug", skip_all)]
+pub async fn delete_project_alias(pool: &SqlitePool, project_path: &str) -> Result<bool> {
+    let result = sqlx::query!("DELETE FROM project_aliases WHERE project_path = ?", project_path)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+#[instrument(level = "debug", skip_all)]
+pub async fn get_setting(pool: &SqlitePool, key: &str) -> Result<Option<String>> {
+    let value = sqlx::query_scalar!("SELECT value FROM settings WHERE key = ?", key)
+        .fetch_optional(pool)
+        .await?;
+    Ok(value)
+}
+
+#[instrument(level = "debug", skip_all)]
+pub async fn set_setting(pool: &SqlitePool, key: &str, value: &str) -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+
+    sqlx::query!(
+        r#"
+        INSERT INTO settings (key, value, updated_at)
+        VALUES (?, ?, ?)
+        ON CONFLICT(key) DO UPDATE SET
+            value = excluded.value,
+            updated_at = excluded.updated_at
+        "#,
+        key,
+        value,
+        now,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Record a notification attempt; `record.id` and `rule_name` are ignored.
+#[instrument(level = "debug", skip_all)]
+pub async fn insert_notification(pool: &SqlitePool, record: &NotificationRecord) -> Result<()> {
+    let created_at = record.created_at.to_rfc3339();
+    sqlx::query!(
+        r#"
+        INSERT INTO notifications
+            (sink, kind, rule_id, session_id, project_name, status, message, result, error, created_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+        record.sink,
+        record.kind,
+        record.rule_id,
+        record.session_id,
+        record.project_name,
+        record.status,
+        record.message,
+        record.result,
+        record.error,
+        created_at,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// A page of notification history, newest first, with the total count.
+#[instrument(level = "debug", skip_all)]
+pub async fn get_notifications(pool: &SqlitePool, limit: u32, offset: u32) -> Result<(Vec<NotificationRecord>, i64)> {
+    let notifications = sqlx::query_as!(
+        NotificationRecord,
+        r#"
+        SELECT n.id, n.sink, n.kind, n.rule_id, r.name AS "rule_name?", n.session_id, n.project_name,
+               n.status, n.message, n.result, n.error, n.created_at AS "created_at: DateTime<Utc>"
+        FROM notifications n
+        LEFT JOIN alert_rules r ON r.id = n.rule_id
+        ORDER BY n.id DESC
+        LIMIT ? OFFSET ?
+        "#,
+        limit,
+        offset,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let total = sqlx::query_scalar!(r#"SELECT COUNT(*) AS "count: i64" FROM notifications"#).fetch_one(pool).await?;
+
+    Ok((notifications, total))
+}
+
+/// Notifications about one session, oldest first.
+#[instrument(level = "debug", skip_all)]
+pub async fn get_session_notifications(pool: &SqlitePool, session_id: &str) -> Result<Vec<NotificationRecord>> {
+    let notifications = sqlx::query_as!(
+        NotificationRecord,
+        r#"
+        SELECT n.id, n.sink, n.kind, n.rule_id, r.name AS "rule_name?", n.session_id, n.project_name,
+               n.status, n.message, n.result, n.error, n.created_at AS "created_at: DateTime<Utc>"
+        FROM notifications n
+        LEFT JOIN alert_rules r ON r.id = n.rule_id
+        WHERE n.session_id = ?
+        ORDER BY n.id
+        "#,
+        session_id,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(notifications)
+}
+
+/// Drop notification history older than `retention`.
+#[instrument(level = "debug", skip_all)]
+pub async fn prune_notifications(pool: &SqlitePool, retention: Duration) -> Result<()> {
+    let cutoff = (Utc::now() - retention).to_rfc3339();
+    sqlx::query!("DELETE FROM notifications WHERE julianday(created_at) < julianday(?)", cutoff)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+#[instrument(level = "debug", skip_all)]
+pub async fn insert_dead_letter(pool: &SqlitePool, path: &str, body: &str, error: &str, keep: u32) -> Result<()> {
+    let received_at = Utc::now().to_rfc3339();
+    let mut tx = pool.begin().await?;
+    sqlx::query!(
+        "INSERT INTO dead_letters (path, body, error, received_at) VALUES (?, ?, ?, ?)",
+        path,
+        body,
+        error,
+        received_at,
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!(
+        "DELETE FROM dead_letters WHERE id NOT IN (SELECT id FROM dead_letters ORDER BY id DESC LIMIT ?)",
+        keep,
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+#[instrument(level = "debug", skip_all)]
+pub async fn get_dead_letters(pool: &SqlitePool, limit: u32) -> Result<Vec<DeadLetter>> {
+    let letters = sqlx::query_as!(
+        DeadLetter,
+        r#"
+        SELECT id, path, body, error, received_at AS "received_at: DateTime<Utc>"
+        FROM dead_letters
+        ORDER BY id DESC
+        LIMIT ?
+        "#,
+        limit,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(letters)
+}
+
+#[instrument(level = "debug", skip_all)]
+pub async fn insert_api_key(pool: &SqlitePool, name: &str, digest: &str, admin: bool) -> Result<Option<ApiKey>> {
+    let created_at = Utc::now();
+    let created = created_at.to_rfc3339();
+    let result = sqlx::query!(
+        "INSERT INTO api_keys (name, digest, created_at, admin) VALUES (?, ?, ?, ?) ON CONFLICT DO NOTHING",
+        name,
+        digest,
+        created,
+        admin,
+    )
+    .execute(pool)
+    .await?;
+    Ok((result.rows_affected() > 0).then(|| ApiKey { name: name.to_string(), created_at, revoked_at: None, admin }))
+}
+
+#[instrument(level = "debug", skip_all)]
+pub async fn get_api_keys(pool: &SqlitePool) -> Result<Vec<ApiKey>> {
+    let keys = sqlx::query_as!(
+        ApiKey,
+        r#"
+        SELECT name AS "name!", created_at AS "created_at: DateTime<Utc>",
+               revoked_at AS "revoked_at: DateTime<Utc>", admin AS "admin: bool"
+        FROM api_keys
+        ORDER BY created_at, name
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(keys)
+}
+
+#[instrument(level = "debug", skip_all)]
+pub async fn get_live_api_keys(pool: &SqlitePool) -> Result<Vec<(String, String, bool)>> {
+    let keys = sqlx::query!(
+        r#"SELECT digest, name AS "name!", admin AS "admin: bool" FROM api_keys WHERE revoked_at IS NULL"#,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(keys.into_iter().map(|key| (key.digest, key.name, key.admin)).collect())
+}
+
+#[instrument(level = "debug", skip_all)]
+pub async fn revoke_api_key(pool: &SqlitePool, name: &str) -> Result<bool> {
+    let now = Utc::now().to_rfc3339();
+    let result = sqlx::query!("UPDATE api_keys SET revoked_at = ? WHERE name = ? AND revoked_at IS NULL", now, name)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Tables holding per-session child rows, keyed by session_id.
+const SESSION_CHILD_TABLES: &[&str] = &["agents", "events", "tool_invocations", "usage", "session_tags"];
+
+/// Delete all rows from sessions and their child tables — but keep the tables intact.
+#[instrument(level = "debug", skip_all)]
+pub async fn clear_all_sessions(pool: &SqlitePool) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    // Order matters: child tables reference sessions via session_id.
+    for table in SESSION_CHILD_TABLES {
+        sqlx::query(&format!("DELETE FROM {table}")).execute(&mut *tx).await?;
+    }
+    sqlx::query!("DELETE FROM sessions").execute(&mut *tx).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Remove unpinned sessions completed over `completed_ttl` ago and sessions in
+/// the trash for longer than `trash_retention`, first adding their activity to
+/// `daily_stats`, `active_intervals` and `hourly_events` and their start and
+/// end to `session_durations`. Their usage rows are kept until they are older
+/// than `usage_retention`, so quota tracking still counts them. With
+/// `keep_events` their events stay too, for `prune_archived_events` to count
+/// into `hourly_events` as it removes them.
+#[instrument(level = "debug", skip_all)]
+pub async fn cleanup_old_completed(
+    pool: &SqlitePool,
+    completed_ttl: Option<Duration>,
+    usage_retention: Duration,
+    trash_retention: Duration,
+    keep_events: bool,
+) -> Result<CleanupReport> {
+    // The same cutoffs throughout, so exactly the archived sessions are
+    // deleted. Without a TTL the cutoff is NULL, which no session is before.
+    let now = Utc::now();
+    let cutoff = completed_ttl.map(|ttl| (now - ttl).to_rfc3339());
+    let trash_cutoff = (now - trash_retention).to_rfc3339();
+    let expired = "SELECT host, session_id FROM sessions \
+                   WHERE (status = 'completed' AND pinned = 0 AND deleted_at IS NULL \
+                          AND julianday(updated_at) <= julianday(?)) \
+                   OR julianday(deleted_at) <= julianday(?)";
+    let mut tx = pool.begin().await?;
+    let mut report = CleanupReport::default();
+
+    let sessions =
+        format!("SELECT host, session_id, updated_at AS ended_at FROM sessions WHERE (host, session_id) IN ({expired})");
+    let completed: Vec<CompletedSession> = sqlx::query_as(&sessions)
+        .bind(&cutoff)
+        .bind(&trash_cutoff)
+        .fetch_all(&mut *tx)
+        .await?;
+    if !completed.is_empty() {
+        let events: Vec<EventRow> = sqlx::query_as(&format!(
+            r#"
+            SELECT e.host, e.session_id, s.project_name, COALESCE(e.agent_name, 'main') AS agent_name, e.event_type,
+                   COALESCE(json_extract(e.payload, '$.needs_input'), 0) AS needs_input, e.timestamp
+            FROM events e
+            JOIN sessions s ON s.host = e.host AND s.session_id = e.session_id
+            WHERE (e.host, e.session_id) IN ({expired})
+            ORDER BY e.host, e.session_id, e.timestamp
+            "#
+        ))
+        .bind(&cutoff)
+        .bind(&trash_cutoff)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        for interval in stats::active_intervals(&events, &completed) {
+            let (start, end) = (interval.start.to_rfc3339(), interval.end.to_rfc3339());
+            sqlx::query!(
+                r#"
+                INSERT INTO active_intervals (host, session_id, project_name, started_at, ended_at)
+                VALUES (?, ?, ?, ?, ?)
+                "#,
+                interval.host,
+                interval.session_id,
+                interval.project_name,
+                start,
+                end,
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        for ((day, host), totals) in stats::daily_activity(&events, &completed) {
+            let day = day.to_string();
+            sqlx::query!(
+                r#"
+                INSERT INTO daily_stats (day, host, sessions, active_seconds, completions)
+                VALUES (?, ?, ?, ?, ?)
+                ON CONFLICT(day, host) DO UPDATE SET
+                    sessions = sessions + excluded.sessions,
+                    active_seconds = active_seconds + excluded.active_seconds,
+                    completions = completions + excluded.completions
+                "#,
+                day,
+                host,
+                totals.sessions,
+                totals.active_seconds,
+                totals.completions,
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        // Kept events are counted as they are pruned.
+        if !keep_events {
+            sqlx::query(&format!(
+                r#"
+                INSERT INTO hourly_events (hour, host, events)
+                SELECT strftime('%Y-%m-%dT%H:00:00Z', timestamp) AS hour, host, COUNT(*) FROM events
+                WHERE (host, session_id) IN ({expired})
+                GROUP BY hour, host
+                ON CONFLICT(hour, host) DO UPDATE SET events = events + excluded.events
+                "#
+            ))
+            .bind(&cutoff)
+            .bind(&trash_cutoff)
+            .execute(&mut *tx)
+            .await?;
+        }
+        sqlx::query(&format!(
+            r#"
+            INSERT INTO session_durations (host, session_id, project_name, started_at, ended_at)
+            SELECT host, session_id, project_name, created_at, updated_at FROM sessions
+            WHERE (host, session_id) IN ({expired})
+            "#
+        ))
+        .bind(&cutoff)
+        .bind(&trash_cutoff)
+        .execute(&mut *tx)
+        .await?;
+
+        for table in SESSION_CHILD_TABLES.iter().filter(|t| **t != "usage" && !(keep_events && **t == "events")) {
+            let deleted = sqlx::query(&format!("DELETE FROM {table} WHERE (host, session_id) IN ({expired})"))
+                .bind(&cutoff)
+                .bind(&trash_cutoff)
+                .execute(&mut *tx)
+                .await?;
+            if *table == "events" {
+                report.events = deleted.rows_affected();
+            }
+        }
+        report.sessions = sqlx::query(&format!("DELETE FROM sessions WHERE (host, session_id) IN ({expired})"))
+            .bind(&cutoff)
+            .bind(&trash_cutoff)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+    }
+    tx.commit().await?;
+
+    let usage_cutoff = (now - usage_retention).to_rfc3339();
+    sqlx::query!(
+        r#"
+        DELETE FROM usage
+        WHERE session_id NOT IN (SELECT session_id FROM sessions)
+        AND julianday(recorded_at) < julianday(?)
+        "#,
+        usage_cutoff,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(report)
+}
+
+/// `PRAGMA optimize`, then return free pages to the filesystem. A database
+/// created before incremental auto-vacuum was turned on is converted by one
+/// full VACUUM; every later pass only truncates the free list.
+#[instrument(level = "debug", skip_all)]
+pub async fn maintain(pool: &SqlitePool) -> Result<MaintenanceReport> {
+    let started = std::time::Instant::now();
+    // VACUUM and the auto_vacuum switch must run on the same connection.
+    let mut conn = pool.acquire().await?;
+    let size_before = database_size(&mut conn).await?;
+
+    let auto_vacuum: i64 = sqlx::query_scalar("PRAGMA auto_vacuum").fetch_one(&mut *conn).await?;
+    let full_vacuum = auto_vacuum != INCREMENTAL_VACUUM;
+    if full_vacuum {
+        sqlx::query("PRAGMA auto_vacuum = INCREMENTAL").execute(&mut *conn).await?;
+        sqlx::query("VACUUM").execute(&mut *conn).await?;
+    } else {
+        sqlx::query("PRAGMA incremental_vacuum").execute(&mut *conn).await?;
+    }
+    sqlx::query("PRAGMA optimize").execute(&mut *conn).await?;
+
+    Ok(MaintenanceReport {
+        size_before,
+        size_after: database_size(&mut conn).await?,
+        full_vacuum,
+        duration_ms: started.elapsed().as_secs_f64() * 1000.0,
+    })
+}
+
+/// `PRAGMA auto_vacuum` value for incremental mode.
+const INCREMENTAL_VACUUM: i64 = 2;
+
+/// Size of the main database file in bytes, not counting the WAL.
+async fn database_size(conn: &mut SqliteConnection) -> Result<u64> {
+    let pages: i64 = sqlx::query_scalar("PRAGMA page_count").fetch_one(&mut *conn).await?;
+    let page_size: i64 = sqlx::query_scalar("PRAGMA page_size").fetch_one(&mut *conn).await?;
+    Ok((pages * page_size) as u64)
+}
+
+/// Bytes of the main database file in use: its pages less the free list.
+#[instrument(level = "debug", skip_all)]
+pub async fn used_size(pool: &SqlitePool) -> Result<u64> {
+    let used = sqlx::query_scalar!(
+        r#"SELECT (page_count - freelist_count) * page_size AS "used!: i64"
+        FROM pragma_page_count, pragma_freelist_count, pragma_page_size"#,
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(used as u64)
+}
+
+/// The oldest `?` events whose session is completed, in the trash or gone.
+const ARCHIVED_EVENTS: &str = r#"
+    SELECT rowid FROM events e
+    WHERE NOT EXISTS (
+        SELECT 1 FROM sessions s
+        WHERE s.host = e.host AND s.session_id = e.session_id AND s.status != 'completed' AND s.deleted_at IS NULL
+    )
+    ORDER BY rowid
+    LIMIT ?
+"#;
+
+#[instrument(level = "debug", skip_all)]
+pub async fn prune_archived_events(pool: &SqlitePool, limit: u32) -> Result<u64> {
+    // One write transaction, so each statement sees the same events.
+    let mut tx = pool.begin().await?;
+    sqlx::query(&format!(
+        r#"
+        INSERT INTO hourly_events (hour, host, events)
+        SELECT strftime('%Y-%m-%dT%H:00:00Z', timestamp) AS hour, host, COUNT(*) FROM events
+        WHERE rowid IN ({ARCHIVED_EVENTS})
+        GROUP BY hour, host
+        ON CONFLICT(hour, host) DO UPDATE SET events = events + excluded.events
+        "#
+    ))
+    .bind(limit)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query(&format!(
+        r#"
+        UPDATE sessions SET pruned_events = pruned_events + pruned.events
+        FROM (
+            SELECT host, session_id, COUNT(*) AS events FROM events
+            WHERE rowid IN ({ARCHIVED_EVENTS})
+            GROUP BY host, session_id
+        ) AS pruned
+        WHERE sessions.host = pruned.host AND sessions.session_id = pruned.session_id
+        "#
+    ))
+    .bind(limit)
+    .execute(&mut *tx)
+    .await?;
+    let deleted = sqlx::query(&format!("DELETE FROM events WHERE rowid IN ({ARCHIVED_EVENTS})"))
+        .bind(limit)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    tx.commit().await?;
+    Ok(deleted)
+}
+
+/// Checkpoint the WAL into the database and truncate it to zero bytes. Open
+/// readers can keep part of it in use; what they hold is copied next time.
+#[instrument(level = "debug", skip_all)]
+pub async fn checkpoint(pool: &SqlitePool) -> Result<()> {
+    let (busy, log, copied): (i64, i64, i64) =
+        sqlx::query_as("PRAGMA wal_checkpoint(TRUNCATE)").fetch_one(pool).await?;
+    if busy != 0 {
+        tracing::debug!("WAL checkpoint incomplete: {copied} of {log} pages copied");
+    }
+    Ok(())
+}
+
+/// Copy the database to a new file at `dest` with SQLite's online backup
+/// API: a consistent snapshot, taken in one step while writers carry on
+/// against the WAL.
+#[instrument(level = "debug", skip_all)]
+pub async fn backup(pool: &SqlitePool, dest: &Path) -> Result<()> {
+    let mut source = pool.acquire().await?;
+    let mut target = SqliteConnectOptions::new().filename(dest).create_if_missing(true).connect().await?;
+    {
+        let mut source = source.lock_handle().await?;
+        let mut target = target.lock_handle().await?;
+        let (from, to) = (source.as_raw_handle().as_ptr(), target.as_raw_handle().as_ptr());
+        // SAFETY: both connections stay locked to this thread until the
+        // backup is finished, and no other statement runs on either meanwhile.
+        unsafe {
+            let main = c"main".as_ptr();
+            let backup = sqlite::sqlite3_backup_init(to, main, from, main);
+            if backup.is_null() {
+                bail!("backup failed: {}", CStr::from_ptr(sqlite::sqlite3_errmsg(to)).to_string_lossy());
+            }
+            sqlite::sqlite3_backup_step(backup, -1);
+            let rc = sqlite::sqlite3_backup_finish(backup);
+            if rc != sqlite::SQLITE_OK {
+                bail!("backup failed: {}", CStr::from_ptr(sqlite::sqlite3_errstr(rc)).to_string_lossy());
+            }
+        }
+    }
+    target.close().await?;
+    Ok(())
+}
+
+#[instrument(level = "debug", skip_all)]
+pub async fn export_sessions(pool: &SqlitePool) -> Result<Vec<ExportedSession>> {
+    let mut sessions: Vec<ExportedSession> = sqlx::query_as(
+        r#"
+        SELECT id, host, session_id, project_path, project_name, status, created_at, updated_at, current_tool,
+               last_message, last_assistant_text, last_tool_call, model, pinned, muted, hidden, notes, git_branch,
+               deleted_at, pruned_events
+        FROM sessions
+        ORDER BY created_at
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+    let tags = sqlx::query!("SELECT host, session_id, tag FROM session_tags ORDER BY created_at, tag")
+        .fetch_all(pool)
+        .await?;
+    store::attach_tags(&mut sessions, tags.into_iter().map(|t| (t.host, t.session_id, t.tag)).collect());
+    Ok(sessions)
+}
+
+#[instrument(level = "debug", skip_all)]
+pub async fn export_agents(pool: &SqlitePool) -> Result<Vec<ExportedAgent>> {
+    let agents = sqlx::query_as!(
+        ExportedAgent,
+        r#"
+        SELECT id AS "id!", host, session_id, agent_name, parent_session_id, status,
+               created_at AS "created_at: DateTime<Utc>", updated_at AS "updated_at: DateTime<Utc>"
+        FROM agents
+        ORDER BY created_at
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(agents)
+}
+
+#[instrument(level = "debug", skip_all)]
+pub async fn export_events(
+    pool: &SqlitePool,
+    after: i64,
+    limit: u32,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+) -> Result<Vec<(i64, ExportedEvent)>> {
+    let from = from.map(|t| t.to_rfc3339());
+    let to = to.map(|t| t.to_rfc3339());
+
+    let rows: Vec<ExportEventRow> = sqlx::query_as(
+        r#"
+        SELECT rowid, id, host, session_id, agent_name, event_type, payload, timestamp, request_id, source
+        FROM events
+        WHERE rowid > ?
+        AND (? IS NULL OR julianday(timestamp) >= julianday(?))
+        AND (? IS NULL OR julianday(timestamp) < julianday(?))
+        ORDER BY rowid
+        LIMIT ?
+        "#,
+    )
+    .bind(after)
+    .bind(&from)
+    .bind(&from)
+    .bind(&to)
+    .bind(&to)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(|row| (row.rowid, row.event)).collect())
+}
+
+#[derive(sqlx::FromRow)]
+struct ExportEventRow {
+    rowid: i64,
+    #[sqlx(flatten)]
+    event: ExportedEvent,
+}
+
+#[instrument(level = "debug", skip_all)]
+pub async fn export_settings(pool: &SqlitePool) -> Result<Vec<ExportedSetting>> {
+    let settings = sqlx::query_as!(
+        ExportedSetting,
+        r#"SELECT key AS "key!", value, updated_at AS "updated_at: DateTime<Utc>" FROM settings ORDER BY key"#,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(settings)
+}
+
+#[instrument(level = "debug", skip_all)]
+pub async fn import(pool: &SqlitePool, data: &Export, on_conflict: OnConflict) -> Result<ImportReport> {
+    let mut report = ImportReport::default();
+    let mut tx = pool.begin().await?;
+
+    let sessions = format!(
+        r#"
+        INSERT INTO sessions (
+            id, host, session_id, project_path, project_name, status, created_at, updated_at, current_tool,
+            last_message, last_assistant_text, last_tool_call, model, pinned, muted, hidden, notes, git_branch,
+            deleted_at, pruned_events
+        )
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        {}
+        "#,
+        store::import_conflict(on_conflict, "host, session_id", store::IMPORTED_SESSION_COLUMNS),
+    );
+    // Tags are listed in the order they were added; stagger their times so
+    // they keep it.
+    let tagged_at = Utc::now();
+    for s in &data.sessions {
+        let written = sqlx::query(&sessions)
+            .bind(&s.id)
+            .bind(&s.host)
+            .bind(&s.session_id)
+            .bind(&s.project_path)
+            .bind(&s.project_name)
+            .bind(&s.status)
+            .bind(s.created_at.to_rfc3339())
+            .bind(s.updated_at.to_rfc3339())
+            .bind(&s.current_tool)
+            .bind(&s.last_message)
+            .bind(&s.last_assistant_text)
+            .bind(&s.last_tool_call)
+            .bind(&s.model)
+            .bind(s.pinned)
+            .bind(s.muted)
+            .bind(s.hidden)
+            .bind(&s.notes)
+            .bind(&s.git_branch)
+            .bind(s.deleted_at.map(|t| t.to_rfc3339()))
+            .bind(s.pruned_events)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected()
+            > 0;
+        store::count_import(&mut report.sessions, written);
+        if !written {
+            continue;
+        }
+        sqlx::query!("DELETE FROM session_tags WHERE host = ? AND session_id = ?", s.host, s.session_id)
+            .execute(&mut *tx)
+            .await?;
+        for (i, tag) in s.tags.iter().enumerate() {
+            let created_at = (tagged_at + Duration::milliseconds(i as i64)).to_rfc3339();
+            sqlx::query!(
+                r#"
+                INSERT INTO session_tags (host, session_id, tag, created_at) VALUES (?, ?, ?, ?)
+                ON CONFLICT DO NOTHING
+                "#,
+                s.host,
+                s.session_id,
+                tag,
+                created_at,
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+    }
+
+    let agents = format!(
+        r#"
+        INSERT INTO agents (id, host, session_id, agent_name, parent_session_id, status, created_at, updated_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+        {}
+        "#,
+        store::import_conflict(on_conflict, "host, session_id, agent_name", store::IMPORTED_AGENT_COLUMNS),
+    );
+    for a in &data.agents {
+        let result = sqlx::query(&agents)
+            .bind(&a.id)
+            .bind(&a.host)
+            .bind(&a.session_id)
+            .bind(&a.agent_name)
+            .bind(&a.parent_session_id)
+            .bind(&a.status)
+            .bind(a.created_at.to_rfc3339())
+            .bind(a.updated_at.to_rfc3339())
+            .execute(&mut *tx)
+            .await?;
+        store::count_import(&mut report.agents, result.rows_affected() > 0);
+    }
+
+    for e in &data.events {
+        let (payload, timestamp) = (e.payload.to_string(), e.timestamp.to_rfc3339());
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO events (id, host, session_id, agent_name, event_type, payload, timestamp, request_id, source)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT DO NOTHING
+            "#,
+            e.id,
+            e.host,
+            e.session_id,
+            e.agent_name,
+            e.event_type,
+            payload,
+            timestamp,
+            e.request_id,
+            e.source,
+        )
+        .execute(&mut *tx)
+        .await?;
+        store::count_import(&mut report.events, result.rows_affected() > 0);
+    }
+
+    let settings = format!(
+        "INSERT INTO settings (key, value, updated_at) VALUES (?, ?, ?) {}",
+        store::import_conflict(on_conflict, "key", &["value", "updated_at"]),
+    );
+    for s in &data.settings {
+        let result = sqlx::query(&settings)
+            .bind(&s.key)
+            .bind(&s.value)
+            .bind(s.updated_at.to_rfc3339())
+            .execute(&mut *tx)
+            .await?;
+        store::count_import(&mut report.settings, result.rows_affected() > 0);
+    }
+
+    tx.commit().await?;
+    Ok(report)
 }
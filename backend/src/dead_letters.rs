@@ -0,0 +1,111 @@
+//! Hook payloads that fail to parse are still rejected, 400 or 422 as
+//! before, but are first kept in the `dead_letters` table with the reason,
+//! so a hook sending something the server doesn't expect can be debugged
+//! after the fact. `GET /api/dead-letters` lists the newest; only the last
+//! 1000 are kept.
+
+use axum::{
+    async_trait,
+    body::Bytes,
+    extract::{FromRequest, Query, Request, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::json;
+use tracing::warn;
+use utoipa::IntoParams;
+
+use crate::{
+    api::AppState,
+    models::{DeadLetter, ErrorResponse},
+};
+
+/// Dead letters kept; older ones are dropped as new ones arrive.
+const KEEP: u32 = 1000;
+
+/// Bytes of each body kept.
+const MAX_BODY: usize = 64 * 1024;
+
+/// `Json` for the hook endpoints: a body that doesn't parse is kept as a
+/// dead letter before being rejected the way `Json` would.
+pub struct HookJson<T>(pub T);
+
+#[async_trait]
+impl<T: DeserializeOwned + Send> FromRequest<AppState> for HookJson<T> {
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &AppState) -> Result<Self, Self::Rejection> {
+        let path = req.uri().path().to_string();
+        let body = Bytes::from_request(req, state).await.map_err(IntoResponse::into_response)?;
+        match Json::<T>::from_bytes(&body) {
+            Ok(Json(value)) => Ok(Self(value)),
+            Err(rejection) => {
+                let error = rejection.body_text();
+                warn!("Rejected a payload posted to {path}: {error}");
+                let kept = String::from_utf8_lossy(&body[..body.len().min(MAX_BODY)]);
+                if let Err(e) = state.store.insert_dead_letter(&path, &kept, &error, KEEP).await {
+                    warn!("Failed to record dead letter: {e}");
+                }
+                Err(rejection.into_response())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DeadLetterQuery {
+    /// Most to return, at most 1000.
+    #[serde(default = "default_limit")]
+    pub limit: u32,
+}
+
+fn default_limit() -> u32 {
+    100
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/dead-letters",
+    tag = "hooks",
+    params(DeadLetterQuery),
+    responses(
+        (status = 200, description = "Payloads that failed to parse, newest first", body = [DeadLetter]),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn get_dead_letters(
+    State(state): State<AppState>,
+    Query(query): Query<DeadLetterQuery>,
+) -> impl IntoResponse {
+    match state.store.get_dead_letters(query.limit.clamp(1, KEEP)).await {
+        Ok(letters) => Json(letters).into_response(),
+        Err(e) => {
+            warn!("get_dead_letters error: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::test_support::{self, event};
+
+    #[tokio::test]
+    async fn malformed_payloads_are_kept() {
+        let server = test_support::spawn().await;
+        let client = &server.client;
+        client.post_event(&event("user_prompt_submit", "s1")).await.unwrap();
+        assert!(client.post_hook(&json!({"session_id": 42})).await.is_err());
+
+        let letters = client.dead_letters(10).await.unwrap();
+        assert_eq!(letters.len(), 1);
+        assert_eq!(letters[0].path, "/api/hooks");
+        assert_eq!(letters[0].body, r#"{"session_id":42}"#);
+        assert!(letters[0].error.contains("session_id"), "{}", letters[0].error);
+    }
+}
@@ -0,0 +1,103 @@
+//! Narrow, stable API for editor statusline plugins (VS Code, Neovim).
+//!
+//! Plugins poll `GET /api/editor/sessions?cwd=<workspace>` and only get the
+//! sessions running inside their workspace, in a compact shape.
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::warn;
+use utoipa::IntoParams;
+
+use crate::{
+    api::AppState,
+    models::{EditorSession, ErrorResponse},
+    paths, projects,
+};
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct EditorQuery {
+    /// The editor's workspace directory.
+    pub cwd: String,
+    /// Only sessions on this host, for a monitor shared by several machines.
+    pub host: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/editor/sessions",
+    tag = "editor",
+    params(EditorQuery),
+    responses(
+        (status = 200, description = "Sessions inside the workspace", body = Vec<EditorSession>),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn get_sessions(
+    State(state): State<AppState>,
+    Query(query): Query<EditorQuery>,
+) -> impl IntoResponse {
+    // Stored project paths are canonical, so canonicalize the workspace the same way.
+    let workspace = paths::canonicalize(&query.cwd);
+
+    match workspace_sessions(&state, &workspace).await {
+        Ok(mut sessions) => {
+            if let Some(host) = &query.host {
+                sessions.retain(|s| &s.host == host);
+            }
+            Json(sessions).into_response()
+        }
+        Err(e) => {
+            warn!("editor get_sessions error: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response()
+        }
+    }
+}
+
+/// Sessions inside `workspace`, under their project aliases.
+async fn workspace_sessions(state: &AppState, workspace: &str) -> anyhow::Result<Vec<EditorSession>> {
+    let mut sessions = state.store.get_workspace_sessions(workspace).await?;
+    let aliases = projects::aliases(state.store.as_ref()).await?;
+    for session in &mut sessions {
+        if let Some(name) = projects::alias_for(&aliases, &session.project_path) {
+            session.project_name = name.clone();
+        }
+    }
+    Ok(sessions)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        models::EditorSession,
+        test_support::{self, event},
+    };
+
+    #[tokio::test]
+    async fn the_root_and_trailing_slashes_are_workspaces_too() {
+        let server = test_support::spawn().await;
+        let mut inside = event("user_prompt_submit", "s1");
+        inside.project_path = Some("/srv/ws/app".to_string());
+        let mut beside = event("user_prompt_submit", "s2");
+        beside.project_path = Some("/srv/wsx".to_string());
+        server.client.post_event(&inside).await.unwrap();
+        server.client.post_event(&beside).await.unwrap();
+
+        let store = &server.state.store;
+        let ids = |sessions: Vec<EditorSession>| {
+            let mut ids: Vec<_> = sessions.into_iter().map(|s| s.session_id).collect();
+            ids.sort();
+            ids
+        };
+        assert_eq!(ids(store.get_workspace_sessions("/").await.unwrap()), ["s1", "s2"]);
+        assert_eq!(ids(store.get_workspace_sessions("/srv/ws/").await.unwrap()), ["s1"]);
+        assert_eq!(ids(store.get_workspace_sessions("/srv/ws").await.unwrap()), ["s1"]);
+        assert_eq!(server.client.editor_sessions("/", None).await.unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,408 @@
+//! `GET /api/export`: every session, agent, event and setting, for moving to
+//! another machine or analysis elsewhere. The default JSON document is built
+//! in memory; `?format=ndjson` streams one record per line instead, reading
+//! events a page at a time, which suits large databases.
+//!
+//! `GET /api/export/events.csv` and `/api/export/sessions.csv` give the same
+//! data flattened for spreadsheets, such as for tracking time per project.
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use futures::{stream, StreamExt};
+use serde::Deserialize;
+use serde_json::json;
+use std::{collections::HashMap, sync::Arc};
+use tracing::warn;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    api::{AppState, HostFilter},
+    models::{
+        ErrorResponse, Export, ExportRecord, ExportedAgent, ExportedEvent, ExportedSession, ExportedSetting,
+        EXPORT_VERSION,
+    },
+    stats::{Bounds, DateRange},
+    store::Store,
+};
+
+/// Events read per query.
+const EVENT_PAGE: u32 = 1000;
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// One `Export` document.
+    #[default]
+    Json,
+    /// Newline-delimited `ExportRecord`s.
+    Ndjson,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExportQuery {
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+/// Everything but the events, which are read separately.
+struct Tables {
+    sessions: Vec<ExportedSession>,
+    agents: Vec<ExportedAgent>,
+    settings: Vec<ExportedSetting>,
+}
+
+async fn tables(store: &dyn Store) -> anyhow::Result<Tables> {
+    Ok(Tables {
+        sessions: store.export_sessions().await?,
+        agents: store.export_agents().await?,
+        settings: store.export_settings().await?,
+    })
+}
+
+async fn all_events(store: &dyn Store) -> anyhow::Result<Vec<ExportedEvent>> {
+    let mut events = Vec::new();
+    let mut after = 0;
+    loop {
+        let page = store.export_events(after, EVENT_PAGE, None, None).await?;
+        let Some(&(last, _)) = page.last() else {
+            return Ok(events);
+        };
+        after = last;
+        events.extend(page.into_iter().map(|(_, event)| event));
+    }
+}
+
+/// `Content-Disposition` naming the download after the time of the export.
+fn attachment(exported_at: DateTime<Utc>, extension: &str) -> String {
+    format!(
+        "attachment; filename=\"claude-monitor-export-{}.{extension}\"",
+        exported_at.format("%Y%m%dT%H%M%SZ")
+    )
+}
+
+fn line(record: &ExportRecord) -> Vec<u8> {
+    let mut line = serde_json::to_vec(record).unwrap_or_default();
+    line.push(b'\n');
+    line
+}
+
+/// Events within `(from, to)`, each encoded by `encode`, a page per chunk.
+/// A failed read ends the body early, which the client sees as a truncated
+/// download.
+fn event_chunks<F>(
+    store: Arc<dyn Store>,
+    (from, to): Bounds,
+    encode: F,
+) -> impl futures::Stream<Item = Result<Bytes, std::io::Error>>
+where
+    F: Fn(ExportedEvent) -> Vec<u8> + Clone + Send + 'static,
+{
+    stream::unfold(Some(0), move |after| {
+        let store = store.clone();
+        let encode = encode.clone();
+        async move {
+            let after = after?;
+            match store.export_events(after, EVENT_PAGE, from, to).await {
+                Ok(page) => {
+                    let &(last, _) = page.last()?;
+                    let chunk: Vec<u8> = page.into_iter().flat_map(|(_, event)| encode(event)).collect();
+                    Some((Ok(chunk.into()), Some(last)))
+                }
+                Err(e) => {
+                    warn!("export_events error: {e}");
+                    Some((Err(std::io::Error::other(e.to_string())), None))
+                }
+            }
+        }
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/export",
+    tag = "export",
+    params(ExportQuery),
+    responses(
+        (status = 200, body = Export, description = "The whole database; one `ExportRecord` per line with \
+                                                     `format=ndjson`"),
+        (status = 401, description = "Neither the listener's token nor an admin key", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn export(State(state): State<AppState>, Query(query): Query<ExportQuery>) -> impl IntoResponse {
+    let exported_at = Utc::now();
+    let tables = match tables(state.store.as_ref()).await {
+        Ok(tables) => tables,
+        Err(e) => {
+            warn!("export error: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response();
+        }
+    };
+
+    match query.format {
+        ExportFormat::Json => {
+            let events = match all_events(state.store.as_ref()).await {
+                Ok(events) => events,
+                Err(e) => {
+                    warn!("export error: {e}");
+                    return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()})))
+                        .into_response();
+                }
+            };
+            let export = Export {
+                version: EXPORT_VERSION,
+                exported_at,
+                sessions: tables.sessions,
+                agents: tables.agents,
+                events,
+                settings: tables.settings,
+            };
+            ([(header::CONTENT_DISPOSITION, attachment(exported_at, "json"))], Json(export)).into_response()
+        }
+        ExportFormat::Ndjson => {
+            let header_line = ExportRecord::Header { version: EXPORT_VERSION, exported_at };
+            let records = std::iter::once(header_line)
+                .chain(tables.sessions.into_iter().map(ExportRecord::Session))
+                .chain(tables.agents.into_iter().map(ExportRecord::Agent))
+                .chain(tables.settings.into_iter().map(ExportRecord::Setting));
+            let head = stream::iter(records.map(|record| Ok(Bytes::from(line(&record)))));
+            let events = event_chunks(state.store.clone(), (None, None), |event| line(&ExportRecord::Event(event)));
+            let body = Body::from_stream(head.chain(events));
+            (
+                [
+                    (header::CONTENT_TYPE, "application/x-ndjson".to_string()),
+                    (header::CONTENT_DISPOSITION, attachment(exported_at, "ndjson")),
+                ],
+                body,
+            )
+                .into_response()
+        }
+    }
+}
+
+/// One CSV record, fields quoted where they need it and CRLF-terminated as
+/// RFC 4180 has it.
+fn csv_row<I, S>(fields: I) -> Vec<u8>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut row = String::new();
+    for (i, field) in fields.into_iter().enumerate() {
+        if i > 0 {
+            row.push(',');
+        }
+        let field = field.as_ref();
+        if field.contains([',', '"', '\n', '\r']) {
+            row.push('"');
+            row.push_str(&field.replace('"', "\"\""));
+            row.push('"');
+        } else {
+            row.push_str(field);
+        }
+    }
+    row.push_str("\r\n");
+    row.into_bytes()
+}
+
+fn timestamp(ts: DateTime<Utc>) -> String {
+    ts.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn csv_response(name: &str, body: Body) -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{name}\"")),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+const EVENT_COLUMNS: [&str; 8] =
+    ["timestamp", "host", "session_id", "project_name", "agent_name", "event_type", "tool_name", "message"];
+
+#[utoipa::path(
+    get,
+    path = "/api/export/events.csv",
+    tag = "export",
+    params(DateRange, HostFilter),
+    responses(
+        (status = 200, content_type = "text/csv", body = String,
+         description = "One row per event within the range, oldest first"),
+        (status = 400, description = "Invalid `from` or `to`", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn events_csv(
+    State(state): State<AppState>,
+    Query(range): Query<DateRange>,
+    Query(hosts): Query<HostFilter>,
+) -> impl IntoResponse {
+    let bounds = match range.parse() {
+        Ok(bounds) => bounds,
+        Err(e) => return (StatusCode::BAD_REQUEST, Json(json!({"error": e}))).into_response(),
+    };
+    let projects: HashMap<String, String> = match state.store.export_sessions().await {
+        Ok(sessions) => sessions.into_iter().map(|s| (s.session_id, s.project_name)).collect(),
+        Err(e) => {
+            warn!("events_csv error: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response();
+        }
+    };
+
+    let (projects, hosts) = (Arc::new(projects), Arc::new(hosts));
+    let rows = event_chunks(state.store.clone(), bounds, move |event| {
+        if !hosts.allows(&event.host) {
+            return Vec::new();
+        }
+        let text = |key: &str| event.payload.get(key).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+        csv_row([
+            timestamp(event.timestamp),
+            event.host.clone(),
+            event.session_id.clone(),
+            projects.get(&event.session_id).cloned().unwrap_or_default(),
+            event.agent_name.clone().unwrap_or_default(),
+            event.event_type.clone(),
+            text("tool_name"),
+            text("message"),
+        ])
+    });
+    let header_row = stream::once(async { Ok(Bytes::from(csv_row(EVENT_COLUMNS))) });
+    csv_response("events.csv", Body::from_stream(header_row.chain(rows)))
+}
+
+const SESSION_COLUMNS: [&str; 7] =
+    ["host", "session_id", "project_name", "status", "started_at", "ended_at", "duration_seconds"];
+
+#[utoipa::path(
+    get,
+    path = "/api/export/sessions.csv",
+    tag = "export",
+    params(DateRange, HostFilter),
+    responses(
+        (status = 200, content_type = "text/csv", body = String,
+         description = "Sessions that completed within the range, including those already cleaned up, then those \
+                        still open that were active in it; `ended_at` is empty for open ones"),
+        (status = 400, description = "Invalid `from` or `to`", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn sessions_csv(
+    State(state): State<AppState>,
+    Query(range): Query<DateRange>,
+    Query(hosts): Query<HostFilter>,
+) -> impl IntoResponse {
+    let (from, to) = match range.parse() {
+        Ok(bounds) => bounds,
+        Err(e) => return (StatusCode::BAD_REQUEST, Json(json!({"error": e}))).into_response(),
+    };
+    let (mut completed, sessions) =
+        match tokio::try_join!(state.store.get_session_durations(from, to), state.store.export_sessions()) {
+            Ok(results) => results,
+            Err(e) => {
+                warn!("sessions_csv error: {e}");
+                return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response();
+            }
+        };
+    completed.sort_by_key(|d| d.started_at);
+
+    let mut csv = csv_row(SESSION_COLUMNS);
+    for d in completed.iter().filter(|d| hosts.allows(&d.host)) {
+        csv.extend(csv_row([
+            d.host.clone(),
+            d.session_id.clone(),
+            d.project_name.clone(),
+            "completed".to_string(),
+            timestamp(d.started_at),
+            timestamp(d.ended_at),
+            d.duration_seconds.to_string(),
+        ]));
+    }
+    let now = Utc::now();
+    let open = sessions.iter().filter(|s| {
+        s.status != "completed"
+            && hosts.allows(&s.host)
+            && to.is_none_or(|to| s.created_at < to)
+            && from.is_none_or(|from| s.updated_at >= from)
+    });
+    for s in open {
+        csv.extend(csv_row([
+            s.host.clone(),
+            s.session_id.clone(),
+            s.project_name.clone(),
+            s.status.clone(),
+            timestamp(s.created_at),
+            String::new(),
+            (now - s.created_at).num_seconds().max(0).to_string(),
+        ]));
+    }
+    csv_response("sessions.csv", Body::from(csv))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        models::ExportRecord,
+        test_support::{self, event},
+    };
+
+    #[tokio::test]
+    async fn exports_every_table() {
+        let server = test_support::spawn().await;
+        let client = &server.client;
+        client.post_event(&event("user_prompt_submit", "s1")).await.unwrap();
+        client.post_event(&event("stop", "s1")).await.unwrap();
+        client.add_tags("s1", &["PROJ-1".to_string()]).await.unwrap();
+        server.flushed().await;
+
+        let export = client.export().await.unwrap();
+        assert_eq!(export.sessions.len(), 1);
+        assert_eq!(export.sessions[0].tags, ["PROJ-1"]);
+        assert_eq!(export.agents.len(), 1);
+        assert_eq!(export.events.len(), 2);
+        assert_eq!(export.events[0].event_type, "user_prompt_submit");
+
+        let url = client.base_url().join("api/export?format=ndjson").unwrap();
+        let body = reqwest::get(url).await.unwrap().text().await.unwrap();
+        let records: Vec<ExportRecord> = body.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert!(matches!(records[0], ExportRecord::Header { .. }));
+        assert_eq!(records.iter().filter(|r| matches!(r, ExportRecord::Event(_))).count(), 2);
+    }
+
+    #[tokio::test]
+    async fn csv_exports_quote_fields_and_honour_the_range() {
+        let server = test_support::spawn().await;
+        let client = &server.client;
+        let mut notification = event("notification", "s1");
+        notification.message = Some("Needs \"approval\", again".to_string());
+        client.post_event(&notification).await.unwrap();
+        client.post_event(&event("session_end", "s1")).await.unwrap();
+        client.post_event(&event("user_prompt_submit", "s2")).await.unwrap();
+        server.flushed().await;
+
+        let events = client.events_csv(None, None, None).await.unwrap();
+        let lines: Vec<&str> = events.split_terminator("\r\n").collect();
+        assert_eq!(lines[0], "timestamp,host,session_id,project_name,agent_name,event_type,tool_name,message");
+        assert_eq!(lines.len(), 4);
+        assert!(lines[1].ends_with(",s1,demo,main,notification,,\"Needs \"\"approval\"\", again\""));
+
+        let sessions = client.sessions_csv(None, None, None).await.unwrap();
+        let lines: Vec<&str> = sessions.split_terminator("\r\n").collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].contains(",s1,demo,completed,"));
+        assert!(lines[2].contains(",s2,demo,"));
+
+        let empty = client.events_csv(Some("2000-01-01"), Some("2000-01-02"), None).await.unwrap();
+        assert_eq!(empty.split_terminator("\r\n").count(), 1);
+        assert!(client.sessions_csv(Some("yesterday"), None, None).await.is_err());
+    }
+}
@@ -0,0 +1,115 @@
+//! GraphQL API at `/api/graphql`, for dashboards that want sessions, their
+//! agents, and recent events in one round-trip:
+//!
+//! ```graphql
+//! { sessions { projectName status agents { agentName recentEvents(limit: 5) { eventType timestamp } } } }
+//! ```
+//!
+//! Subscriptions over `/api/graphql/ws` follow the same broadcast as `/ws`.
+//! A GET on `/api/graphql` opens GraphiQL, pointed at them under the
+//! prefix the client sees, see `proxy`.
+
+use async_graphql::{http::GraphiQLSource, Context, EmptyMutation, Object, Result, Schema, Subscription};
+use axum::{
+    extract::State,
+    http::HeaderMap,
+    response::{Html, IntoResponse},
+};
+use futures::{future::BoxFuture, stream, FutureExt, Stream};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+use crate::{
+    api::{AppState, SessionFilter, SessionSnapshot, MAX_EVENTS},
+    models::{EventSource, SessionEvent, SessionWithAgents},
+    proxy,
+    store::Store,
+};
+
+pub type MonitorSchema = Schema<QueryRoot, EmptyMutation, SubscriptionRoot>;
+
+pub fn schema(state: AppState) -> MonitorSchema {
+    let events: Arc<dyn EventSource> = Arc::new(StoredEvents(state.store.clone()));
+    Schema::build(QueryRoot, EmptyMutation, SubscriptionRoot)
+        .data(state)
+        .data(events)
+        .finish()
+}
+
+/// Serves the `recentEvents` fields from the events table.
+struct StoredEvents(Arc<dyn Store>);
+
+impl EventSource for StoredEvents {
+    fn recent_events<'a>(
+        &'a self,
+        session_id: &'a str,
+        agent_name: Option<&'a str>,
+        limit: u32,
+    ) -> BoxFuture<'a, Result<Vec<SessionEvent>>> {
+        async move { Ok(self.0.get_recent_events(session_id, agent_name, limit.min(MAX_EVENTS)).await?) }.boxed()
+    }
+}
+
+pub async fn graphiql(State(state): State<AppState>, headers: HeaderMap) -> impl IntoResponse {
+    let prefix = proxy::prefix(&headers, &proxy::base_path(&state.config.listen.base_path));
+    let (endpoint, subscriptions) = (format!("{prefix}/api/graphql"), format!("{prefix}/api/graphql/ws"));
+    Html(GraphiQLSource::build().endpoint(&endpoint).subscription_endpoint(&subscriptions).finish())
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// Active sessions, including those muted with `hide`, as GET /api/sessions
+    /// returns them.
+    async fn sessions(
+        &self,
+        ctx: &Context<'_>,
+        host: Option<String>,
+        #[graphql(default)] tags: Vec<String>,
+    ) -> Result<Vec<SessionWithAgents>> {
+        let state = ctx.data::<AppState>()?;
+        let filter = SessionFilter { host, tags, include_hidden: true };
+        Ok(filter.apply(&state.active_sessions().await?))
+    }
+
+    /// One active session by its Claude session ID.
+    async fn session(&self, ctx: &Context<'_>, session_id: String) -> Result<Option<SessionWithAgents>> {
+        let state = ctx.data::<AppState>()?;
+        Ok(state.active_sessions().await?.into_iter().find(|s| s.session_id == session_id))
+    }
+}
+
+pub struct SubscriptionRoot;
+
+#[Subscription]
+impl SubscriptionRoot {
+    /// The session list, sent on subscribe and again on every change. Sessions
+    /// muted with `hide` are left out unless `includeHidden` is set, as on `/ws`.
+    async fn sessions(
+        &self,
+        ctx: &Context<'_>,
+        host: Option<String>,
+        #[graphql(default)] tags: Vec<String>,
+        #[graphql(default)] include_hidden: bool,
+    ) -> Result<impl Stream<Item = Vec<SessionWithAgents>>> {
+        let state = ctx.data::<AppState>()?;
+        // Subscribe before the initial fetch so no update slips in between.
+        let rx = state.tx.subscribe();
+        let initial: SessionSnapshot = state.active_sessions().await?.into();
+        let filter = SessionFilter { host, tags, include_hidden };
+        Ok(stream::unfold((rx, Some(initial), filter), |(mut rx, pending, filter)| async move {
+            let snapshot = match pending {
+                Some(snapshot) => snapshot,
+                None => loop {
+                    match rx.recv().await {
+                        Ok(snapshot) => break snapshot,
+                        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(broadcast::error::RecvError::Closed) => return None,
+                    }
+                },
+            };
+            Some((filter.apply(&snapshot), (rx, None, filter)))
+        }))
+    }
+}
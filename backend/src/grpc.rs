@@ -0,0 +1,203 @@
+//! gRPC API (`proto/monitor.proto`) for backend services and typed clients.
+//! It is served on the HTTP port: axum speaks HTTP/2 without TLS, so the
+//! generated service is mounted as a route like any other.
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse};
+use chrono::{DateTime, Utc};
+use futures::{stream, Stream};
+use std::pin::Pin;
+use tokio::sync::broadcast;
+use tonic::{Request, Response, Status};
+
+use crate::{
+    api::{self, AppState, SessionFilter, SessionSnapshot},
+    dead_letters::HookJson,
+    keys::PresentedKey,
+    models::{Agent, HookEvent, SessionWithAgents, TokenUsage},
+    request_id::RequestId,
+};
+
+#[allow(clippy::all)]
+pub mod proto {
+    tonic::include_proto!("claude_monitor.v1");
+}
+
+use proto::monitor_server::{Monitor, MonitorServer};
+
+/// Route the generated service's methods live under.
+pub const ROUTE: &str = "/claude_monitor.v1.Monitor/*rpc";
+
+pub fn service(state: AppState) -> MonitorServer<Service> {
+    MonitorServer::new(Service { state })
+}
+
+pub struct Service {
+    state: AppState,
+}
+
+fn timestamp(t: DateTime<Utc>) -> prost_types::Timestamp {
+    prost_types::Timestamp {
+        seconds: t.timestamp(),
+        nanos: t.timestamp_subsec_nanos() as i32,
+    }
+}
+
+impl From<&TokenUsage> for proto::TokenUsage {
+    fn from(usage: &TokenUsage) -> Self {
+        Self {
+            input_tokens: usage.input_tokens,
+            output_tokens: usage.output_tokens,
+            cache_creation_input_tokens: usage.cache_creation_input_tokens,
+            cache_read_input_tokens: usage.cache_read_input_tokens,
+        }
+    }
+}
+
+impl From<&Agent> for proto::Agent {
+    fn from(agent: &Agent) -> Self {
+        Self {
+            id: agent.id.to_string(),
+            session_id: agent.session_id.clone(),
+            agent_name: agent.agent_name.clone(),
+            parent_session_id: agent.parent_session_id.clone(),
+            status: agent.status.clone(),
+            created_at: Some(timestamp(agent.created_at)),
+            updated_at: Some(timestamp(agent.updated_at)),
+            runtime_seconds: agent.runtime_seconds,
+        }
+    }
+}
+
+impl From<&SessionWithAgents> for proto::Session {
+    fn from(s: &SessionWithAgents) -> Self {
+        Self {
+            id: s.id.to_string(),
+            host: s.host.clone(),
+            session_id: s.session_id.clone(),
+            project_name: s.project_name.clone(),
+            project_path: s.project_path.clone(),
+            status: s.status.clone(),
+            status_text: s.status_text.clone(),
+            model: s.model.clone(),
+            git_branch: s.git_branch.clone(),
+            current_tool: s.current_tool.clone(),
+            last_message: s.last_message.clone(),
+            last_assistant_text: s.last_assistant_text.clone(),
+            last_tool_call: s.last_tool_call.clone(),
+            pinned: s.pinned,
+            muted: s.muted,
+            hidden: s.hidden,
+            tags: s.tags.clone(),
+            notes: s.notes.clone(),
+            created_at: Some(timestamp(s.created_at)),
+            updated_at: Some(timestamp(s.updated_at)),
+            agents: s.agents.iter().map(Into::into).collect(),
+            usage: Some((&s.usage).into()),
+            estimated_cost_usd: s.estimated_cost_usd,
+            source: s.source.clone(),
+        }
+    }
+}
+
+impl From<proto::HookEvent> for HookEvent {
+    fn from(e: proto::HookEvent) -> Self {
+        Self {
+            event_type: e.event_type,
+            session_id: e.session_id,
+            host: e.host,
+            project_path: e.project_path,
+            project_name: e.project_name,
+            agent_name: e.agent_name,
+            parent_session_id: e.parent_session_id,
+            needs_input: e.needs_input,
+            tool_name: e.tool_name,
+            transcript_path: e.transcript_path,
+            message: e.message,
+            tool_use_id: e.tool_use_id,
+            model: e.model,
+            git_branch: e.git_branch,
+        }
+    }
+}
+
+fn session_list(filter: &SessionFilter, sessions: &[SessionWithAgents]) -> Vec<proto::Session> {
+    filter.apply(sessions).iter().map(Into::into).collect()
+}
+
+type UpdateStream = Pin<Box<dyn Stream<Item = Result<proto::SessionList, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl Monitor for Service {
+    async fn list_sessions(
+        &self,
+        request: Request<proto::ListSessionsRequest>,
+    ) -> Result<Response<proto::ListSessionsResponse>, Status> {
+        let request = request.into_inner();
+        let filter = SessionFilter {
+            host: request.host,
+            tags: request.tags,
+            include_hidden: true,
+        };
+        let sessions = self.state.active_sessions().await.map_err(|e| Status::internal(e.to_string()))?;
+        Ok(Response::new(proto::ListSessionsResponse {
+            sessions: session_list(&filter, &sessions),
+        }))
+    }
+
+    type StreamUpdatesStream = UpdateStream;
+
+    async fn stream_updates(
+        &self,
+        request: Request<proto::StreamUpdatesRequest>,
+    ) -> Result<Response<UpdateStream>, Status> {
+        let request = request.into_inner();
+        let filter = SessionFilter {
+            host: request.host,
+            tags: request.tags,
+            include_hidden: request.include_hidden,
+        };
+        // Subscribe before the initial fetch so no update slips in between.
+        let rx = self.state.tx.subscribe();
+        let initial: SessionSnapshot = self
+            .state
+            .active_sessions()
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?
+            .into();
+        let updates = stream::unfold((rx, Some(initial), filter), |(mut rx, pending, filter)| async move {
+            let snapshot = match pending {
+                Some(snapshot) => snapshot,
+                None => loop {
+                    match rx.recv().await {
+                        Ok(snapshot) => break snapshot,
+                        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(broadcast::error::RecvError::Closed) => return None,
+                    }
+                },
+            };
+            let list = proto::SessionList {
+                sessions: session_list(&filter, &snapshot),
+            };
+            Some((Ok(list), (rx, None, filter)))
+        });
+        Ok(Response::new(Box::pin(updates)))
+    }
+
+    async fn post_event(
+        &self,
+        request: Request<proto::HookEvent>,
+    ) -> Result<Response<proto::PostEventResponse>, Status> {
+        let headers = request.metadata().clone().into_headers();
+        // The HTTP layer in front has set it, as for any request.
+        let (request_id, key) = (RequestId::from_headers(&headers), PresentedKey::from_headers(&headers));
+        let event: HookEvent = request.into_inner().into();
+        let state = State(self.state.clone());
+        let response = api::post_event(state, request_id, key, HookJson(event)).await.into_response();
+        match response.status() {
+            status if status.is_success() => {}
+            StatusCode::UNAUTHORIZED => return Err(Status::unauthenticated("missing, unknown or revoked x-api-key")),
+            status => return Err(Status::internal(format!("event not recorded: {status}"))),
+        }
+        Ok(Response::new(proto::PostEventResponse {}))
+    }
+}
@@ -0,0 +1,377 @@
+//! Claude CLI hook integration: the settings.json entries `install-hooks`
+//! writes, the `claude-monitor hook` client they run, and the `/api/hooks`
+//! endpoint it posts Claude's hook input to.
+
+use anyhow::{bail, Context, Result};
+use axum::{extract::State, response::IntoResponse};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::{
+    io::Read,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use utoipa::ToSchema;
+
+use crate::{
+    api,
+    api::AppState,
+    auth,
+    cli::{HookArgs, InstallHooksArgs},
+    dead_letters::HookJson,
+    keys::{self, PresentedKey},
+    listen::Target,
+    models::{ErrorResponse, HookEvent},
+    paths, relay,
+    request_id::RequestId,
+};
+
+/// Hook events installed, in the order they appear in settings.json.
+pub const HOOK_TYPES: &[&str] = &[
+    "SessionStart",
+    "SessionEnd",
+    "UserPromptSubmit",
+    "Stop",
+    "Notification",
+    "SubagentStart",
+    "SubagentStop",
+    "PreToolUse",
+    "PostToolUse",
+    "PostToolUseFailure",
+];
+
+/// Route the hook client posts to. Earlier installs ran curl against it
+/// directly, which is how their entries are recognized on reinstall.
+const HOOK_PATH: &str = "/api/hooks";
+
+/// Hook input as Claude passes it on stdin, plus what the hook client adds.
+/// Only the fields the monitor uses.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ClaudeHookInput {
+    pub hook_event_name: String,
+    pub session_id: String,
+    pub cwd: Option<String>,
+    pub transcript_path: Option<String>,
+    pub tool_name: Option<String>,
+    pub tool_use_id: Option<String>,
+    pub message: Option<String>,
+    pub notification_type: Option<String>,
+    pub agent_type: Option<String>,
+    pub model: Option<String>,
+    pub host: Option<String>,
+    pub project_name: Option<String>,
+    pub git_branch: Option<String>,
+}
+
+impl From<ClaudeHookInput> for HookEvent {
+    fn from(input: ClaudeHookInput) -> Self {
+        let (event_type, needs_input) = match (input.hook_event_name.as_str(), input.notification_type.as_deref()) {
+            ("Notification", Some("permission_prompt")) => ("needs_permission".to_string(), None),
+            ("Notification", kind) => ("notification".to_string(), Some(kind == Some("idle_prompt"))),
+            ("PostToolUseFailure", _) => ("post_tool_use".to_string(), None),
+            (name, _) => (snake_case(name), None),
+        };
+        let agent_name = match input.hook_event_name.as_str() {
+            "SubagentStart" | "SubagentStop" => input.agent_type,
+            _ => None,
+        };
+        HookEvent {
+            event_type,
+            session_id: input.session_id,
+            host: input.host,
+            project_path: input.cwd,
+            project_name: input.project_name,
+            agent_name,
+            parent_session_id: None,
+            needs_input,
+            tool_name: input.tool_name,
+            transcript_path: input.transcript_path,
+            message: input.message,
+            tool_use_id: input.tool_use_id,
+            model: input.model,
+            git_branch: input.git_branch,
+        }
+    }
+}
+
+/// `PreToolUse` → `pre_tool_use`.
+fn snake_case(name: &str) -> String {
+    let mut out = String::with_capacity(name.len() + 4);
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            out.push('_');
+        }
+        out.extend(c.to_lowercase());
+    }
+    out
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/hooks",
+    tag = "hooks",
+    request_body(content = ClaudeHookInput, description = "Hook input as Claude passes it on stdin"),
+    responses(
+        (status = 200, description = "Event recorded"),
+        (status = 401, description = "Missing, unknown or revoked API key", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn post_hook(
+    state: State<AppState>,
+    request_id: RequestId,
+    key: PresentedKey,
+    HookJson(input): HookJson<ClaudeHookInput>,
+) -> impl IntoResponse {
+    api::post_event(state, request_id, key, HookJson(input.into())).await
+}
+
+/// Hook events spooled while the server was unreachable, oldest first by name.
+fn spool_dir() -> Result<PathBuf> {
+    let home = dirs::home_dir().context("could not determine home directory")?;
+    Ok(home.join(".claude-monitor").join("spool"))
+}
+
+/// Past this many spooled events new ones are discarded, so a server that
+/// never comes back can't fill the disk.
+const MAX_SPOOLED: usize = 5000;
+
+/// Spooled events resent per hook run, keeping each run short.
+const FLUSH_BATCH: usize = 100;
+
+const POST_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Add what Claude's input lacks: the project name, its git branch, and this
+/// machine's hostname.
+fn enrich(input: &mut Map<String, Value>) {
+    let cwd = input.get("cwd").and_then(Value::as_str).unwrap_or("").to_string();
+    if let Some(name) = paths::basename(&cwd) {
+        input.entry("project_name").or_insert_with(|| json!(name));
+    }
+    if let Some(branch) = git_branch(&cwd) {
+        input.entry("git_branch").or_insert_with(|| json!(branch));
+    }
+    if let Some(host) = relay::hostname() {
+        input.entry("host").or_insert_with(|| json!(host));
+    }
+}
+
+fn git_branch(cwd: &str) -> Option<String> {
+    if cwd.is_empty() {
+        return None;
+    }
+    let out = std::process::Command::new("git")
+        .args(["-C", cwd, "rev-parse", "--abbrev-ref", "HEAD"])
+        .stderr(std::process::Stdio::null())
+        .output()
+        .ok()?;
+    let branch = String::from_utf8(out.stdout).ok()?.trim().to_string();
+    // Outside a repo git fails; on a detached HEAD it prints "HEAD".
+    (out.status.success() && !branch.is_empty() && branch != "HEAD").then_some(branch)
+}
+
+/// POST one event. Only network errors and 5xx fail: the server would reject
+/// a rejected event again, so it isn't worth spooling.
+async fn post(client: &reqwest::Client, url: &str, body: &str) -> Result<()> {
+    let response = client
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(body.to_string())
+        .send()
+        .await?;
+    if response.status().is_server_error() {
+        bail!("server returned {}", response.status());
+    }
+    Ok(())
+}
+
+/// Resend spooled events in order, stopping at the first that fails.
+async fn flush_spool(client: &reqwest::Client, url: &str, dir: &Path) -> Result<()> {
+    let mut files: Vec<PathBuf> = match std::fs::read_dir(dir) {
+        Ok(entries) => entries.filter_map(|e| e.ok().map(|e| e.path())).collect(),
+        Err(_) => return Ok(()),
+    };
+    files.retain(|f| f.extension().is_some_and(|ext| ext == "json"));
+    files.sort();
+    for file in files.into_iter().take(FLUSH_BATCH) {
+        let body = std::fs::read_to_string(&file)?;
+        post(client, url, &body).await?;
+        std::fs::remove_file(&file)?;
+    }
+    Ok(())
+}
+
+fn spool(dir: &Path, body: &str) -> Result<()> {
+    std::fs::create_dir_all(dir)?;
+    if std::fs::read_dir(dir)?.count() >= MAX_SPOOLED {
+        bail!("spool is full");
+    }
+    let nanos = chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default();
+    let name = format!("{nanos:020}-{}.json", uuid::Uuid::new_v4().simple());
+    std::fs::write(dir.join(name), body)?;
+    Ok(())
+}
+
+/// `claude-monitor hook`: read one hook input from stdin and post it to the
+/// server, spooling it on failure. Never fails, so a stopped monitor can't get
+/// in Claude's way.
+pub async fn run_client(args: &HookArgs) -> Result<()> {
+    let mut raw = String::new();
+    if std::io::stdin().read_to_string(&mut raw).is_err() {
+        return Ok(());
+    }
+    let Ok(Value::Object(mut input)) = serde_json::from_str::<Value>(&raw) else {
+        return Ok(());
+    };
+    enrich(&mut input);
+    let body = Value::Object(input).to_string();
+
+    let target = Target::resolve(args.server.as_deref());
+    let api_key = args.api_key.clone().or_else(|| std::env::var(keys::ENV).ok()).filter(|key| !key.is_empty());
+    let client = auth::http_client(POST_TIMEOUT, target.token.as_deref(), api_key.as_deref());
+    let url = format!("{}{HOOK_PATH}", target.url.trim_end_matches('/'));
+    let Ok(dir) = spool_dir() else { return Ok(()) };
+
+    // Spooled events go first so the server sees them in order.
+    let delivered = match flush_spool(&client, &url, &dir).await {
+        Ok(()) => post(&client, &url, &body).await.is_ok(),
+        Err(_) => false,
+    };
+    if !delivered {
+        let _ = spool(&dir, &body);
+    }
+    Ok(())
+}
+
+/// Quote `s` for a POSIX shell.
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// The hook command: this binary's `hook` subcommand, posting to `server`
+/// or, without one, the local server.
+fn hook_command(exe: &Path, server: Option<&str>, api_key: Option<&str>) -> String {
+    let mut command = format!("{} hook", shell_quote(&exe.display().to_string()));
+    if let Some(server) = server {
+        command.push_str(&format!(" --server {}", shell_quote(server)));
+    }
+    if let Some(api_key) = api_key {
+        command.push_str(&format!(" --api-key {}", shell_quote(api_key)));
+    }
+    command
+}
+
+fn is_monitor_hook(hook: &Value) -> bool {
+    let Some(command) = hook.get("command").and_then(Value::as_str) else {
+        return false;
+    };
+    // As written by `hook_command`, whatever directory the binary is in.
+    command.contains("claude-monitor' hook ")
+        || command.ends_with("claude-monitor' hook")
+        || command.contains(HOOK_PATH)
+}
+
+/// What merging did to one hook type.
+#[derive(Debug, PartialEq)]
+enum Change {
+    Added,
+    Updated,
+    Unchanged,
+}
+
+/// Put the monitor's hook under `hook_type`, replacing any earlier version of
+/// it and leaving the user's own hooks alone.
+fn merge_hook(hooks: &mut Map<String, Value>, hook_type: &str, command: &str) -> Result<Change> {
+    let entry = json!({"type": "command", "command": command, "async": true});
+    let rules = hooks.entry(hook_type).or_insert_with(|| json!([]));
+    let Some(rules) = rules.as_array_mut() else {
+        bail!("`hooks.{hook_type}` in settings is not a list");
+    };
+
+    let ours = |rule: &Value| {
+        rule.get("hooks")
+            .and_then(Value::as_array)
+            .is_some_and(|hooks| hooks.iter().any(is_monitor_hook))
+    };
+    let found = rules.iter().any(ours);
+    let current = rules.iter().any(|rule| {
+        rule.get("hooks")
+            .and_then(Value::as_array)
+            .is_some_and(|hooks| hooks.contains(&entry))
+    });
+    if current && rules.iter().filter(|r| ours(r)).count() == 1 {
+        return Ok(Change::Unchanged);
+    }
+
+    // Drop stale monitor hooks, and any rule they leave empty.
+    for rule in rules.iter_mut() {
+        if let Some(hooks) = rule.get_mut("hooks").and_then(Value::as_array_mut) {
+            hooks.retain(|h| !is_monitor_hook(h));
+        }
+    }
+    rules.retain(|rule| rule.get("hooks").and_then(Value::as_array).is_none_or(|h| !h.is_empty()));
+    rules.push(json!({"matcher": "", "hooks": [entry]}));
+    Ok(if found { Change::Updated } else { Change::Added })
+}
+
+fn default_settings_path() -> Result<PathBuf> {
+    let home = dirs::home_dir().context("could not determine home directory")?;
+    Ok(home.join(".claude").join("settings.json"))
+}
+
+fn read_settings(path: &Path) -> Result<Map<String, Value>> {
+    if !path.exists() {
+        return Ok(Map::new());
+    }
+    let raw = std::fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    if raw.trim().is_empty() {
+        return Ok(Map::new());
+    }
+    match serde_json::from_str(&raw).with_context(|| format!("failed to parse {}", path.display()))? {
+        Value::Object(settings) => Ok(settings),
+        _ => bail!("{} is not a JSON object", path.display()),
+    }
+}
+
+/// `claude-monitor install-hooks`.
+pub fn install(args: &InstallHooksArgs) -> Result<()> {
+    let path = match &args.settings {
+        Some(path) => path.clone(),
+        None => default_settings_path()?,
+    };
+    let mut settings = read_settings(&path)?;
+    let exe = std::env::current_exe().context("could not locate the claude-monitor binary")?;
+    let command = hook_command(&exe, args.server.as_deref(), args.api_key.as_deref());
+
+    let hooks = settings.entry("hooks").or_insert_with(|| json!({}));
+    let Some(hooks) = hooks.as_object_mut() else {
+        bail!("`hooks` in {} is not an object", path.display());
+    };
+    let mut changed = false;
+    for hook_type in HOOK_TYPES {
+        let change = merge_hook(hooks, hook_type, &command)?;
+        println!("  {change:?} {hook_type}");
+        changed |= change != Change::Unchanged;
+    }
+
+    let rendered = serde_json::to_string_pretty(&settings)? + "\n";
+    if args.dry_run {
+        println!("\nWould write {}:\n{rendered}", path.display());
+        return Ok(());
+    }
+    if !changed {
+        println!("\nHooks in {} are up to date", path.display());
+        return Ok(());
+    }
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    }
+    // Write beside the original and rename, so a crash can't leave it half-written.
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, rendered).with_context(|| format!("failed to write {}", tmp.display()))?;
+    std::fs::rename(&tmp, &path).with_context(|| format!("failed to replace {}", path.display()))?;
+    let server = Target::resolve(args.server.as_deref()).url;
+    println!("\nHooks installed in {}, reporting to {server}", path.display());
+    Ok(())
+}
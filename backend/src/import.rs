@@ -0,0 +1,171 @@
+//! `POST /api/import`: merge a `GET /api/export` into this database, either
+//! format, to restore a backup or move to another machine. Rows are matched
+//! on the keys the tables already have (a session's host and id, an agent's
+//! session and name, an event's id, a setting's key), so importing the same
+//! export twice adds nothing; `?on_conflict=replace` has the imported copies
+//! win instead. The whole import is one transaction.
+
+use axum::{
+    body::Bytes,
+    extract::{Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::{info, warn};
+use utoipa::IntoParams;
+
+use crate::{
+    api::AppState,
+    models::{ErrorResponse, Export, ExportRecord, ImportReport, OnConflict, EXPORT_VERSION},
+};
+
+/// Largest export accepted, well above the JSON extractor's default.
+pub const MAX_BYTES: usize = 512 * 1024 * 1024;
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ImportQuery {
+    #[serde(default)]
+    pub on_conflict: OnConflict,
+}
+
+/// Read an export in either format: NDJSON when the request says so,
+/// otherwise one JSON document.
+fn parse(headers: &HeaderMap, body: &[u8]) -> Result<Export, String> {
+    let ndjson = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/x-ndjson"));
+    let export = if ndjson { parse_ndjson(body)? } else { serde_json::from_slice(body).map_err(|e| e.to_string())? };
+    if export.version == 0 || export.version > EXPORT_VERSION {
+        return Err(format!("unsupported export version {}; this server reads up to {EXPORT_VERSION}", export.version));
+    }
+    Ok(export)
+}
+
+fn parse_ndjson(body: &[u8]) -> Result<Export, String> {
+    let text = std::str::from_utf8(body).map_err(|e| e.to_string())?;
+    let mut lines = text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+    let record = |(i, line): (usize, &str)| {
+        serde_json::from_str::<ExportRecord>(line).map_err(|e| format!("line {}: {e}", i + 1))
+    };
+
+    let Some(ExportRecord::Header { version, exported_at }) = lines.next().map(record).transpose()? else {
+        return Err("the first line must be the export's header record".to_string());
+    };
+    let mut export = Export {
+        version,
+        exported_at,
+        sessions: Vec::new(),
+        agents: Vec::new(),
+        events: Vec::new(),
+        settings: Vec::new(),
+    };
+    for line in lines {
+        match record(line)? {
+            ExportRecord::Header { .. } => return Err("more than one header record".to_string()),
+            ExportRecord::Session(session) => export.sessions.push(session),
+            ExportRecord::Agent(agent) => export.agents.push(agent),
+            ExportRecord::Setting(setting) => export.settings.push(setting),
+            ExportRecord::Event(event) => export.events.push(event),
+        }
+    }
+    Ok(export)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/import",
+    tag = "export",
+    params(ImportQuery),
+    request_body(
+        description = "A `GET /api/export` document, or its NDJSON form",
+        content((Export = "application/json"), (String = "application/x-ndjson"))
+    ),
+    responses(
+        (status = 200, description = "Rows imported and skipped per table", body = ImportReport),
+        (status = 400, description = "Not an export this server can read", body = ErrorResponse),
+        (status = 401, description = "Neither the listener's token nor an admin key", body = ErrorResponse),
+        (status = 500, description = "Database error; nothing was imported", body = ErrorResponse)
+    )
+)]
+pub async fn import(
+    State(state): State<AppState>,
+    Query(query): Query<ImportQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> impl IntoResponse {
+    let export = match parse(&headers, &body) {
+        Ok(export) => export,
+        Err(e) => return (StatusCode::BAD_REQUEST, Json(json!({"error": e}))).into_response(),
+    };
+    match state.store.import(&export, query.on_conflict).await {
+        Ok(report) => {
+            info!(
+                "Imported {} sessions, {} agents, {} events and {} settings from an export of {}",
+                report.sessions.imported,
+                report.agents.imported,
+                report.events.imported,
+                report.settings.imported,
+                export.exported_at,
+            );
+            state.broadcast_sessions();
+            Json(report).into_response()
+        }
+        Err(e) => {
+            warn!("import error: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        models::{ExportedSession, ImportCounts, OnConflict},
+        test_support::{self, event, TestServer},
+    };
+
+    async fn session(server: &TestServer, session_id: &str) -> ExportedSession {
+        let export = server.client.export().await.unwrap();
+        export.sessions.into_iter().find(|s| s.session_id == session_id).unwrap()
+    }
+
+    #[tokio::test]
+    async fn imports_merge_by_key() {
+        let source = test_support::spawn().await;
+        source.client.post_event(&event("user_prompt_submit", "s1")).await.unwrap();
+        source.client.add_tags("s1", &["PROJ-1".to_string()]).await.unwrap();
+        source.flushed().await;
+        let mut export = source.client.export().await.unwrap();
+
+        let target = test_support::spawn().await;
+        target.client.post_event(&event("user_prompt_submit", "s2")).await.unwrap();
+        target.flushed().await;
+
+        let report = target.client.import(&export, OnConflict::Skip).await.unwrap();
+        assert_eq!(report.sessions, ImportCounts { imported: 1, skipped: 0 });
+        assert_eq!(report.events, ImportCounts { imported: 1, skipped: 0 });
+        let sessions = target.client.sessions(&Default::default()).await.unwrap();
+        assert_eq!(sessions.len(), 2);
+
+        export.sessions[0].notes = Some("from the laptop".to_string());
+        let report = target.client.import(&export, OnConflict::Skip).await.unwrap();
+        assert_eq!(report.sessions, ImportCounts { imported: 0, skipped: 1 });
+        assert_eq!(report.events, ImportCounts { imported: 0, skipped: 1 });
+        let s1 = session(&target, "s1").await;
+        assert_eq!(s1.notes, None);
+
+        let report = target.client.import(&export, OnConflict::Replace).await.unwrap();
+        assert_eq!(report.sessions, ImportCounts { imported: 1, skipped: 0 });
+        let s1 = session(&target, "s1").await;
+        assert_eq!(s1.notes.as_deref(), Some("from the laptop"));
+        assert_eq!(s1.tags, ["PROJ-1"]);
+
+        export.version = 99;
+        assert!(target.client.import(&export, OnConflict::Skip).await.is_err());
+    }
+}
@@ -0,0 +1,330 @@
+//! Write-behind queue for event inserts. Handlers hand events to a bounded
+//! channel and move on; one task writes whatever has queued up in a single
+//! transaction, at most every `FLUSH_INTERVAL`. A full queue makes handlers
+//! wait, which is counted so sustained overload shows up in
+//! `GET /api/stats/ingest`.
+//!
+//! Each event is timed from its handler starting to its row being written
+//! and on to the first broadcast read after that, which carries it to
+//! clients; the histograms are in the stats and `/metrics`.
+//!
+//! After each write, sessions it added to are trimmed to their newest
+//! events, so a long session can't grow the database without bound:
+//!
+//! ```toml
+//! [events]
+//! max_per_session = 5000   # 0 keeps every event
+//! ```
+
+use axum::{extract::State, response::IntoResponse, Json};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::{
+    collections::BTreeSet,
+    future::Future,
+    sync::{
+        atomic::{AtomicI64, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tracing::{instrument, warn};
+use uuid::Uuid;
+
+use crate::{
+    api::AppState,
+    db,
+    metrics::Histogram,
+    models::{IngestStats, NewEvent},
+    request_id::RequestId,
+};
+
+/// Events the queue holds before handlers have to wait.
+const CAPACITY: usize = 4096;
+/// Most events written in one transaction.
+const MAX_BATCH: usize = 1000;
+/// Minimum gap between flushes.
+const FLUSH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// `[events]` section of config.toml.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct EventsConfig {
+    /// Newest events kept per session; older ones are dropped as new ones
+    /// are written. 0 keeps them all.
+    pub max_per_session: u32,
+}
+
+impl Default for EventsConfig {
+    fn default() -> Self {
+        Self { max_per_session: 5000 }
+    }
+}
+
+/// Handle for queueing events with the writer task.
+#[derive(Clone)]
+pub struct EventWriter {
+    tx: mpsc::Sender<NewEvent>,
+    counters: Arc<Counters>,
+}
+
+#[derive(Default)]
+struct Counters {
+    queued: AtomicU64,
+    waited: AtomicU64,
+    written: AtomicU64,
+    failed: AtomicU64,
+    pruned: AtomicU64,
+    batches: AtomicU64,
+    last_batch: AtomicU64,
+    last_flush_micros: AtomicU64,
+    /// When events were last stored, in Unix milliseconds; 0 before then.
+    last_written_millis: AtomicI64,
+    /// From an event's handler starting to its row being written.
+    write_latency: Histogram,
+    /// From an event's handler starting to a broadcast carrying it.
+    broadcast_latency: Histogram,
+    /// When the events written since the last broadcast were received.
+    unbroadcast: Mutex<Vec<Instant>>,
+}
+
+/// Events timed per broadcast at most; past this the rest aren't.
+const MAX_UNBROADCAST: usize = 10_000;
+
+impl Counters {
+    fn time_written(&self, batch: &[NewEvent]) {
+        let mut unbroadcast = self.unbroadcast.lock().unwrap_or_else(|e| e.into_inner());
+        for event in batch {
+            self.write_latency.observe(event.received.elapsed());
+            if unbroadcast.len() < MAX_UNBROADCAST {
+                unbroadcast.push(event.received);
+            }
+        }
+    }
+}
+
+/// The delivery an event came in: its request's id, the name of the API key
+/// it was sent with, and when its handler started.
+pub struct Origin {
+    pub request_id: RequestId,
+    pub source: Option<String>,
+    pub received: Instant,
+}
+
+impl Origin {
+    pub fn new(request_id: RequestId, source: Option<String>) -> Self {
+        Self { request_id, source, received: Instant::now() }
+    }
+}
+
+/// Receiving end of an [`EventWriter`], consumed by `run`.
+pub struct EventReceiver(mpsc::Receiver<NewEvent>);
+
+impl EventWriter {
+    /// Create the handle and the receiver that `run` consumes.
+    pub fn channel() -> (Self, EventReceiver) {
+        let (tx, rx) = mpsc::channel(CAPACITY);
+        (Self { tx, counters: Arc::default() }, EventReceiver(rx))
+    }
+
+    /// Queue an event, stamped now. Waits only while the queue is full.
+    pub async fn insert(
+        &self,
+        host: &str,
+        session_id: &str,
+        agent_name: Option<&str>,
+        event_type: &str,
+        payload: &str,
+        origin: &Origin,
+    ) {
+        let event = NewEvent {
+            id: Uuid::new_v4().to_string(),
+            host: host.to_string(),
+            session_id: session_id.to_string(),
+            agent_name: agent_name.map(str::to_string),
+            event_type: event_type.to_string(),
+            payload: payload.to_string(),
+            timestamp: Utc::now(),
+            request_id: origin.request_id.0.clone(),
+            source: origin.source.clone(),
+            received: origin.received,
+        };
+        self.counters.queued.fetch_add(1, Ordering::Relaxed);
+        let sent = match self.tx.try_send(event) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(event)) => {
+                self.counters.waited.fetch_add(1, Ordering::Relaxed);
+                self.tx.send(event).await.map_err(drop)
+            }
+            Err(TrySendError::Closed(_)) => Err(()),
+        };
+        if sent.is_err() {
+            warn!("event writer stopped; dropping {event_type} event for {session_id}");
+            self.counters.failed.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn stats(&self) -> IngestStats {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        IngestStats {
+            pending: (CAPACITY - self.tx.capacity()) as u64,
+            capacity: CAPACITY as u64,
+            queued: load(&self.counters.queued),
+            waited: load(&self.counters.waited),
+            written: load(&self.counters.written),
+            failed: load(&self.counters.failed),
+            pruned: load(&self.counters.pruned),
+            batches: load(&self.counters.batches),
+            last_batch: load(&self.counters.last_batch),
+            last_flush_ms: load(&self.counters.last_flush_micros) as f64 / 1000.0,
+            retried_writes: db::retried_writes(),
+            write_latency: self.counters.write_latency.summary(),
+            broadcast_latency: self.counters.broadcast_latency.summary(),
+        }
+    }
+
+    pub fn write_latency(&self) -> &Histogram {
+        &self.counters.write_latency
+    }
+
+    pub fn broadcast_latency(&self) -> &Histogram {
+        &self.counters.broadcast_latency
+    }
+
+    /// Take the receipt times of the events written since the last call,
+    /// for [`EventWriter::broadcast`]: a broadcast read after this carries
+    /// them.
+    pub fn take_written(&self) -> Vec<Instant> {
+        std::mem::take(&mut *self.counters.unbroadcast.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    /// The events received at `received` have gone out in a broadcast.
+    pub fn broadcast(&self, received: &[Instant]) {
+        for received in received {
+            self.counters.broadcast_latency.observe(received.elapsed());
+        }
+    }
+
+    /// When the writer last stored events, if it has since startup.
+    pub fn last_written(&self) -> Option<DateTime<Utc>> {
+        match self.counters.last_written_millis.load(Ordering::Relaxed) {
+            0 => None,
+            millis => DateTime::from_timestamp_millis(millis),
+        }
+    }
+}
+
+/// Write queued events until every handle is dropped or `stop` completes,
+/// and then the ones still queued; events queued after `stop` are refused.
+/// Each flush is followed by a broadcast, so streams that follow events see
+/// the new rows.
+pub async fn run(state: AppState, EventReceiver(mut rx): EventReceiver, stop: impl Future<Output = ()>) {
+    let mut stop = std::pin::pin!(stop);
+    let mut batch = Vec::with_capacity(MAX_BATCH);
+    loop {
+        tokio::select! {
+            () = &mut stop, if !rx.is_closed() => rx.close(),
+            received = rx.recv_many(&mut batch, MAX_BATCH) => {
+                if received == 0 {
+                    break;
+                }
+                write(&state, &batch).await;
+                batch.clear();
+                state.broadcast_sessions();
+                if !rx.is_closed() {
+                    tokio::time::sleep(FLUSH_INTERVAL).await;
+                }
+            }
+        }
+    }
+}
+
+/// Store one batch, trimming its sessions to `max_per_session` after.
+#[instrument(level = "debug", skip_all, fields(events = batch.len()))]
+async fn write(state: &AppState, batch: &[NewEvent]) {
+    let counters = &state.events.counters;
+    let keep = state.config.events.max_per_session;
+    let started = Instant::now();
+    let count = batch.len() as u64;
+    match state.store.insert_events(batch).await {
+        Ok(()) => {
+            counters.time_written(batch);
+            if keep > 0 {
+                let sessions: BTreeSet<(&str, &str)> =
+                    batch.iter().map(|event| (event.host.as_str(), event.session_id.as_str())).collect();
+                let sessions: Vec<(String, String)> =
+                    sessions.into_iter().map(|(host, id)| (host.to_string(), id.to_string())).collect();
+                match state.store.cap_session_events(&sessions, keep).await {
+                    Ok(pruned) => counters.pruned.fetch_add(pruned, Ordering::Relaxed),
+                    Err(e) => {
+                        warn!("cap_session_events error: {e}");
+                        0
+                    }
+                };
+            }
+            counters.last_written_millis.store(Utc::now().timestamp_millis(), Ordering::Relaxed);
+            counters.written.fetch_add(count, Ordering::Relaxed)
+        }
+        Err(e) => {
+            warn!("insert_events error, {count} events lost: {e}");
+            counters.failed.fetch_add(count, Ordering::Relaxed)
+        }
+    };
+    counters.batches.fetch_add(1, Ordering::Relaxed);
+    counters.last_batch.store(count, Ordering::Relaxed);
+    counters.last_flush_micros.store(started.elapsed().as_micros() as u64, Ordering::Relaxed);
+}
+
+/// Depth and throughput of the event write queue since startup.
+#[utoipa::path(get, path = "/api/stats/ingest", tag = "stats", responses((status = 200, body = IngestStats)))]
+pub async fn get_stats(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.events.stats())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        config::Config,
+        test_support::{self, event},
+    };
+
+    #[tokio::test]
+    async fn sessions_keep_their_newest_events() {
+        let mut config = Config::default();
+        config.events.max_per_session = 3;
+        let server = test_support::spawn_with(config).await;
+        for _ in 0..5 {
+            server.client.post_event(&event("pre_tool_use", "s1")).await.unwrap();
+        }
+        server.flushed().await;
+
+        assert_eq!(server.client.session_events("s1", 100, None).await.unwrap().len(), 3);
+        let sessions = server.client.sessions(&Default::default()).await.unwrap();
+        assert_eq!(sessions[0].pruned_events, 2);
+        assert_eq!(server.client.ingest_stats().await.unwrap().pruned, 2);
+    }
+
+    #[tokio::test]
+    async fn events_are_timed_to_their_broadcast() {
+        let server = test_support::spawn().await;
+        server.client.post_event(&event("user_prompt_submit", "s1")).await.unwrap();
+        server.flushed().await;
+        let mut stats = server.client.ingest_stats().await.unwrap();
+        for _ in 0..100 {
+            if stats.broadcast_latency.count > 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            stats = server.client.ingest_stats().await.unwrap();
+        }
+
+        assert_eq!(stats.write_latency.count, 1);
+        assert_eq!(stats.broadcast_latency.count, 1);
+        assert!(stats.broadcast_latency.max_ms >= stats.write_latency.max_ms, "{stats:?}");
+        let metrics = server.client.metrics().await.unwrap();
+        assert!(metrics.contains("claude_monitor_ingest_broadcast_seconds_count 1\n"), "{metrics}");
+    }
+}
@@ -0,0 +1,408 @@
+//! Named API keys for hooks, one per machine or pipeline (`laptop`,
+//! `dev-server`, `ci`), so each event says where it came from and one
+//! machine's key can be revoked or rotated without touching the others:
+//!
+//! ```bash
+//! curl -X POST localhost:9147/api/admin/keys -H "Authorization: Bearer $TOKEN" \
+//!     -H 'Content-Type: application/json' -d '{"name": "laptop"}'
+//! claude-monitor install-hooks --api-key cm_...
+//! ```
+//!
+//! Once any key has been created, events must be posted with a live one as
+//! `X-Api-Key`, and store its name as their `source`; until then they need
+//! none. Only each key's SHA-256 digest is kept. `DELETE
+//! /api/admin/keys/:name` revokes one; its name stays on the events it
+//! posted and isn't given out again.
+//!
+//! The `/api/admin` routes, these included, and the routes that clear,
+//! replace or dump everything (see `main::router`) need the listener's token
+//! where it has one (see `auth`); an admin key doesn't stand in for it there.
+//! On a listener with no token they need a key created with `"admin": true`
+//! while one is live, and are open otherwise, so that the first can be
+//! created and revoking the last doesn't lock them for good.
+
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Path, Request, State},
+    http::{request::Parts, HeaderMap, HeaderName, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::{collections::HashMap, convert::Infallible, sync::Arc};
+use tokio::sync::RwLock;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::{
+    api::AppState,
+    auth::Authenticated,
+    models::{ApiKey, ApiKeyInput, CreatedApiKey, ErrorResponse},
+    store::Store,
+};
+
+pub const HEADER: HeaderName = HeaderName::from_static("x-api-key");
+
+/// Where `claude-monitor hook` reads its key when not given `--api-key`.
+pub const ENV: &str = "CLAUDE_MONITOR_API_KEY";
+
+/// Longest name a key can be given.
+const MAX_NAME: usize = 64;
+
+/// The key a request was sent with, if any.
+#[derive(Debug, Clone, Default)]
+pub struct PresentedKey(pub Option<String>);
+
+impl PresentedKey {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        Self(headers.get(&HEADER).and_then(|key| key.to_str().ok()).map(str::to_string))
+    }
+}
+
+#[async_trait]
+impl<S: Sync> FromRequestParts<S> for PresentedKey {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self::from_headers(&parts.headers))
+    }
+}
+
+fn digest(key: &str) -> String {
+    format!("{:x}", Sha256::digest(key))
+}
+
+/// The keys as last read from the store, so posting an event doesn't have
+/// to; read again after one is created or revoked.
+#[derive(Clone, Default)]
+pub struct Keys(Arc<RwLock<Option<Arc<Known>>>>);
+
+struct Known {
+    /// Whether any key was ever created, so that events need one.
+    required: bool,
+    /// Whether a live admin key exists, so that the admin routes need one
+    /// where there is no token.
+    admin_required: bool,
+    /// The live keys, by digest.
+    live: HashMap<String, Live>,
+}
+
+struct Live {
+    name: String,
+    admin: bool,
+}
+
+/// Why an event was turned away.
+pub enum Refused {
+    Missing,
+    Unknown,
+    NotAdmin,
+    Store(anyhow::Error),
+}
+
+impl IntoResponse for Refused {
+    fn into_response(self) -> Response {
+        let (status, error) = match self {
+            Self::Missing => (StatusCode::UNAUTHORIZED, "an X-Api-Key is required".to_string()),
+            Self::Unknown => (StatusCode::UNAUTHORIZED, "unknown or revoked API key".to_string()),
+            Self::NotAdmin => (StatusCode::UNAUTHORIZED, "the token or an admin key is required".to_string()),
+            Self::Store(e) => {
+                warn!("api key check error: {e}");
+                (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+            }
+        };
+        (status, Json(json!({"error": error}))).into_response()
+    }
+}
+
+impl Keys {
+    /// The name of the live key `presented` is, or `None` when none was sent
+    /// and none is needed.
+    pub async fn source(&self, store: &dyn Store, presented: &PresentedKey) -> Result<Option<String>, Refused> {
+        let known = self.known(store).await.map_err(Refused::Store)?;
+        match &presented.0 {
+            Some(key) => known.live.get(&digest(key)).map(|live| Some(live.name.clone())).ok_or(Refused::Unknown),
+            None if known.required => Err(Refused::Missing),
+            None => Ok(None),
+        }
+    }
+
+    /// Whether `presented` is a live admin key, or none is needed yet.
+    async fn admin(&self, store: &dyn Store, presented: &PresentedKey) -> anyhow::Result<bool> {
+        let known = self.known(store).await?;
+        if !known.admin_required {
+            return Ok(true);
+        }
+        let Some(key) = &presented.0 else { return Ok(false) };
+        Ok(known.live.get(&digest(key)).is_some_and(|live| live.admin))
+    }
+
+    async fn known(&self, store: &dyn Store) -> anyhow::Result<Arc<Known>> {
+        if let Some(known) = self.0.read().await.as_ref() {
+            return Ok(known.clone());
+        }
+        let mut slot = self.0.write().await;
+        if let Some(known) = slot.as_ref() {
+            return Ok(known.clone());
+        }
+        let keys = store.get_api_keys().await?;
+        let live: HashMap<_, _> = store
+            .get_live_api_keys()
+            .await?
+            .into_iter()
+            .map(|(digest, name, admin)| (digest, Live { name, admin }))
+            .collect();
+        let known = Arc::new(Known {
+            required: !keys.is_empty(),
+            admin_required: live.values().any(|live| live.admin),
+            live,
+        });
+        *slot = Some(known.clone());
+        Ok(known)
+    }
+
+    /// Create a key called `name`; `None` when one already was.
+    async fn create(&self, store: &dyn Store, input: &ApiKeyInput) -> anyhow::Result<Option<CreatedApiKey>> {
+        // Held across the write, so no check can cache what it read before.
+        let mut slot = self.0.write().await;
+        let key = format!("cm_{}", Uuid::new_v4().simple());
+        let created = store.insert_api_key(&input.name, &digest(&key), input.admin).await?;
+        *slot = None;
+        Ok(created.map(|api_key| CreatedApiKey { api_key, key }))
+    }
+
+    async fn revoke(&self, store: &dyn Store, name: &str) -> anyhow::Result<bool> {
+        let mut slot = self.0.write().await;
+        let revoked = store.revoke_api_key(name).await?;
+        *slot = None;
+        Ok(revoked)
+    }
+}
+
+/// Middleware for the `/api/admin` routes and the others as destructive,
+/// letting through requests that came with the listener's token or an admin
+/// key, or any while no admin key is live. Only listeners without a token
+/// pass on requests without it, so there the token is always needed.
+pub async fn admin_only(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    if request.extensions().get::<Authenticated>().is_some() {
+        return next.run(request).await;
+    }
+    match state.keys.admin(state.store.as_ref(), &PresentedKey::from_headers(request.headers())).await {
+        Ok(true) => next.run(request).await,
+        Ok(false) => Refused::NotAdmin.into_response(),
+        Err(e) => Refused::Store(e).into_response(),
+    }
+}
+
+fn validate(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.len() > MAX_NAME {
+        return Err(format!("name must be 1 to {MAX_NAME} characters"));
+    }
+    if !name.bytes().all(|b| b.is_ascii_alphanumeric() || b"-_.".contains(&b)) {
+        return Err("name may only contain letters, digits, '-', '_' and '.'".to_string());
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/admin/keys",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Every API key, revoked ones too, oldest first", body = [ApiKey]),
+        (status = 401, description = "Neither the listener's token nor an admin key", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn list_keys(State(state): State<AppState>) -> impl IntoResponse {
+    match state.store.get_api_keys().await {
+        Ok(keys) => Json(keys).into_response(),
+        Err(e) => {
+            warn!("list_keys error: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/admin/keys",
+    tag = "admin",
+    request_body = ApiKeyInput,
+    responses(
+        (status = 201, description = "The new key, shown only this once", body = CreatedApiKey),
+        (status = 400, description = "Invalid name", body = ErrorResponse),
+        (status = 401, description = "Neither the listener's token nor an admin key", body = ErrorResponse),
+        (status = 409, description = "A key by that name exists", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn create_key(State(state): State<AppState>, Json(input): Json<ApiKeyInput>) -> impl IntoResponse {
+    if let Err(e) = validate(&input.name) {
+        return (StatusCode::BAD_REQUEST, Json(json!({"error": e}))).into_response();
+    }
+    match state.keys.create(state.store.as_ref(), &input).await {
+        Ok(Some(created)) => (StatusCode::CREATED, Json(created)).into_response(),
+        Ok(None) => {
+            let error = format!("a key named '{}' exists", input.name);
+            (StatusCode::CONFLICT, Json(json!({"error": error}))).into_response()
+        }
+        Err(e) => {
+            warn!("create_key error: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/admin/keys/{name}",
+    tag = "admin",
+    params(("name" = String, Path, description = "Key name")),
+    responses(
+        (status = 200, description = "Key revoked"),
+        (status = 401, description = "Neither the listener's token nor an admin key", body = ErrorResponse),
+        (status = 404, description = "No such key, or already revoked", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn revoke_key(State(state): State<AppState>, Path(name): Path<String>) -> impl IntoResponse {
+    match state.keys.revoke(state.store.as_ref(), &name).await {
+        Ok(true) => StatusCode::OK.into_response(),
+        Ok(false) => (StatusCode::NOT_FOUND, Json(json!({"error": "no live key by that name"}))).into_response(),
+        Err(e) => {
+            warn!("revoke_key error: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{HeaderMap, HeaderValue, StatusCode};
+    use claude_monitor_client::Client;
+    use tokio::net::TcpListener;
+
+    use super::HEADER;
+    use crate::test_support::{self, event, TestServer};
+
+    fn with_key(server: &TestServer, key: &str) -> Client {
+        let headers = HeaderMap::from_iter([(HEADER, HeaderValue::from_str(key).unwrap())]);
+        let http = reqwest::Client::builder().default_headers(headers).build().unwrap();
+        Client::with_http(server.client.base_url().as_str(), http).unwrap()
+    }
+
+    #[tokio::test]
+    async fn events_are_attributed_to_their_key_until_it_is_revoked() {
+        let server = test_support::spawn().await;
+        let client = &server.client;
+        client.post_event(&event("user_prompt_submit", "s1")).await.unwrap();
+
+        let laptop = client.create_api_key("laptop", false).await.unwrap();
+        assert!(client.create_api_key("laptop", false).await.is_err());
+        assert!(client.create_api_key("two words", false).await.is_err());
+        let ci = client.create_api_key("ci", false).await.unwrap();
+        // Keys exist now, so one is needed.
+        assert!(client.post_event(&event("pre_tool_use", "s1")).await.is_err());
+        assert!(with_key(&server, "cm_guess").post_event(&event("pre_tool_use", "s1")).await.is_err());
+        with_key(&server, &laptop.key).post_event(&event("pre_tool_use", "s1")).await.unwrap();
+        with_key(&server, &ci.key).post_event(&event("stop", "s1")).await.unwrap();
+        server.flushed().await;
+
+        let events = client.session_events("s1", 10, None).await.unwrap();
+        let sources: Vec<_> = events.iter().map(|e| e.source.as_deref()).collect();
+        assert_eq!(sources, [Some("ci"), Some("laptop"), None]);
+
+        client.revoke_api_key("laptop").await.unwrap();
+        assert!(client.revoke_api_key("laptop").await.is_err());
+        assert!(with_key(&server, &laptop.key).post_event(&event("stop", "s1")).await.is_err());
+        with_key(&server, &ci.key).post_event(&event("stop", "s1")).await.unwrap();
+        let keys = client.api_keys().await.unwrap();
+        assert_eq!(keys.len(), 2);
+        assert!(keys.iter().all(|key| key.revoked_at.is_some() == (key.name == "laptop")));
+    }
+
+    /// The app of `server` on a port with no token, without the test
+    /// server's pass; returns its URL.
+    async fn serve_without_token(server: &TestServer) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let app = crate::router(server.state.clone());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        url
+    }
+
+    #[tokio::test]
+    async fn the_first_admin_key_is_made_without_a_token() {
+        let server = test_support::spawn().await;
+        let url = serve_without_token(&server).await;
+        let http = reqwest::Client::new();
+        let keys = format!("{url}/api/admin/keys");
+
+        let hook = http.post(&keys).json(&serde_json::json!({"name": "laptop"})).send().await.unwrap();
+        assert_eq!(hook.status(), StatusCode::CREATED);
+        let admin = http.post(&keys).json(&serde_json::json!({"name": "ops", "admin": true})).send().await.unwrap();
+        assert_eq!(admin.status(), StatusCode::CREATED);
+        let admin: serde_json::Value = admin.json().await.unwrap();
+
+        // Closed from then on.
+        assert_eq!(http.get(&keys).send().await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        let as_admin = http.get(&keys).header(HEADER, admin["key"].as_str().unwrap()).send().await.unwrap();
+        assert!(as_admin.status().is_success());
+    }
+
+    #[tokio::test]
+    async fn admin_routes_need_the_token_or_an_admin_key_even_without_a_token() {
+        let server = test_support::spawn().await;
+        let admin = server.client.create_api_key("ops", true).await.unwrap();
+        let hook = server.client.create_api_key("laptop", false).await.unwrap();
+        let url = serve_without_token(&server).await;
+
+        let http = reqwest::Client::new();
+        let keys = format!("{url}/api/admin/keys");
+        assert_eq!(http.get(&keys).send().await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        let backup = http.post(format!("{url}/api/admin/backup")).send().await.unwrap();
+        assert_eq!(backup.status(), StatusCode::UNAUTHORIZED);
+        let as_hook = http.get(&keys).header(HEADER, &hook.key).send().await.unwrap();
+        assert_eq!(as_hook.status(), StatusCode::UNAUTHORIZED);
+        let as_admin = http.get(&keys).header(HEADER, &admin.key).send().await.unwrap();
+        assert!(as_admin.status().is_success());
+        // So do the routes that clear, replace or dump everything.
+        let clear = http.delete(format!("{url}/api/sessions")).send().await.unwrap();
+        assert_eq!(clear.status(), StatusCode::UNAUTHORIZED);
+        let settings = http.put(format!("{url}/api/settings")).json(&serde_json::json!({})).send().await.unwrap();
+        assert_eq!(settings.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(http.get(format!("{url}/api/export")).send().await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        let import = http.post(format!("{url}/api/import")).send().await.unwrap();
+        assert_eq!(import.status(), StatusCode::UNAUTHORIZED);
+        // Everything else stays open there.
+        assert!(http.get(format!("{url}/api/sessions")).send().await.unwrap().status().is_success());
+        assert!(http.get(format!("{url}/api/settings")).send().await.unwrap().status().is_success());
+
+        let spare = server.client.create_api_key("spare", true).await.unwrap();
+        server.client.revoke_api_key("ops").await.unwrap();
+        let revoked = http.get(&keys).header(HEADER, &admin.key).send().await.unwrap();
+        assert_eq!(revoked.status(), StatusCode::UNAUTHORIZED);
+        assert!(http.get(&keys).header(HEADER, &spare.key).send().await.unwrap().status().is_success());
+    }
+
+    #[tokio::test]
+    async fn revoking_the_last_admin_key_reopens_the_admin_routes() {
+        let server = test_support::spawn().await;
+        server.client.create_api_key("ops", true).await.unwrap();
+        server.client.create_api_key("laptop", false).await.unwrap();
+        let url = serve_without_token(&server).await;
+        let http = reqwest::Client::new();
+        let keys = format!("{url}/api/admin/keys");
+        assert_eq!(http.get(&keys).send().await.unwrap().status(), StatusCode::UNAUTHORIZED);
+
+        // Not locked for good: a new admin key can be made where there is no token.
+        server.client.revoke_api_key("ops").await.unwrap();
+        assert!(http.get(&keys).send().await.unwrap().status().is_success());
+        let admin = http.post(&keys).json(&serde_json::json!({"name": "ops2", "admin": true})).send().await.unwrap();
+        assert_eq!(admin.status(), StatusCode::CREATED);
+        assert_eq!(http.get(&keys).send().await.unwrap().status(), StatusCode::UNAUTHORIZED);
+    }
+}
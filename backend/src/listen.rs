@@ -0,0 +1,300 @@
+//! Where the server listens: 127.0.0.1, unless `expose_lan` opens it to
+//! other machines, which then need its `token` (see `auth`). When the port
+//! is taken it can move up to one of the next `fallback_ports`, and writes
+//! the one it got to `~/.claude-monitor/port`, where hooks, `status`,
+//! `watch` and the overlay look for it when not given a `--server`:
+//!
+//! ```toml
+//! [listen]
+//! port = 9147
+//! fallback_ports = 10   # try up to 9157 when 9147 is taken
+//! expose_lan = true     # every interface, not just 127.0.0.1
+//! token = "..."
+//! ```
+//!
+//! Or on exact addresses instead, each with its own token and certificate,
+//! or else the `token` under [listen]; any but a loopback address needs one.
+//! `expose_lan` has no say over these. The first one's port is the one
+//! recorded:
+//!
+//! ```toml
+//! [[listen.bind]]
+//! address = "127.0.0.1:9147"
+//!
+//! [[listen.bind]]
+//! address = "[::1]:9147"
+//!
+//! [[listen.bind]]
+//! address = "100.64.0.7:9147"   # a tailnet address
+//! token = "..."
+//! tls_cert = "/etc/claude-monitor/cert.pem"
+//! tls_key = "/etc/claude-monitor/key.pem"
+//! ```
+
+use anyhow::{bail, Context, Result};
+use axum::Router;
+use axum_server::tls_rustls::RustlsConfig;
+use serde::Deserialize;
+use std::{
+    io::ErrorKind,
+    net::{Ipv4Addr, SocketAddr},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tokio::net::TcpListener;
+use tracing::{info, warn};
+
+use crate::{auth, config::Config, proxy, shutdown::{self, Shutdown}};
+
+pub const DEFAULT_PORT: u16 = 9147;
+
+const PORT_FILE: &str = "port";
+
+/// `[listen]` section of config.toml.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ListenConfig {
+    pub port: u16,
+    /// How many ports after `port` to try when it is in use; 0 fails instead.
+    pub fallback_ports: u16,
+    /// Listen on every interface rather than only 127.0.0.1; needs `token`.
+    pub expose_lan: bool,
+    /// Token requests to `port` must carry.
+    pub token: Option<String>,
+    /// Addresses to listen on instead of `port`.
+    pub bind: Vec<BindConfig>,
+    /// Path every route is served beneath, see `proxy`.
+    pub base_path: String,
+}
+
+impl Default for ListenConfig {
+    fn default() -> Self {
+        Self {
+            port: DEFAULT_PORT,
+            fallback_ports: 0,
+            expose_lan: false,
+            token: None,
+            bind: Vec::new(),
+            base_path: String::new(),
+        }
+    }
+}
+
+/// One `[[listen.bind]]` entry.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BindConfig {
+    pub address: SocketAddr,
+    /// Token requests to this address must carry, if not `[listen] token`.
+    pub token: Option<String>,
+    /// PEM certificate chain and private key to serve HTTPS with.
+    pub tls_cert: Option<PathBuf>,
+    pub tls_key: Option<PathBuf>,
+}
+
+/// A bound socket and how to serve it.
+pub struct Listener {
+    listener: TcpListener,
+    tls: Option<RustlsConfig>,
+    token: Option<String>,
+    /// Where the routes are, for the token check to know `/health`.
+    base_path: String,
+}
+
+impl Listener {
+    pub fn url(&self) -> String {
+        let scheme = if self.tls.is_some() { "https" } else { "http" };
+        match self.listener.local_addr() {
+            Ok(addr) => format!("{scheme}://{addr}"),
+            Err(_) => format!("{scheme}://?"),
+        }
+    }
+
+    /// Serve `app` until `shutdown`, then until the open connections end or,
+    /// for TLS, `shutdown::GRACE` has passed.
+    pub async fn serve(self, app: Router, shutdown: Shutdown) -> Result<()> {
+        let app = match &self.token {
+            Some(token) => auth::require(app, token, &self.base_path),
+            None => app,
+        };
+        match self.tls {
+            // The peer address is who sent a request, unless a proxy says otherwise.
+            None => {
+                let app = app.into_make_service_with_connect_info::<SocketAddr>();
+                axum::serve(self.listener, app).with_graceful_shutdown(shutdown.requested()).await?
+            }
+            Some(tls) => {
+                let app = app.into_make_service_with_connect_info::<SocketAddr>();
+                let handle = axum_server::Handle::new();
+                let stopping = handle.clone();
+                tokio::spawn(async move {
+                    shutdown.requested().await;
+                    stopping.graceful_shutdown(Some(shutdown::GRACE));
+                });
+                axum_server::from_tcp_rustls(self.listener.into_std()?, tls).handle(handle).serve(app).await?
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Bind everything `config` lists and record the first port in `data_dir`.
+pub async fn bind(config: &ListenConfig, data_dir: &Path) -> Result<Vec<Listener>> {
+    let base_path = proxy::base_path(&config.base_path);
+    let mut listeners = Vec::new();
+    if config.bind.is_empty() {
+        if config.expose_lan && config.token.is_none() {
+            bail!("`expose_lan` needs a `token` under [listen] for other machines to send");
+        }
+        let listener = bind_port(config).await?;
+        listeners.push(Listener { listener, tls: None, token: config.token.clone(), base_path: base_path.clone() });
+    }
+    if !config.bind.is_empty() && config.expose_lan {
+        warn!("`expose_lan` is ignored alongside [[listen.bind]], which lists the addresses to listen on");
+    }
+    for bind in &config.bind {
+        if bind.token.is_none() && config.token.is_some() {
+            info!("{} has no token of its own, so it takes the one under [listen]", bind.address);
+        }
+        let token = bind.token.as_deref().or(config.token.as_deref());
+        listeners.push(bind_address(bind, token, &base_path).await?);
+    }
+    let port = listeners[0].listener.local_addr()?.port();
+    let path = data_dir.join(PORT_FILE);
+    std::fs::write(&path, format!("{port}\n")).with_context(|| format!("failed to write {}", path.display()))?;
+    Ok(listeners)
+}
+
+/// The first free port of `config`'s range.
+async fn bind_port(config: &ListenConfig) -> Result<TcpListener> {
+    let ip = if config.expose_lan { Ipv4Addr::UNSPECIFIED } else { Ipv4Addr::LOCALHOST };
+    let last = config.port.saturating_add(config.fallback_ports);
+    for port in config.port..=last {
+        match TcpListener::bind(SocketAddr::from((ip, port))).await {
+            Ok(listener) => {
+                if port != config.port {
+                    warn!("Port {} is in use, listening on {port} instead", config.port);
+                }
+                return Ok(listener);
+            }
+            Err(e) if e.kind() == ErrorKind::AddrInUse && port < last => {}
+            Err(e) => return Err(e).with_context(|| format!("failed to bind to port {port}")),
+        }
+    }
+    bail!("no port to try")
+}
+
+async fn bind_address(bind: &BindConfig, token: Option<&str>, base_path: &str) -> Result<Listener> {
+    if !bind.address.ip().is_loopback() && token.is_none() {
+        bail!("{} can be reached from other machines, so it needs a `token`", bind.address);
+    }
+    let tls = match (&bind.tls_cert, &bind.tls_key) {
+        (Some(cert), Some(key)) => Some(
+            RustlsConfig::from_pem_file(cert, key)
+                .await
+                .with_context(|| format!("failed to load the certificate for {}", bind.address))?,
+        ),
+        (None, None) => None,
+        _ => bail!("{} needs both tls_cert and tls_key", bind.address),
+    };
+    let listener =
+        TcpListener::bind(bind.address).await.with_context(|| format!("failed to bind to {}", bind.address))?;
+    Ok(Listener { listener, tls, token: token.map(str::to_string), base_path: base_path.to_string() })
+}
+
+/// Token for a `--server`, which the local config can't know.
+const TOKEN_ENV: &str = "CLAUDE_MONITOR_TOKEN";
+
+/// A server for the CLI's subcommands to talk to.
+pub struct Target {
+    pub url: String,
+    pub token: Option<String>,
+}
+
+impl Target {
+    /// `server` if given, with `$CLAUDE_MONITOR_TOKEN`, else the local server
+    /// at the port it last bound, with its token from config.toml.
+    pub fn resolve(server: Option<&str>) -> Self {
+        let env_token = std::env::var(TOKEN_ENV).ok().filter(|token| !token.is_empty());
+        if let Some(server) = server {
+            return Self { url: server.to_string(), token: env_token };
+        }
+        let dir = dirs::home_dir().map(|home| home.join(".claude-monitor"));
+        let port = dir
+            .as_ref()
+            .and_then(|dir| std::fs::read_to_string(dir.join(PORT_FILE)).ok())
+            .and_then(|port| port.trim().parse().ok())
+            .unwrap_or(DEFAULT_PORT);
+        let config = dir.and_then(|dir| Config::load(&dir.join("config.toml")).ok()).unwrap_or_default();
+        // The recorded port is the first listener's.
+        let (ip, token) = match config.listen.bind.first() {
+            Some(bind) if !bind.address.ip().is_unspecified() => (bind.address.ip(), bind.token.clone()),
+            Some(bind) => (Ipv4Addr::LOCALHOST.into(), bind.token.clone()),
+            None => (Ipv4Addr::LOCALHOST.into(), None),
+        };
+        let token = token.or(config.listen.token);
+        Self { url: format!("http://{}", SocketAddr::new(ip, port)), token: token.or(env_token) }
+    }
+
+    /// An HTTP client that sends the token, if any.
+    pub fn http(&self, timeout: Duration) -> reqwest::Client {
+        auth::http_client(timeout, self.token.as_deref(), None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bind, BindConfig, ListenConfig, PORT_FILE};
+
+    #[tokio::test]
+    async fn a_taken_port_falls_back_to_the_next() {
+        let dir = std::env::temp_dir().join(format!("claude-monitor-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let taken = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = taken.local_addr().unwrap().port();
+
+        assert!(bind(&ListenConfig { port, ..Default::default() }, &dir).await.is_err());
+        // The ports after it may be taken too, so allow a few.
+        let listeners = bind(&ListenConfig { port, fallback_ports: 5, ..Default::default() }, &dir).await.unwrap();
+        let bound = listeners[0].listener.local_addr().unwrap().port();
+        assert!(bound > port && bound <= port + 5);
+        assert_eq!(std::fs::read_to_string(dir.join(PORT_FILE)).unwrap(), format!("{bound}\n"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn listens_on_loopback_unless_exposed_with_a_token() {
+        let dir = std::env::temp_dir().join(format!("claude-monitor-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let listeners = bind(&ListenConfig { port: 0, ..Default::default() }, &dir).await.unwrap();
+        let addr = listeners[0].listener.local_addr().unwrap();
+        assert_eq!(addr.ip(), std::net::Ipv4Addr::LOCALHOST);
+
+        let exposed = ListenConfig { port: 0, expose_lan: true, ..Default::default() };
+        assert!(bind(&exposed, &dir).await.is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn bind_entries_without_a_token_take_the_shared_one() {
+        let dir = std::env::temp_dir().join(format!("claude-monitor-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let entry = |address: &str, token: Option<&str>| BindConfig {
+            address: address.parse().unwrap(),
+            token: token.map(str::to_string),
+            tls_cert: None,
+            tls_key: None,
+        };
+        let open = ListenConfig { bind: vec![entry("0.0.0.0:0", None)], ..Default::default() };
+        assert!(bind(&open, &dir).await.is_err());
+
+        let config = ListenConfig {
+            token: Some("shared".to_string()),
+            bind: vec![entry("0.0.0.0:0", None), entry("127.0.0.1:0", Some("own"))],
+            ..Default::default()
+        };
+        let listeners = bind(&config, &dir).await.unwrap();
+        let tokens: Vec<_> = listeners.iter().map(|listener| listener.token.as_deref()).collect();
+        assert_eq!(tokens, [Some("shared"), Some("own")]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,159 @@
+//! The server's log, on stderr: plain text by default, or with
+//! `--log-format json` one JSON object a line carrying the fields of the
+//! spans it was written in, such as an event's `session_id`. `RUST_LOG`
+//! picks what is logged either way.
+//!
+//! Running as a service, the log can also go to files under
+//! `~/.claude-monitor/logs`, a new one each day, in the same format:
+//!
+//! ```toml
+//! [logging]
+//! file = true
+//! keep_days = 7        # files kept; older ones are deleted
+//! max_total_mb = 100   # oldest deleted past this; 0 for no cap
+//! ```
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tracing::warn;
+use tracing_appender::{
+    non_blocking::WorkerGuard,
+    rolling::{RollingFileAppender, Rotation},
+};
+use tracing_subscriber::{
+    fmt::{self, MakeWriter},
+    layer::SubscriberExt,
+    util::SubscriberInitExt,
+    EnvFilter, Layer, Registry,
+};
+
+use crate::{cli::LogFormat, config::Config, telemetry};
+
+/// `[logging]` section of config.toml.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LoggingConfig {
+    /// Also write the log to `~/.claude-monitor/logs`, a file a day.
+    pub file: bool,
+    /// Daily files kept, counting today's.
+    pub keep_days: usize,
+    /// Megabytes the files may take together before the oldest are
+    /// deleted, checked hourly; 0 for no cap.
+    pub max_total_mb: u64,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self { file: false, keep_days: 7, max_total_mb: 100 }
+    }
+}
+
+/// Start of every log file's name; the date and `.log` follow.
+const PREFIX: &str = "claude-monitor";
+
+/// Install the global subscriber, exporting spans too when `[telemetry]`
+/// names a collector. With file logging on, the returned guard flushes the
+/// file when dropped, so keep it for the life of the server.
+pub fn init(format: LogFormat, config: &Config, data_dir: &Path) -> Result<Option<WorkerGuard>> {
+    let mut layers = vec![layer(format, std::io::stderr, true)];
+    let mut guard = None;
+    let logging = &config.logging;
+    if logging.file {
+        let dir = data_dir.join("logs");
+        let appender = RollingFileAppender::builder()
+            .rotation(Rotation::DAILY)
+            .filename_prefix(PREFIX)
+            .filename_suffix("log")
+            .max_log_files(logging.keep_days.max(1))
+            .build(&dir)
+            .with_context(|| format!("failed to open a log file in {}", dir.display()))?;
+        let (writer, flush) = tracing_appender::non_blocking(appender);
+        layers.push(layer(format, writer, false));
+        guard = Some(flush);
+        if logging.max_total_mb > 0 {
+            spawn_pruner(dir, logging.max_total_mb * 1024 * 1024);
+        }
+    }
+    layers.extend(telemetry::layer(&config.telemetry)?);
+    tracing_subscriber::registry().with(layers).init();
+    Ok(guard)
+}
+
+fn layer<W>(format: LogFormat, writer: W, ansi: bool) -> Box<dyn Layer<Registry> + Send + Sync>
+where
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    // Each layer filters for itself, so spans can be exported that are
+    // not logged.
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| "claude_monitor=info,tower_http=info".into());
+    let layer = fmt::layer().with_writer(writer).with_ansi(ansi);
+    match format {
+        LogFormat::Text => layer.with_filter(filter).boxed(),
+        LogFormat::Json => layer.json().with_current_span(true).with_span_list(true).with_filter(filter).boxed(),
+    }
+}
+
+fn spawn_pruner(dir: PathBuf, max_bytes: u64) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(3600));
+        loop {
+            interval.tick().await;
+            if let Err(e) = prune(&dir, max_bytes) {
+                warn!("failed to prune log files: {e:#}");
+            }
+        }
+    });
+}
+
+/// Delete the oldest log files in `dir` until they total at most
+/// `max_bytes`. Dated names sort by age; the newest, still being written,
+/// is always kept.
+fn prune(dir: &Path, max_bytes: u64) -> Result<()> {
+    let mut files: Vec<(PathBuf, u64)> = std::fs::read_dir(dir)?
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let name = entry.file_name();
+            let name = name.to_str()?;
+            (name.starts_with(PREFIX) && name.ends_with(".log")).then_some((entry.path(), entry.metadata().ok()?.len()))
+        })
+        .collect();
+    files.sort();
+    let mut total: u64 = files.iter().map(|(_, size)| size).sum();
+    for (path, size) in &files[..files.len().saturating_sub(1)] {
+        if total <= max_bytes {
+            break;
+        }
+        std::fs::remove_file(path).with_context(|| format!("failed to remove {}", path.display()))?;
+        total -= size;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::prune;
+
+    #[test]
+    fn oldest_files_go_first_and_the_newest_stays() {
+        let dir = std::env::temp_dir().join(format!("claude-monitor-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for day in ["2026-01-01", "2026-01-02", "2026-01-03"] {
+            std::fs::write(dir.join(format!("claude-monitor.{day}.log")), [0; 100]).unwrap();
+        }
+        std::fs::write(dir.join("notes.txt"), [0; 100]).unwrap();
+
+        prune(&dir, 250).unwrap();
+        assert!(!dir.join("claude-monitor.2026-01-01.log").exists());
+        assert!(dir.join("claude-monitor.2026-01-02.log").exists());
+        prune(&dir, 0).unwrap();
+        assert!(!dir.join("claude-monitor.2026-01-02.log").exists());
+        assert!(dir.join("claude-monitor.2026-01-03.log").exists());
+        assert!(dir.join("notes.txt").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
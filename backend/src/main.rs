@@ -1,29 +1,98 @@
+mod aggregator;
+mod announce;
 mod api;
+mod auth;
+mod backup;
+mod build_info;
+mod cleanup;
+mod cli;
+mod config;
+mod datadir;
+mod dead_letters;
 mod db;
+mod editor;
+mod export;
+mod graphql;
+mod grpc;
+mod hooks;
+mod import;
+mod ingest;
+mod keys;
+mod listen;
+mod logging;
+mod maintenance;
+mod mcp;
+mod metrics;
 mod models;
+mod notifier;
+mod openapi;
+mod paths;
+mod pricing;
+mod projects;
+mod proxy;
+mod quota;
+mod relay;
+mod request_id;
+mod rules;
+mod search;
+mod service;
+mod settings;
+mod shutdown;
+mod stats;
+mod status;
+mod storage;
+mod store;
+mod telemetry;
+mod templates;
+#[cfg(test)]
+mod test_support;
+mod transcript;
+mod trash;
+mod update;
+mod watch;
+mod web;
 mod ws;
 
 use anyhow::{Context, Result};
+use async_graphql_axum::{GraphQL, GraphQLSubscription};
+use clap::Parser;
 use axum::{
-    routing::{delete, get, post},
+    extract::DefaultBodyLimit,
+    http::{header, HeaderName},
+    middleware,
+    routing::{delete, get, post, put},
     Router,
 };
-use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
-use std::{str::FromStr, time::Duration};
-use tokio::sync::broadcast;
-use tower_http::cors::{Any, CorsLayer};
-use tracing::info;
+use std::sync::{atomic::Ordering, Arc};
+use tokio::sync::{broadcast, oneshot};
+use tower_http::{
+    compression::CompressionLayer,
+    cors::{Any, CorsLayer},
+    trace::TraceLayer,
+};
+use tracing::{info, warn};
 
-use api::AppState;
+use api::{AppState, SessionSnapshot};
+use cli::{Cli, Command};
+use config::Config;
+use ingest::EventWriter;
+use shutdown::Shutdown;
+use templates::Catalog;
+use transcript::TranscriptTailer;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "claude_monitor=info,tower_http=info".into()),
-        )
-        .init();
+    let cli = Cli::parse();
+    match &cli.command {
+        Some(Command::InstallHooks(args)) => return hooks::install(args),
+        Some(Command::Hook(args)) => return hooks::run_client(args).await,
+        Some(Command::Status(args)) => return status::run(args).await,
+        Some(Command::Watch(args)) => return watch::run(args).await,
+        Some(Command::Mcp(args)) => return mcp::run(args).await,
+        Some(Command::Service(command)) => return service::run_command(command),
+        Some(Command::SelfUpdate(args)) => return update::run(args).await,
+        None => {}
+    }
 
     // Resolve DB directory.
     let home = dirs::home_dir().context("could not determine home directory")?;
@@ -31,60 +100,187 @@ async fn main() -> Result<()> {
     std::fs::create_dir_all(&db_dir)
         .with_context(|| format!("failed to create {}", db_dir.display()))?;
 
-    let db_path = db_dir.join("sessions.db");
-    let db_url = format!("sqlite:{}", db_path.display());
+    let config_path = db_dir.join("config.toml");
+    let config = Config::load(&config_path)?;
+    let _log_file = logging::init(cli.log_format, &config, &db_dir)?;
+    info!("Claude Monitor {}", build_info::Summary(build_info::get()));
+    datadir::check_compatible(&db_dir, cli.force_downgrade)?;
+
+    let templates = Catalog::load(&config.templates, &db_dir.join("templates"))?;
+    info!("Using '{}' templates", templates.locale);
+
+    let store = store::open(cli.db.as_deref(), &db_dir, config.maintenance.wal_autocheckpoint).await?;
+    info!("Using database at {}", store.describe());
+    datadir::write_meta(&db_dir)?;
+
+    let (tx, _rx) = broadcast::channel::<SessionSnapshot>(100);
 
-    info!("Using database at {}", db_path.display());
+    let (transcripts, transcript_rx) = if config.transcripts.enabled {
+        let (tailer, rx) = TranscriptTailer::channel();
+        (Some(tailer), Some(rx))
+    } else {
+        (None, None)
+    };
+    let relay = config.relay.clone().map(relay::Relay::spawn);
+    let (events, event_rx) = EventWriter::channel();
+    let state = AppState::new(
+        store.clone(),
+        tx.clone(),
+        Arc::new(config),
+        Arc::new(templates),
+        transcripts,
+        relay,
+        events,
+    );
+    let shutdown = Shutdown::on_signal();
+    // Dropped once the listeners have stopped, for the writer to store the rest.
+    let (stop_writer, writer_stopping) = oneshot::channel::<()>();
+    let writer = tokio::spawn(ingest::run(state.clone(), event_rx, async {
+        let _ = writer_stopping.await;
+    }));
 
-    let connect_opts = SqliteConnectOptions::from_str(&db_url)?
-        .create_if_missing(true)
-        .journal_mode(sqlx::sqlite::SqliteJournalMode::Wal)
-        .foreign_keys(true);
+    if let Some(rx) = transcript_rx {
+        let state = state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = transcript::run(state, rx).await {
+                tracing::warn!("transcript tailer stopped: {e}");
+            }
+        });
+    }
+
+    api::spawn_broadcaster(&state);
+    notifier::spawn(&state);
+    aggregator::spawn(&state);
+    maintenance::spawn(&state);
+    backup::spawn(&state);
+    storage::spawn(&state);
+    cleanup::spawn(&state);
 
-    let pool: SqlitePool = SqlitePoolOptions::new()
-        .max_connections(5)
-        .connect_with(connect_opts)
-        .await
-        .context("failed to open SQLite database")?;
+    let app = router(state.clone());
+    let listening = state.listening.clone();
 
-    db::init_db(&pool).await.context("failed to run schema migrations")?;
+    let mut listen = state.config.listen.clone();
+    listen.port = cli.port.unwrap_or(listen.port);
+    let listeners = listen::bind(&listen, &db_dir).await?;
+    listening.store(true, Ordering::Relaxed);
 
-    let (tx, _rx) = broadcast::channel::<String>(100);
-    let state = AppState::new(pool.clone(), tx.clone());
+    for listener in &listeners {
+        info!("Claude Monitor listening on {}", listener.url());
+    }
+    let servers = listeners.into_iter().map(|listener| listener.serve(app.clone(), shutdown.clone()));
+    let grace = async {
+        shutdown.clone().requested().await;
+        tokio::time::sleep(shutdown::GRACE).await;
+    };
+    tokio::select! {
+        served = futures::future::try_join_all(servers) => {
+            served.context("server error")?;
+        }
+        () = grace => warn!("Connections still open after {:?}, closing them", shutdown::GRACE),
+    }
+
+    drop(stop_writer);
+    let queued = state.events.stats().pending;
+    info!("Writing {queued} queued events");
+    writer.await.context("event writer failed")?;
+    Ok(())
+}
 
+/// Every HTTP, WebSocket and gRPC route, with the layers they share.
+fn router(state: AppState) -> Router {
     let cors = CorsLayer::new()
         .allow_origin(Any)
         .allow_methods(Any)
-        .allow_headers(Any);
+        .allow_headers(Any)
+        .expose_headers([
+            header::ETAG,
+            HeaderName::from_static(api::TOTAL_COUNT_HEADER),
+            HeaderName::from_static(api::NEXT_CURSOR_HEADER),
+            request_id::HEADER,
+        ]);
 
-    let app = Router::new()
+    let schema = graphql::schema(state.clone());
+    let base_path = proxy::base_path(&state.config.listen.base_path);
+    // Whatever clears, replaces or dumps everything sits with the admin routes.
+    let admin = Router::new()
+        .route("/api/sessions", delete(api::clear_all_sessions))
+        .route("/api/settings", put(settings::update_settings))
+        .route("/api/export", get(export::export))
+        .route("/api/import", post(import::import).layer(DefaultBodyLimit::max(import::MAX_BYTES)))
+        .route("/api/admin/maintenance", post(maintenance::run_maintenance))
+        .route("/api/admin/cleanup", post(cleanup::run_cleanup))
+        .route("/api/admin/backup", post(backup::create_backup))
+        .route("/api/admin/keys", get(keys::list_keys).post(keys::create_key))
+        .route("/api/admin/keys/:name", delete(keys::revoke_key))
+        .route_layer(middleware::from_fn_with_state(state.clone(), keys::admin_only));
+    let router = Router::new()
         .route("/health", get(api::health))
+        .route("/livez", get(api::livez))
+        .route("/readyz", get(api::readyz))
+        .route("/metrics", get(metrics::get_metrics))
+        .route("/api/build-info", get(build_info::get_build_info))
         .route("/api/events", post(api::post_event))
-        .route("/api/sessions", get(api::get_sessions).delete(api::clear_all_sessions))
-        .route("/api/sessions/:session_id", delete(api::delete_session))
+        .route("/api/hooks", post(hooks::post_hook))
+        .route("/api/dead-letters", get(dead_letters::get_dead_letters))
+        .route("/api/sessions", get(api::get_sessions))
+        .route("/api/sessions/:session_id", delete(api::delete_session).patch(api::update_session))
+        .route("/api/sessions/:session_id/ack", post(api::ack_session))
+        .route("/api/sessions/:session_id/restore", post(trash::restore_session))
+        .route("/api/trash", get(trash::get_trash))
+        .route("/api/sessions/:session_id/pin", post(api::pin_session))
+        .route("/api/sessions/:session_id/unpin", post(api::unpin_session))
+        .route("/api/sessions/:session_id/mute", post(api::mute_session))
+        .route("/api/sessions/:session_id/unmute", post(api::unmute_session))
+        .route("/api/sessions/:session_id/tags", post(api::add_tags).delete(api::remove_tags))
+        .route("/api/sessions/:session_id/tools", get(api::get_session_tools))
+        .route("/api/sessions/:session_id/usage", get(api::get_session_usage))
+        .route("/api/sessions/:session_id/timeline", get(api::get_session_timeline))
+        .route("/api/sessions/:session_id/events", get(api::get_session_events))
+        .route("/api/search", get(search::search))
+        .route("/api/stats", get(stats::get_summary))
+        .route("/api/stats/costs", get(stats::get_costs))
+        .route("/api/stats/projects", get(stats::get_projects))
+        .route("/api/stats/daily", get(stats::get_daily))
+        .route("/api/stats/durations", get(stats::get_durations))
+        .route("/api/stats/concurrency", get(stats::get_concurrency))
+        .route("/api/stats/heatmap", get(stats::get_heatmap))
+        .route("/api/stats/burn-rate", get(stats::get_burn_rate))
+        .route("/api/stats/ingest", get(ingest::get_stats))
+        .route("/api/stats/ws", get(ws::get_stats))
+        .route("/api/projects", get(projects::get_projects))
+        .route(
+            "/api/projects/aliases",
+            get(projects::list_aliases).put(projects::set_alias).delete(projects::delete_alias),
+        )
+        .route("/api/quota", get(quota::get_quota))
+        .route("/api/quota/weekly", get(quota::get_weekly_quota))
+        .route("/api/rules", get(rules::list_rules).post(rules::create_rule))
+        .route(
+            "/api/rules/:rule_id",
+            get(rules::get_rule).put(rules::update_rule).delete(rules::delete_rule),
+        )
+        .route("/api/settings", get(settings::get_settings))
+        .route("/api/notifications", get(notifier::history::list_notifications))
+        .route("/api/stream/text", get(announce::sse_handler))
+        .route("/api/editor/sessions", get(editor::get_sessions))
+        .route("/api/editor/sessions/:session_id/ack", post(api::ack_session))
+        .route("/api/export/events.csv", get(export::events_csv))
+        .route("/api/export/sessions.csv", get(export::sessions_csv))
+        .merge(admin)
+        .route("/api/graphql", get(graphql::graphiql).post_service(GraphQL::new(schema.clone())))
+        .route_service("/api/graphql/ws", GraphQLSubscription::new(schema))
         .route("/ws", get(ws::ws_handler))
+        .route("/ws/sessions/:session_id", get(ws::session_ws_handler))
+        .route_service(grpc::ROUTE, grpc::service(state.clone()))
+        .merge(openapi::swagger_ui())
+        .fallback(web::serve)
         .layer(cors)
-        .with_state(state.clone());
-
-    // Cleanup background task.
-    tokio::spawn(async move {
-        let mut interval = tokio::time::interval(Duration::from_secs(30));
-        loop {
-            interval.tick().await;
-            match db::cleanup_old_completed(&pool).await {
-                Ok(()) => state.broadcast_sessions().await,
-                Err(e) => tracing::warn!("cleanup error: {e}"),
-            }
-        }
-    });
-
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:9147")
-        .await
-        .context("failed to bind to port 9147")?;
-
-    info!("Claude Monitor listening on http://0.0.0.0:9147");
-
-    axum::serve(listener, app).await.context("server error")?;
-
-    Ok(())
+        // Failures are logged by the handlers, with more to say.
+        .layer(TraceLayer::new_for_http().make_span_with(telemetry::request_span).on_failure(()))
+        // Outside the trace layer, so the request's span has the id.
+        .layer(middleware::from_fn(request_id::middleware))
+        // Skips SSE, gRPC and small bodies; WebSocket frames are untouched.
+        .layer(CompressionLayer::new())
+        .with_state(state);
+    proxy::nest(router, &base_path)
 }
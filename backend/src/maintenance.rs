@@ -0,0 +1,139 @@
+//! Database upkeep. The events table churns as sessions come and go, leaving
+//! free pages behind; a periodic pass refreshes planner statistics and gives
+//! that space back, and `POST /api/admin/maintenance` runs one on demand.
+//! Separately, SQLite's write-ahead log is truncated every few minutes, as
+//! one that a reader kept busy can otherwise grow without bound.
+//!
+//! ```toml
+//! [maintenance]
+//! interval_hours = 24      # 0 turns the schedule off
+//! checkpoint_minutes = 5   # likewise
+//! wal_autocheckpoint = 1000
+//! ```
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use serde::Deserialize;
+use serde_json::json;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::{
+    api::AppState,
+    models::{ErrorResponse, MaintenanceReport},
+    store::Store,
+};
+
+/// `[maintenance]` section of config.toml.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MaintenanceConfig {
+    /// Hours between scheduled passes; 0 disables them.
+    pub interval_hours: u64,
+    /// Minutes between TRUNCATE checkpoints of the WAL; 0 disables them.
+    pub checkpoint_minutes: u64,
+    /// WAL pages after which SQLite checkpoints on commit, SQLite's own
+    /// default; 0 turns that off and leaves it to the scheduled checkpoints.
+    pub wal_autocheckpoint: u32,
+}
+
+impl Default for MaintenanceConfig {
+    fn default() -> Self {
+        Self { interval_hours: 24, checkpoint_minutes: 5, wal_autocheckpoint: 1000 }
+    }
+}
+
+/// Held for the length of a pass, so scheduled and requested ones don't overlap.
+static RUNNING: Mutex<()> = Mutex::const_new(());
+
+/// Run a pass every `interval_hours` and a checkpoint every
+/// `checkpoint_minutes`, each starting one interval after startup.
+pub fn spawn(state: &AppState) {
+    let config = &state.config.maintenance;
+    if config.interval_hours > 0 {
+        let store = state.store.clone();
+        let mut interval = every(Duration::from_secs(config.interval_hours * 3600));
+        tokio::spawn(async move {
+            loop {
+                interval.tick().await;
+                match run(store.as_ref()).await {
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) => warn!("maintenance error: {e}"),
+                    None => info!("Skipping scheduled maintenance; a pass is already running"),
+                }
+            }
+        });
+    }
+    if config.checkpoint_minutes > 0 {
+        let store = state.store.clone();
+        let mut interval = every(Duration::from_secs(config.checkpoint_minutes * 60));
+        tokio::spawn(async move {
+            loop {
+                interval.tick().await;
+                if let Err(e) = store.checkpoint().await {
+                    warn!("checkpoint error: {e}");
+                }
+            }
+        });
+    }
+}
+
+/// An interval whose first tick is one `period` from now.
+fn every(period: Duration) -> tokio::time::Interval {
+    tokio::time::interval_at(tokio::time::Instant::now() + period, period)
+}
+
+/// One pass, or `None` when another is in progress.
+async fn run(store: &dyn Store) -> Option<anyhow::Result<MaintenanceReport>> {
+    let _running = RUNNING.try_lock().ok()?;
+    let result = store.maintain().await;
+    if let Ok(report) = &result {
+        info!(
+            "Maintenance took {:.0} ms; database {} -> {} bytes{}",
+            report.duration_ms,
+            report.size_before,
+            report.size_after,
+            if report.full_vacuum { " (full vacuum)" } else { "" },
+        );
+    }
+    Some(result)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/admin/maintenance",
+    tag = "admin",
+    responses(
+        (status = 200, body = MaintenanceReport),
+        (status = 401, description = "Neither the listener's token nor an admin key", body = ErrorResponse),
+        (status = 409, description = "A pass is already running", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn run_maintenance(State(state): State<AppState>) -> impl IntoResponse {
+    match run(state.store.as_ref()).await {
+        Some(Ok(report)) => Json(report).into_response(),
+        Some(Err(e)) => {
+            warn!("run_maintenance error: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response()
+        }
+        None => (StatusCode::CONFLICT, Json(json!({"error": "maintenance is already running"}))).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::{self, event};
+
+    #[tokio::test]
+    async fn maintenance_runs_on_demand() {
+        let server = test_support::spawn().await;
+        server.client.post_event(&event("user_prompt_submit", "s1")).await.unwrap();
+        server.flushed().await;
+
+        let report = server.client.run_maintenance().await.unwrap();
+        // New databases start out in incremental mode.
+        assert!(!report.full_vacuum);
+        assert!(report.size_after > 0);
+    }
+}
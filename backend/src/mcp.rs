@@ -0,0 +1,194 @@
+//! `claude-monitor mcp`: a Model Context Protocol server on stdio, so Claude
+//! sessions, orchestration agents in particular, can ask which other
+//! sessions are running and what they are doing. Register it with
+//! `claude mcp add claude-monitor -- claude-monitor mcp`.
+//!
+//! Tools answer from a running server through its HTTP API; stdout carries
+//! nothing but JSON-RPC messages, one per line.
+
+use anyhow::{Context, Result};
+use claude_monitor_client::{Client, SessionQuery};
+use serde_json::{json, Value};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+use crate::{api, cli::McpArgs, listen::Target, models::SessionWithAgents};
+
+/// Protocol revisions understood, newest first. A client asking for one of
+/// these gets it back; any other gets the newest.
+const PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+struct Monitor {
+    client: Client,
+}
+
+pub async fn run(args: &McpArgs) -> Result<()> {
+    let target = Target::resolve(args.server.as_deref());
+    let monitor = Monitor { client: Client::with_http(&target.url, target.http(Duration::from_secs(5)))? };
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    let mut stdout = tokio::io::stdout();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let reply = match serde_json::from_str::<Value>(&line) {
+            Ok(message) => monitor.handle(message).await,
+            Err(e) => Some(error(Value::Null, PARSE_ERROR, &e.to_string())),
+        };
+        if let Some(reply) = reply {
+            stdout.write_all(format!("{reply}\n").as_bytes()).await?;
+            stdout.flush().await?;
+        }
+    }
+    Ok(())
+}
+
+fn error(id: Value, code: i64, message: &str) -> Value {
+    json!({"jsonrpc": "2.0", "id": id, "error": {"code": code, "message": message}})
+}
+
+fn tools() -> Value {
+    json!([
+        {
+            "name": "list_sessions",
+            "description": "List the Claude sessions the monitor currently tracks, with each one's project, \
+                            status, running tool, last message, and agents.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "host": {"type": "string", "description": "Only sessions on this machine."},
+                    "tag": {"type": "string", "description": "Comma-separated tags; sessions with any of them."}
+                }
+            }
+        },
+        {
+            "name": "get_session",
+            "description": "Everything the monitor knows about one session, including token usage and cost.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {"session_id": {"type": "string"}},
+                "required": ["session_id"]
+            }
+        },
+        {
+            "name": "get_session_events",
+            "description": "A session's most recent hook events, newest first: tool calls, notifications, \
+                            stops, and subagent starts and stops.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "session_id": {"type": "string"},
+                    "limit": {"type": "integer", "minimum": 1, "maximum": 500, "default": 20},
+                    "agent": {"type": "string", "description": "Only events from this agent, e.g. \"main\"."}
+                },
+                "required": ["session_id"]
+            }
+        }
+    ])
+}
+
+/// The fields of a session worth an agent's context window.
+fn summary(s: &SessionWithAgents) -> Value {
+    json!({
+        "session_id": s.session_id,
+        "host": s.host,
+        "project_name": s.project_name,
+        "project_path": s.project_path,
+        "git_branch": s.git_branch,
+        "status": s.status,
+        "status_text": s.status_text,
+        "current_tool": s.current_tool,
+        "last_message": s.last_message,
+        "tags": s.tags,
+        "agents": s.agents.iter().map(|a| json!({"name": a.agent_name, "status": a.status})).collect::<Vec<_>>(),
+        "started_at": s.created_at,
+        "updated_at": s.updated_at,
+    })
+}
+
+fn string_arg<'a>(arguments: &'a Value, name: &str) -> Option<&'a str> {
+    arguments.get(name).and_then(Value::as_str).filter(|s| !s.is_empty())
+}
+
+impl Monitor {
+    /// Reply to one message; notifications and responses get none.
+    async fn handle(&self, message: Value) -> Option<Value> {
+        let id = message.get("id").cloned()?;
+        let method = message.get("method").and_then(Value::as_str)?;
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        let result = match method {
+            "initialize" => {
+                let requested = params.get("protocolVersion").and_then(Value::as_str);
+                let version = requested.filter(|v| PROTOCOL_VERSIONS.contains(v)).unwrap_or(PROTOCOL_VERSIONS[0]);
+                json!({
+                    "protocolVersion": version,
+                    "capabilities": {"tools": {}},
+                    "serverInfo": {"name": "claude-monitor", "version": env!("CARGO_PKG_VERSION")},
+                    "instructions": "Tools report on every Claude session the monitor tracks, \
+                                     including ones running in other terminals or on other machines."
+                })
+            }
+            "ping" => json!({}),
+            "tools/list" => json!({"tools": tools()}),
+            "tools/call" => {
+                let name = params.get("name").and_then(Value::as_str).unwrap_or_default();
+                let arguments = params.get("arguments").cloned().unwrap_or_else(|| json!({}));
+                match self.call(name, &arguments).await {
+                    Some(Ok(output)) => {
+                        let text = serde_json::to_string_pretty(&output).unwrap_or_default();
+                        json!({"content": [{"type": "text", "text": text}], "isError": false})
+                    }
+                    // Failures go back to the model as tool output it can act on.
+                    Some(Err(e)) => json!({"content": [{"type": "text", "text": format!("{e:#}")}], "isError": true}),
+                    None => return Some(error(id, INVALID_PARAMS, &format!("unknown tool: {name}"))),
+                }
+            }
+            _ => return Some(error(id, METHOD_NOT_FOUND, &format!("unknown method: {method}"))),
+        };
+        Some(json!({"jsonrpc": "2.0", "id": id, "result": result}))
+    }
+
+    /// Run a tool; `None` if there is no such tool.
+    async fn call(&self, name: &str, arguments: &Value) -> Option<Result<Value>> {
+        Some(match name {
+            "list_sessions" => self.list_sessions(arguments).await,
+            "get_session" => self.get_session(arguments).await,
+            "get_session_events" => self.get_session_events(arguments).await,
+            _ => return None,
+        })
+    }
+
+    async fn list_sessions(&self, arguments: &Value) -> Result<Value> {
+        let query = SessionQuery {
+            host: string_arg(arguments, "host").map(str::to_string),
+            tags: string_arg(arguments, "tag").map(api::parse_tags).unwrap_or_default(),
+            ..Default::default()
+        };
+        let sessions = self.client.sessions(&query).await?;
+        Ok(Value::Array(sessions.iter().map(summary).collect()))
+    }
+
+    async fn get_session(&self, arguments: &Value) -> Result<Value> {
+        let session_id = string_arg(arguments, "session_id").context("`session_id` is required")?;
+        let session = self
+            .client
+            .sessions(&SessionQuery::default())
+            .await?
+            .into_iter()
+            .find(|s| s.session_id == session_id)
+            .with_context(|| format!("no active session {session_id}"))?;
+        Ok(serde_json::to_value(session)?)
+    }
+
+    async fn get_session_events(&self, arguments: &Value) -> Result<Value> {
+        let session_id = string_arg(arguments, "session_id").context("`session_id` is required")?;
+        let limit = arguments.get("limit").and_then(Value::as_u64).unwrap_or(20);
+        let limit = u32::try_from(limit).unwrap_or(u32::MAX);
+        let events = self.client.session_events(session_id, limit, string_arg(arguments, "agent")).await?;
+        Ok(serde_json::to_value(events)?)
+    }
+}
@@ -0,0 +1,175 @@
+//! `GET /metrics`: the server's counters and latency histograms in the
+//! Prometheus text format, for scraping. The same figures are in the JSON
+//! `/api/stats/*` endpoints.
+
+use axum::{extract::State, http::header, response::IntoResponse};
+use std::{
+    fmt::Write,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use crate::{api::AppState, models::LatencySummary};
+
+/// Upper bounds of the histogram buckets, in seconds; a last one catches
+/// the rest.
+const BOUNDS: [f64; 14] = [0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// Latencies counted into `BOUNDS`, lock-free.
+#[derive(Debug, Default)]
+pub struct Histogram {
+    buckets: [AtomicU64; BOUNDS.len() + 1],
+    sum_micros: AtomicU64,
+    max_micros: AtomicU64,
+}
+
+impl Histogram {
+    pub fn observe(&self, elapsed: Duration) {
+        let bucket = BOUNDS.iter().position(|&bound| elapsed.as_secs_f64() <= bound).unwrap_or(BOUNDS.len());
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        let micros = elapsed.as_micros() as u64;
+        self.sum_micros.fetch_add(micros, Ordering::Relaxed);
+        self.max_micros.fetch_max(micros, Ordering::Relaxed);
+    }
+
+    fn counts(&self) -> [u64; BOUNDS.len() + 1] {
+        std::array::from_fn(|i| self.buckets[i].load(Ordering::Relaxed))
+    }
+
+    pub fn summary(&self) -> LatencySummary {
+        let counts = self.counts();
+        let count: u64 = counts.iter().sum();
+        if count == 0 {
+            return LatencySummary::default();
+        }
+        let max_ms = self.max_micros.load(Ordering::Relaxed) as f64 / 1000.0;
+        let percentile = |p: f64| {
+            let rank = (count as f64 * p).ceil() as u64;
+            let mut seen = 0;
+            for (bucket, n) in counts.iter().enumerate() {
+                seen += n;
+                if seen >= rank {
+                    return BOUNDS.get(bucket).map_or(max_ms, |bound| (bound * 1000.0).min(max_ms));
+                }
+            }
+            max_ms
+        };
+        LatencySummary {
+            count,
+            mean_ms: self.sum_micros.load(Ordering::Relaxed) as f64 / 1000.0 / count as f64,
+            p50_ms: percentile(0.5),
+            p95_ms: percentile(0.95),
+            p99_ms: percentile(0.99),
+            max_ms,
+        }
+    }
+}
+
+/// Builds the exposition, one metric family at a time.
+#[derive(Default)]
+struct Exposition(String);
+
+impl Exposition {
+    fn header(&mut self, name: &str, kind: &str, help: &str) {
+        let _ = writeln!(self.0, "# HELP claude_monitor_{name} {help}\n# TYPE claude_monitor_{name} {kind}");
+    }
+
+    fn counter(&mut self, name: &str, help: &str, value: u64) {
+        self.header(name, "counter", help);
+        let _ = writeln!(self.0, "claude_monitor_{name} {value}");
+    }
+
+    fn gauge(&mut self, name: &str, help: &str, value: u64) {
+        self.header(name, "gauge", help);
+        let _ = writeln!(self.0, "claude_monitor_{name} {value}");
+    }
+
+    fn histogram(&mut self, name: &str, help: &str, histogram: &Histogram) {
+        self.header(name, "histogram", help);
+        let mut cumulative = 0;
+        for (bucket, n) in histogram.counts().iter().enumerate() {
+            cumulative += n;
+            let le = BOUNDS.get(bucket).map_or("+Inf".to_string(), f64::to_string);
+            let _ = writeln!(self.0, "claude_monitor_{name}_bucket{{le=\"{le}\"}} {cumulative}");
+        }
+        let sum = histogram.sum_micros.load(Ordering::Relaxed) as f64 / 1e6;
+        let _ = writeln!(self.0, "claude_monitor_{name}_sum {sum}\nclaude_monitor_{name}_count {cumulative}");
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "system",
+    responses((status = 200, description = "Prometheus text format", body = String, content_type = "text/plain"))
+)]
+pub async fn get_metrics(State(state): State<AppState>) -> impl IntoResponse {
+    let events = &state.events;
+    let ingest = events.stats();
+    let ws = &state.ws;
+    let mut out = Exposition::default();
+    out.gauge("ingest_pending", "Events waiting to be written.", ingest.pending);
+    out.counter("ingest_queued_total", "Events queued for writing.", ingest.queued);
+    out.counter("ingest_written_total", "Events written.", ingest.written);
+    out.counter("ingest_failed_total", "Events lost to a failed write.", ingest.failed);
+    out.histogram(
+        "ingest_write_seconds",
+        "From an event's handler starting to its row being written.",
+        events.write_latency(),
+    );
+    out.histogram(
+        "ingest_broadcast_seconds",
+        "From an event's handler starting to the broadcast that carries it.",
+        events.broadcast_latency(),
+    );
+    out.gauge("ws_clients", "WebSocket clients connected now.", ws.count() as u64);
+    out.counter("ws_connections_total", "WebSocket connections accepted.", ws.connections());
+    out.counter("ws_lag_events_total", "Times a WebSocket client fell behind the broadcasts.", ws.lag_events());
+    out.counter("ws_dropped_messages_total", "Broadcasts dropped for lagging WebSocket clients.", ws.dropped());
+    out.counter("broadcasts_total", "Session broadcasts published.", state.history.published());
+    out.histogram("ws_fanout_seconds", "From a broadcast being published to a client being sent it.", ws.fanout());
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], out.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use claude_monitor_client::SessionFilter;
+    use futures::StreamExt;
+    use std::time::Duration;
+
+    use super::Histogram;
+    use crate::test_support::{self, event};
+
+    #[tokio::test]
+    async fn ws_clients_and_fanout_are_exported() {
+        let histogram = Histogram::default();
+        for ms in [1, 2, 3, 40] {
+            histogram.observe(Duration::from_millis(ms));
+        }
+        let summary = histogram.summary();
+        assert_eq!(summary.count, 4);
+        assert_eq!(summary.p50_ms, 2.5);
+        assert_eq!(summary.max_ms, 40.0);
+
+        let server = test_support::spawn().await;
+        let mut updates = server.client.subscribe(&SessionFilter::default()).await.unwrap();
+        updates.next().await.unwrap().unwrap();
+        server.client.post_event(&event("user_prompt_submit", "s1")).await.unwrap();
+        while updates.next().await.unwrap().unwrap().is_empty() {}
+        // The client can have the frame before the server has counted it.
+        let mut stats = server.client.ws_stats().await.unwrap();
+        for _ in 0..100 {
+            if stats.fanout.count > 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            stats = server.client.ws_stats().await.unwrap();
+        }
+
+        assert_eq!((stats.connected, stats.connections), (1, 1));
+        assert!(stats.broadcasts >= stats.fanout.count && stats.fanout.count > 0, "{stats:?}");
+        let metrics = server.client.metrics().await.unwrap();
+        assert!(metrics.contains("claude_monitor_ws_clients 1\n"), "{metrics}");
+        assert!(metrics.contains("claude_monitor_ws_fanout_seconds_bucket{le=\"+Inf\"}"), "{metrics}");
+    }
+}
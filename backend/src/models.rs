@@ -1,58 +1,93 @@
-use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
-use uuid::Uuid;
+//! API types come from the `claude-monitor-models` crate; the rows here are
+//! the server's own.
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Session {
-    pub id: Uuid,
+use chrono::{DateTime, NaiveDate, Utc};
+use std::time::Instant;
+
+pub use claude_monitor_models::*;
+
+/// Usage for one (session, model) pair, the unit cost estimation works on.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ModelUsageRow {
+    pub host: String,
     pub session_id: String,
-    pub project_path: String,
     pub project_name: String,
-    pub status: String,
-    pub created_at: DateTime<Utc>,
-    pub updated_at: DateTime<Utc>,
+    pub model: Option<String>,
+    #[sqlx(flatten)]
+    pub usage: TokenUsage,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Agent {
-    pub id: Uuid,
+/// One stored event, reduced to what status replay needs.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct EventRow {
+    pub host: String,
     pub session_id: String,
+    pub project_name: String,
     pub agent_name: String,
-    pub parent_session_id: Option<String>,
-    pub status: String,
-    pub created_at: DateTime<Utc>,
-    pub updated_at: DateTime<Utc>,
+    pub event_type: String,
+    pub needs_input: bool,
+    pub timestamp: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SessionWithAgents {
-    pub id: Uuid,
+/// An event waiting in the write-behind queue, see `ingest`.
+#[derive(Debug, Clone)]
+pub struct NewEvent {
+    pub id: String,
+    pub host: String,
     pub session_id: String,
-    pub project_name: String,
-    pub project_path: String,
-    pub status: String,
-    pub created_at: DateTime<Utc>,
-    pub updated_at: DateTime<Utc>,
-    pub agents: Vec<Agent>,
+    pub agent_name: Option<String>,
+    pub event_type: String,
+    pub payload: String,
+    pub timestamp: DateTime<Utc>,
+    pub request_id: Option<String>,
+    pub source: Option<String>,
+    /// When its handler started, for the latency histograms.
+    pub received: Instant,
 }
 
-/// Incoming event payload from Claude CLI hooks.
-#[derive(Debug, Deserialize)]
-pub struct HookEvent {
-    pub event_type: String,
+/// Usage recorded since some point in time, across every session.
+#[derive(Debug, Clone, Default, sqlx::FromRow)]
+pub struct WindowUsage {
+    #[sqlx(flatten)]
+    pub usage: TokenUsage,
+    pub messages: i64,
+    pub oldest: Option<DateTime<Utc>>,
+}
+
+
+/// Events one host recorded in one minute.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct EventCountRow {
+    pub host: String,
+    /// Whole minutes between the start of the window and the events.
+    pub minute: i64,
+    pub events: i64,
+}
+
+/// Events one host recorded in one UTC hour.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct HourlyEventCount {
+    pub host: String,
+    pub hour: DateTime<Utc>,
+    pub events: i64,
+}
+
+/// A completed session, as daily stats count its completion.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct CompletedSession {
+    pub host: String,
     pub session_id: String,
-    pub project_path: Option<String>,
-    pub project_name: Option<String>,
-    pub agent_name: Option<String>,
-    pub parent_session_id: Option<String>,
-    pub needs_input: Option<bool>,
-    pub tool_name: Option<String>,
-    pub transcript_path: Option<String>,
-    pub message: Option<String>,
+    pub ended_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Serialize)]
-pub struct HealthResponse {
-    pub status: &'static str,
-    pub version: &'static str,
+/// Activity on one host on one day.
+#[derive(Debug, Clone, Default)]
+pub struct DailyTotals {
+    /// Sessions with any event that day.
+    pub sessions: i64,
+    pub active_seconds: i64,
+    pub completions: i64,
 }
+
+/// `DailyTotals` keyed by (day, host).
+pub type DailyActivity = std::collections::BTreeMap<(NaiveDate, String), DailyTotals>;
@@ -0,0 +1,122 @@
+//! Native desktop notifications when a session starts waiting on you, so the
+//! overlay doesn't have to stay visible to notice Claude is blocked.
+
+use anyhow::Result;
+use futures::future::BoxFuture;
+use notify_rust::Notification;
+use serde::Deserialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::{debug, warn};
+
+use super::{history::Dropped, Notifier, Transition};
+use crate::{models::SessionWithAgents, templates::Catalog};
+
+/// `[notifications.desktop]` section of config.toml.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DesktopConfig {
+    pub enabled: bool,
+    /// Notify when Claude finishes and waits for your next prompt.
+    pub waiting_input: bool,
+    /// Notify when Claude asks to run a tool.
+    pub needs_permission: bool,
+}
+
+impl Default for DesktopConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            waiting_input: true,
+            needs_permission: true,
+        }
+    }
+}
+
+pub struct Desktop {
+    config: DesktopConfig,
+    /// Headless machines have no notification service; say so once, not per alert.
+    warned: AtomicBool,
+}
+
+impl Desktop {
+    pub fn new(config: DesktopConfig) -> Self {
+        Self {
+            config,
+            warned: AtomicBool::new(false),
+        }
+    }
+}
+
+impl Notifier for Desktop {
+    fn name(&self) -> &str {
+        "desktop"
+    }
+
+    fn wants(&self, transition: &Transition) -> bool {
+        match transition.to.as_str() {
+            "waiting_input" => self.config.waiting_input,
+            "needs_permission" => self.config.needs_permission,
+            _ => false,
+        }
+    }
+
+    fn notify<'a>(&'a self, transition: &'a Transition, catalog: &'a Catalog) -> BoxFuture<'a, Result<()>> {
+        Box::pin(self.show(alert_for(&transition.session, catalog)))
+    }
+
+    fn summarize<'a>(&'a self, title: &'a str, lines: &'a [String]) -> BoxFuture<'a, Result<()>> {
+        let alert = Alert {
+            title: title.to_string(),
+            body: lines.join("\n"),
+        };
+        Box::pin(self.show(alert))
+    }
+}
+
+impl Desktop {
+    async fn show(&self, alert: Alert) -> Result<()> {
+        // Showing a notification blocks on the platform's notification service.
+        let shown = tokio::task::spawn_blocking(move || {
+            Notification::new()
+                .summary(&alert.title)
+                .body(&alert.body)
+                .appname("Claude Monitor")
+                .show()
+                .map(|_| alert.title)
+        })
+        .await?;
+
+        match shown {
+            Ok(title) => {
+                debug!(title = %title, "Sent desktop notification");
+                Ok(())
+            }
+            Err(e) => {
+                if !self.warned.swap(true, Ordering::Relaxed) {
+                    warn!("Desktop notifications unavailable: {e}");
+                }
+                Err(Dropped("no notification service").into())
+            }
+        }
+    }
+}
+
+/// A notification to show, already phrased.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alert {
+    pub title: String,
+    pub body: String,
+}
+
+fn alert_for(session: &SessionWithAgents, catalog: &Catalog) -> Alert {
+    let project = session.project_name.as_str();
+    let title = catalog.render(&format!("notify.{}.title", session.status), &[("project", project)]);
+
+    let body = match (session.status.as_str(), session.current_tool.as_deref(), session.last_message.as_deref()) {
+        (_, _, Some(message)) if !message.is_empty() => message.to_string(),
+        ("needs_permission", Some(tool), _) => catalog.render("notify.needs_permission.body_tool", &[("tool", tool)]),
+        (status, _, _) => catalog.render(&format!("notify.{status}.body"), &[("project", project)]),
+    };
+
+    Alert { title, body }
+}
@@ -0,0 +1,126 @@
+//! Discord webhook notifications, posted as embeds with the project, agent
+//! tree, status, and how long the session has been running.
+//!
+//! ```toml
+//! [notifications.discord]
+//! webhook_url = "https://discord.com/api/webhooks/000/XXXX"
+//! needs_permission = true
+//! waiting_input = true
+//! max_per_minute = 10
+//! ```
+
+use anyhow::Result;
+use chrono::Utc;
+use futures::future::BoxFuture;
+use serde::Deserialize;
+use serde_json::json;
+
+use super::{chat_message, format_minutes, history::Dropped, post_json, Notifier, RateLimiter, StatusFlags, Transition};
+use crate::{models::SessionWithAgents, templates::Catalog};
+
+/// `[notifications.discord]` section of config.toml.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DiscordConfig {
+    pub webhook_url: String,
+    #[serde(flatten)]
+    pub statuses: StatusFlags,
+    #[serde(default = "default_max_per_minute")]
+    pub max_per_minute: u32,
+    /// Overrides the webhook's display name.
+    pub username: Option<String>,
+}
+
+fn default_max_per_minute() -> u32 {
+    10
+}
+
+pub struct Discord {
+    config: DiscordConfig,
+    client: reqwest::Client,
+    limiter: RateLimiter,
+}
+
+impl Discord {
+    pub fn new(config: DiscordConfig) -> Self {
+        Self {
+            limiter: RateLimiter::new(config.max_per_minute),
+            config,
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+impl Notifier for Discord {
+    fn name(&self) -> &str {
+        "discord"
+    }
+
+    fn wants(&self, transition: &Transition) -> bool {
+        self.config.statuses.wants(&transition.to)
+    }
+
+    fn notify<'a>(&'a self, transition: &'a Transition, catalog: &'a Catalog) -> BoxFuture<'a, Result<()>> {
+        let session = &transition.session;
+        let status = catalog.status_text(&transition.to, session.current_tool.as_deref());
+        let embed = json!({
+            "title": chat_message(transition, catalog),
+            "color": status_color(&transition.to),
+            "timestamp": Utc::now().to_rfc3339(),
+            "fields": [
+                { "name": catalog.render("field.project", &[]), "value": session.project_name, "inline": true },
+                { "name": catalog.render("field.status", &[]), "value": status, "inline": true },
+                { "name": catalog.render("field.elapsed", &[]), "value": elapsed(session), "inline": true },
+                { "name": catalog.render("field.agents", &[]), "value": agent_tree(session) },
+            ],
+        });
+        let mut payload = json!({ "embeds": [embed] });
+        if let Some(username) = &self.config.username {
+            payload["username"] = json!(username);
+        }
+
+        Box::pin(async move {
+            if !self.limiter.try_acquire() {
+                return Err(Dropped("rate limit reached").into());
+            }
+            post_json(&self.client, &self.config.webhook_url, &payload, 3).await
+        })
+    }
+
+    fn summarize<'a>(&'a self, title: &'a str, lines: &'a [String]) -> BoxFuture<'a, Result<()>> {
+        // Embed descriptions are capped at 4096 characters.
+        let description: String = lines.join("\n").chars().take(4000).collect();
+        let mut payload = json!({ "embeds": [{ "title": title, "description": description }] });
+        if let Some(username) = &self.config.username {
+            payload["username"] = json!(username);
+        }
+        Box::pin(async move { post_json(&self.client, &self.config.webhook_url, &payload, 3).await })
+    }
+}
+
+/// Embed colours, matching the TUI's palette.
+fn status_color(status: &str) -> u32 {
+    match status {
+        "active" => 0xF5A623,
+        "waiting_input" => 0x2ECC71,
+        "needs_permission" => 0xE74C3C,
+        _ => 0x95A5A6,
+    }
+}
+
+fn elapsed(session: &SessionWithAgents) -> String {
+    format_minutes((Utc::now() - session.created_at).num_minutes())
+}
+
+/// The main agent with its subagents beneath, one per line.
+fn agent_tree(session: &SessionWithAgents) -> String {
+    if session.agents.is_empty() {
+        return "main".to_string();
+    }
+    let (roots, subagents): (Vec<_>, Vec<_>) = session.agents.iter().partition(|a| a.agent_name == "main");
+    roots
+        .iter()
+        .map(|a| format!("{} · {}", a.agent_name, a.status))
+        .chain(subagents.iter().map(|a| format!("└ {} · {}", a.agent_name, a.status)))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,313 @@
+//! SMTP digest emails, for when you've stepped away from the desk.
+//!
+//! A sink like the others, so alert rules route to it as `email`; with no
+//! rules it takes sessions moving into `waiting_input` or `needs_permission`.
+//! A session is mailed about once it has stayed in the status it alerted
+//! about for `after_minutes`; those are gathered for `digest_minutes` after
+//! the first and mailed as one digest, a session appearing once however
+//! often it alerted. Each is looked up again before the digest goes, and
+//! left out if it has moved on.
+//!
+//! ```toml
+//! [notifications.email]
+//! smtp_host = "smtp.fastmail.com"
+//! username = "me@example.com"
+//! password = "app-password"
+//! from = "Claude Monitor <me@example.com>"
+//! to = ["me@example.com"]
+//! after_minutes = 30
+//! digest_minutes = 10
+//! ```
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use lettre::{
+    message::Mailbox, transport::smtp::authentication::Credentials, AsyncSmtpTransport, AsyncTransport, Message,
+    Tokio1Executor,
+};
+use serde::Deserialize;
+use std::sync::Mutex;
+use tracing::info;
+
+use super::{format_minutes, Notifier, StatusFlags, Transition};
+use crate::{api::AppState, models::SessionWithAgents, templates::Catalog};
+
+/// `[notifications.email]` section of config.toml.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EmailConfig {
+    pub smtp_host: String,
+    /// Defaults to 587 with STARTTLS, or 465 when `tls = "implicit"`.
+    pub smtp_port: Option<u16>,
+    #[serde(default)]
+    pub tls: SmtpTls,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+    /// Minutes a session must stay in the status it alerted about before it
+    /// is mailed about.
+    #[serde(default = "default_after_minutes")]
+    pub after_minutes: u32,
+    /// Minutes sessions are gathered for, from the first past
+    /// `after_minutes`, before the digest of them is mailed.
+    #[serde(default = "default_digest_minutes")]
+    pub digest_minutes: u32,
+}
+
+fn default_after_minutes() -> u32 {
+    30
+}
+
+fn default_digest_minutes() -> u32 {
+    10
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpTls {
+    #[default]
+    Starttls,
+    Implicit,
+    /// Plain text, for a relay on localhost.
+    None,
+}
+
+pub struct Email {
+    config: EmailConfig,
+    /// Or why there is none, reported with each send.
+    mailer: Result<AsyncSmtpTransport<Tokio1Executor>, String>,
+    /// For the sessions as they are when a digest goes.
+    state: AppState,
+    /// Sessions waiting to be mailed about, each with the status it last
+    /// alerted about.
+    pending: Mutex<Vec<Pending>>,
+}
+
+#[derive(Debug)]
+struct Pending {
+    host: String,
+    session_id: String,
+    status: String,
+}
+
+impl Pending {
+    fn of(session: &SessionWithAgents) -> Self {
+        Self { host: session.host.clone(), session_id: session.session_id.clone(), status: session.status.clone() }
+    }
+
+    /// The session as it is now, if it is still in the status alerted about.
+    fn current<'a>(&self, sessions: &'a [SessionWithAgents]) -> Option<&'a SessionWithAgents> {
+        sessions
+            .iter()
+            .find(|s| s.host == self.host && s.session_id == self.session_id)
+            .filter(|s| s.status == self.status && !s.muted)
+    }
+}
+
+impl Email {
+    pub fn new(config: EmailConfig, state: AppState) -> Self {
+        Self {
+            mailer: build_transport(&config).map_err(|e| e.to_string()),
+            config,
+            state,
+            pending: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Mail digests until no session is left waiting for one: each gathers
+    /// those past `after_minutes` by `digest_minutes` after the first was.
+    async fn gather(&self, mut first_due: DateTime<Utc>, catalog: &Catalog) -> Result<()> {
+        let window = chrono::Duration::minutes(self.config.digest_minutes.into());
+        loop {
+            let wake = first_due.max(Utc::now()) + window;
+            tokio::time::sleep((wake - Utc::now()).to_std().unwrap_or_default()).await;
+            let sessions = self.state.active_sessions().await?;
+            let now = Utc::now();
+            let (due, next) = {
+                let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+                take_due(&mut pending, &sessions, now, self.after())
+            };
+            if !due.is_empty() {
+                self.send(digest(&self.config, &due, now, catalog)?).await?;
+                info!("Mailed digest of {} session(s)", due.len());
+            }
+            match next {
+                Some(next) => first_due = next,
+                None => return Ok(()),
+            }
+        }
+    }
+
+    fn after(&self) -> chrono::Duration {
+        chrono::Duration::minutes(self.config.after_minutes.into())
+    }
+
+    async fn send(&self, message: Message) -> Result<()> {
+        let mailer = self.mailer.as_ref().map_err(|e| anyhow!("no SMTP transport: {e}"))?;
+        mailer.send(message).await?;
+        Ok(())
+    }
+}
+
+impl Notifier for Email {
+    fn name(&self) -> &str {
+        "email"
+    }
+
+    fn wants(&self, transition: &Transition) -> bool {
+        StatusFlags::default().wants(&transition.to)
+    }
+
+    /// The alert that finds no session waiting mails the digests until none
+    /// is, and reports whether they went; those joining it only that they
+    /// were added.
+    fn notify<'a>(&'a self, transition: &'a Transition, catalog: &'a Catalog) -> BoxFuture<'a, Result<()>> {
+        let session = &transition.session;
+        let opens = {
+            let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+            pending.retain(|p| p.session_id != session.session_id || p.host != session.host);
+            pending.push(Pending::of(session));
+            pending.len() == 1
+        };
+        Box::pin(async move {
+            if !opens {
+                return Ok(());
+            }
+            let outcome = self.gather(session.updated_at + self.after(), catalog).await;
+            if outcome.is_err() {
+                // Otherwise no later alert would open a digest again.
+                self.pending.lock().unwrap_or_else(|e| e.into_inner()).clear();
+            }
+            outcome
+        })
+    }
+
+    fn summarize<'a>(&'a self, title: &'a str, lines: &'a [String]) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let message = envelope(&self.config)?.subject(title).body(lines.join("\n"))?;
+            self.send(message).await
+        })
+    }
+}
+
+fn build_transport(config: &EmailConfig) -> Result<AsyncSmtpTransport<Tokio1Executor>> {
+    let builder = match config.tls {
+        SmtpTls::Starttls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.smtp_host)?,
+        SmtpTls::Implicit => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.smtp_host)?,
+        SmtpTls::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.smtp_host),
+    };
+    let port = config.smtp_port.unwrap_or(match config.tls {
+        SmtpTls::Implicit => 465,
+        SmtpTls::Starttls => 587,
+        SmtpTls::None => 25,
+    });
+    let mut builder = builder.port(port);
+    if let (Some(username), Some(password)) = (&config.username, &config.password) {
+        builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+    }
+    Ok(builder.build())
+}
+
+/// `from` and `to` of every mail.
+fn envelope(config: &EmailConfig) -> Result<lettre::message::MessageBuilder> {
+    let mut builder = Message::builder().from(config.from.parse::<Mailbox>().context("invalid `from` address")?);
+    for to in &config.to {
+        builder = builder.to(to.parse::<Mailbox>().with_context(|| format!("invalid `to` address '{to}'"))?);
+    }
+    Ok(builder)
+}
+
+/// Take the sessions of `pending` that have been in their status for
+/// `after` by `now` out of it, and drop those no longer in it; returns them
+/// and when the first of the rest will have been, if any are left.
+fn take_due(
+    pending: &mut Vec<Pending>,
+    sessions: &[SessionWithAgents],
+    now: DateTime<Utc>,
+    after: chrono::Duration,
+) -> (Vec<SessionWithAgents>, Option<DateTime<Utc>>) {
+    let mut due = Vec::new();
+    let mut next: Option<DateTime<Utc>> = None;
+    pending.retain(|p| match p.current(sessions) {
+        None => false,
+        Some(session) if now - session.updated_at >= after => {
+            due.push(session.clone());
+            false
+        }
+        Some(session) => {
+            let at = session.updated_at + after;
+            next = Some(next.map_or(at, |next| next.min(at)));
+            true
+        }
+    });
+    (due, next)
+}
+
+/// One email listing every session in the digest, each blocked as of `now`.
+fn digest(
+    config: &EmailConfig,
+    sessions: &[SessionWithAgents],
+    now: DateTime<Utc>,
+    catalog: &Catalog,
+) -> Result<Message> {
+    let count = sessions.len().to_string();
+    let subject = catalog.render("email.subject", &[("count", &count)]);
+
+    let mut body = catalog.render("email.intro", &[("count", &count)]);
+    body.push_str("\n\n");
+    for session in sessions {
+        let duration = format_minutes((now - session.updated_at).num_minutes());
+        let status = catalog.status_text(&session.status, session.current_tool.as_deref());
+        let short_id: String = session.session_id.chars().take(8).collect();
+        body.push_str(&catalog.render(
+            "email.line",
+            &[
+                ("project", &session.project_name),
+                ("session", &short_id),
+                ("status", &status),
+                ("duration", &duration),
+            ],
+        ));
+        body.push('\n');
+        if let Some(message) = session.last_message.as_deref().filter(|m| !m.is_empty()) {
+            body.push_str(&format!("    {message}\n"));
+        }
+    }
+    Ok(envelope(config)?.subject(subject).body(body)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::{take_due, Pending};
+    use crate::test_support::{self, event};
+
+    #[tokio::test]
+    async fn only_sessions_still_blocked_past_the_threshold_are_due() {
+        let server = test_support::spawn().await;
+        for id in ["s1", "s2"] {
+            server.client.post_event(&event("needs_permission", id)).await.unwrap();
+        }
+        server.flushed().await;
+        let pending = server.state.active_sessions().await.unwrap();
+        // s2 has been answered since it alerted.
+        server.client.post_event(&event("user_prompt_submit", "s2")).await.unwrap();
+        server.flushed().await;
+        let sessions = server.state.active_sessions().await.unwrap();
+        let blocked_at = sessions.iter().find(|s| s.session_id == "s1").unwrap().updated_at;
+        let after = Duration::minutes(30);
+
+        let mut waiting: Vec<Pending> = pending.iter().map(Pending::of).collect();
+        let (due, next) = take_due(&mut waiting, &sessions, blocked_at + Duration::minutes(10), after);
+        assert!(due.is_empty());
+        assert_eq!(next, Some(blocked_at + after));
+        assert_eq!(waiting.len(), 1);
+
+        let (due, next) = take_due(&mut waiting, &sessions, blocked_at + after, after);
+        assert_eq!(due.iter().map(|s| s.session_id.as_str()).collect::<Vec<_>>(), ["s1"]);
+        assert_eq!(next, None);
+        assert!(waiting.is_empty());
+    }
+}
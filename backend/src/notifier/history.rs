@@ -0,0 +1,124 @@
+//! Notification history: every attempt to alert, successful or not, is kept
+//! in the `notifications` table and listed by GET /api/notifications.
+
+use anyhow::Result;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::json;
+use std::fmt;
+use tracing::{debug, warn};
+use utoipa::IntoParams;
+
+use super::Transition;
+use crate::{
+    api::AppState,
+    models::{ErrorResponse, NotificationPage, NotificationRecord},
+    store::Store,
+};
+
+/// How long history is kept.
+pub const RETENTION: chrono::Duration = chrono::Duration::days(30);
+
+const MAX_LIMIT: u32 = 500;
+
+/// Returned by a sink that deliberately skipped a notification, such as one
+/// over its rate limit. Recorded as "dropped" rather than "failed".
+#[derive(Debug)]
+pub struct Dropped(pub &'static str);
+
+impl fmt::Display for Dropped {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+impl std::error::Error for Dropped {}
+
+/// What was sent, before its outcome is known.
+pub struct Attempt<'a> {
+    pub sink: &'a str,
+    pub kind: &'a str,
+    pub rule_id: Option<&'a str>,
+    pub transition: Option<&'a Transition>,
+    pub message: String,
+}
+
+/// Store `attempt` with its outcome, logging failures as they happen.
+pub async fn record(store: &dyn Store, attempt: Attempt<'_>, outcome: &Result<()>) {
+    let (result, error) = match outcome {
+        Ok(()) => ("sent", None),
+        Err(e) if e.is::<Dropped>() => {
+            debug!("{} {} dropped: {e}", attempt.sink, attempt.kind);
+            ("dropped", Some(e.to_string()))
+        }
+        Err(e) => {
+            warn!("{} {} failed: {e}", attempt.sink, attempt.kind);
+            ("failed", Some(e.to_string()))
+        }
+    };
+    let session = attempt.transition.map(|t| &t.session);
+    let record = NotificationRecord {
+        id: 0,
+        sink: attempt.sink.to_string(),
+        kind: attempt.kind.to_string(),
+        rule_id: attempt.rule_id.map(str::to_string),
+        rule_name: None,
+        session_id: session.map(|s| s.session_id.clone()),
+        project_name: session.map(|s| s.project_name.clone()),
+        status: attempt.transition.map(|t| t.to.clone()),
+        message: attempt.message,
+        result: result.to_string(),
+        error,
+        created_at: Utc::now(),
+    };
+    if let Err(e) = store.insert_notification(&record).await {
+        warn!("Failed to record notification: {e}");
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct HistoryQuery {
+    /// Page size, at most 500.
+    #[serde(default = "default_limit")]
+    pub limit: u32,
+    #[serde(default)]
+    pub offset: u32,
+}
+
+fn default_limit() -> u32 {
+    50
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/notifications",
+    tag = "notifications",
+    params(HistoryQuery),
+    responses(
+        (status = 200, body = NotificationPage),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn list_notifications(State(state): State<AppState>, Query(query): Query<HistoryQuery>) -> impl IntoResponse {
+    let limit = query.limit.clamp(1, MAX_LIMIT);
+    match state.store.get_notifications(limit, query.offset).await {
+        Ok((notifications, total)) => Json(NotificationPage {
+            notifications,
+            total,
+            limit,
+            offset: query.offset,
+        })
+        .into_response(),
+        Err(e) => {
+            warn!("list_notifications error: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response()
+        }
+    }
+}
@@ -0,0 +1,437 @@
+//! Outbound notifications about session status changes.
+//!
+//! One task follows the broadcast channel like a WS client, diffs consecutive
+//! snapshots into [`Transition`]s, and hands each to every configured
+//! [`Notifier`] that wants it. Sinks live in submodules and are configured
+//! under `[notifications]` in config.toml; every attempt is recorded in
+//! [`history`].
+
+mod desktop;
+mod discord;
+mod email;
+pub mod history;
+mod ntfy;
+pub mod quiet;
+mod slack;
+mod telegram;
+mod webhook;
+
+use anyhow::{bail, Result};
+use chrono::Utc;
+use futures::future::BoxFuture;
+use serde::Deserialize;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::sync::broadcast;
+use tracing::{debug, warn};
+
+use crate::{
+    announce,
+    api::AppState,
+    models::{AlertRule, SessionWithAgents},
+    rules::{self, RuleEngine},
+    templates::Catalog,
+};
+
+pub use desktop::DesktopConfig;
+pub use discord::DiscordConfig;
+pub use email::EmailConfig;
+pub use ntfy::NtfyConfig;
+pub use quiet::QuietHours;
+pub use slack::SlackConfig;
+pub use telegram::TelegramConfig;
+pub use webhook::WebhookConfig;
+
+/// `[notifications]` section of config.toml.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct NotificationConfig {
+    pub desktop: DesktopConfig,
+    pub webhooks: Vec<WebhookConfig>,
+    pub slack: Option<SlackConfig>,
+    pub discord: Option<DiscordConfig>,
+    pub telegram: Option<TelegramConfig>,
+    pub ntfy: Option<NtfyConfig>,
+    pub email: Option<EmailConfig>,
+    pub quiet_hours: Option<QuietHours>,
+}
+
+/// A session moving from one status to another. Sessions that drop out of
+/// the active list are reported as moving to `completed`.
+#[derive(Debug, Clone)]
+pub struct Transition {
+    pub session: SessionWithAgents,
+    /// `None` when the session is new.
+    pub from: Option<String>,
+    pub to: String,
+}
+
+/// A destination for transitions.
+pub trait Notifier: Send + Sync {
+    /// Short label for logs.
+    fn name(&self) -> &str;
+
+    fn wants(&self, transition: &Transition) -> bool;
+
+    /// Returns a [`history::Dropped`] error for alerts the sink chose to skip.
+    fn notify<'a>(&'a self, transition: &'a Transition, catalog: &'a Catalog) -> BoxFuture<'a, Result<()>>;
+
+    /// Send one message standing in for alerts held back during quiet hours
+    /// or do-not-disturb; `lines` has one entry per missed alert.
+    fn summarize<'a>(&'a self, title: &'a str, lines: &'a [String]) -> BoxFuture<'a, Result<()>>;
+}
+
+/// Status changes between two snapshots, matching sessions by host and id.
+pub fn transitions(prev: &[SessionWithAgents], next: &[SessionWithAgents]) -> Vec<Transition> {
+    let before: HashMap<(&str, &str), &SessionWithAgents> =
+        prev.iter().map(|s| ((s.host.as_str(), s.session_id.as_str()), s)).collect();
+    let mut changes = Vec::new();
+
+    for session in next {
+        let from = before.get(&(session.host.as_str(), session.session_id.as_str())).map(|s| s.status.as_str());
+        if from != Some(session.status.as_str()) {
+            changes.push(Transition {
+                session: session.clone(),
+                from: from.map(str::to_string),
+                to: session.status.clone(),
+            });
+        }
+    }
+
+    // Pinned sessions that ended are already `completed`; unpinning drops them silently.
+    for session in prev.iter().filter(|s| s.status != "completed") {
+        if !next.iter().any(|s| s.host == session.host && s.session_id == session.session_id) {
+            changes.push(Transition {
+                session: session.clone(),
+                from: Some(session.status.clone()),
+                to: "completed".to_string(),
+            });
+        }
+    }
+
+    changes
+}
+
+/// Every sink enabled in `state`'s config.
+pub fn from_config(state: &AppState) -> Vec<Arc<dyn Notifier>> {
+    let config = &state.config.notifications;
+    let mut notifiers: Vec<Arc<dyn Notifier>> = Vec::new();
+    if config.desktop.enabled {
+        notifiers.push(Arc::new(desktop::Desktop::new(config.desktop.clone())));
+    }
+    for hook in &config.webhooks {
+        notifiers.push(Arc::new(webhook::Webhook::new(hook.clone())));
+    }
+    if let Some(slack) = &config.slack {
+        notifiers.push(Arc::new(slack::Slack::new(slack.clone())));
+    }
+    if let Some(discord) = &config.discord {
+        notifiers.push(Arc::new(discord::Discord::new(discord.clone())));
+    }
+    if let Some(telegram) = &config.telegram {
+        notifiers.push(Arc::new(telegram::Telegram::new(telegram.clone())));
+    }
+    if let Some(ntfy) = &config.ntfy {
+        notifiers.push(Arc::new(ntfy::Ntfy::new(ntfy.clone())));
+    }
+    if let Some(email) = &config.email {
+        notifiers.push(Arc::new(email::Email::new(email.clone(), state.clone())));
+    }
+    notifiers
+}
+
+/// Start dispatching to every configured sink.
+pub fn spawn(state: &AppState) {
+    let notifiers = from_config(state);
+    if !notifiers.is_empty() {
+        tokio::spawn(run(state.clone(), notifiers));
+    }
+}
+
+/// How often alert rules are re-checked between snapshots, so duration
+/// conditions fire without waiting for the next event.
+const RULE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Most alerts held back for one summary; older ones are dropped past this.
+const MAX_HELD: usize = 500;
+
+/// An alert waiting to go out to one sink.
+struct Due {
+    /// Index into the notifier list.
+    notifier: usize,
+    transition: Arc<Transition>,
+    /// The alert rule that routed it, if any.
+    rule_id: Option<String>,
+}
+
+/// Follow session snapshots and dispatch alerts until the channel closes,
+/// routed by alert rules when any are enabled and by each sink's own
+/// triggers otherwise.
+pub async fn run(state: AppState, notifiers: Vec<Arc<dyn Notifier>>) {
+    let mut rx = state.tx.subscribe();
+    let mut prev = announce::baseline(&state).await;
+    let mut engine = RuleEngine::default();
+    // Seed the engine so rules already matching at startup don't all fire.
+    let rules = enabled_rules(&state).await;
+    engine.evaluate(&prev, &rules, &rules::burn_rates(&state, &rules).await, Utc::now());
+    let mut interval = tokio::time::interval(RULE_CHECK_INTERVAL);
+    let mut held: VecDeque<Due> = VecDeque::new();
+
+    loop {
+        let next = tokio::select! {
+            msg = rx.recv() => match msg {
+                Ok(next) => next,
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            _ = interval.tick() => prev.clone(),
+        };
+
+        let rules = enabled_rules(&state).await;
+        let burn = rules::burn_rates(&state, &rules).await;
+        let alerts = engine.evaluate(&next, &rules, &burn, Utc::now());
+        let mut due: Vec<Due> = Vec::new();
+        if rules.is_empty() {
+            for transition in transitions(&prev, &next) {
+                let transition = Arc::new(transition);
+                for (i, _) in notifiers.iter().enumerate().filter(|(_, n)| n.wants(&transition)) {
+                    due.push(Due {
+                        notifier: i,
+                        transition: transition.clone(),
+                        rule_id: None,
+                    });
+                }
+            }
+        } else {
+            for (transition, sinks) in alerts {
+                let transition = Arc::new(transition);
+                for (i, notifier) in notifiers.iter().enumerate() {
+                    if let Some(rule_id) = sinks.get(notifier.name()) {
+                        due.push(Due {
+                            notifier: i,
+                            transition: transition.clone(),
+                            rule_id: Some(rule_id.clone()),
+                        });
+                    }
+                }
+            }
+        }
+        due.retain(|d| !d.transition.session.muted);
+        prev = next;
+
+        if quiet::suppressed(state.store.as_ref(), state.config.notifications.quiet_hours.as_ref()).await {
+            held.extend(due);
+            while held.len() > MAX_HELD {
+                held.pop_front();
+            }
+            continue;
+        }
+        if !held.is_empty() {
+            summarize_held(&state, &notifiers, held.drain(..).collect());
+        }
+        for alert in due {
+            deliver(&state, notifiers[alert.notifier].clone(), alert);
+        }
+    }
+}
+
+/// Send each sink one summary of the alerts it missed.
+fn summarize_held(state: &AppState, notifiers: &[Arc<dyn Notifier>], held: Vec<Due>) {
+    let catalog = &state.templates;
+    for (i, notifier) in notifiers.iter().enumerate() {
+        let lines: Vec<String> = held
+            .iter()
+            .filter(|d| d.notifier == i)
+            .map(|d| chat_message(&d.transition, catalog))
+            .collect();
+        if lines.is_empty() {
+            continue;
+        }
+        let title = catalog.render("summary.title", &[("count", &lines.len().to_string())]);
+        let notifier = notifier.clone();
+        let store = state.store.clone();
+        tokio::spawn(async move {
+            let outcome = notifier.summarize(&title, &lines).await;
+            let attempt = history::Attempt {
+                sink: notifier.name(),
+                kind: "summary",
+                rule_id: None,
+                transition: None,
+                message: format!("{title}\n{}", lines.join("\n")),
+            };
+            history::record(store.as_ref(), attempt, &outcome).await;
+        });
+    }
+}
+
+async fn enabled_rules(state: &AppState) -> Vec<AlertRule> {
+    match state.store.get_alert_rules().await {
+        Ok(rules) => rules.into_iter().filter(|r| r.enabled).collect(),
+        Err(e) => {
+            warn!("Failed to load alert rules: {e}");
+            Vec::new()
+        }
+    }
+}
+
+fn deliver(state: &AppState, notifier: Arc<dyn Notifier>, alert: Due) {
+    let store = state.store.clone();
+    let catalog = state.templates.clone();
+    // Each delivery runs on its own so a slow or retrying sink holds up no one.
+    tokio::spawn(async move {
+        let outcome = notifier.notify(&alert.transition, &catalog).await;
+        let attempt = history::Attempt {
+            sink: notifier.name(),
+            kind: "alert",
+            rule_id: alert.rule_id.as_deref(),
+            transition: Some(&alert.transition),
+            message: chat_message(&alert.transition, &catalog),
+        };
+        history::record(store.as_ref(), attempt, &outcome).await;
+    });
+}
+
+/// Names alert rules can route to, one per configured sink.
+pub fn sink_names(state: &AppState) -> Vec<String> {
+    from_config(state).iter().map(|n| n.name().to_string()).collect()
+}
+
+/// Whether `transition` matches a trigger such as `needs_permission` (any
+/// change into that status), `active->idle`, or `*`.
+pub fn matches_trigger(trigger: &str, transition: &Transition) -> bool {
+    if trigger == "*" {
+        return true;
+    }
+    match trigger.split_once("->") {
+        Some((from, to)) => {
+            let from = from.trim();
+            (from == "*" || transition.from.as_deref() == Some(from)) && to.trim() == transition.to
+        }
+        None => trigger.trim() == transition.to,
+    }
+}
+
+/// Which statuses a chat sink posts about. Defaults to the two that need you.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct StatusFlags {
+    pub waiting_input: bool,
+    pub needs_permission: bool,
+    pub active: bool,
+    pub idle: bool,
+    pub completed: bool,
+}
+
+impl Default for StatusFlags {
+    fn default() -> Self {
+        Self {
+            waiting_input: true,
+            needs_permission: true,
+            active: false,
+            idle: false,
+            completed: false,
+        }
+    }
+}
+
+impl StatusFlags {
+    pub fn wants(&self, status: &str) -> bool {
+        match status {
+            "waiting_input" => self.waiting_input,
+            "needs_permission" => self.needs_permission,
+            "active" => self.active,
+            "idle" => self.idle,
+            "completed" => self.completed,
+            _ => false,
+        }
+    }
+}
+
+/// Caps deliveries to `per_minute` over any sliding minute; the rest are
+/// dropped so a burst of transitions doesn't flood a channel.
+pub struct RateLimiter {
+    per_minute: usize,
+    sent: Mutex<VecDeque<Instant>>,
+}
+
+impl RateLimiter {
+    pub fn new(per_minute: u32) -> Self {
+        Self {
+            per_minute: per_minute as usize,
+            sent: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Record a delivery if there is room for one.
+    pub fn try_acquire(&self) -> bool {
+        let now = Instant::now();
+        let mut sent = self.sent.lock().unwrap_or_else(|e| e.into_inner());
+        while sent.front().is_some_and(|t| now.duration_since(*t) >= Duration::from_secs(60)) {
+            sent.pop_front();
+        }
+        if sent.len() >= self.per_minute {
+            return false;
+        }
+        sent.push_back(now);
+        true
+    }
+}
+
+/// One-line description of a transition for chat sinks, e.g. "Session 1a2b3c4d
+/// in project api needs permission for Bash".
+pub fn chat_message(transition: &Transition, catalog: &Catalog) -> String {
+    let session = &transition.session;
+    let short_id: String = session.session_id.chars().take(8).collect();
+    let mut vars = vec![("session", short_id.as_str()), ("project", session.project_name.as_str())];
+
+    let key = match (transition.to.as_str(), session.current_tool.as_deref()) {
+        ("needs_permission", Some(tool)) => {
+            vars.push(("tool", tool));
+            "message.needs_permission_tool".to_string()
+        }
+        (status, _) => format!("message.{status}"),
+    };
+    catalog.render(&key, &vars)
+}
+
+/// Compact duration for messages: "<1m", "45m", "2h 5m".
+pub fn format_minutes(minutes: i64) -> String {
+    match minutes.max(0) {
+        0 => "<1m".to_string(),
+        m if m < 60 => format!("{m}m"),
+        m => format!("{}h {}m", m / 60, m % 60),
+    }
+}
+
+/// POST `body` as JSON, retrying connection failures, 429s, and 5xx responses
+/// with exponential backoff (1s, 2s, 4s, ...). Errors leave out the URL,
+/// which may embed a secret token.
+pub async fn post_json(client: &reqwest::Client, url: &str, body: &serde_json::Value, retries: u32) -> Result<()> {
+    let mut delay = Duration::from_secs(1);
+    let mut attempt = 0;
+
+    loop {
+        let error = match client.post(url).json(body).send().await {
+            Ok(response) if response.status().is_success() => return Ok(()),
+            Ok(response) => {
+                let status = response.status();
+                if !(status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS) {
+                    bail!("server returned {status}");
+                }
+                format!("server returned {status}")
+            }
+            Err(e) => e.without_url().to_string(),
+        };
+
+        if attempt >= retries {
+            bail!("{error} (gave up after {} attempts)", attempt + 1);
+        }
+        attempt += 1;
+        debug!("{error}; retrying in {delay:?}");
+        tokio::time::sleep(delay).await;
+        delay *= 2;
+    }
+}
@@ -0,0 +1,123 @@
+//! Push notifications through an ntfy topic, on ntfy.sh or a self-hosted server.
+//!
+//! ```toml
+//! [notifications.ntfy]
+//! topic = "my-claude-sessions"
+//! server = "https://ntfy.sh"
+//! token = "tk_..."   # only for protected topics
+//! ```
+
+use anyhow::Result;
+use futures::future::BoxFuture;
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::warn;
+
+use super::{chat_message, history::Dropped, post_json, Notifier, RateLimiter, StatusFlags, Transition};
+use crate::templates::Catalog;
+
+/// `[notifications.ntfy]` section of config.toml.
+#[derive(Debug, Clone, Deserialize)]
+pub struct NtfyConfig {
+    pub topic: String,
+    #[serde(default = "default_server")]
+    pub server: String,
+    /// Access token for protected topics.
+    pub token: Option<String>,
+    /// Opened when the notification is tapped.
+    pub click_url: Option<String>,
+    #[serde(flatten)]
+    pub statuses: StatusFlags,
+    #[serde(default = "default_max_per_minute")]
+    pub max_per_minute: u32,
+}
+
+fn default_server() -> String {
+    "https://ntfy.sh".to_string()
+}
+
+fn default_max_per_minute() -> u32 {
+    10
+}
+
+pub struct Ntfy {
+    config: NtfyConfig,
+    client: reqwest::Client,
+    limiter: RateLimiter,
+}
+
+impl Ntfy {
+    pub fn new(config: NtfyConfig) -> Self {
+        let mut headers = HeaderMap::new();
+        if let Some(token) = &config.token {
+            match HeaderValue::from_str(&format!("Bearer {token}")) {
+                Ok(value) => {
+                    headers.insert(AUTHORIZATION, value);
+                }
+                Err(_) => warn!("Ignoring ntfy token: not a valid header value"),
+            }
+        }
+        let client = reqwest::Client::builder()
+            .default_headers(headers)
+            .build()
+            .unwrap_or_default();
+
+        Self {
+            limiter: RateLimiter::new(config.max_per_minute),
+            config,
+            client,
+        }
+    }
+}
+
+impl Notifier for Ntfy {
+    fn name(&self) -> &str {
+        "ntfy"
+    }
+
+    fn wants(&self, transition: &Transition) -> bool {
+        self.config.statuses.wants(&transition.to)
+    }
+
+    fn notify<'a>(&'a self, transition: &'a Transition, catalog: &'a Catalog) -> BoxFuture<'a, Result<()>> {
+        let session = &transition.session;
+        // ntfy priorities run 1-5; permission prompts block work, so they ring louder.
+        let (priority, tag) = match transition.to.as_str() {
+            "needs_permission" => (4, "warning"),
+            "waiting_input" => (3, "speech_balloon"),
+            "completed" => (3, "white_check_mark"),
+            _ => (2, "robot"),
+        };
+        let mut payload = json!({
+            "topic": self.config.topic,
+            "title": session.project_name,
+            "message": chat_message(transition, catalog),
+            "priority": priority,
+            "tags": [tag],
+        });
+        if let Some(click) = &self.config.click_url {
+            payload["click"] = json!(click);
+        }
+        // Publishing JSON goes to the server root; the topic is in the body.
+        let url = self.config.server.trim_end_matches('/').to_string();
+
+        Box::pin(async move {
+            if !self.limiter.try_acquire() {
+                return Err(Dropped("rate limit reached").into());
+            }
+            post_json(&self.client, &url, &payload, 3).await
+        })
+    }
+
+    fn summarize<'a>(&'a self, title: &'a str, lines: &'a [String]) -> BoxFuture<'a, Result<()>> {
+        let payload = json!({
+            "topic": self.config.topic,
+            "title": title,
+            "message": lines.join("\n"),
+            "tags": ["zzz"],
+        });
+        let url = self.config.server.trim_end_matches('/').to_string();
+        Box::pin(async move { post_json(&self.client, &url, &payload, 3).await })
+    }
+}
@@ -0,0 +1,27 @@
+//! Quiet hours and do-not-disturb. While either is in effect alerts are held
+//! back, and each sink gets one summary of what it missed once both lift.
+
+use tracing::warn;
+
+use crate::store::Store;
+
+pub use crate::models::QuietHours;
+
+/// Settings key holding the do-not-disturb toggle.
+pub const DND_KEY: &str = "notifications.dnd";
+
+pub async fn dnd_enabled(store: &dyn Store) -> bool {
+    match store.get_setting(DND_KEY).await {
+        Ok(value) => value.as_deref() == Some("true"),
+        Err(e) => {
+            warn!("Failed to read do-not-disturb setting: {e}");
+            false
+        }
+    }
+}
+
+/// Whether alerts should be held back right now.
+pub async fn suppressed(store: &dyn Store, quiet_hours: Option<&QuietHours>) -> bool {
+    let now = chrono::Local::now().time();
+    quiet_hours.is_some_and(|q| q.contains(now)) || dnd_enabled(store).await
+}
@@ -0,0 +1,73 @@
+//! Slack incoming-webhook notifications.
+//!
+//! ```toml
+//! [notifications.slack]
+//! webhook_url = "https://hooks.slack.com/services/T000/B000/XXXX"
+//! needs_permission = true
+//! waiting_input = true
+//! idle = false
+//! max_per_minute = 10
+//! ```
+
+use anyhow::Result;
+use futures::future::BoxFuture;
+use serde::Deserialize;
+use serde_json::json;
+
+use super::{chat_message, history::Dropped, post_json, Notifier, RateLimiter, StatusFlags, Transition};
+use crate::templates::Catalog;
+
+/// `[notifications.slack]` section of config.toml.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SlackConfig {
+    pub webhook_url: String,
+    #[serde(flatten)]
+    pub statuses: StatusFlags,
+    #[serde(default = "default_max_per_minute")]
+    pub max_per_minute: u32,
+}
+
+fn default_max_per_minute() -> u32 {
+    10
+}
+
+pub struct Slack {
+    config: SlackConfig,
+    client: reqwest::Client,
+    limiter: RateLimiter,
+}
+
+impl Slack {
+    pub fn new(config: SlackConfig) -> Self {
+        Self {
+            limiter: RateLimiter::new(config.max_per_minute),
+            config,
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+impl Notifier for Slack {
+    fn name(&self) -> &str {
+        "slack"
+    }
+
+    fn wants(&self, transition: &Transition) -> bool {
+        self.config.statuses.wants(&transition.to)
+    }
+
+    fn notify<'a>(&'a self, transition: &'a Transition, catalog: &'a Catalog) -> BoxFuture<'a, Result<()>> {
+        let payload = json!({ "text": chat_message(transition, catalog) });
+        Box::pin(async move {
+            if !self.limiter.try_acquire() {
+                return Err(Dropped("rate limit reached").into());
+            }
+            post_json(&self.client, &self.config.webhook_url, &payload, 3).await
+        })
+    }
+
+    fn summarize<'a>(&'a self, title: &'a str, lines: &'a [String]) -> BoxFuture<'a, Result<()>> {
+        let payload = json!({ "text": format!("*{title}*\n{}", lines.join("\n")) });
+        Box::pin(async move { post_json(&self.client, &self.config.webhook_url, &payload, 3).await })
+    }
+}
@@ -0,0 +1,111 @@
+//! Telegram bot notifications, with a link back to the dashboard.
+//!
+//! ```toml
+//! [notifications.telegram]
+//! bot_token = "123456:ABC-DEF"
+//! chat_id = "987654321"
+//! dashboard_url = "http://my-mac.local:9147"
+//! ```
+
+use anyhow::Result;
+use futures::future::BoxFuture;
+use serde::Deserialize;
+use serde_json::json;
+
+use super::{chat_message, history::Dropped, post_json, Notifier, RateLimiter, StatusFlags, Transition};
+use crate::templates::Catalog;
+
+/// `[notifications.telegram]` section of config.toml.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TelegramConfig {
+    pub bot_token: String,
+    /// Numeric chat id, or `@channelname`.
+    pub chat_id: String,
+    /// Base URL the link in each message points at.
+    #[serde(default = "default_dashboard_url")]
+    pub dashboard_url: String,
+    /// Bot API server, for self-hosted ones.
+    #[serde(default = "default_api_url")]
+    pub api_url: String,
+    #[serde(flatten)]
+    pub statuses: StatusFlags,
+    #[serde(default = "default_max_per_minute")]
+    pub max_per_minute: u32,
+}
+
+fn default_dashboard_url() -> String {
+    "http://localhost:9147".to_string()
+}
+
+fn default_api_url() -> String {
+    "https://api.telegram.org".to_string()
+}
+
+fn default_max_per_minute() -> u32 {
+    10
+}
+
+pub struct Telegram {
+    config: TelegramConfig,
+    client: reqwest::Client,
+    limiter: RateLimiter,
+}
+
+impl Telegram {
+    pub fn new(config: TelegramConfig) -> Self {
+        Self {
+            limiter: RateLimiter::new(config.max_per_minute),
+            config,
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+impl Telegram {
+    fn send_message_url(&self) -> String {
+        format!("{}/bot{}/sendMessage", self.config.api_url.trim_end_matches('/'), self.config.bot_token)
+    }
+}
+
+impl Notifier for Telegram {
+    fn name(&self) -> &str {
+        "telegram"
+    }
+
+    fn wants(&self, transition: &Transition) -> bool {
+        self.config.statuses.wants(&transition.to)
+    }
+
+    fn notify<'a>(&'a self, transition: &'a Transition, catalog: &'a Catalog) -> BoxFuture<'a, Result<()>> {
+        let link = format!(
+            "{}/?session={}",
+            self.config.dashboard_url.trim_end_matches('/'),
+            transition.session.session_id
+        );
+        let text = format!("{}\n{}", chat_message(transition, catalog), link);
+        let payload = json!({
+            "chat_id": self.config.chat_id,
+            "text": text,
+            "disable_web_page_preview": true,
+        });
+        let url = self.send_message_url();
+
+        Box::pin(async move {
+            if !self.limiter.try_acquire() {
+                return Err(Dropped("rate limit reached").into());
+            }
+            post_json(&self.client, &url, &payload, 3).await
+        })
+    }
+
+    fn summarize<'a>(&'a self, title: &'a str, lines: &'a [String]) -> BoxFuture<'a, Result<()>> {
+        // Telegram messages are capped at 4096 characters.
+        let text: String = format!("{title}\n{}", lines.join("\n")).chars().take(4000).collect();
+        let payload = json!({
+            "chat_id": self.config.chat_id,
+            "text": text,
+            "disable_web_page_preview": true,
+        });
+        Box::pin(async move { post_json(&self.client, &self.send_message_url(), &payload, 3).await })
+    }
+}
@@ -0,0 +1,88 @@
+//! Generic outbound webhooks, for home automation and custom scripts.
+//!
+//! ```toml
+//! [[notifications.webhooks]]
+//! name = "home-assistant"
+//! url = "http://homeassistant.local:8123/api/webhook/claude"
+//! on = ["needs_permission", "waiting_input", "active->idle"]
+//! retries = 3
+//! ```
+
+use anyhow::Result;
+use chrono::Utc;
+use futures::future::BoxFuture;
+use serde::Deserialize;
+use serde_json::json;
+
+use super::{matches_trigger, post_json, Notifier, Transition};
+use crate::templates::Catalog;
+
+/// One `[[notifications.webhooks]]` entry.
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebhookConfig {
+    /// How alert rules refer to this webhook; defaults to its URL.
+    pub name: Option<String>,
+    pub url: String,
+    /// Triggers, see [`matches_trigger`]; empty means every transition.
+    #[serde(default)]
+    pub on: Vec<String>,
+    /// Extra attempts after a failed delivery.
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+}
+
+fn default_retries() -> u32 {
+    3
+}
+
+pub struct Webhook {
+    config: WebhookConfig,
+    client: reqwest::Client,
+}
+
+impl Webhook {
+    pub fn new(config: WebhookConfig) -> Self {
+        Self {
+            config,
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+impl Notifier for Webhook {
+    fn name(&self) -> &str {
+        self.config.name.as_deref().unwrap_or(&self.config.url)
+    }
+
+    fn wants(&self, transition: &Transition) -> bool {
+        self.config.on.is_empty() || self.config.on.iter().any(|t| matches_trigger(t, transition))
+    }
+
+    fn notify<'a>(&'a self, transition: &'a Transition, _catalog: &'a Catalog) -> BoxFuture<'a, Result<()>> {
+        let session = &transition.session;
+        let payload = json!({
+            "event": "status_changed",
+            "timestamp": Utc::now().to_rfc3339(),
+            "session_id": session.session_id,
+            "project_name": session.project_name,
+            "project_path": session.project_path,
+            "from": transition.from,
+            "to": transition.to,
+            "status_text": session.status_text,
+            "model": session.model,
+            "current_tool": session.current_tool,
+            "last_message": session.last_message,
+        });
+        Box::pin(async move { post_json(&self.client, &self.config.url, &payload, self.config.retries).await })
+    }
+
+    fn summarize<'a>(&'a self, title: &'a str, lines: &'a [String]) -> BoxFuture<'a, Result<()>> {
+        let payload = json!({
+            "event": "summary",
+            "timestamp": Utc::now().to_rfc3339(),
+            "title": title,
+            "alerts": lines,
+        });
+        Box::pin(async move { post_json(&self.client, &self.config.url, &payload, self.config.retries).await })
+    }
+}
@@ -0,0 +1,112 @@
+//! OpenAPI description of the HTTP API, served at `/api/openapi.json` with a
+//! Swagger UI at `/api/docs`, for scripts and third-party clients.
+//!
+//! Each handler carries its own `#[utoipa::path]` annotation; this lists them.
+//! Schemas are collected from the types those annotations reference.
+
+use utoipa::OpenApi;
+use utoipa_swagger_ui::{Config, SwaggerUi};
+
+use crate::{
+    announce, api, backup, build_info, cleanup, dead_letters, editor, export, hooks, import, ingest, keys,
+    maintenance, metrics, notifier, projects, quota, rules, search, settings, stats, trash, ws,
+};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Claude Monitor",
+        description = "Sessions reported by Claude CLI hooks. `GET /ws` streams the same session list as \
+                       `GET /api/sessions` over WebSocket whenever it changes; `GET /ws/sessions/{session_id}` \
+                       follows one session and the events recorded for it."
+    ),
+    paths(
+        api::health,
+        api::livez,
+        api::readyz,
+        build_info::get_build_info,
+        api::post_event,
+        hooks::post_hook,
+        dead_letters::get_dead_letters,
+        api::get_sessions,
+        api::clear_all_sessions,
+        api::delete_session,
+        trash::get_trash,
+        trash::restore_session,
+        api::update_session,
+        api::ack_session,
+        api::pin_session,
+        api::unpin_session,
+        api::mute_session,
+        api::unmute_session,
+        api::add_tags,
+        api::remove_tags,
+        api::get_session_tools,
+        api::get_session_usage,
+        api::get_session_timeline,
+        api::get_session_events,
+        search::search,
+        stats::get_summary,
+        stats::get_costs,
+        stats::get_projects,
+        stats::get_daily,
+        stats::get_durations,
+        stats::get_concurrency,
+        stats::get_heatmap,
+        stats::get_burn_rate,
+        ingest::get_stats,
+        ws::get_stats,
+        metrics::get_metrics,
+        projects::get_projects,
+        projects::list_aliases,
+        projects::set_alias,
+        projects::delete_alias,
+        quota::get_quota,
+        quota::get_weekly_quota,
+        rules::list_rules,
+        rules::create_rule,
+        rules::get_rule,
+        rules::update_rule,
+        rules::delete_rule,
+        settings::get_settings,
+        settings::update_settings,
+        notifier::history::list_notifications,
+        announce::sse_handler,
+        editor::get_sessions,
+        export::export,
+        export::events_csv,
+        export::sessions_csv,
+        import::import,
+        maintenance::run_maintenance,
+        cleanup::run_cleanup,
+        backup::create_backup,
+        keys::list_keys,
+        keys::create_key,
+        keys::revoke_key,
+    ),
+    tags(
+        (name = "hooks", description = "Event ingestion from Claude CLI hooks"),
+        (name = "sessions", description = "Active sessions and actions on them"),
+        (name = "search", description = "Full-text search over event messages"),
+        (name = "stats", description = "Token usage and estimated cost"),
+        (name = "projects", description = "Per-project rollups and display names"),
+        (name = "quota", description = "Usage against the configured plan limits"),
+        (name = "rules", description = "Alert rules routing sessions to notification sinks"),
+        (name = "settings", description = "Runtime settings such as do-not-disturb"),
+        (name = "notifications", description = "History of notifications sent"),
+        (name = "stream", description = "Plain-text announcements for assistive tooling"),
+        (name = "editor", description = "Compact session view for editor plugins"),
+        (name = "export", description = "The whole database, for migration and analysis"),
+        (name = "admin", description = "Database upkeep, backups and API keys"),
+        (name = "system", description = "Server health and probes"),
+    )
+)]
+pub struct ApiDoc;
+
+/// Routes for the spec and the Swagger UI, merged into the main router.
+/// The UI finds the spec relative to itself, so it works under a prefix.
+pub fn swagger_ui() -> SwaggerUi {
+    SwaggerUi::new("/api/docs")
+        .url("/api/openapi.json", ApiDoc::openapi())
+        .config(Config::from("../openapi.json"))
+}
@@ -0,0 +1,66 @@
+use std::{borrow::Cow, path::Path};
+
+/// Canonical form of a project path as stored at ingestion: `~` expanded,
+/// symlinks resolved when the path exists locally, trailing slashes removed.
+/// Case is preserved so the path is still fit for display.
+pub fn canonicalize(path: &str) -> String {
+    let expanded = expand_home(path.trim());
+    if expanded.is_empty() {
+        return expanded;
+    }
+
+    let resolved = match std::fs::canonicalize(Path::new(&expanded)) {
+        Ok(canonical) => canonical.to_string_lossy().into_owned(),
+        Err(_) => expanded,
+    };
+
+    let trimmed = resolved.trim_end_matches('/');
+    if trimmed.is_empty() && resolved.starts_with('/') {
+        "/".to_string()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+/// Comparison key for a path: the canonical form, additionally case-folded on
+/// macOS where the default filesystem is case-insensitive.
+pub fn normalize(path: &str) -> String {
+    fold_case(&canonicalize(path)).into_owned()
+}
+
+/// Comparison key for a path already in canonical form, as stored paths are;
+/// never touches the filesystem.
+pub fn fold_case(path: &str) -> Cow<'_, str> {
+    if cfg!(target_os = "macos") {
+        Cow::Owned(path.to_lowercase())
+    } else {
+        Cow::Borrowed(path)
+    }
+}
+
+/// Whether `path` is `root` or lies beneath it. Both must already be normalized.
+pub fn is_within(path: &str, root: &str) -> bool {
+    if root == "/" {
+        return path.starts_with('/');
+    }
+    path == root
+        || path
+            .strip_prefix(root)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// Last path component, used as a fallback project name.
+pub fn basename(path: &str) -> Option<&str> {
+    path.rsplit('/').find(|part| !part.is_empty())
+}
+
+fn expand_home(path: &str) -> String {
+    let rest = match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => rest,
+        _ => return path.to_string(),
+    };
+    match dirs::home_dir() {
+        Some(home) => format!("{}{rest}", home.display()),
+        None => path.to_string(),
+    }
+}
@@ -0,0 +1,89 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::models::TokenUsage;
+
+/// USD per million tokens for one model family.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ModelPrice {
+    pub input: f64,
+    pub output: f64,
+    pub cache_write: f64,
+    pub cache_read: f64,
+}
+
+/// Built-in list prices, matched by substring of the model name.
+const BUILTIN_PRICES: &[(&str, ModelPrice)] = &[
+    ("opus", ModelPrice { input: 15.0, output: 75.0, cache_write: 18.75, cache_read: 1.50 }),
+    ("sonnet", ModelPrice { input: 3.0, output: 15.0, cache_write: 3.75, cache_read: 0.30 }),
+    ("haiku", ModelPrice { input: 0.80, output: 4.0, cache_write: 1.0, cache_read: 0.08 }),
+];
+
+/// `[pricing]` section of config.toml.
+///
+/// ```toml
+/// [pricing]
+/// fallback_model = "sonnet"
+///
+/// [pricing.models.claude-opus-4]
+/// input = 15.0
+/// output = 75.0
+/// cache_write = 18.75
+/// cache_read = 1.5
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PricingConfig {
+    /// Overrides and additions, keyed by a substring of the model name.
+    /// The longest matching key wins, and these take precedence over built-ins.
+    pub models: BTreeMap<String, ModelPrice>,
+    /// Model whose price is used when usage has no model or an unknown one.
+    pub fallback_model: String,
+}
+
+impl Default for PricingConfig {
+    fn default() -> Self {
+        Self {
+            models: BTreeMap::new(),
+            fallback_model: "sonnet".to_string(),
+        }
+    }
+}
+
+impl PricingConfig {
+    pub fn price_for(&self, model: Option<&str>) -> Option<ModelPrice> {
+        self.lookup(model.unwrap_or(""))
+            .or_else(|| self.lookup(&self.fallback_model))
+    }
+
+    /// Estimated USD cost of `usage` billed at `model`'s rates.
+    pub fn cost(&self, model: Option<&str>, usage: &TokenUsage) -> f64 {
+        let Some(price) = self.price_for(model) else {
+            return 0.0;
+        };
+        (usage.input_tokens as f64 * price.input
+            + usage.output_tokens as f64 * price.output
+            + usage.cache_creation_input_tokens as f64 * price.cache_write
+            + usage.cache_read_input_tokens as f64 * price.cache_read)
+            / 1_000_000.0
+    }
+
+    fn lookup(&self, model: &str) -> Option<ModelPrice> {
+        if model.is_empty() {
+            return None;
+        }
+        let model = model.to_lowercase();
+        let configured = self
+            .models
+            .iter()
+            .filter(|(key, _)| model.contains(&key.to_lowercase()))
+            .max_by_key(|(key, _)| key.len())
+            .map(|(_, price)| *price);
+        configured.or_else(|| {
+            BUILTIN_PRICES
+                .iter()
+                .find(|(key, _)| model.contains(key))
+                .map(|(_, price)| *price)
+        })
+    }
+}
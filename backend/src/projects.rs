@@ -0,0 +1,205 @@
+//! Projects under `/api/projects`: a per-project rollup of active sessions,
+//! and friendly display names for project paths.
+//!
+//! Hooks report `project_name` as the directory basename, which is often
+//! unhelpful. An alias maps a project path to a name shown instead wherever
+//! sessions are served.
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use serde_json::json;
+use std::collections::HashMap;
+use tracing::warn;
+
+use crate::{
+    api::{AppState, HostFilter},
+    models::{ErrorResponse, ProjectAlias, ProjectAliasInput, ProjectAliasKey, ProjectRollup, SessionWithAgents},
+    paths,
+    store::Store,
+};
+
+/// Longest display name accepted, in characters.
+const MAX_NAME_LEN: usize = 100;
+
+/// Statuses from most to least in need of attention.
+pub const URGENCY: &[&str] = &["needs_permission", "waiting_input", "active", "acknowledged", "idle", "completed"];
+
+pub fn urgency(status: &str) -> usize {
+    URGENCY.iter().position(|s| *s == status).unwrap_or(URGENCY.len())
+}
+
+/// Group sessions by project, most urgent project first.
+pub fn rollup(sessions: Vec<SessionWithAgents>) -> Vec<ProjectRollup> {
+    let mut projects: Vec<ProjectRollup> = Vec::new();
+    // Keyed by host and path; sessions without a path are grouped by name instead.
+    let mut index: HashMap<String, usize> = HashMap::new();
+
+    for session in sessions {
+        let key = if session.project_path.is_empty() {
+            format!("{}\0name:{}", session.host, session.project_name)
+        } else {
+            format!("{}\0{}", session.host, paths::normalize(&session.project_path))
+        };
+        let i = *index.entry(key).or_insert_with(|| {
+            projects.push(ProjectRollup {
+                host: session.host.clone(),
+                project_name: session.project_name.clone(),
+                project_path: session.project_path.clone(),
+                status: session.status.clone(),
+                session_count: 0,
+                agent_count: 0,
+                status_counts: Default::default(),
+                sessions: Vec::new(),
+            });
+            projects.len() - 1
+        });
+
+        let project = &mut projects[i];
+        if urgency(&session.status) < urgency(&project.status) {
+            project.status = session.status.clone();
+        }
+        project.session_count += 1;
+        project.agent_count += session.agents.len().max(1);
+        *project.status_counts.entry(session.status.clone()).or_default() += 1;
+        project.sessions.push(session);
+    }
+
+    projects.sort_by(|a, b| {
+        urgency(&a.status)
+            .cmp(&urgency(&b.status))
+            .then_with(|| a.project_name.cmp(&b.project_name))
+    });
+    projects
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/projects",
+    tag = "projects",
+    params(HostFilter),
+    responses(
+        (status = 200, description = "Most urgent project first", body = Vec<ProjectRollup>),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn get_projects(State(state): State<AppState>, Query(hosts): Query<HostFilter>) -> impl IntoResponse {
+    match state.active_sessions().await {
+        Ok(mut sessions) => {
+            sessions.retain(|s| hosts.allows(&s.host));
+            Json(rollup(sessions)).into_response()
+        }
+        Err(e) => {
+            warn!("get_projects error: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response()
+        }
+    }
+}
+
+/// Display names keyed by normalized project path.
+pub async fn aliases(store: &dyn Store) -> anyhow::Result<HashMap<String, String>> {
+    Ok(store.get_project_aliases()
+        .await?
+        .into_iter()
+        .map(|a| (a.project_path, a.display_name))
+        .collect())
+}
+
+/// The alias for the project at `project_path`, if it has one.
+pub fn alias_for<'a>(aliases: &'a HashMap<String, String>, project_path: &str) -> Option<&'a String> {
+    if aliases.is_empty() {
+        return None;
+    }
+    aliases.get(&paths::normalize(project_path))
+}
+
+/// Rename sessions whose project has an alias.
+pub async fn apply_aliases(store: &dyn Store, sessions: &mut [SessionWithAgents]) -> anyhow::Result<()> {
+    let aliases = aliases(store).await?;
+    for session in sessions {
+        if let Some(name) = alias_for(&aliases, &session.project_path) {
+            session.project_name = name.clone();
+        }
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/projects/aliases",
+    tag = "projects",
+    responses(
+        (status = 200, body = Vec<ProjectAlias>),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn list_aliases(State(state): State<AppState>) -> impl IntoResponse {
+    match state.store.get_project_aliases().await {
+        Ok(aliases) => Json(aliases).into_response(),
+        Err(e) => {
+            warn!("list_aliases error: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/projects/aliases",
+    tag = "projects",
+    request_body = ProjectAliasInput,
+    responses(
+        (status = 200, description = "The stored alias", body = ProjectAliasInput),
+        (status = 400, description = "Empty path or invalid name", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn set_alias(State(state): State<AppState>, Json(alias): Json<ProjectAliasInput>) -> impl IntoResponse {
+    let project_path = paths::normalize(&alias.project_path);
+    let display_name = alias.display_name.trim();
+    if project_path.is_empty() {
+        return (StatusCode::BAD_REQUEST, Json(json!({"error": "`project_path` must not be empty"}))).into_response();
+    }
+    if display_name.is_empty() || display_name.chars().count() > MAX_NAME_LEN {
+        let error = format!("`display_name` must be 1-{MAX_NAME_LEN} characters");
+        return (StatusCode::BAD_REQUEST, Json(json!({"error": error}))).into_response();
+    }
+    match state.store.set_project_alias(&project_path, display_name).await {
+        Ok(()) => {
+            state.broadcast_sessions();
+            Json(json!({"project_path": project_path, "display_name": display_name})).into_response()
+        }
+        Err(e) => {
+            warn!("set_alias error: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/projects/aliases",
+    tag = "projects",
+    request_body = ProjectAliasKey,
+    responses(
+        (status = 200, description = "Alias removed"),
+        (status = 404, description = "No alias for that path", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn delete_alias(State(state): State<AppState>, Json(key): Json<ProjectAliasKey>) -> impl IntoResponse {
+    match state.store.delete_project_alias(&paths::normalize(&key.project_path)).await {
+        Ok(true) => {
+            state.broadcast_sessions();
+            StatusCode::OK.into_response()
+        }
+        Ok(false) => (StatusCode::NOT_FOUND, Json(json!({"error": "alias not found"}))).into_response(),
+        Err(e) => {
+            warn!("delete_alias error: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response()
+        }
+    }
+}
@@ -0,0 +1,88 @@
+//! Serving behind a reverse proxy. With `base_path` set under `[listen]`
+//! every route moves beneath it, for a proxy that passes the path through:
+//!
+//! ```toml
+//! [listen]
+//! base_path = "/claude"
+//! ```
+//!
+//! A proxy that strips the prefix instead can send it as
+//! `X-Forwarded-Prefix`. Either way the dashboard and GraphiQL link to the
+//! prefixed paths, and `X-Forwarded-For` names the client in the log.
+
+use axum::{
+    extract::{ConnectInfo, Request},
+    http::HeaderMap,
+    Router,
+};
+use std::net::SocketAddr;
+
+/// `base_path` as a route prefix: a leading slash and none trailing, or
+/// empty for the root.
+pub fn base_path(configured: &str) -> String {
+    match configured.trim_matches('/') {
+        "" => String::new(),
+        path => format!("/{path}"),
+    }
+}
+
+/// `router` beneath `base_path`.
+pub fn nest(router: Router, base_path: &str) -> Router {
+    if base_path.is_empty() {
+        return router;
+    }
+    // `nest` leaves out the path with a trailing slash, the one the
+    // dashboard is usually opened at; unrouted, it falls back to the page.
+    Router::new().nest(base_path, router.clone()).route_service(&format!("{base_path}/"), router.into_service())
+}
+
+/// The path the client sees the server at, to put before its links:
+/// `X-Forwarded-Prefix` when sent, else `base_path`.
+pub fn prefix(headers: &HeaderMap, base_path: &str) -> String {
+    let forwarded = headers.get("x-forwarded-prefix").and_then(|value| value.to_str().ok());
+    // Ends up in HTML, so only plain path characters are let through.
+    match forwarded {
+        Some(prefix) if prefix.bytes().all(|b| b.is_ascii_alphanumeric() || b"/-._~".contains(&b)) => {
+            self::base_path(prefix)
+        }
+        _ => base_path.to_string(),
+    }
+}
+
+/// Who sent `request`: the first `X-Forwarded-For` hop when behind a
+/// proxy, else the peer address.
+pub fn client(request: &Request) -> Option<String> {
+    let forwarded = request.headers().get("x-forwarded-for").and_then(|value| value.to_str().ok());
+    match forwarded.and_then(|hops| hops.split(',').next()).map(str::trim) {
+        Some(hop) if !hop.is_empty() => Some(hop.to_string()),
+        _ => request.extensions().get::<ConnectInfo<SocketAddr>>().map(|info| info.0.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{HeaderMap, HeaderValue};
+    use std::time::Duration;
+
+    use super::{base_path, prefix};
+    use crate::{config::Config, listen::ListenConfig, test_support};
+
+    #[tokio::test]
+    async fn routes_and_links_move_under_the_base_path() {
+        let listen = ListenConfig { base_path: "/claude/".to_string(), ..Default::default() };
+        let server = test_support::spawn_with(Config { listen, ..Default::default() }).await;
+        let url = server.client.base_url().as_str().trim_end_matches('/').to_string();
+        let http = reqwest::Client::builder().timeout(Duration::from_secs(5)).build().unwrap();
+
+        assert!(http.get(format!("{url}/health")).send().await.unwrap().status().is_client_error());
+        assert!(http.get(format!("{url}/claude/health")).send().await.unwrap().status().is_success());
+        let page = http.get(format!("{url}/claude/")).send().await.unwrap().text().await.unwrap();
+        assert!(page.contains(r#"<base href="/claude/">"#), "{page}");
+        let page = http.get(format!("{url}/claude/sessions/s1")).header("x-forwarded-prefix", "/monitor").send();
+        assert!(page.await.unwrap().text().await.unwrap().contains(r#"<base href="/monitor/">"#));
+
+        let headers = HeaderMap::from_iter([("x-forwarded-prefix".parse().unwrap(), HeaderValue::from_static("/\"x"))]);
+        assert_eq!(prefix(&headers, "/claude"), "/claude");
+        assert_eq!(base_path("/"), "");
+    }
+}
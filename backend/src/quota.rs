@@ -0,0 +1,232 @@
+//! Consumption within Claude's rolling usage window.
+//!
+//! Plans throttle on the tokens and messages used over the last few hours
+//! (five by default), across every session. The monitor can't see the real
+//! limits, so they are configured, and remaining capacity is an estimate.
+//! Served at `/api/quota` and over WebSocket with `/ws?quota=true`.
+//!
+//! A separate weekly cap resets at a fixed time each week; `/api/quota/weekly`
+//! reports it along with when the cap would be hit at the week's burn rate.
+
+use anyhow::Result;
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use chrono::{DateTime, Datelike, Duration, NaiveTime, Utc, Weekday};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::warn;
+
+use crate::{
+    api::AppState,
+    models::{ErrorResponse, QuotaStatus, WeeklyQuotaStatus, WindowUsage},
+    store::Store,
+};
+
+/// `[quota]` section of config.toml.
+///
+/// ```toml
+/// [quota]
+/// window_hours = 5
+/// token_limit = 2000000
+/// message_limit = 200
+///
+/// [quota.weekly]
+/// reset_day = "monday"
+/// reset_hour = 0
+/// token_limit = 40000000
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct QuotaConfig {
+    pub window_hours: u32,
+    /// Input + output tokens allowed per window, if known.
+    pub token_limit: Option<i64>,
+    /// Assistant messages allowed per window, if known.
+    pub message_limit: Option<i64>,
+    pub weekly: WeeklyQuotaConfig,
+}
+
+impl Default for QuotaConfig {
+    fn default() -> Self {
+        Self {
+            window_hours: 5,
+            token_limit: None,
+            message_limit: None,
+            weekly: WeeklyQuotaConfig::default(),
+        }
+    }
+}
+
+impl QuotaConfig {
+    pub fn window(&self) -> Duration {
+        Duration::hours(self.window_hours.into())
+    }
+
+    /// How long usage rows must outlive their session to cover both windows.
+    pub fn retention(&self) -> Duration {
+        self.window().max(Duration::weeks(1))
+    }
+}
+
+/// `[quota.weekly]` section of config.toml.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct WeeklyQuotaConfig {
+    /// Day of the week the cap resets, e.g. "monday" or "mon".
+    pub reset_day: Weekday,
+    /// Hour of `reset_day` (UTC, 0-23) the cap resets.
+    pub reset_hour: u32,
+    pub token_limit: Option<i64>,
+    pub message_limit: Option<i64>,
+}
+
+impl Default for WeeklyQuotaConfig {
+    fn default() -> Self {
+        Self {
+            reset_day: Weekday::Mon,
+            reset_hour: 0,
+            token_limit: None,
+            message_limit: None,
+        }
+    }
+}
+
+impl WeeklyQuotaConfig {
+    /// The most recent reset at or before `now`.
+    pub fn week_start(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let days_back = (7 + now.weekday().num_days_from_monday() - self.reset_day.num_days_from_monday()) % 7;
+        let hour = NaiveTime::from_hms_opt(self.reset_hour.min(23), 0, 0).unwrap_or(NaiveTime::MIN);
+        let start = (now.date_naive() - Duration::days(days_back.into())).and_time(hour).and_utc();
+        if start > now {
+            start - Duration::weeks(1)
+        } else {
+            start
+        }
+    }
+}
+
+/// Usage within the window ending now, measured against the configured limits.
+pub async fn current(store: &dyn Store, config: &QuotaConfig) -> Result<QuotaStatus> {
+    let now = Utc::now();
+    let window_start = now - config.window();
+    let window = store.get_usage_since(window_start).await?;
+
+    // The oldest message still counted is the next to drop out.
+    let resets_at = window.oldest.map(|t| t + config.window());
+    Ok(measure(window, window_start, now, resets_at, config.token_limit, config.message_limit))
+}
+
+/// Usage since this week's reset, with a projection of when the weekly cap is
+/// reached if the week continues at its average rate so far.
+pub async fn weekly(store: &dyn Store, config: &WeeklyQuotaConfig) -> Result<WeeklyQuotaStatus> {
+    let now = Utc::now();
+    let week_start = config.week_start(now);
+    let resets_at = week_start + Duration::weeks(1);
+    let window = store.get_usage_since(week_start).await?;
+    let status = measure(window, week_start, now, Some(resets_at), config.token_limit, config.message_limit);
+
+    let elapsed_hours = (now - week_start).num_seconds().max(1) as f64 / 3600.0;
+    let tokens_per_hour = status.tokens as f64 / elapsed_hours;
+    let messages_per_hour = status.messages as f64 / elapsed_hours;
+
+    let projected_limit_at = [
+        status.tokens_remaining.map(|left| hours_until(left, tokens_per_hour)),
+        status.messages_remaining.map(|left| hours_until(left, messages_per_hour)),
+    ]
+    .into_iter()
+    .flatten()
+    .flatten()
+    .reduce(f64::min)
+    .map(|hours| now + Duration::seconds((hours * 3600.0) as i64))
+    .filter(|at| *at < resets_at);
+
+    Ok(WeeklyQuotaStatus {
+        status,
+        tokens_per_hour,
+        messages_per_hour,
+        projected_limit_at,
+    })
+}
+
+fn measure(
+    window: WindowUsage,
+    window_start: DateTime<Utc>,
+    window_end: DateTime<Utc>,
+    resets_at: Option<DateTime<Utc>>,
+    token_limit: Option<i64>,
+    message_limit: Option<i64>,
+) -> QuotaStatus {
+    let tokens = window.usage.input_tokens + window.usage.output_tokens;
+    let tokens_remaining = token_limit.map(|limit| (limit - tokens).max(0));
+    let messages_remaining = message_limit.map(|limit| (limit - window.messages).max(0));
+    let used_fraction = [
+        token_limit.map(|limit| fraction(tokens, limit)),
+        message_limit.map(|limit| fraction(window.messages, limit)),
+    ]
+    .into_iter()
+    .flatten()
+    .reduce(f64::max);
+
+    QuotaStatus {
+        window_start,
+        window_end,
+        resets_at,
+        usage: window.usage,
+        tokens,
+        messages: window.messages,
+        token_limit,
+        message_limit,
+        tokens_remaining,
+        messages_remaining,
+        used_fraction,
+    }
+}
+
+/// Hours until `remaining` is used up at `per_hour`; `None` if it never is.
+fn hours_until(remaining: i64, per_hour: f64) -> Option<f64> {
+    if remaining == 0 {
+        return Some(0.0);
+    }
+    (per_hour > 0.0).then(|| remaining as f64 / per_hour)
+}
+
+fn fraction(used: i64, limit: i64) -> f64 {
+    if limit <= 0 {
+        return 1.0;
+    }
+    used as f64 / limit as f64
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/quota",
+    tag = "quota",
+    responses((status = 200, body = QuotaStatus), (status = 500, description = "Database error", body = ErrorResponse))
+)]
+pub async fn get_quota(State(state): State<AppState>) -> impl IntoResponse {
+    match current(state.store.as_ref(), &state.config.quota).await {
+        Ok(status) => Json(status).into_response(),
+        Err(e) => {
+            warn!("get_quota error: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/quota/weekly",
+    tag = "quota",
+    responses(
+        (status = 200, body = WeeklyQuotaStatus),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn get_weekly_quota(State(state): State<AppState>) -> impl IntoResponse {
+    match weekly(state.store.as_ref(), &state.config.quota.weekly).await {
+        Ok(status) => Json(status).into_response(),
+        Err(e) => {
+            warn!("get_weekly_quota error: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response()
+        }
+    }
+}
@@ -0,0 +1,163 @@
+//! Relay mode: re-POST every ingested event to a central monitor, so remote
+//! machines report into one dashboard.
+//!
+//! ```toml
+//! [relay]
+//! upstream = "http://central.local:9147"
+//! host = "devbox"   # defaults to this machine's hostname
+//! token = "..."     # if the upstream has one
+//! api_key = "cm_..."   # likewise, see `keys`
+//! ```
+//!
+//! Events are queued in memory and sent in order. While the upstream is
+//! unreachable the queue holds up to `max_buffered` events, dropping the
+//! oldest, and delivery resumes with backoff once it answers again.
+
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use std::{collections::VecDeque, time::Duration};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use crate::{auth, models::HookEvent};
+
+/// `[relay]` section of config.toml.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RelayConfig {
+    /// Base URL of the central monitor.
+    pub upstream: String,
+    /// Host reported for events that don't name one.
+    pub host: Option<String>,
+    /// Token the upstream requires, if any.
+    pub token: Option<String>,
+    /// API key the upstream attributes these events to, if it has keys.
+    pub api_key: Option<String>,
+    #[serde(default = "default_max_buffered")]
+    pub max_buffered: usize,
+}
+
+fn default_max_buffered() -> usize {
+    10_000
+}
+
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Handle for queueing events to the relay task.
+#[derive(Clone)]
+pub struct Relay {
+    tx: mpsc::UnboundedSender<HookEvent>,
+    host: String,
+}
+
+impl Relay {
+    /// Start the relay task and return its handle.
+    pub fn spawn(config: RelayConfig) -> Self {
+        let host = config.host.clone().filter(|h| !h.trim().is_empty()).unwrap_or_else(|| {
+            hostname().unwrap_or_else(|| {
+                warn!("Could not determine hostname; set `host` under [relay]");
+                "unknown".to_string()
+            })
+        });
+        info!("Relaying events to {} as host '{host}'", config.upstream);
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(run(config, rx));
+        Self { tx, host }
+    }
+
+    /// Queue `event` for the upstream, tagged with this machine's host.
+    pub fn forward(&self, event: &HookEvent) {
+        let mut event = event.clone();
+        if event.host.as_deref().is_none_or(|h| h.trim().is_empty()) {
+            event.host = Some(self.host.clone());
+        }
+        // The transcript lives on this machine; the upstream can't tail it.
+        event.transcript_path = None;
+        let _ = self.tx.send(event);
+    }
+}
+
+/// This machine's name, as printed by `hostname`.
+pub fn hostname() -> Option<String> {
+    std::process::Command::new("hostname")
+        .output()
+        .ok()
+        .and_then(|out| String::from_utf8(out.stdout).ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+async fn run(config: RelayConfig, mut rx: mpsc::UnboundedReceiver<HookEvent>) {
+    let client = auth::http_client(Duration::from_secs(10), config.token.as_deref(), config.api_key.as_deref());
+    let url = format!("{}/api/events", config.upstream.trim_end_matches('/'));
+    let mut queue: VecDeque<HookEvent> = VecDeque::new();
+    let mut backoff = MIN_BACKOFF;
+    let mut dropped = 0usize;
+
+    loop {
+        if queue.is_empty() {
+            match rx.recv().await {
+                Some(event) => queue.push_back(event),
+                None => return,
+            }
+        }
+        while let Ok(event) = rx.try_recv() {
+            queue.push_back(event);
+        }
+        if queue.len() > config.max_buffered {
+            let excess = queue.len() - config.max_buffered;
+            queue.drain(..excess);
+            dropped += excess;
+        }
+
+        let Some(event) = queue.front() else { continue };
+        match send(&client, &url, event).await {
+            Ok(Delivery::Accepted) => {
+                queue.pop_front();
+                if backoff > MIN_BACKOFF {
+                    info!("Relay to {} restored; {} events queued", config.upstream, queue.len());
+                    if dropped > 0 {
+                        warn!("Relay dropped {dropped} events while the upstream was unreachable");
+                        dropped = 0;
+                    }
+                }
+                backoff = MIN_BACKOFF;
+            }
+            Ok(Delivery::Rejected(status)) => {
+                // Retrying won't change the answer; skip it rather than block the queue.
+                warn!("Upstream rejected {} event with {status}; dropping it", event.event_type);
+                queue.pop_front();
+            }
+            Err(e) => {
+                if backoff == MIN_BACKOFF {
+                    warn!("Relay to {} failed: {e}; buffering events", config.upstream);
+                }
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        }
+    }
+}
+
+enum Delivery {
+    Accepted,
+    Rejected(reqwest::StatusCode),
+}
+
+/// POST one event. Errors are the retryable outcomes: network failures and 5xx.
+async fn send(client: &reqwest::Client, url: &str, event: &HookEvent) -> Result<Delivery> {
+    let response = client
+        .post(url)
+        .json(event)
+        .send()
+        .await
+        .map_err(|e| anyhow::anyhow!(e.without_url()))?;
+    let status = response.status();
+    if status.is_success() {
+        Ok(Delivery::Accepted)
+    } else if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        bail!("server returned {status}")
+    } else {
+        Ok(Delivery::Rejected(status))
+    }
+}
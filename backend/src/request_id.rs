@@ -0,0 +1,82 @@
+//! `X-Request-Id` on every request: the client's own when it sends a usable
+//! one, a new UUID otherwise, echoed on the response. The request's span
+//! carries it, so every line logged while serving it does too, and the
+//! events it posts store it as `request_id`, tying a hook delivery to its
+//! rows and to the `/ws/sessions/:id` frames they go out in.
+
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Request},
+    http::{request::Parts, HeaderMap, HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use std::convert::Infallible;
+use uuid::Uuid;
+
+pub const HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Longest id taken from a client; longer ones are replaced.
+const MAX_LEN: usize = 128;
+
+/// Give the request an id unless it has a usable one, and answer with it.
+pub async fn middleware(mut request: Request, next: Next) -> Response {
+    let id = match request.headers().get(&HEADER) {
+        Some(id) if usable(id) => id.clone(),
+        _ => HeaderValue::try_from(Uuid::new_v4().to_string()).expect("a UUID is a valid header value"),
+    };
+    request.headers_mut().insert(HEADER, id.clone());
+    let mut response = next.run(request).await;
+    response.headers_mut().insert(HEADER, id);
+    response
+}
+
+fn usable(id: &HeaderValue) -> bool {
+    id.len() <= MAX_LEN && id.to_str().is_ok_and(|id| !id.is_empty() && id.bytes().all(|b| b.is_ascii_graphic()))
+}
+
+/// The request's id, for handlers; `None` only outside `middleware`.
+#[derive(Debug, Clone, Default)]
+pub struct RequestId(pub Option<String>);
+
+impl RequestId {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        Self(headers.get(&HEADER).and_then(|id| id.to_str().ok()).map(str::to_string))
+    }
+}
+
+#[async_trait]
+impl<S: Sync> FromRequestParts<S> for RequestId {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self::from_headers(&parts.headers))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{HeaderMap, HeaderValue};
+    use claude_monitor_client::Client;
+    use uuid::Uuid;
+
+    use super::{usable, HEADER};
+    use crate::test_support::{self, event};
+
+    #[tokio::test]
+    async fn events_keep_the_id_of_the_request_that_posted_them() {
+        let server = test_support::spawn().await;
+        let headers = HeaderMap::from_iter([(HEADER, HeaderValue::from_static("hook-42"))]);
+        let http = reqwest::Client::builder().default_headers(headers).build().unwrap();
+        let tagged = Client::with_http(server.client.base_url().as_str(), http).unwrap();
+        tagged.post_event(&event("pre_tool_use", "s1")).await.unwrap();
+        server.client.post_event(&event("stop", "s1")).await.unwrap();
+        server.flushed().await;
+
+        let events = server.client.session_events("s1", 10, None).await.unwrap();
+        assert_eq!(events[1].request_id.as_deref(), Some("hook-42"));
+        assert!(Uuid::parse_str(events[0].request_id.as_deref().unwrap()).is_ok());
+        assert!(!usable(&HeaderValue::from_str(&"x".repeat(200)).unwrap()));
+        assert!(!usable(&HeaderValue::from_static("two words")));
+    }
+}
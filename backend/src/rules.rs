@@ -0,0 +1,306 @@
+//! Alert rules: conditions over session state mapped to notification sinks.
+//!
+//! Rules live in the `alert_rules` table and are managed through `/api/rules`.
+//! While at least one rule is enabled they replace the per-sink triggers in
+//! config.toml; with none, sinks fall back to those. A rule fires once when a
+//! session starts matching it and again only after it has stopped matching.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use tracing::warn;
+
+use crate::{
+    api::AppState,
+    models::{AlertRule, AlertRuleInput, ErrorResponse, RuleConditions, SessionWithAgents},
+    notifier::{self, Transition},
+};
+
+/// Minutes of usage the `min_tokens_per_minute` condition averages over.
+pub const BURN_WINDOW_MINUTES: i64 = 5;
+
+const STATUSES: &[&str] = &["active", "waiting_input", "needs_permission", "acknowledged", "idle", "completed"];
+
+/// What a rule is checked against for one session.
+pub struct RuleContext<'a> {
+    pub session: &'a SessionWithAgents,
+    pub minutes_in_status: i64,
+    pub tokens_per_minute: f64,
+}
+
+impl RuleContext<'_> {
+    pub fn matches(&self, conditions: &RuleConditions) -> bool {
+        let session = self.session;
+        conditions.status.as_ref().is_none_or(|statuses| statuses.contains(&session.status))
+            && conditions.min_minutes_in_status.is_none_or(|min| self.minutes_in_status >= i64::from(min))
+            && conditions.project.as_deref().is_none_or(|pattern| glob_match(pattern, &session.project_name))
+            && conditions.min_agents.is_none_or(|min| session.agents.len().max(1) >= min)
+            && conditions.min_tokens_per_minute.is_none_or(|min| self.tokens_per_minute >= min)
+    }
+}
+
+/// `*` matches any run of characters; everything else matches literally.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == text;
+    }
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if !text.starts_with(first) || !text[first.len()..].ends_with(last) {
+        return false;
+    }
+    let mut rest = &text[first.len()..text.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+/// A session as (host, session_id).
+type SessionKey = (String, String);
+
+fn key(session: &SessionWithAgents) -> SessionKey {
+    (session.host.clone(), session.session_id.clone())
+}
+
+/// Tracks which rules currently match which sessions, and since when each
+/// session has been in its status.
+#[derive(Default)]
+pub struct RuleEngine {
+    last: HashMap<SessionKey, SessionWithAgents>,
+    status_since: HashMap<SessionKey, (String, DateTime<Utc>)>,
+    /// (rule id, session).
+    firing: HashSet<(String, SessionKey)>,
+}
+
+impl RuleEngine {
+    /// Check `rules` against the latest snapshot and return the alerts that
+    /// start firing, one per session with the union of their sinks, each
+    /// mapped to the first rule routing to it. Sessions that left the snapshot
+    /// are checked once more as `completed`.
+    pub fn evaluate(
+        &mut self,
+        sessions: &[SessionWithAgents],
+        rules: &[AlertRule],
+        burn: &HashMap<String, f64>,
+        now: DateTime<Utc>,
+    ) -> Vec<(Transition, BTreeMap<String, String>)> {
+        let mut current: Vec<SessionWithAgents> = sessions.to_vec();
+        for (id, session) in &self.last {
+            if !sessions.iter().any(|s| key(s) == *id) {
+                let mut ended = session.clone();
+                ended.status = "completed".to_string();
+                current.push(ended);
+            }
+        }
+
+        let mut alerts: HashMap<SessionKey, (Transition, BTreeMap<String, String>)> = HashMap::new();
+        let mut firing = HashSet::new();
+        for session in &current {
+            let id = key(session);
+            let since = match self.status_since.get(&id) {
+                Some((status, since)) if *status == session.status => *since,
+                // First sight of this session: its last update is the best guess.
+                None => session.updated_at,
+                Some(_) => now,
+            };
+            self.status_since.insert(id.clone(), (session.status.clone(), since));
+
+            let ctx = RuleContext {
+                session,
+                minutes_in_status: (now - since).num_minutes(),
+                tokens_per_minute: burn.get(&session.session_id).copied().unwrap_or(0.0),
+            };
+            for rule in rules.iter().filter(|r| r.enabled && ctx.matches(&r.conditions)) {
+                let rule_session = (rule.id.clone(), id.clone());
+                if !self.firing.contains(&rule_session) {
+                    let entry = alerts.entry(id.clone()).or_insert_with(|| {
+                        let transition = Transition {
+                            session: session.clone(),
+                            from: self.last.get(&id).map(|s| s.status.clone()),
+                            to: session.status.clone(),
+                        };
+                        (transition, BTreeMap::new())
+                    });
+                    for sink in &rule.sinks {
+                        entry.1.entry(sink.clone()).or_insert_with(|| rule.id.clone());
+                    }
+                }
+                firing.insert(rule_session);
+            }
+        }
+
+        self.firing = firing;
+        self.last = sessions.iter().map(|s| (key(s), s.clone())).collect();
+        self.status_since.retain(|id, _| self.last.contains_key(id));
+        alerts.into_values().collect()
+    }
+}
+
+/// Tokens per minute over the burn window for each running session, or an
+/// empty map when no rule needs it.
+pub async fn burn_rates(state: &AppState, rules: &[AlertRule]) -> HashMap<String, f64> {
+    if !rules.iter().any(|r| r.enabled && r.conditions.min_tokens_per_minute.is_some()) {
+        return HashMap::new();
+    }
+    let since = Utc::now() - chrono::Duration::minutes(BURN_WINDOW_MINUTES);
+    let rows = match state.store.get_active_usage_since(since).await {
+        Ok(rows) => rows,
+        Err(e) => {
+            warn!("Failed to fetch usage for alert rules: {e}");
+            return HashMap::new();
+        }
+    };
+    let mut rates: HashMap<String, f64> = HashMap::new();
+    for row in rows {
+        let tokens = (row.usage.input_tokens + row.usage.output_tokens) as f64;
+        *rates.entry(row.session_id).or_default() += tokens / BURN_WINDOW_MINUTES as f64;
+    }
+    rates
+}
+
+fn validate(state: &AppState, rule: &AlertRuleInput) -> Result<(), String> {
+    if rule.name.trim().is_empty() {
+        return Err("`name` must not be empty".to_string());
+    }
+    if rule.sinks.is_empty() {
+        return Err("`sinks` must name at least one sink".to_string());
+    }
+    let configured = notifier::sink_names(state);
+    if let Some(unknown) = rule.sinks.iter().find(|s| !configured.contains(s)) {
+        return Err(format!("unknown sink '{unknown}'; configured sinks: {}", configured.join(", ")));
+    }
+    if let Some(statuses) = &rule.conditions.status {
+        if let Some(bad) = statuses.iter().find(|s| !STATUSES.contains(&s.as_str())) {
+            return Err(format!("unknown status '{bad}'; expected one of {}", STATUSES.join(", ")));
+        }
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/rules",
+    tag = "rules",
+    responses(
+        (status = 200, body = Vec<AlertRule>),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn list_rules(State(state): State<AppState>) -> impl IntoResponse {
+    match state.store.get_alert_rules().await {
+        Ok(rules) => Json(rules).into_response(),
+        Err(e) => {
+            warn!("list_rules error: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/rules",
+    tag = "rules",
+    request_body = AlertRuleInput,
+    responses(
+        (status = 201, body = AlertRule),
+        (status = 400, description = "Invalid rule", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn create_rule(State(state): State<AppState>, Json(rule): Json<AlertRuleInput>) -> impl IntoResponse {
+    if let Err(e) = validate(&state, &rule) {
+        return (StatusCode::BAD_REQUEST, Json(json!({"error": e}))).into_response();
+    }
+    match state.store.insert_alert_rule(&rule).await {
+        Ok(rule) => (StatusCode::CREATED, Json(rule)).into_response(),
+        Err(e) => {
+            warn!("create_rule error: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/rules/{rule_id}",
+    tag = "rules",
+    params(("rule_id" = String, Path, description = "Rule ID")),
+    responses(
+        (status = 200, body = AlertRule),
+        (status = 404, description = "No such rule", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn get_rule(State(state): State<AppState>, Path(id): Path<String>) -> impl IntoResponse {
+    match state.store.get_alert_rule(&id).await {
+        Ok(Some(rule)) => Json(rule).into_response(),
+        Ok(None) => (StatusCode::NOT_FOUND, Json(json!({"error": "rule not found"}))).into_response(),
+        Err(e) => {
+            warn!("get_rule error: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/rules/{rule_id}",
+    tag = "rules",
+    params(("rule_id" = String, Path, description = "Rule ID")),
+    request_body = AlertRuleInput,
+    responses(
+        (status = 200, body = AlertRule),
+        (status = 400, description = "Invalid rule", body = ErrorResponse),
+        (status = 404, description = "No such rule", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn update_rule(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(rule): Json<AlertRuleInput>,
+) -> impl IntoResponse {
+    if let Err(e) = validate(&state, &rule) {
+        return (StatusCode::BAD_REQUEST, Json(json!({"error": e}))).into_response();
+    }
+    match state.store.update_alert_rule(&id, &rule).await {
+        Ok(Some(rule)) => Json(rule).into_response(),
+        Ok(None) => (StatusCode::NOT_FOUND, Json(json!({"error": "rule not found"}))).into_response(),
+        Err(e) => {
+            warn!("update_rule error: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/rules/{rule_id}",
+    tag = "rules",
+    params(("rule_id" = String, Path, description = "Rule ID")),
+    responses(
+        (status = 200, description = "Rule deleted"),
+        (status = 404, description = "No such rule", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn delete_rule(State(state): State<AppState>, Path(id): Path<String>) -> impl IntoResponse {
+    match state.store.delete_alert_rule(&id).await {
+        Ok(true) => StatusCode::OK.into_response(),
+        Ok(false) => (StatusCode::NOT_FOUND, Json(json!({"error": "rule not found"}))).into_response(),
+        Err(e) => {
+            warn!("delete_rule error: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response()
+        }
+    }
+}
@@ -0,0 +1,126 @@
+//! Full-text search over events at `/api/search`, backed by the `events_fts`
+//! index of tool names and messages. Only events still in the database are
+//! searched; cleanup removes those of completed sessions.
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::warn;
+use utoipa::IntoParams;
+
+use crate::{
+    api::{AppState, HostFilter},
+    models::{ErrorResponse, SearchResponse},
+};
+
+const MAX_RESULTS: u32 = 100;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchQuery {
+    /// Words to find, all of them, in any order; `"…"` matches a phrase.
+    pub q: String,
+    /// At most 100.
+    #[serde(default = "default_limit")]
+    pub limit: u32,
+}
+
+fn default_limit() -> u32 {
+    20
+}
+
+/// `q` as an FTS5 query: each word or `"…"` phrase becomes a quoted string,
+/// so punctuation such as `migrations.rs` is matched rather than parsed.
+fn fts_query(q: &str) -> String {
+    q.split('"')
+        .enumerate()
+        .flat_map(|(i, part)| {
+            // Odd parts were inside quotes.
+            if i % 2 == 1 {
+                vec![part]
+            } else {
+                part.split_whitespace().collect()
+            }
+        })
+        .filter(|term| !term.trim().is_empty())
+        .map(|term| format!("\"{term}\""))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/search",
+    tag = "search",
+    params(SearchQuery, HostFilter),
+    responses(
+        (status = 200, body = SearchResponse),
+        (status = 400, description = "Empty query", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn search(
+    State(state): State<AppState>,
+    Query(query): Query<SearchQuery>,
+    Query(hosts): Query<HostFilter>,
+) -> impl IntoResponse {
+    let fts = fts_query(&query.q);
+    if fts.is_empty() {
+        return (StatusCode::BAD_REQUEST, Json(json!({"error": "`q` must not be empty"}))).into_response();
+    }
+    let limit = query.limit.clamp(1, MAX_RESULTS);
+    match state.store.search_events(&fts, hosts.host.as_deref(), limit).await {
+        Ok(hits) => Json(SearchResponse { query: query.q, hits }).into_response(),
+        Err(e) => {
+            warn!("search error: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::{self, event};
+
+    #[tokio::test]
+    async fn finds_events_by_message() {
+        let server = test_support::spawn().await;
+        let client = &server.client;
+        let mut note = event("notification", "s1");
+        note.message = Some("Need approval for migrations.rs".to_string());
+        client.post_event(&note).await.unwrap();
+        client.post_event(&event("stop", "s2")).await.unwrap();
+        server.flushed().await;
+
+        let found = client.search("approval", 10, None).await.unwrap();
+        assert_eq!(found.hits.len(), 1);
+        assert_eq!(found.hits[0].session_id, "s1");
+        assert!(found.hits[0].snippet.contains("approval"));
+
+        assert!(client.search("   ", 10, None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn hits_keep_to_their_own_host() {
+        let server = test_support::spawn().await;
+        let client = &server.client;
+        for (host, project) in [("laptop", "alpha"), ("server", "beta")] {
+            let mut note = event("notification", "s1");
+            note.host = Some(host.to_string());
+            note.project_name = Some(project.to_string());
+            note.message = Some(format!("Need approval on {host}"));
+            client.post_event(&note).await.unwrap();
+        }
+        server.flushed().await;
+
+        let found = client.search("approval", 10, None).await.unwrap();
+        let mut hits: Vec<_> = found.hits.iter().map(|h| (h.host.as_str(), h.project_name.as_str())).collect();
+        hits.sort();
+        assert_eq!(hits, [("laptop", "alpha"), ("server", "beta")]);
+    }
+}
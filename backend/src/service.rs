@@ -0,0 +1,179 @@
+//! `claude-monitor service`: run the server at login as a systemd user unit
+//! on Linux or a launchd agent on macOS.
+//!
+//! Logs go to the journal (`journalctl --user -u claude-monitor`) or, through
+//! `logger`, to unified logging (`log stream --predicate 'process == "logger"'`).
+
+use anyhow::{bail, Context, Result};
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::cli::ServiceCommand;
+
+const UNIT_NAME: &str = "claude-monitor.service";
+const LAUNCHD_LABEL: &str = "io.github.jinhedman.claude-monitor";
+
+enum Manager {
+    Systemd,
+    Launchd,
+}
+
+impl Manager {
+    fn current() -> Result<Self> {
+        if cfg!(target_os = "macos") {
+            Ok(Self::Launchd)
+        } else if cfg!(target_os = "linux") {
+            Ok(Self::Systemd)
+        } else {
+            bail!("services are only supported on Linux (systemd) and macOS (launchd)")
+        }
+    }
+
+    fn definition_path(&self) -> Result<PathBuf> {
+        let home = dirs::home_dir().context("could not determine home directory")?;
+        Ok(match self {
+            Self::Systemd => home.join(".config/systemd/user").join(UNIT_NAME),
+            Self::Launchd => home.join("Library/LaunchAgents").join(format!("{LAUNCHD_LABEL}.plist")),
+        })
+    }
+
+    fn definition(&self, exe: &Path) -> String {
+        let exe = exe.display();
+        match self {
+            Self::Systemd => format!(
+                "[Unit]\n\
+                 Description=Claude session monitor\n\
+                 After=network.target\n\
+                 \n\
+                 [Service]\n\
+                 ExecStart=\"{exe}\"\n\
+                 Restart=on-failure\n\
+                 RestartSec=5\n\
+                 Environment=NO_COLOR=1\n\
+                 \n\
+                 [Install]\n\
+                 WantedBy=default.target\n"
+            ),
+            // launchd only writes stdout to files, so pipe it into unified logging.
+            Self::Launchd => format!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{LAUNCHD_LABEL}</string>
+    <key>ProgramArguments</key>
+    <array>
+        <string>/bin/sh</string>
+        <string>-c</string>
+        <string>'{exe}' 2&gt;&amp;1 | /usr/bin/logger -t claude-monitor</string>
+    </array>
+    <key>EnvironmentVariables</key>
+    <dict>
+        <key>NO_COLOR</key>
+        <string>1</string>
+    </dict>
+    <key>RunAtLoad</key>
+    <true/>
+    <key>KeepAlive</key>
+    <dict>
+        <key>SuccessfulExit</key>
+        <false/>
+    </dict>
+</dict>
+</plist>
+"#
+            ),
+        }
+    }
+}
+
+/// Run a service manager command, failing with its output if it fails.
+fn run(program: &str, args: &[&str]) -> Result<()> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .with_context(|| format!("failed to run {program}"))?;
+    if !output.status.success() {
+        bail!(
+            "`{program} {}` failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+fn launchd_domain() -> Result<String> {
+    let output = Command::new("id").arg("-u").output().context("failed to run id")?;
+    Ok(format!("gui/{}", String::from_utf8_lossy(&output.stdout).trim()))
+}
+
+pub fn run_command(command: &ServiceCommand) -> Result<()> {
+    let manager = Manager::current()?;
+    let path = manager.definition_path()?;
+    match command {
+        ServiceCommand::Install { dry_run } => {
+            let exe = std::env::current_exe().context("could not locate the claude-monitor binary")?;
+            let definition = manager.definition(&exe);
+            if *dry_run {
+                print!("Would write {}:\n{definition}", path.display());
+                return Ok(());
+            }
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+            }
+            std::fs::write(&path, definition).with_context(|| format!("failed to write {}", path.display()))?;
+            match manager {
+                Manager::Systemd => {
+                    run("systemctl", &["--user", "daemon-reload"])?;
+                    run("systemctl", &["--user", "enable", "--now", UNIT_NAME])?;
+                }
+                Manager::Launchd => {
+                    let domain = launchd_domain()?;
+                    // Reinstalling replaces a loaded agent; bootout fails harmlessly if none is.
+                    let _ = run("launchctl", &["bootout", &format!("{domain}/{LAUNCHD_LABEL}")]);
+                    run("launchctl", &["bootstrap", &domain, &path.display().to_string()])?;
+                }
+            }
+            println!("Installed {} and started the server", path.display());
+        }
+        ServiceCommand::Uninstall => {
+            if !path.exists() {
+                println!("No service installed at {}", path.display());
+                return Ok(());
+            }
+            match manager {
+                Manager::Systemd => {
+                    run("systemctl", &["--user", "disable", "--now", UNIT_NAME])?;
+                    std::fs::remove_file(&path)?;
+                    run("systemctl", &["--user", "daemon-reload"])?;
+                }
+                Manager::Launchd => {
+                    let _ = run("launchctl", &["bootout", &format!("{}/{LAUNCHD_LABEL}", launchd_domain()?)]);
+                    std::fs::remove_file(&path)?;
+                }
+            }
+            println!("Removed {}", path.display());
+        }
+        ServiceCommand::Status => {
+            if !path.exists() {
+                println!("Not installed ({} is missing)", path.display());
+                return Ok(());
+            }
+            // Passed through as-is: both tools already print a readable summary.
+            let status = match manager {
+                Manager::Systemd => Command::new("systemctl")
+                    .args(["--user", "status", "--no-pager", UNIT_NAME])
+                    .status(),
+                Manager::Launchd => Command::new("launchctl")
+                    .args(["print", &format!("{}/{LAUNCHD_LABEL}", launchd_domain()?)])
+                    .status(),
+            };
+            status.context("failed to query the service manager")?;
+        }
+    }
+    Ok(())
+}
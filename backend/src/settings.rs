@@ -0,0 +1,64 @@
+//! Runtime settings under `/api/settings`, persisted in the `settings` table
+//! so they survive restarts. Config-file values are reported read-only,
+//! except the cleanup schedule's, which these override.
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use serde_json::json;
+use tracing::{info, warn};
+
+use crate::{
+    api::AppState,
+    models::{ErrorResponse, Settings, SettingsUpdate},
+    notifier::quiet,
+};
+
+async fn current(state: &AppState) -> Settings {
+    let quiet_hours = state.config.notifications.quiet_hours;
+    let cleanup = state.cleanup.current();
+    Settings {
+        dnd: quiet::dnd_enabled(state.store.as_ref()).await,
+        quiet_hours,
+        suppressed: quiet::suppressed(state.store.as_ref(), quiet_hours.as_ref()).await,
+        cleanup_interval_secs: cleanup.interval_secs,
+        completed_ttl_secs: cleanup.completed_ttl_secs,
+    }
+}
+
+#[utoipa::path(get, path = "/api/settings", tag = "settings", responses((status = 200, body = Settings)))]
+pub async fn get_settings(State(state): State<AppState>) -> impl IntoResponse {
+    Json(current(&state).await)
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/settings",
+    tag = "settings",
+    request_body = SettingsUpdate,
+    responses(
+        (status = 200, description = "Settings after the update", body = Settings),
+        (status = 400, description = "`cleanup_interval_secs` is 0", body = ErrorResponse),
+        (status = 401, description = "Neither the listener's token nor an admin key", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn update_settings(State(state): State<AppState>, Json(update): Json<SettingsUpdate>) -> impl IntoResponse {
+    if update.cleanup_interval_secs == Some(0) {
+        let error = "cleanup_interval_secs must be at least 1";
+        return (StatusCode::BAD_REQUEST, Json(json!({"error": error}))).into_response();
+    }
+    if let Some(dnd) = update.dnd {
+        if let Err(e) = state.store.set_setting(quiet::DND_KEY, &dnd.to_string()).await {
+            warn!("update_settings error: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response();
+        }
+        info!("Do-not-disturb {}", if dnd { "on" } else { "off" });
+        // Wakes the notifier so alerts held during DND are summarized now.
+        state.broadcast_sessions();
+    }
+    let cleanup = state.cleanup.update(state.store.as_ref(), update.cleanup_interval_secs, update.completed_ttl_secs);
+    if let Err(e) = cleanup.await {
+        warn!("update_settings error: {e}");
+        return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response();
+    }
+    Json(current(&state).await).into_response()
+}
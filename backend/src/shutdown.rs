@@ -0,0 +1,64 @@
+//! Stopping on Ctrl-C or SIGTERM: the listeners stop accepting connections
+//! and finish the requests they have, waiting up to `GRACE` for streams,
+//! then the event writer stores whatever is still queued.
+
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+/// How long open connections, such as WebSocket and SSE streams, have to end
+/// once stopping.
+pub const GRACE: Duration = Duration::from_secs(5);
+
+/// Resolves, through [`Shutdown::requested`], once the server is to stop.
+#[derive(Clone)]
+pub struct Shutdown(watch::Receiver<bool>);
+
+impl Shutdown {
+    /// Stop on the first Ctrl-C or SIGTERM.
+    pub fn on_signal() -> Self {
+        let (tx, rx) = watch::channel(false);
+        tokio::spawn(async move {
+            signal().await;
+            info!("Shutting down");
+            let _ = tx.send(true);
+        });
+        Self(rx)
+    }
+
+    /// Never stop, for listeners that live as long as a test.
+    #[cfg(test)]
+    pub fn never() -> Self {
+        Self(watch::channel(false).1)
+    }
+
+    pub async fn requested(mut self) {
+        // The sender is only dropped without sending by `never`.
+        if self.0.wait_for(|stopping| *stopping).await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
+#[cfg(unix)]
+async fn signal() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    match signal(SignalKind::terminate()) {
+        Ok(mut terminate) => {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {}
+                _ = terminate.recv() => {}
+            }
+        }
+        Err(e) => {
+            warn!("can't listen for SIGTERM, stopping on Ctrl-C only: {e}");
+            let _ = tokio::signal::ctrl_c().await;
+        }
+    }
+}
+
+#[cfg(not(unix))]
+async fn signal() {
+    let _ = tokio::signal::ctrl_c().await;
+}
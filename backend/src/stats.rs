@@ -0,0 +1,757 @@
+//! Aggregate statistics endpoints under `/api/stats`.
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, Timelike, Utc};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use tracing::warn;
+use utoipa::IntoParams;
+
+use crate::{
+    api::{self, AppState, HostFilter},
+    models::{
+        ActiveInterval, ActivityHeatmap, BurnRate, CompletedSession, ConcurrencyLevel, ConcurrencyStats, CostStats,
+        DailyActivity, DailyStats, DailyStatsResponse, DailyTotals, DurationStats, ErrorResponse, EventRow, HeatmapCell,
+        ModelCost, ProjectCost, ProjectStats, ProjectStatsResponse, ProjectSummary, SessionBurnRate, SessionWithAgents,
+        StatsSummary, TokenUsage,
+    },
+};
+
+/// Span of the event-rate figures in GET /api/stats.
+const EVENT_WINDOW_MINUTES: i64 = 60;
+
+/// Agents of `session` still running; a session without agent rows counts
+/// its main agent.
+fn active_agents(session: &SessionWithAgents) -> usize {
+    if session.agents.is_empty() {
+        usize::from(session.status != "completed")
+    } else {
+        session.agents.iter().filter(|a| a.status != "completed").count()
+    }
+}
+
+/// Session, agent and event counts for a status ribbon, without fetching
+/// the sessions themselves.
+#[utoipa::path(
+    get,
+    path = "/api/stats",
+    tag = "stats",
+    params(HostFilter),
+    responses((status = 200, body = StatsSummary), (status = 500, description = "Database error", body = ErrorResponse))
+)]
+pub async fn get_summary(State(state): State<AppState>, Query(hosts): Query<HostFilter>) -> impl IntoResponse {
+    let since = Utc::now() - Duration::minutes(EVENT_WINDOW_MINUTES);
+    let (sessions, event_counts) =
+        match tokio::try_join!(state.active_sessions(), state.store.get_event_counts_since(since)) {
+            Ok(results) => results,
+            Err(e) => {
+                warn!("get_summary error: {e}");
+                return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response();
+            }
+        };
+
+    let mut summary = StatsSummary {
+        sessions: 0,
+        by_status: BTreeMap::new(),
+        agents: 0,
+        active_agents: 0,
+        projects: Vec::new(),
+        events_last_hour: 0,
+        events_per_minute: 0.0,
+        events_by_minute: vec![0; EVENT_WINDOW_MINUTES as usize],
+    };
+    let mut projects: BTreeMap<String, ProjectSummary> = BTreeMap::new();
+    for session in sessions.iter().filter(|s| hosts.allows(&s.host)) {
+        let agents = session.agents.len().max(1) as i64;
+        let active = active_agents(session) as i64;
+        summary.sessions += 1;
+        *summary.by_status.entry(session.status.clone()).or_default() += 1;
+        summary.agents += agents;
+        summary.active_agents += active;
+
+        let project = projects.entry(session.project_name.clone()).or_insert_with(|| ProjectSummary {
+            project_name: session.project_name.clone(),
+            sessions: 0,
+            agents: 0,
+            active_agents: 0,
+        });
+        project.sessions += 1;
+        project.agents += agents;
+        project.active_agents += active;
+    }
+    summary.projects = projects.into_values().collect();
+    summary.projects.sort_by_key(|p| std::cmp::Reverse(p.sessions));
+
+    for row in event_counts.iter().filter(|r| hosts.allows(&r.host)) {
+        // Rounding can put an event from this very moment one past the end.
+        let minute = row.minute.clamp(0, EVENT_WINDOW_MINUTES - 1) as usize;
+        summary.events_by_minute[minute] += row.events;
+        summary.events_last_hour += row.events;
+    }
+    summary.events_per_minute = summary.events_last_hour as f64 / EVENT_WINDOW_MINUTES as f64;
+
+    Json(summary).into_response()
+}
+
+/// Estimated spend across every session still in the database, broken down by
+/// model and by project.
+#[utoipa::path(
+    get,
+    path = "/api/stats/costs",
+    tag = "stats",
+    params(HostFilter),
+    responses((status = 200, body = CostStats), (status = 500, description = "Database error", body = ErrorResponse))
+)]
+pub async fn get_costs(State(state): State<AppState>, Query(hosts): Query<HostFilter>) -> impl IntoResponse {
+    let rows = match state.store.get_usage_by_session_model(None, None).await {
+        Ok(rows) => rows.into_iter().filter(|r| hosts.allows(&r.host)).collect::<Vec<_>>(),
+        Err(e) => {
+            warn!("get_costs error: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response();
+        }
+    };
+
+    let pricing = &state.config.pricing;
+    let mut by_model: BTreeMap<String, (TokenUsage, f64)> = BTreeMap::new();
+    let mut by_project: BTreeMap<String, (HashSet<String>, f64)> = BTreeMap::new();
+    let mut total_usd = 0.0;
+
+    for row in rows {
+        let cost = pricing.cost(row.model.as_deref(), &row.usage);
+        total_usd += cost;
+
+        let model = row.model.unwrap_or_else(|| "unknown".to_string());
+        let entry = by_model.entry(model).or_default();
+        entry.0 = entry.0.add(&row.usage);
+        entry.1 += cost;
+
+        let project = by_project.entry(row.project_name).or_default();
+        project.0.insert(row.session_id);
+        project.1 += cost;
+    }
+
+    let mut by_model: Vec<ModelCost> = by_model
+        .into_iter()
+        .map(|(model, (usage, cost_usd))| ModelCost { model, usage, cost_usd })
+        .collect();
+    by_model.sort_by(|a, b| b.cost_usd.total_cmp(&a.cost_usd));
+
+    let mut by_project: Vec<ProjectCost> = by_project
+        .into_iter()
+        .map(|(project_name, (sessions, cost_usd))| ProjectCost {
+            project_name,
+            sessions: sessions.len() as i64,
+            cost_usd,
+        })
+        .collect();
+    by_project.sort_by(|a, b| b.cost_usd.total_cmp(&a.cost_usd));
+
+    Json(CostStats {
+        total_usd,
+        by_model,
+        by_project,
+    })
+    .into_response()
+}
+
+/// `?from=&to=` query shared by the range-based stats endpoints. Accepts
+/// RFC 3339 timestamps or plain `YYYY-MM-DD` dates; a date in `to` includes
+/// that whole day.
+/// Parsed `(from, to)` bounds; `None` leaves that side open.
+pub type Bounds = (Option<DateTime<Utc>>, Option<DateTime<Utc>>);
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DateRange {
+    /// Start, inclusive: `YYYY-MM-DD` or RFC 3339.
+    pub from: Option<String>,
+    /// End, exclusive; a plain date includes that day.
+    pub to: Option<String>,
+}
+
+impl DateRange {
+    pub fn parse(&self) -> Result<Bounds, String> {
+        let from = self.from.as_deref().map(|s| parse_bound(s, false)).transpose()?;
+        let to = self.to.as_deref().map(|s| parse_bound(s, true)).transpose()?;
+        if let (Some(from), Some(to)) = (from, to) {
+            if from >= to {
+                return Err("`from` must be before `to`".to_string());
+            }
+        }
+        Ok((from, to))
+    }
+}
+
+/// A range bound: RFC 3339, or a UTC date meaning its start, or with
+/// `end_of_day` the start of the next day.
+pub fn parse_bound(raw: &str, end_of_day: bool) -> Result<DateTime<Utc>, String> {
+    if let Ok(ts) = DateTime::parse_from_rfc3339(raw) {
+        return Ok(ts.with_timezone(&Utc));
+    }
+    let date = NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+        .map_err(|_| format!("invalid date '{raw}': expected YYYY-MM-DD or RFC 3339"))?;
+    let date = if end_of_day { date + Duration::days(1) } else { date };
+    Ok(date.and_time(NaiveTime::MIN).and_utc())
+}
+
+/// Whether two events, ordered per session, belong to the same session.
+fn same_session(a: &EventRow, b: &EventRow) -> bool {
+    a.host == b.host && a.session_id == b.session_id
+}
+
+/// Seconds per session, keyed by (host, session_id), spent in `status` within
+/// `[from, to)`, replayed from event history. A session whose last event left
+/// it in `status` counts up to `to` (or now).
+pub fn seconds_in_status(
+    events: &[EventRow],
+    status: &str,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+) -> HashMap<(String, String), i64> {
+    let end = to.unwrap_or_else(Utc::now).min(Utc::now());
+    let mut totals: HashMap<(String, String), i64> = HashMap::new();
+
+    for session in events.chunk_by(same_session) {
+        let mut seconds = 0;
+        for (i, event) in session.iter().enumerate() {
+            let (session_status, _) = api::statuses_for_event(&event.event_type, event.needs_input);
+            if session_status != status {
+                continue;
+            }
+            let next = session.get(i + 1).map_or(end, |e| e.timestamp.min(end));
+            let start = from.map_or(event.timestamp, |f| event.timestamp.max(f));
+            if next > start {
+                seconds += (next - start).num_seconds();
+            }
+        }
+        totals.insert((session[0].host.clone(), session[0].session_id.clone()), seconds);
+    }
+
+    totals
+}
+
+/// Stretches each session spent `active`, replayed from event history.
+/// Sessions not in `completed` count as running until now.
+pub fn active_intervals(events: &[EventRow], completed: &[CompletedSession]) -> Vec<ActiveInterval> {
+    let now = Utc::now();
+    let ended: HashMap<(&str, &str), DateTime<Utc>> =
+        completed.iter().map(|c| ((c.host.as_str(), c.session_id.as_str()), c.ended_at)).collect();
+    let mut intervals = Vec::new();
+
+    for session in events.chunk_by(same_session) {
+        let end = ended.get(&(session[0].host.as_str(), session[0].session_id.as_str())).map_or(now, |&t| t.min(now));
+        let mut push = |start: DateTime<Utc>, stop: DateTime<Utc>| {
+            if stop > start {
+                intervals.push(ActiveInterval {
+                    host: session[0].host.clone(),
+                    session_id: session[0].session_id.clone(),
+                    project_name: session[0].project_name.clone(),
+                    start,
+                    end: stop,
+                });
+            }
+        };
+        let mut start = None;
+        for event in session {
+            let active = api::statuses_for_event(&event.event_type, event.needs_input).0 == "active";
+            match (active, start) {
+                (true, None) => start = Some(event.timestamp.min(end)),
+                (false, Some(since)) => {
+                    push(since, event.timestamp.min(end));
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(since) = start {
+            push(since, end);
+        }
+    }
+
+    intervals
+}
+
+/// Per-day activity replayed from event history, keyed by (day, host):
+/// sessions with an event that day, time spent active, split at UTC
+/// midnight, and completions.
+pub fn daily_activity(events: &[EventRow], completed: &[CompletedSession]) -> DailyActivity {
+    let mut days = DailyActivity::new();
+
+    for session in events.chunk_by(same_session) {
+        let session_days: HashSet<NaiveDate> = session.iter().map(|e| e.timestamp.date_naive()).collect();
+        for day in session_days {
+            days.entry((day, session[0].host.clone())).or_default().sessions += 1;
+        }
+    }
+    for interval in active_intervals(events, completed) {
+        let mut start = interval.start;
+        while start < interval.end {
+            let midnight = (start.date_naive() + Duration::days(1)).and_time(NaiveTime::MIN).and_utc();
+            let stop = interval.end.min(midnight);
+            days.entry((start.date_naive(), interval.host.clone())).or_default().active_seconds +=
+                (stop - start).num_seconds();
+            start = stop;
+        }
+    }
+    for session in completed {
+        days.entry((session.ended_at.date_naive(), session.host.clone())).or_default().completions += 1;
+    }
+
+    days
+}
+
+/// Sessions, active minutes and completions per UTC day, for a calendar
+/// view. Combines the totals archived as sessions are cleaned up with those
+/// still in the database; every day in the range is listed, from the
+/// earliest with data if `from` is open, through today if `to` is.
+#[utoipa::path(
+    get,
+    path = "/api/stats/daily",
+    tag = "stats",
+    params(DateRange, HostFilter),
+    responses(
+        (status = 200, body = DailyStatsResponse),
+        (status = 400, description = "Invalid date range", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn get_daily(
+    State(state): State<AppState>,
+    Query(range): Query<DateRange>,
+    Query(hosts): Query<HostFilter>,
+) -> impl IntoResponse {
+    let (from, to) = match range.parse() {
+        Ok(range) => range,
+        Err(e) => return (StatusCode::BAD_REQUEST, Json(json!({"error": e}))).into_response(),
+    };
+
+    let (archived, events, completed) = match tokio::try_join!(
+        state.store.get_daily_stats(),
+        state.store.get_session_events_in_range(from, to),
+        state.store.get_completed_sessions(),
+    ) {
+        Ok(results) => results,
+        Err(e) => {
+            warn!("get_daily error: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response();
+        }
+    };
+
+    let mut totals: BTreeMap<NaiveDate, DailyTotals> = BTreeMap::new();
+    for ((day, host), day_totals) in archived.into_iter().chain(daily_activity(&events, &completed)) {
+        if !hosts.allows(&host) {
+            continue;
+        }
+        let entry = totals.entry(day).or_default();
+        entry.sessions += day_totals.sessions;
+        entry.active_seconds += day_totals.active_seconds;
+        entry.completions += day_totals.completions;
+    }
+
+    // A day is in range if any part of it is.
+    let today = Utc::now().date_naive();
+    let first = match from {
+        Some(from) => from.date_naive(),
+        None => totals.keys().next().copied().unwrap_or(today),
+    };
+    let last = match to {
+        Some(to) => (to - Duration::nanoseconds(1)).date_naive().min(today),
+        None => today,
+    };
+    let days = first
+        .iter_days()
+        .take_while(|day| *day <= last)
+        .map(|day| {
+            let day_totals = totals.remove(&day).unwrap_or_default();
+            DailyStats {
+                day,
+                sessions: day_totals.sessions,
+                active_minutes: (day_totals.active_seconds + 30) / 60,
+                completions: day_totals.completions,
+            }
+        })
+        .collect();
+
+    Json(DailyStatsResponse { from, to, days }).into_response()
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct HeatmapQuery {
+    /// Hours east of UTC to bucket weekdays and hours in, -12 to 14.
+    #[serde(default)]
+    pub utc_offset: i32,
+}
+
+/// Events by day-of-week and hour-of-day, for an activity heatmap. Combines
+/// the hourly counts archived as sessions are cleaned up with events still
+/// in the database; an hour is in range if it starts in it.
+#[utoipa::path(
+    get,
+    path = "/api/stats/heatmap",
+    tag = "stats",
+    params(DateRange, HeatmapQuery, HostFilter),
+    responses(
+        (status = 200, body = ActivityHeatmap),
+        (status = 400, description = "Invalid date range or offset", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn get_heatmap(
+    State(state): State<AppState>,
+    Query(range): Query<DateRange>,
+    Query(query): Query<HeatmapQuery>,
+    Query(hosts): Query<HostFilter>,
+) -> impl IntoResponse {
+    let (from, to) = match range.parse() {
+        Ok(range) => range,
+        Err(e) => return (StatusCode::BAD_REQUEST, Json(json!({"error": e}))).into_response(),
+    };
+    if !(-12..=14).contains(&query.utc_offset) {
+        return (StatusCode::BAD_REQUEST, Json(json!({"error": "`utc_offset` must be between -12 and 14"})))
+            .into_response();
+    }
+
+    let hours = match state.store.get_hourly_event_counts(from, to).await {
+        Ok(rows) => rows,
+        Err(e) => {
+            warn!("get_heatmap error: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response();
+        }
+    };
+
+    let mut counts = [[0i64; 24]; 7];
+    for row in hours.iter().filter(|r| hosts.allows(&r.host)) {
+        let local = row.hour + Duration::hours(query.utc_offset.into());
+        counts[local.weekday().num_days_from_monday() as usize][local.hour() as usize] += row.events;
+    }
+    let cells: Vec<HeatmapCell> = (0..7)
+        .flat_map(|weekday| (0..24).map(move |hour| (weekday, hour)))
+        .map(|(weekday, hour)| HeatmapCell { weekday, hour, events: counts[weekday as usize][hour as usize] })
+        .collect();
+
+    Json(ActivityHeatmap {
+        from,
+        to,
+        utc_offset: query.utc_offset,
+        total_events: cells.iter().map(|c| c.events).sum(),
+        max_events: cells.iter().map(|c| c.events).max().unwrap_or(0),
+        cells,
+    })
+    .into_response()
+}
+
+/// Range GET /api/stats/concurrency covers when `from` is left open.
+const DEFAULT_CONCURRENCY_HOURS: i64 = 24;
+
+/// How many sessions ran in parallel over the range, as a step function,
+/// with each session's active stretches. Defaults to the last 24 hours.
+#[utoipa::path(
+    get,
+    path = "/api/stats/concurrency",
+    tag = "stats",
+    params(DateRange, HostFilter),
+    responses(
+        (status = 200, body = ConcurrencyStats),
+        (status = 400, description = "Invalid date range", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn get_concurrency(
+    State(state): State<AppState>,
+    Query(range): Query<DateRange>,
+    Query(hosts): Query<HostFilter>,
+) -> impl IntoResponse {
+    let (from, to) = match range.parse() {
+        Ok(range) => range,
+        Err(e) => return (StatusCode::BAD_REQUEST, Json(json!({"error": e}))).into_response(),
+    };
+    let to = to.unwrap_or_else(Utc::now).min(Utc::now());
+    let from = from.unwrap_or(to - Duration::hours(DEFAULT_CONCURRENCY_HOURS));
+    if from >= to {
+        return (StatusCode::BAD_REQUEST, Json(json!({"error": "`from` must be before now"}))).into_response();
+    }
+
+    let (archived, events, completed) = match tokio::try_join!(
+        state.store.get_archived_intervals(from, to),
+        state.store.get_session_events_in_range(None, Some(to)),
+        state.store.get_completed_sessions(),
+    ) {
+        Ok(results) => results,
+        Err(e) => {
+            warn!("get_concurrency error: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response();
+        }
+    };
+
+    // Live sessions are replayed in full: one active since before `from`
+    // may have no event inside the range.
+    let mut intervals: Vec<ActiveInterval> = archived
+        .into_iter()
+        .chain(active_intervals(&events, &completed))
+        .filter(|i| hosts.allows(&i.host))
+        .filter_map(|mut i| {
+            i.start = i.start.max(from);
+            i.end = i.end.min(to);
+            (i.end > i.start).then_some(i)
+        })
+        .collect();
+    intervals.sort_by_key(|i| i.start);
+
+    // Sweep over start (+1) and end (-1) points; ends first at ties, so
+    // back-to-back stretches don't count as overlapping.
+    let mut points: Vec<(DateTime<Utc>, i64)> =
+        intervals.iter().flat_map(|i| [(i.start, 1), (i.end, -1)]).collect();
+    points.sort();
+    let mut levels: Vec<ConcurrencyLevel> = Vec::new();
+    let mut sessions = 0;
+    for (i, (at, delta)) in points.iter().enumerate() {
+        sessions += delta;
+        let Some((next, _)) = points.get(i + 1) else { break };
+        if sessions == 0 || next == at {
+            continue;
+        }
+        match levels.last_mut() {
+            Some(last) if last.end == *at && last.sessions == sessions => last.end = *next,
+            _ => levels.push(ConcurrencyLevel { start: *at, end: *next, sessions }),
+        }
+    }
+
+    Json(ConcurrencyStats {
+        from,
+        to,
+        max_concurrent: levels.iter().map(|l| l.sessions).max().unwrap_or(0),
+        levels,
+        intervals,
+    })
+    .into_response()
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DurationsQuery {
+    /// How many of the longest sessions to list, at most 100.
+    #[serde(default = "default_longest")]
+    pub longest: u32,
+}
+
+fn default_longest() -> u32 {
+    10
+}
+
+/// Nearest-rank percentile of ascending `sorted`.
+fn percentile(sorted: &[i64], p: f64) -> Option<i64> {
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted.get(rank.max(1) - 1).copied()
+}
+
+/// Duration percentiles, from start to completion, of sessions that
+/// completed in the range, and the longest of them.
+#[utoipa::path(
+    get,
+    path = "/api/stats/durations",
+    tag = "stats",
+    params(DateRange, DurationsQuery, HostFilter),
+    responses(
+        (status = 200, body = DurationStats),
+        (status = 400, description = "Invalid date range", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn get_durations(
+    State(state): State<AppState>,
+    Query(range): Query<DateRange>,
+    Query(query): Query<DurationsQuery>,
+    Query(hosts): Query<HostFilter>,
+) -> impl IntoResponse {
+    let (from, to) = match range.parse() {
+        Ok(range) => range,
+        Err(e) => return (StatusCode::BAD_REQUEST, Json(json!({"error": e}))).into_response(),
+    };
+
+    let mut sessions = match state.store.get_session_durations(from, to).await {
+        Ok(rows) => rows.into_iter().filter(|r| hosts.allows(&r.host)).collect::<Vec<_>>(),
+        Err(e) => {
+            warn!("get_durations error: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response();
+        }
+    };
+    sessions.sort_by_key(|s| std::cmp::Reverse(s.duration_seconds));
+
+    let mut sorted: Vec<i64> = sessions.iter().map(|s| s.duration_seconds).collect();
+    sorted.reverse();
+    sessions.truncate(query.longest.min(100) as usize);
+
+    Json(DurationStats {
+        from,
+        to,
+        sessions: sorted.len() as i64,
+        p50_seconds: percentile(&sorted, 50.0),
+        p90_seconds: percentile(&sorted, 90.0),
+        p99_seconds: percentile(&sorted, 99.0),
+        longest: sessions,
+    })
+    .into_response()
+}
+
+/// Tokens, estimated cost, session count, and active time per project.
+#[utoipa::path(
+    get,
+    path = "/api/stats/projects",
+    tag = "stats",
+    params(DateRange, HostFilter),
+    responses(
+        (status = 200, body = ProjectStatsResponse),
+        (status = 400, description = "Invalid date range", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn get_projects(
+    State(state): State<AppState>,
+    Query(range): Query<DateRange>,
+    Query(hosts): Query<HostFilter>,
+) -> impl IntoResponse {
+    let (from, to) = match range.parse() {
+        Ok(range) => range,
+        Err(e) => return (StatusCode::BAD_REQUEST, Json(json!({"error": e}))).into_response(),
+    };
+
+    let (usage, events) = match tokio::try_join!(
+        state.store.get_usage_by_session_model(from, to),
+        state.store.get_session_events_in_range(from, to),
+    ) {
+        Ok((usage, events)) => (
+            usage.into_iter().filter(|r| hosts.allows(&r.host)).collect::<Vec<_>>(),
+            events.into_iter().filter(|e| hosts.allows(&e.host)).collect::<Vec<_>>(),
+        ),
+        Err(e) => {
+            warn!("get_projects error: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response();
+        }
+    };
+
+    // A project's sessions, as (host, session_id), and totals.
+    type Project<'a> = (HashSet<(&'a str, &'a str)>, ProjectStats);
+    fn project_entry<'p, 'a>(projects: &'p mut BTreeMap<&'a str, Project<'a>>, name: &'a str) -> &'p mut Project<'a> {
+        projects.entry(name).or_insert_with(|| {
+            let stats = ProjectStats {
+                project_name: name.to_string(),
+                sessions: 0,
+                usage: TokenUsage::default(),
+                cost_usd: 0.0,
+                active_seconds: 0,
+            };
+            (HashSet::new(), stats)
+        })
+    }
+    let mut projects: BTreeMap<&str, Project> = BTreeMap::new();
+
+    for row in &usage {
+        let (sessions, stats) = project_entry(&mut projects, &row.project_name);
+        sessions.insert((&row.host, &row.session_id));
+        stats.usage = stats.usage.add(&row.usage);
+        stats.cost_usd += state.config.pricing.cost(row.model.as_deref(), &row.usage);
+    }
+
+    let active = seconds_in_status(&events, "active", from, to);
+    for session in events.chunk_by(same_session) {
+        let event = &session[0];
+        let (sessions, stats) = project_entry(&mut projects, &event.project_name);
+        sessions.insert((&event.host, &event.session_id));
+        stats.active_seconds += active.get(&(event.host.clone(), event.session_id.clone())).copied().unwrap_or(0);
+    }
+
+    let mut projects: Vec<ProjectStats> = projects
+        .into_values()
+        .map(|(sessions, mut stats)| {
+            stats.sessions = sessions.len() as i64;
+            stats
+        })
+        .collect();
+    projects.sort_by(|a, b| b.cost_usd.total_cmp(&a.cost_usd).then(b.active_seconds.cmp(&a.active_seconds)));
+
+    Json(ProjectStatsResponse { from, to, projects }).into_response()
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct BurnRateQuery {
+    /// Look-back window in minutes.
+    #[serde(default = "default_burn_minutes")]
+    pub minutes: u32,
+}
+
+fn default_burn_minutes() -> u32 {
+    10
+}
+
+/// Tokens and estimated spend per minute over the last `minutes`, across
+/// sessions that are still running.
+#[utoipa::path(
+    get,
+    path = "/api/stats/burn-rate",
+    tag = "stats",
+    params(BurnRateQuery, HostFilter),
+    responses((status = 200, body = BurnRate), (status = 500, description = "Database error", body = ErrorResponse))
+)]
+pub async fn get_burn_rate(
+    State(state): State<AppState>,
+    Query(query): Query<BurnRateQuery>,
+    Query(hosts): Query<HostFilter>,
+) -> impl IntoResponse {
+    if query.minutes == 0 {
+        return (StatusCode::BAD_REQUEST, Json(json!({"error": "`minutes` must be at least 1"}))).into_response();
+    }
+    let since = Utc::now() - Duration::minutes(query.minutes.into());
+
+    let rows = match state.store.get_active_usage_since(since).await {
+        Ok(rows) => rows.into_iter().filter(|r| hosts.allows(&r.host)).collect::<Vec<_>>(),
+        Err(e) => {
+            warn!("get_burn_rate error: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response();
+        }
+    };
+
+    let minutes = f64::from(query.minutes);
+    let mut sessions: BTreeMap<String, SessionBurnRate> = BTreeMap::new();
+    for row in rows {
+        let tokens = row.usage.input_tokens + row.usage.output_tokens;
+        let cost = state.config.pricing.cost(row.model.as_deref(), &row.usage);
+        let entry = sessions.entry(row.session_id.clone()).or_insert_with(|| SessionBurnRate {
+            session_id: row.session_id,
+            project_name: row.project_name,
+            tokens: 0,
+            tokens_per_minute: 0.0,
+            cost_usd_per_minute: 0.0,
+        });
+        entry.tokens += tokens;
+        entry.cost_usd_per_minute += cost / minutes;
+    }
+
+    let mut sessions: Vec<SessionBurnRate> = sessions
+        .into_values()
+        .filter(|s| s.tokens > 0)
+        .map(|mut s| {
+            s.tokens_per_minute = s.tokens as f64 / minutes;
+            s
+        })
+        .collect();
+    sessions.sort_by_key(|s| std::cmp::Reverse(s.tokens));
+
+    let tokens = sessions.iter().map(|s| s.tokens).sum::<i64>();
+    Json(BurnRate {
+        minutes: query.minutes,
+        since,
+        tokens,
+        tokens_per_minute: tokens as f64 / minutes,
+        cost_usd_per_minute: sessions.iter().map(|s| s.cost_usd_per_minute).sum(),
+        sessions,
+    })
+    .into_response()
+}
@@ -0,0 +1,79 @@
+//! `claude-monitor status`: a table of active sessions from a running server,
+//! for a quick look without opening the overlay.
+
+use anyhow::{anyhow, Result};
+use claude_monitor_client::{self as client, Client};
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+
+use crate::{cli::StatusArgs, listen::Target, models::SessionWithAgents};
+
+/// Widest a last-message cell gets before it is cut off.
+const MESSAGE_WIDTH: usize = 60;
+
+pub async fn run(args: &StatusArgs) -> Result<()> {
+    let target = Target::resolve(args.server.as_deref());
+    let client = Client::with_http(&target.url, target.http(Duration::from_secs(5)))?;
+    let sessions = client.sessions(&Default::default()).await.map_err(|e| match e {
+        client::Error::Http(e) => anyhow!("could not reach the monitor at {}: {}", target.url, e.without_url()),
+        e => e.into(),
+    })?;
+
+    if sessions.is_empty() {
+        println!("No active sessions");
+        return Ok(());
+    }
+    print!("{}", render(&sessions, Utc::now()));
+    Ok(())
+}
+
+fn render(sessions: &[SessionWithAgents], now: DateTime<Utc>) -> String {
+    let header = ["PROJECT", "STATUS", "AGENTS", "ELAPSED", "LAST MESSAGE"].map(String::from);
+    let rows: Vec<[String; 5]> = sessions
+        .iter()
+        .map(|s| {
+            let message = s.last_message.as_deref().unwrap_or("").replace('\n', " ");
+            [
+                s.project_name.clone(),
+                s.status.clone(),
+                s.agents.len().max(1).to_string(),
+                elapsed(now - s.created_at),
+                truncate(message.trim(), MESSAGE_WIDTH),
+            ]
+        })
+        .collect();
+
+    let mut widths = header.clone().map(|h| h.chars().count());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut out = String::new();
+    for row in std::iter::once(&header).chain(&rows) {
+        let cells: Vec<String> = row.iter().zip(widths).map(|(cell, w)| format!("{cell:<w$}")).collect();
+        out.push_str(cells.join("  ").trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+/// `1h 05m`, `12m`, `40s`.
+pub fn elapsed(duration: chrono::Duration) -> String {
+    let secs = duration.num_seconds().max(0);
+    match (secs / 3600, secs % 3600 / 60) {
+        (0, 0) => format!("{secs}s"),
+        (0, m) => format!("{m}m"),
+        (h, m) => format!("{h}h {m:02}m"),
+    }
+}
+
+fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let mut cut: String = text.chars().take(width - 1).collect();
+    cut.push('…');
+    cut
+}
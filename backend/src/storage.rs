@@ -0,0 +1,147 @@
+//! A ceiling on the database's size. Once a minute the space in use is
+//! checked against the budget, and while it's over, the oldest events of
+//! finished sessions, completed, in the trash or removed by cleanup, are
+//! pruned, their counts kept in the hourly stats. Cleanup leaves the events
+//! of the sessions it removes to this. Live sessions are never touched, so
+//! a database of nothing but active sessions can still outgrow it.
+//! `GET /health` reports the size and the headroom left.
+//!
+//! ```toml
+//! [storage]
+//! max_mb = 500   # 0 turns the budget off
+//! ```
+//!
+//! Only SQLite is measured; Postgres keeps the space it frees, see
+//! `Store::used_size`.
+
+use serde::Deserialize;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::{api::AppState, store::Store};
+
+/// `[storage]` section of config.toml.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct StorageConfig {
+    /// Megabytes the database may use before archived events are pruned;
+    /// 0 for no limit.
+    pub max_mb: u64,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self { max_mb: 500 }
+    }
+}
+
+impl StorageConfig {
+    /// The budget in bytes, if there is one.
+    pub fn budget(&self) -> Option<u64> {
+        (self.max_mb > 0).then(|| self.max_mb * 1024 * 1024)
+    }
+}
+
+/// Events deleted per transaction, so pruning never holds the writer long.
+const BATCH: u32 = 1000;
+
+/// Check the budget at startup and every minute after.
+pub fn spawn(state: &AppState) {
+    let Some(budget) = state.config.storage.budget() else {
+        return;
+    };
+    let store = state.store.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(60));
+        let mut warned = false;
+        loop {
+            interval.tick().await;
+            match enforce(store.as_ref(), budget).await {
+                Ok(Some(over)) if !warned => {
+                    warn!("Database is {over} bytes over its budget with no archived events left to prune");
+                    warned = true;
+                }
+                Ok(Some(_)) => {}
+                Ok(None) => warned = false,
+                Err(e) => warn!("storage budget error: {e}"),
+            }
+        }
+    });
+}
+
+/// Prune archived events until the database is within `budget` bytes.
+/// Returns how far over it still is when there was nothing left to prune.
+async fn enforce(store: &dyn Store, budget: u64) -> anyhow::Result<Option<u64>> {
+    let mut pruned = 0;
+    let result = loop {
+        let Some(used) = store.used_size().await? else {
+            break None;
+        };
+        if used <= budget {
+            break None;
+        }
+        match store.prune_archived_events(BATCH).await? {
+            0 => break Some(used - budget),
+            deleted => pruned += deleted,
+        }
+    };
+    if pruned > 0 {
+        info!("Pruned {pruned} archived events to keep the database within {budget} bytes");
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::enforce;
+    use crate::test_support::{self, event};
+
+    #[tokio::test]
+    async fn only_archived_events_are_pruned() {
+        let server = test_support::spawn().await;
+        let client = &server.client;
+        client.post_event(&event("user_prompt_submit", "s1")).await.unwrap();
+        client.post_event(&event("stop", "s1")).await.unwrap();
+        client.post_event(&event("session_end", "s1")).await.unwrap();
+        client.post_event(&event("user_prompt_submit", "s2")).await.unwrap();
+        server.flushed().await;
+
+        // Nothing fits in a byte, so everything that may go, goes.
+        let over = enforce(server.state.store.as_ref(), 1).await.unwrap();
+        assert!(over.is_some());
+        assert!(client.session_events("s1", 100, None).await.unwrap().is_empty());
+        assert_eq!(client.session_events("s2", 100, None).await.unwrap().len(), 1);
+
+        let health = client.health().await.unwrap();
+        assert_eq!(health.db_budget_bytes, Some(500 * 1024 * 1024));
+        assert!(health.db_headroom_bytes.unwrap() > 0);
+    }
+
+    #[tokio::test]
+    async fn events_kept_by_cleanup_are_pruned_back_under_the_budget() {
+        let server = test_support::spawn().await;
+        let client = &server.client;
+        let store = server.state.store.as_ref();
+        for session in ["s1", "s2", "s3", "s4", "s5"] {
+            for _ in 0..100 {
+                let mut prompt = event("user_prompt_submit", session);
+                prompt.message = Some("x".repeat(4096));
+                client.post_event(&prompt).await.unwrap();
+            }
+            client.post_event(&event("session_end", session)).await.unwrap();
+        }
+        server.flushed().await;
+
+        let config = &server.state.config;
+        let report = store
+            .cleanup_old_completed(Some(chrono::Duration::zero()), config.quota.retention(), config.trash.retention(), true)
+            .await
+            .unwrap();
+        assert_eq!((report.sessions, report.events), (5, 0));
+        let used = store.used_size().await.unwrap().unwrap();
+
+        let budget = used - 1024 * 1024;
+        assert_eq!(enforce(store, budget).await.unwrap(), None);
+        assert!(store.used_size().await.unwrap().unwrap() <= budget);
+    }
+}
@@ -0,0 +1,777 @@
+//! Where sessions, events and everything derived from them are kept. The
+//! server talks to a `Store`; SQLite (`db.rs`) is the default, and builds with
+//! the `postgres` feature can use a shared Postgres database instead:
+//!
+//! ```text
+//! claude-monitor --db postgres://monitor@db.internal/monitor
+//! ```
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use futures::future::BoxFuture;
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+use crate::{
+    api::{self, SortKey},
+    models::{
+        ActiveInterval, AgentUsage, AlertRule, AlertRuleInput, ApiKey, CleanupReport, CompletedSession, DailyActivity,
+        DeadLetter, EditorSession, EventCountRow, EventRow, Export, ExportedAgent, ExportedEvent, ExportedSession,
+        ExportedSetting, HourlyEventCount, ImportCounts, ImportReport, MaintenanceReport, ModelUsageRow, NewEvent,
+        NotificationRecord, OnConflict, ProjectAlias, SearchHit, SessionDuration, SessionEvent, SessionSort,
+        SessionUsage, SessionWithAgents, SortOrder, TimelineEntry, TokenUsage, ToolStats, WindowUsage,
+    },
+    projects,
+};
+
+#[cfg(feature = "postgres")]
+mod postgres;
+mod sqlite;
+
+pub use sqlite::SqliteStore;
+
+/// Open the database `url` names: a `postgres://` URL, `memory` for an
+/// in-memory SQLite database, or otherwise a SQLite file path, `sessions.db`
+/// in `data_dir` when `url` is `None`. SQLite files checkpoint their WAL
+/// after `wal_autocheckpoint` pages.
+pub async fn open(url: Option<&str>, data_dir: &Path, wal_autocheckpoint: u32) -> Result<Arc<dyn Store>> {
+    match url {
+        Some(url) if url.starts_with("postgres://") || url.starts_with("postgresql://") => open_postgres(url).await,
+        Some("memory" | "sqlite::memory:") => Ok(Arc::new(SqliteStore::memory().await?)),
+        Some(path) => {
+            let path = Path::new(path.trim_start_matches("sqlite:"));
+            Ok(Arc::new(SqliteStore::open(path, wal_autocheckpoint).await?))
+        }
+        None => Ok(Arc::new(SqliteStore::open(&data_dir.join("sessions.db"), wal_autocheckpoint).await?)),
+    }
+}
+
+#[cfg(feature = "postgres")]
+async fn open_postgres(url: &str) -> Result<Arc<dyn Store>> {
+    Ok(Arc::new(postgres::PgStore::connect(url).await?))
+}
+
+#[cfg(not(feature = "postgres"))]
+async fn open_postgres(_url: &str) -> Result<Arc<dyn Store>> {
+    anyhow::bail!("this build has no Postgres support; rebuild with `--features postgres`")
+}
+
+/// Every query the server makes. Timestamps are UTC throughout; `limit`s and
+/// windows mean the same on every backend.
+pub trait Store: Send + Sync {
+    /// Human-readable location, for the startup log.
+    fn describe(&self) -> String;
+
+    /// Migrations this build has yet to apply, 0 when the schema is current.
+    /// Failing means the database didn't answer.
+    fn pending_migrations(&self) -> BoxFuture<'_, Result<usize>>;
+
+    fn upsert_session<'a>(
+        &'a self,
+        host: &'a str,
+        session_id: &'a str,
+        project_path: &'a str,
+        project_name: &'a str,
+        status: &'a str,
+    ) -> BoxFuture<'a, Result<()>>;
+
+    fn upsert_agent<'a>(
+        &'a self,
+        host: &'a str,
+        session_id: &'a str,
+        agent_name: &'a str,
+        parent_session_id: Option<&'a str>,
+        status: &'a str,
+    ) -> BoxFuture<'a, Result<()>>;
+
+    /// Insert queued events in one transaction, in order.
+    fn insert_events<'a>(&'a self, events: &'a [NewEvent]) -> BoxFuture<'a, Result<()>>;
+
+    /// Drop the oldest events of each of `sessions`, as (host, session_id),
+    /// beyond its newest `keep`, adding them to the session's `pruned_events`
+    /// and to the hourly archive so the heatmap still counts them. Returns
+    /// how many went.
+    fn cap_session_events<'a>(&'a self, sessions: &'a [(String, String)], keep: u32) -> BoxFuture<'a, Result<u64>>;
+
+    /// The session's latest `limit` events, newest first, optionally only
+    /// those of one agent.
+    fn get_recent_events<'a>(
+        &'a self,
+        session_id: &'a str,
+        agent_name: Option<&'a str>,
+        limit: u32,
+    ) -> BoxFuture<'a, Result<Vec<SessionEvent>>>;
+
+    /// Sequence number of the newest event, 0 if there are none; the
+    /// starting point for `get_events_after`.
+    fn latest_event_seq(&self) -> BoxFuture<'_, Result<i64>>;
+
+    /// The session's events stored after sequence number `after`, oldest
+    /// first, each with its sequence number.
+    fn get_events_after<'a>(
+        &'a self,
+        session_id: &'a str,
+        after: i64,
+    ) -> BoxFuture<'a, Result<Vec<(i64, SessionEvent)>>>;
+
+    /// Events whose tool name or message contains every quoted term of
+    /// `query`, best match first, with their sessions.
+    fn search_events<'a>(
+        &'a self,
+        query: &'a str,
+        host: Option<&'a str>,
+        limit: u32,
+    ) -> BoxFuture<'a, Result<Vec<SearchHit>>>;
+
+    /// Open a tool invocation on pre_tool_use, until the matching
+    /// post_tool_use closes it.
+    fn start_tool_invocation<'a>(
+        &'a self,
+        host: &'a str,
+        session_id: &'a str,
+        agent_name: &'a str,
+        tool_name: &'a str,
+        tool_use_id: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<()>>;
+
+    /// Close the open invocation matching a post_tool_use, by tool_use_id
+    /// when there is one, and record its duration.
+    fn finish_tool_invocation<'a>(
+        &'a self,
+        host: &'a str,
+        session_id: &'a str,
+        agent_name: &'a str,
+        tool_name: &'a str,
+        tool_use_id: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<()>>;
+
+    fn set_current_tool<'a>(
+        &'a self,
+        host: &'a str,
+        session_id: &'a str,
+        tool_name: &'a str,
+    ) -> BoxFuture<'a, Result<()>>;
+
+    /// Clear the session's current tool; with `tool_name`, only while that
+    /// tool is still the current one.
+    fn clear_current_tool<'a>(
+        &'a self,
+        host: &'a str,
+        session_id: &'a str,
+        tool_name: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<()>>;
+
+    fn set_session_model<'a>(&'a self, host: &'a str, session_id: &'a str, model: &'a str)
+        -> BoxFuture<'a, Result<()>>;
+
+    fn set_session_git_branch<'a>(
+        &'a self,
+        host: &'a str,
+        session_id: &'a str,
+        branch: &'a str,
+    ) -> BoxFuture<'a, Result<()>>;
+
+    fn set_last_message<'a>(&'a self, host: &'a str, session_id: &'a str, message: &'a str)
+        -> BoxFuture<'a, Result<()>>;
+
+    /// Store what the transcript tailer last saw. `None` keeps the previous value.
+    fn set_transcript_summary<'a>(
+        &'a self,
+        host: &'a str,
+        session_id: &'a str,
+        assistant_text: Option<&'a str>,
+        tool_call: Option<&'a str>,
+        model: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<()>>;
+
+    /// Record one assistant message's token usage, keeping the largest counts
+    /// seen for a `message_id`.
+    fn record_usage<'a>(
+        &'a self,
+        session_id: &'a str,
+        agent_name: &'a str,
+        message_id: Option<&'a str>,
+        model: Option<&'a str>,
+        usage: &'a TokenUsage,
+    ) -> BoxFuture<'a, Result<()>>;
+
+    /// Token totals for a session, overall and broken down by agent.
+    fn get_session_usage<'a>(&'a self, session_id: &'a str) -> BoxFuture<'a, Result<SessionUsage>>;
+
+    /// Token usage grouped by session and model, optionally only that
+    /// recorded within `[from, to)`, newest activity first.
+    fn get_usage_by_session_model(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'_, Result<Vec<ModelUsageRow>>>;
+
+    /// Usage of the sessions with these IDs, grouped by session and model.
+    fn get_usage_for_sessions<'a>(&'a self, session_ids: &'a [String]) -> BoxFuture<'a, Result<Vec<ModelUsageRow>>>;
+
+    /// Usage recorded at or after `since` by sessions that haven't completed,
+    /// grouped by session and model.
+    fn get_active_usage_since(&self, since: DateTime<Utc>) -> BoxFuture<'_, Result<Vec<ModelUsageRow>>>;
+
+    /// Event history, ordered per session by time, of every session with an
+    /// event in `[from, to)`, including its events before `from`.
+    fn get_session_events_in_range(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'_, Result<Vec<EventRow>>>;
+
+    /// One session's event history, oldest first.
+    fn get_session_event_rows<'a>(&'a self, session_id: &'a str) -> BoxFuture<'a, Result<Vec<EventRow>>>;
+
+    /// One session's tool calls as timeline entries, oldest first.
+    fn get_tool_timeline<'a>(&'a self, session_id: &'a str) -> BoxFuture<'a, Result<Vec<TimelineEntry>>>;
+
+    /// Completed sessions still stored, with when they completed.
+    fn get_completed_sessions(&self) -> BoxFuture<'_, Result<Vec<CompletedSession>>>;
+
+    /// Completed sessions that ended within `[from, to)`, archived or not.
+    fn get_session_durations(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'_, Result<Vec<SessionDuration>>>;
+
+    /// Events per UTC hour and host for hours starting within `[from, to)`,
+    /// archived or not.
+    fn get_hourly_event_counts(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'_, Result<Vec<HourlyEventCount>>>;
+
+    /// Archived active stretches that overlap `[from, to)`.
+    fn get_archived_intervals(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> BoxFuture<'_, Result<Vec<ActiveInterval>>>;
+
+    /// Archived daily totals.
+    fn get_daily_stats(&self) -> BoxFuture<'_, Result<DailyActivity>>;
+
+    /// Events recorded at or after `since`, counted per host and minute.
+    fn get_event_counts_since(&self, since: DateTime<Utc>) -> BoxFuture<'_, Result<Vec<EventCountRow>>>;
+
+    /// Usage recorded at or after `since`, including that of sessions since
+    /// cleaned up.
+    fn get_usage_since(&self, since: DateTime<Utc>) -> BoxFuture<'_, Result<WindowUsage>>;
+
+    fn get_tool_stats<'a>(&'a self, session_id: &'a str) -> BoxFuture<'a, Result<Vec<ToolStats>>>;
+
+    /// Sessions that haven't completed, and pinned ones that have, with their
+    /// agents, usage and tags.
+    fn get_active_sessions(&self) -> BoxFuture<'_, Result<Vec<SessionWithAgents>>>;
+
+    /// The active sessions `page` selects, in its order and after its cursor,
+    /// and how many match its filters across every page.
+    fn get_session_page<'a>(
+        &'a self,
+        page: &'a SessionPage<'a>,
+    ) -> BoxFuture<'a, Result<(Vec<SessionWithAgents>, u64)>>;
+
+    /// Non-completed sessions whose project_path is `workspace` or lies beneath it.
+    fn get_workspace_sessions<'a>(&'a self, workspace: &'a str) -> BoxFuture<'a, Result<Vec<EditorSession>>>;
+
+    // The methods below that take `host: Option<_>` act on the session with
+    // that id on `host`, or on every host when it is `None`.
+
+    /// Set or clear a session's note. Returns whether the session exists.
+    fn set_session_notes<'a>(
+        &'a self,
+        host: Option<&'a str>,
+        session_id: &'a str,
+        notes: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<bool>>;
+
+    fn get_session_tags<'a>(&'a self, host: Option<&'a str>, session_id: &'a str)
+        -> BoxFuture<'a, Result<Vec<String>>>;
+
+    /// Add tags to a session, ignoring ones it already has.
+    fn add_session_tags<'a>(
+        &'a self,
+        host: Option<&'a str>,
+        session_id: &'a str,
+        tags: &'a [String],
+    ) -> BoxFuture<'a, Result<()>>;
+
+    fn remove_session_tags<'a>(
+        &'a self,
+        host: Option<&'a str>,
+        session_id: &'a str,
+        tags: &'a [String],
+    ) -> BoxFuture<'a, Result<()>>;
+
+    fn session_exists<'a>(&'a self, host: Option<&'a str>, session_id: &'a str) -> BoxFuture<'a, Result<bool>>;
+
+    /// Complete a session and all of its agents.
+    fn mark_session_completed<'a>(&'a self, host: &'a str, session_id: &'a str) -> BoxFuture<'a, Result<()>>;
+
+    /// Move an `active` session and its `active` agents to `idle`.
+    fn mark_active_session_idle<'a>(&'a self, host: &'a str, session_id: &'a str) -> BoxFuture<'a, Result<()>>;
+
+    /// Move a session blocked on the user to `acknowledged`. Returns whether
+    /// it was blocked.
+    fn acknowledge_session<'a>(&'a self, host: Option<&'a str>, session_id: &'a str) -> BoxFuture<'a, Result<bool>>;
+
+    /// Set or clear a session's pin. Returns whether the session exists.
+    fn set_session_pinned<'a>(
+        &'a self,
+        host: Option<&'a str>,
+        session_id: &'a str,
+        pinned: bool,
+    ) -> BoxFuture<'a, Result<bool>>;
+
+    /// Move a session to the trash, which takes it out of every listing but
+    /// `get_trash`. Returns whether the session exists.
+    fn trash_session<'a>(&'a self, host: Option<&'a str>, session_id: &'a str) -> BoxFuture<'a, Result<bool>>;
+
+    /// Take a session back out of the trash. Returns whether it was in it.
+    fn restore_session<'a>(&'a self, host: Option<&'a str>, session_id: &'a str) -> BoxFuture<'a, Result<bool>>;
+
+    /// Sessions in the trash, most recently deleted first.
+    fn get_trash(&self) -> BoxFuture<'_, Result<Vec<SessionWithAgents>>>;
+
+    /// Mute or unmute a session; `hidden` only applies while muted. Returns
+    /// whether the session exists.
+    fn set_session_muted<'a>(
+        &'a self,
+        host: Option<&'a str>,
+        session_id: &'a str,
+        muted: bool,
+        hidden: bool,
+    ) -> BoxFuture<'a, Result<bool>>;
+
+    fn get_alert_rules(&self) -> BoxFuture<'_, Result<Vec<AlertRule>>>;
+
+    fn get_alert_rule<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<Option<AlertRule>>>;
+
+    fn insert_alert_rule<'a>(&'a self, rule: &'a AlertRuleInput) -> BoxFuture<'a, Result<AlertRule>>;
+
+    /// Replace a rule's fields. Returns `None` if no rule has that id.
+    fn update_alert_rule<'a>(
+        &'a self,
+        id: &'a str,
+        rule: &'a AlertRuleInput,
+    ) -> BoxFuture<'a, Result<Option<AlertRule>>>;
+
+    fn delete_alert_rule<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<bool>>;
+
+    fn get_project_aliases(&self) -> BoxFuture<'_, Result<Vec<ProjectAlias>>>;
+
+    /// Create or rename the alias for `project_path`, which must already be normalized.
+    fn set_project_alias<'a>(&'a self, project_path: &'a str, display_name: &'a str) -> BoxFuture<'a, Result<()>>;
+
+    fn delete_project_alias<'a>(&'a self, project_path: &'a str) -> BoxFuture<'a, Result<bool>>;
+
+    fn get_setting<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<Option<String>>>;
+
+    fn set_setting<'a>(&'a self, key: &'a str, value: &'a str) -> BoxFuture<'a, Result<()>>;
+
+    /// Record a notification attempt; `record.id` and `rule_name` are ignored.
+    fn insert_notification<'a>(&'a self, record: &'a NotificationRecord) -> BoxFuture<'a, Result<()>>;
+
+    /// A page of notification history, newest first, with the total count.
+    fn get_notifications(&self, limit: u32, offset: u32) -> BoxFuture<'_, Result<(Vec<NotificationRecord>, i64)>>;
+
+    /// Notifications about one session, oldest first.
+    fn get_session_notifications<'a>(&'a self, session_id: &'a str) -> BoxFuture<'a, Result<Vec<NotificationRecord>>>;
+
+    /// Drop notification history older than `retention`.
+    fn prune_notifications(&self, retention: Duration) -> BoxFuture<'_, Result<()>>;
+
+    /// Keep a payload that failed to parse, then drop all but the newest `keep`.
+    fn insert_dead_letter<'a>(
+        &'a self,
+        path: &'a str,
+        body: &'a str,
+        error: &'a str,
+        keep: u32,
+    ) -> BoxFuture<'a, Result<()>>;
+
+    /// The newest `limit` dead letters, newest first.
+    fn get_dead_letters(&self, limit: u32) -> BoxFuture<'_, Result<Vec<DeadLetter>>>;
+
+    /// Save a new API key by name and digest; `None` when the name is taken.
+    fn insert_api_key<'a>(
+        &'a self,
+        name: &'a str,
+        digest: &'a str,
+        admin: bool,
+    ) -> BoxFuture<'a, Result<Option<ApiKey>>>;
+
+    /// Every API key, revoked ones too, oldest first.
+    fn get_api_keys(&self) -> BoxFuture<'_, Result<Vec<ApiKey>>>;
+
+    /// `(digest, name, admin)` of each API key not revoked.
+    fn get_live_api_keys(&self) -> BoxFuture<'_, Result<Vec<(String, String, bool)>>>;
+
+    /// Revoke an API key; `false` when there's no such key or it already was.
+    fn revoke_api_key<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<bool>>;
+
+    /// Delete every session and its child rows.
+    fn clear_all_sessions(&self) -> BoxFuture<'_, Result<()>>;
+
+    /// Archive then remove unpinned sessions completed more than
+    /// `completed_ttl` ago, none when it is `None`, and sessions in the trash
+    /// for longer than `trash_retention`, keeping their usage until it is
+    /// older than `usage_retention`. With `keep_events` their events stay,
+    /// for `prune_archived_events` to remove. Returns how many rows went.
+    fn cleanup_old_completed(
+        &self,
+        completed_ttl: Option<Duration>,
+        usage_retention: Duration,
+        trash_retention: Duration,
+        keep_events: bool,
+    ) -> BoxFuture<'_, Result<CleanupReport>>;
+
+    /// Refresh planner statistics and hand free space back to the
+    /// filesystem, see `maintenance`.
+    fn maintain(&self) -> BoxFuture<'_, Result<MaintenanceReport>>;
+
+    /// Copy the write-ahead log into the database and truncate it. A no-op
+    /// for backends without one.
+    fn checkpoint(&self) -> BoxFuture<'_, Result<()>>;
+
+    /// Current size of the write-ahead log, when the backend has one on disk.
+    fn wal_size(&self) -> Option<u64>;
+
+    /// Size of the database file, the WAL aside, when the backend keeps one
+    /// on this machine.
+    fn file_size(&self) -> Option<u64>;
+
+    /// Bytes of the database in use, not counting pages free for reuse, or
+    /// `None` when the backend can't tell the two apart.
+    fn used_size(&self) -> BoxFuture<'_, Result<Option<u64>>>;
+
+    /// Delete up to `limit` of the oldest events of sessions that are over,
+    /// completed or in the trash, folding them into the hourly counts and
+    /// each session's `pruned_events`. Returns how many were deleted.
+    fn prune_archived_events(&self, limit: u32) -> BoxFuture<'_, Result<u64>>;
+
+    /// Write a consistent snapshot of the database to a new file at `dest`.
+    fn backup<'a>(&'a self, dest: &'a Path) -> BoxFuture<'a, Result<()>>;
+
+    /// Every session row, oldest first, with its tags.
+    fn export_sessions(&self) -> BoxFuture<'_, Result<Vec<ExportedSession>>>;
+
+    fn export_agents(&self) -> BoxFuture<'_, Result<Vec<ExportedAgent>>>;
+
+    /// Up to `limit` events inserted after sequence number `after` with
+    /// timestamps within `[from, to)`, in insertion order, each with its
+    /// sequence number to continue from.
+    fn export_events(
+        &self,
+        after: i64,
+        limit: u32,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'_, Result<Vec<(i64, ExportedEvent)>>>;
+
+    fn export_settings(&self) -> BoxFuture<'_, Result<Vec<ExportedSetting>>>;
+
+    /// Merge an export into the database in one transaction.
+    fn import<'a>(&'a self, data: &'a Export, on_conflict: OnConflict) -> BoxFuture<'a, Result<ImportReport>>;
+}
+
+/// `ON CONFLICT` clause for an imported row whose `key` is taken: keep what
+/// is there, or overwrite its `columns` with the imported values.
+pub(crate) fn import_conflict(on_conflict: OnConflict, key: &str, columns: &[&str]) -> String {
+    match on_conflict {
+        OnConflict::Skip => "ON CONFLICT DO NOTHING".to_string(),
+        OnConflict::Replace => {
+            let set: Vec<String> = columns.iter().map(|column| format!("{column} = excluded.{column}")).collect();
+            format!("ON CONFLICT ({key}) DO UPDATE SET {}", set.join(", "))
+        }
+    }
+}
+
+/// Session columns an imported session overwrites, everything but its keys.
+pub(crate) const IMPORTED_SESSION_COLUMNS: &[&str] = &[
+    "project_path",
+    "project_name",
+    "status",
+    "created_at",
+    "updated_at",
+    "current_tool",
+    "last_message",
+    "last_assistant_text",
+    "last_tool_call",
+    "model",
+    "pinned",
+    "muted",
+    "hidden",
+    "notes",
+    "git_branch",
+    "deleted_at",
+    "pruned_events",
+];
+
+pub(crate) const IMPORTED_AGENT_COLUMNS: &[&str] = &["parent_session_id", "status", "created_at", "updated_at"];
+
+/// One page of GET /api/sessions, for the store to filter, order and cut off
+/// in its query.
+pub struct SessionPage<'a> {
+    pub host: Option<&'a str>,
+    /// Sessions carrying any of these; empty matches all.
+    pub tags: &'a [String],
+    /// Empty matches all.
+    pub statuses: &'a [String],
+    /// A project's name, as aliased, or its path.
+    pub project: Option<&'a str>,
+    /// Only sessions updated at or after this.
+    pub since: Option<DateTime<Utc>>,
+    pub sort: SessionSort,
+    pub order: SortOrder,
+    /// Only sessions ordered after this one, from a `?cursor=`.
+    pub after: Option<&'a SortKey<'a>>,
+    /// Every session after `after` when `None`.
+    pub limit: Option<usize>,
+}
+
+/// A value bound into the query for a [`SessionPage`].
+#[derive(Clone)]
+pub(crate) enum Param {
+    Text(String),
+    Time(DateTime<Utc>),
+    Bool(bool),
+    Int(i64),
+}
+
+/// How one backend writes what a [`SessionPage`] query compares.
+pub(crate) struct Dialect {
+    /// The `n`th placeholder, counting from 1.
+    pub placeholder: fn(usize) -> String,
+    /// A text expression, compared byte by byte as Rust compares strings,
+    /// whatever the database's collation.
+    pub bytewise: fn(&str) -> String,
+}
+
+/// The SQL for a [`SessionPage`], over `sessions s` joined to its project's
+/// alias `pa`. The page is `SELECT ... {from}{after} ORDER BY {order_by}`,
+/// binding `params` then `after_params`; its total only needs `from`.
+pub(crate) struct PageSql {
+    pub from: String,
+    pub params: Vec<Param>,
+    pub after: String,
+    pub after_params: Vec<Param>,
+    pub order_by: String,
+    pub limit: String,
+}
+
+/// One column of a page's order.
+struct OrderColumn {
+    expr: String,
+    ascending: bool,
+    /// The cursor's value for it.
+    after: Option<Param>,
+}
+
+impl SessionPage<'_> {
+    pub(crate) fn sql(&self, dialect: &Dialect) -> PageSql {
+        let mut bound = 0;
+        let mut placeholder = || {
+            bound += 1;
+            (dialect.placeholder)(bound)
+        };
+        // Aliases are keyed by normalized path, which macOS also case-folds.
+        let path = if cfg!(target_os = "macos") { "LOWER(s.project_path)" } else { "s.project_path" };
+        let mut from = format!(
+            "FROM sessions s LEFT JOIN project_aliases pa ON pa.project_path = {path} \
+             WHERE (s.status != 'completed' OR s.pinned = TRUE) AND s.deleted_at IS NULL"
+        );
+        let mut params = Vec::new();
+        if let Some(host) = self.host {
+            from.push_str(&format!(" AND s.host = {}", placeholder()));
+            params.push(Param::Text(host.to_string()));
+        }
+        if !self.statuses.is_empty() {
+            let list: Vec<String> = self.statuses.iter().map(|_| placeholder()).collect();
+            from.push_str(&format!(" AND s.status IN ({})", list.join(", ")));
+            params.extend(self.statuses.iter().cloned().map(Param::Text));
+        }
+        if !self.tags.is_empty() {
+            let list: Vec<String> = self.tags.iter().map(|_| placeholder()).collect();
+            from.push_str(&format!(
+                " AND EXISTS (SELECT 1 FROM session_tags t WHERE t.session_id = s.session_id AND t.tag IN ({}))",
+                list.join(", ")
+            ));
+            params.extend(self.tags.iter().cloned().map(Param::Text));
+        }
+        if let Some(project) = self.project {
+            let (name, path) = (placeholder(), placeholder());
+            from.push_str(&format!(" AND (COALESCE(pa.display_name, s.project_name) = {name} OR s.project_path = {path})"));
+            params.extend([Param::Text(project.to_string()), Param::Text(project.to_string())]);
+        }
+        if let Some(since) = self.since {
+            from.push_str(&format!(" AND s.updated_at >= {}", placeholder()));
+            params.push(Param::Time(since));
+        }
+
+        let columns = self.order_columns(dialect);
+        let mut after = String::new();
+        let mut after_params = Vec::new();
+        if self.after.is_some() {
+            // Lexicographic: equal on every column before one, and past the cursor on that one.
+            let mut terms = Vec::new();
+            for (i, column) in columns.iter().enumerate() {
+                let mut term: Vec<String> = Vec::new();
+                for earlier in &columns[..i] {
+                    term.push(format!("{} = {}", earlier.expr, placeholder()));
+                    after_params.extend(earlier.after.clone());
+                }
+                let op = if column.ascending { ">" } else { "<" };
+                term.push(format!("{} {op} {}", column.expr, placeholder()));
+                after_params.extend(column.after.clone());
+                terms.push(format!("({})", term.join(" AND ")));
+            }
+            after = format!(" AND ({})", terms.join(" OR "));
+        }
+        let order_by: Vec<String> = columns
+            .iter()
+            .map(|column| format!("{} {}", column.expr, if column.ascending { "ASC" } else { "DESC" }))
+            .collect();
+        PageSql {
+            from,
+            params,
+            after,
+            after_params,
+            order_by: order_by.join(", "),
+            limit: self.limit.map(|limit| format!(" LIMIT {limit}")).unwrap_or_default(),
+        }
+    }
+
+    /// The order of `api::SortKey::cmp`: pinned first, then by `sort`, then
+    /// host and session ID. Times are compared as stored, to the nanosecond.
+    fn order_columns(&self, dialect: &Dialect) -> Vec<OrderColumn> {
+        let asc = self.order == SortOrder::Asc;
+        let after = self.after;
+        let column = |expr: String, ascending: bool, value: Option<Param>| OrderColumn { expr, ascending, after: value };
+        let text = |expr: &str| (dialect.bytewise)(expr);
+        let mut columns = vec![column("s.pinned".to_string(), false, after.map(|k| Param::Bool(k.pinned)))];
+        match self.sort {
+            SessionSort::CreatedAt => {
+                columns.push(column("s.created_at".to_string(), asc, after.map(|k| Param::Time(k.created_at))))
+            }
+            SessionSort::UpdatedAt => {
+                columns.push(column("s.updated_at".to_string(), asc, after.map(|k| Param::Time(k.updated_at))))
+            }
+            SessionSort::Status => {
+                columns.push(column(text("s.status"), asc, after.map(|k| Param::Text(k.status.to_string()))))
+            }
+            SessionSort::Project => {
+                // ASCII only, as SQLite's LOWER folds.
+                let name = format!("LOWER({})", text("COALESCE(pa.display_name, s.project_name)"));
+                columns.push(column(name, asc, after.map(|k| Param::Text(k.project_name.to_ascii_lowercase()))))
+            }
+            SessionSort::Attention => {
+                let cases: String = projects::URGENCY
+                    .iter()
+                    .enumerate()
+                    .map(|(rank, status)| format!(" WHEN '{status}' THEN {rank}"))
+                    .collect();
+                let urgency = format!("CASE s.status{cases} ELSE {} END", projects::URGENCY.len());
+                let rank = |k: &SortKey| Param::Int(projects::urgency(&k.status) as i64);
+                columns.push(column(urgency, asc, after.map(rank)));
+                columns.push(column("s.updated_at".to_string(), !asc, after.map(|k| Param::Time(k.updated_at))));
+            }
+        }
+        columns.push(column(text("s.host"), true, after.map(|k| Param::Text(k.host.to_string()))));
+        columns.push(column(text("s.session_id"), true, after.map(|k| Param::Text(k.session_id.to_string()))));
+        columns
+    }
+}
+
+/// `workspace` without a trailing slash, and what the paths beneath it start
+/// with: every absolute path for the root.
+pub(crate) fn workspace_prefix(workspace: &str) -> (&str, String) {
+    match workspace.trim_end_matches('/') {
+        "" => ("/", "/".to_string()),
+        trimmed => (trimmed, format!("{trimmed}/")),
+    }
+}
+
+/// Tally one imported row, written or not.
+pub(crate) fn count_import(counts: &mut ImportCounts, written: bool) {
+    if written {
+        counts.imported += 1;
+    } else {
+        counts.skipped += 1;
+    }
+}
+
+/// Fill in each session's `tags` from `(session_id, tag)` pairs, which are
+/// in the order the tags were added.
+pub(crate) fn attach_tags(sessions: &mut [ExportedSession], tags: Vec<(String, String, String)>) {
+    let mut by_session: HashMap<(String, String), Vec<String>> = HashMap::new();
+    for (host, session_id, tag) in tags {
+        by_session.entry((host, session_id)).or_default().push(tag);
+    }
+    for session in sessions {
+        session.tags = by_session.remove(&(session.host.clone(), session.session_id.clone())).unwrap_or_default();
+    }
+}
+
+/// Token totals across `agents`.
+pub(crate) fn usage_totals(session_id: &str, agents: Vec<AgentUsage>) -> SessionUsage {
+    SessionUsage {
+        session_id: session_id.to_string(),
+        totals: agents.iter().fold(TokenUsage::default(), |acc, a| acc.add(&a.usage)),
+        agents,
+    }
+}
+
+/// One event as agent runtimes are replayed from it.
+#[derive(sqlx::FromRow)]
+pub(crate) struct RuntimeEvent {
+    pub agent_name: String,
+    pub event_type: String,
+    pub needs_input: bool,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// One agent's `active` time, replayed from its events.
+#[derive(Default)]
+pub(crate) struct AgentRuntime {
+    /// Closed intervals.
+    active: Duration,
+    /// Start of the interval still open at the agent's last event.
+    active_since: Option<DateTime<Utc>>,
+}
+
+impl AgentRuntime {
+    /// Seconds in total. An open interval runs until now if the agent is
+    /// still `active`, or until its last update if something other than an
+    /// event, such as a dismissal, ended it.
+    pub fn seconds(&self, status: &str, updated_at: DateTime<Utc>) -> i64 {
+        let now = Utc::now();
+        let open = self.active_since.map_or(Duration::zero(), |since| {
+            let end = if status == "active" { now } else { updated_at.min(now) };
+            (end - since).max(Duration::zero())
+        });
+        (self.active + open).num_seconds()
+    }
+}
+
+/// Each agent's runtime, from a session's events in time order.
+pub(crate) fn agent_runtimes(events: Vec<RuntimeEvent>) -> HashMap<String, AgentRuntime> {
+    let mut runtimes: HashMap<String, AgentRuntime> = HashMap::new();
+    for event in events {
+        let (_, status) = api::statuses_for_event(&event.event_type, event.needs_input);
+
+        let runtime = runtimes.entry(event.agent_name).or_default();
+        match (status == "active", runtime.active_since) {
+            (true, None) => runtime.active_since = Some(event.timestamp),
+            (false, Some(since)) => {
+                runtime.active += event.timestamp - since;
+                runtime.active_since = None;
+            }
+            _ => {}
+        }
+    }
+    runtimes
+}
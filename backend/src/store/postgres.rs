@@ -0,0 +1,2187 @@
+//! A shared Postgres database, for a monitor that a team reports to. The
+//! schema in `migrations/postgres/schema.sql` is applied on connect; it uses
+//! native timestamps, booleans and JSONB, and a generated tsvector column in
+//! place of SQLite's FTS5 table.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use futures::{future::BoxFuture, FutureExt};
+use sqlx::{
+    postgres::{PgConnectOptions, PgPool, PgPoolOptions},
+    types::Json,
+    Executor,
+};
+use std::{collections::HashMap, path::Path, str::FromStr};
+use tracing::{debug_span, Instrument};
+use uuid::Uuid;
+
+use super::{AgentRuntime, Dialect, Param, SessionPage, Store};
+use crate::{
+    models::{
+        ActiveInterval, Agent, AgentUsage, AlertRule, AlertRuleInput, ApiKey, CleanupReport, CompletedSession,
+        DailyActivity, DailyTotals, DeadLetter, EditorSession, EventCountRow, EventRow, Export, ExportedAgent,
+        ExportedEvent, ExportedSession, ExportedSetting, HourlyEventCount, ImportReport, MaintenanceReport,
+        ModelUsageRow, NewEvent, NotificationRecord, OnConflict, ProjectAlias, SearchHit, SessionDuration, SessionEvent,
+        SessionUsage, SessionWithAgents, TimelineEntry, TokenUsage, ToolStats, WindowUsage,
+    },
+    stats,
+};
+
+const SCHEMA: &str = include_str!("../../migrations/postgres/schema.sql");
+
+/// Tables holding per-session child rows, keyed by session_id.
+const SESSION_CHILD_TABLES: &[&str] = &["agents", "events", "tool_invocations", "usage", "session_tags"];
+
+/// `EventRow` columns, from `events e JOIN sessions s`.
+const EVENT_ROW_COLUMNS: &str = "e.host, e.session_id, s.project_name, COALESCE(e.agent_name, 'main') AS agent_name, \
+     e.event_type, COALESCE(e.payload->>'needs_input' = 'true', FALSE) AS needs_input, e.timestamp";
+
+pub struct PgStore {
+    pool: PgPool,
+    location: String,
+}
+
+/// How Postgres writes a [`SessionPage`]'s comparisons. Under the "C"
+/// collation text compares byte by byte and LOWER folds ASCII only.
+const DIALECT: Dialect = Dialect { placeholder: |n| format!("${n}"), bytewise: |expr| format!("({expr}) COLLATE \"C\"") };
+
+fn bind_params<'q, O>(
+    mut query: sqlx::query::QueryAs<'q, sqlx::Postgres, O, sqlx::postgres::PgArguments>,
+    params: &'q [Param],
+) -> sqlx::query::QueryAs<'q, sqlx::Postgres, O, sqlx::postgres::PgArguments> {
+    for param in params {
+        query = match param {
+            Param::Text(text) => query.bind(text.as_str()),
+            Param::Time(time) => query.bind(*time),
+            Param::Bool(flag) => query.bind(*flag),
+            Param::Int(int) => query.bind(*int),
+        };
+    }
+    query
+}
+
+impl PgStore {
+    pub async fn connect(url: &str) -> Result<Self> {
+        // Hour buckets and dates are UTC, whatever the server's default zone.
+        let options = PgConnectOptions::from_str(url)?.options([("TimeZone", "UTC")]);
+        let location = format!(
+            "postgres://{}@{}:{}/{}",
+            options.get_username(),
+            options.get_host(),
+            options.get_port(),
+            options.get_database().unwrap_or_default()
+        );
+        let pool = PgPoolOptions::new()
+            .max_connections(10)
+            .connect_with(options)
+            .await
+            .context("failed to connect to Postgres")?;
+        // Without bound arguments this is a simple query, which may hold
+        // several statements.
+        pool.execute(SCHEMA).await.context("failed to apply the Postgres schema")?;
+        Ok(Self { pool, location })
+    }
+
+    async fn agents_for_session(&self, host: &str, session_id: &str) -> Result<Vec<Agent>> {
+        let mut agents: Vec<Agent> = sqlx::query_as(
+            r#"
+            SELECT id, session_id, agent_name, parent_session_id, status, created_at, updated_at
+            FROM agents
+            WHERE host = $1 AND session_id = $2
+            ORDER BY created_at ASC
+            "#,
+        )
+        .bind(host)
+        .bind(session_id)
+        .fetch_all(&self.pool)
+        .await?;
+        let runtimes = self.agent_runtimes(host, session_id).await?;
+
+        for agent in &mut agents {
+            agent.runtime_seconds = runtimes
+                .get(&agent.agent_name)
+                .map_or(0, |runtime| runtime.seconds(&agent.status, agent.updated_at));
+        }
+        Ok(agents)
+    }
+
+    async fn agent_runtimes(&self, host: &str, session_id: &str) -> Result<HashMap<String, AgentRuntime>> {
+        let events = sqlx::query_as(
+            r#"
+            SELECT COALESCE(agent_name, 'main') AS agent_name, event_type,
+                   COALESCE(payload->>'needs_input' = 'true', FALSE) AS needs_input, timestamp
+            FROM events
+            WHERE host = $1 AND session_id = $2
+            ORDER BY timestamp
+            "#,
+        )
+        .bind(host)
+        .bind(session_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(super::agent_runtimes(events))
+    }
+
+    async fn session_usage(&self, session_id: &str) -> Result<SessionUsage> {
+        let agents: Vec<AgentUsage> = sqlx::query_as(
+            r#"
+            SELECT agent_name,
+                   SUM(input_tokens)::BIGINT AS input_tokens,
+                   SUM(output_tokens)::BIGINT AS output_tokens,
+                   SUM(cache_creation_input_tokens)::BIGINT AS cache_creation_input_tokens,
+                   SUM(cache_read_input_tokens)::BIGINT AS cache_read_input_tokens
+            FROM usage
+            WHERE session_id = $1
+            GROUP BY agent_name
+            ORDER BY agent_name
+            "#,
+        )
+        .bind(session_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(super::usage_totals(session_id, agents))
+    }
+
+    /// The tags of the session on `host`, or of every session with that id.
+    async fn session_tags(&self, host: Option<&str>, session_id: &str) -> Result<Vec<String>> {
+        let tags = sqlx::query_scalar(
+            r#"
+            SELECT tag FROM session_tags
+            WHERE session_id = $1 AND ($2::TEXT IS NULL OR host = $2)
+            GROUP BY tag
+            ORDER BY MIN(created_at), tag
+            "#,
+        )
+        .bind(session_id)
+        .bind(host)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(tags)
+    }
+
+    /// Attach each session's agents, usage and tags.
+    async fn fill_sessions(&self, sessions: &mut [SessionWithAgents]) -> Result<()> {
+        for session in sessions {
+            session.agents = self.agents_for_session(&session.host, &session.session_id).await?;
+            session.usage = self.session_usage(&session.session_id).await?.totals;
+            session.tags = self.session_tags(Some(&session.host), &session.session_id).await?;
+        }
+        Ok(())
+    }
+
+    async fn alert_rule(&self, id: &str) -> Result<Option<AlertRule>> {
+        let rule = sqlx::query_as(
+            "SELECT id, name, enabled, conditions, sinks, created_at, updated_at FROM alert_rules WHERE id = $1",
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(rule)
+    }
+
+    /// Set `status` on a session and its agents, optionally only where the
+    /// current status is one of `from`. Returns whether the session changed.
+    /// Move the session on `host`, or every session with that id, and its
+    /// agents to `status`; with `from`, only those in one of those statuses.
+    async fn set_status(
+        &self,
+        host: Option<&str>,
+        session_id: &str,
+        status: &str,
+        from: Option<&[&str]>,
+    ) -> Result<bool> {
+        let now = Utc::now();
+        let from: Option<Vec<String>> = from.map(|from| from.iter().map(|s| s.to_string()).collect());
+        let mut tx = self.pool.begin().await?;
+        let result = sqlx::query(
+            r#"
+            UPDATE sessions SET status = $1, updated_at = $2
+            WHERE session_id = $3 AND ($5::TEXT IS NULL OR host = $5) AND ($4::TEXT[] IS NULL OR status = ANY($4))
+            "#,
+        )
+        .bind(status)
+        .bind(now)
+        .bind(session_id)
+        .bind(&from)
+        .bind(host)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            r#"
+            UPDATE agents SET status = $1, updated_at = $2
+            WHERE session_id = $3 AND ($5::TEXT IS NULL OR host = $5) AND ($4::TEXT[] IS NULL OR status = ANY($4))
+            "#,
+        )
+        .bind(status)
+        .bind(now)
+        .bind(session_id)
+        .bind(&from)
+        .bind(host)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn cleanup(
+        &self,
+        completed_ttl: Option<Duration>,
+        usage_retention: Duration,
+        trash_retention: Duration,
+        keep_events: bool,
+    ) -> Result<CleanupReport> {
+        // The same cutoffs throughout, so exactly the archived sessions are
+        // deleted. Without a TTL the cutoff is NULL, which no session is before.
+        let now = Utc::now();
+        let cutoff = completed_ttl.map(|ttl| now - ttl);
+        let trash_cutoff = now - trash_retention;
+        let expired = "SELECT host, session_id FROM sessions \
+                       WHERE (status = 'completed' AND NOT pinned AND deleted_at IS NULL AND updated_at <= $1) \
+                       OR deleted_at <= $2";
+        let mut tx = self.pool.begin().await?;
+        let mut report = CleanupReport::default();
+
+        let completed: Vec<CompletedSession> = sqlx::query_as(&format!(
+            "SELECT host, session_id, updated_at AS ended_at FROM sessions WHERE (host, session_id) IN ({expired})"
+        ))
+        .bind(cutoff)
+        .bind(trash_cutoff)
+        .fetch_all(&mut *tx)
+        .await?;
+        if !completed.is_empty() {
+            let events: Vec<EventRow> = sqlx::query_as(&format!(
+                r#"
+                SELECT {EVENT_ROW_COLUMNS}
+                FROM events e
+                JOIN sessions s ON s.host = e.host AND s.session_id = e.session_id
+                WHERE (e.host, e.session_id) IN ({expired})
+                ORDER BY e.host, e.session_id, e.timestamp
+                "#
+            ))
+            .bind(cutoff)
+            .bind(trash_cutoff)
+            .fetch_all(&mut *tx)
+            .await?;
+
+            for interval in stats::active_intervals(&events, &completed) {
+                sqlx::query(
+                    r#"
+                    INSERT INTO active_intervals (host, session_id, project_name, started_at, ended_at)
+                    VALUES ($1, $2, $3, $4, $5)
+                    "#,
+                )
+                .bind(&interval.host)
+                .bind(&interval.session_id)
+                .bind(&interval.project_name)
+                .bind(interval.start)
+                .bind(interval.end)
+                .execute(&mut *tx)
+                .await?;
+            }
+            for ((day, host), totals) in stats::daily_activity(&events, &completed) {
+                sqlx::query(
+                    r#"
+                    INSERT INTO daily_stats (day, host, sessions, active_seconds, completions)
+                    VALUES ($1, $2, $3, $4, $5)
+                    ON CONFLICT (day, host) DO UPDATE SET
+                        sessions = daily_stats.sessions + excluded.sessions,
+                        active_seconds = daily_stats.active_seconds + excluded.active_seconds,
+                        completions = daily_stats.completions + excluded.completions
+                    "#,
+                )
+                .bind(day)
+                .bind(host)
+                .bind(totals.sessions)
+                .bind(totals.active_seconds)
+                .bind(totals.completions)
+                .execute(&mut *tx)
+                .await?;
+            }
+
+            // Kept events are counted as they are pruned.
+            if !keep_events {
+                sqlx::query(&format!(
+                    r#"
+                    INSERT INTO hourly_events (hour, host, events)
+                    SELECT date_trunc('hour', timestamp) AS hour, host, COUNT(*) FROM events
+                    WHERE (host, session_id) IN ({expired})
+                    GROUP BY 1, host
+                    ON CONFLICT (hour, host) DO UPDATE SET events = hourly_events.events + excluded.events
+                    "#
+                ))
+                .bind(cutoff)
+                .bind(trash_cutoff)
+                .execute(&mut *tx)
+                .await?;
+            }
+            sqlx::query(&format!(
+                r#"
+                INSERT INTO session_durations (host, session_id, project_name, started_at, ended_at)
+                SELECT host, session_id, project_name, created_at, updated_at FROM sessions
+                WHERE (host, session_id) IN ({expired})
+                "#
+            ))
+            .bind(cutoff)
+            .bind(trash_cutoff)
+            .execute(&mut *tx)
+            .await?;
+
+            for table in SESSION_CHILD_TABLES.iter().filter(|t| **t != "usage" && !(keep_events && **t == "events")) {
+                let deleted = sqlx::query(&format!("DELETE FROM {table} WHERE (host, session_id) IN ({expired})"))
+                    .bind(cutoff)
+                    .bind(trash_cutoff)
+                    .execute(&mut *tx)
+                    .await?;
+                if *table == "events" {
+                    report.events = deleted.rows_affected();
+                }
+            }
+            report.sessions = sqlx::query(&format!("DELETE FROM sessions WHERE (host, session_id) IN ({expired})"))
+                .bind(cutoff)
+                .bind(trash_cutoff)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+        }
+        tx.commit().await?;
+
+        sqlx::query(
+            "DELETE FROM usage WHERE session_id NOT IN (SELECT session_id FROM sessions) AND recorded_at < $1",
+        )
+        .bind(now - usage_retention)
+        .execute(&self.pool)
+        .await?;
+        Ok(report)
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct EventAfterRow {
+    seq: i64,
+    #[sqlx(flatten)]
+    event: SessionEvent,
+}
+
+#[derive(sqlx::FromRow)]
+struct ExportEventRow {
+    seq: i64,
+    #[sqlx(flatten)]
+    event: ExportedEvent,
+}
+
+#[derive(sqlx::FromRow)]
+struct ToolCallRow {
+    agent_name: String,
+    tool_name: String,
+    started_at: DateTime<Utc>,
+    ended_at: Option<DateTime<Utc>>,
+    duration_ms: Option<i64>,
+}
+
+/// Columns of a `ModelUsageRow`, from `usage u JOIN sessions s`.
+const MODEL_USAGE_COLUMNS: &str = "s.host, u.session_id, s.project_name, u.model, \
+     SUM(u.input_tokens)::BIGINT AS input_tokens, SUM(u.output_tokens)::BIGINT AS output_tokens, \
+     SUM(u.cache_creation_input_tokens)::BIGINT AS cache_creation_input_tokens, \
+     SUM(u.cache_read_input_tokens)::BIGINT AS cache_read_input_tokens";
+
+impl Store for PgStore {
+    fn describe(&self) -> String {
+        self.location.clone()
+    }
+
+    /// The whole schema is applied on connect, so nothing is ever pending.
+    fn pending_migrations(&self) -> BoxFuture<'_, Result<usize>> {
+        async move {
+            sqlx::query("SELECT 1").execute(&self.pool).await?;
+            Ok(0)
+        }
+        .instrument(debug_span!("pending_migrations"))
+        .boxed()
+    }
+
+    fn upsert_session<'a>(
+        &'a self,
+        host: &'a str,
+        session_id: &'a str,
+        project_path: &'a str,
+        project_name: &'a str,
+        status: &'a str,
+    ) -> BoxFuture<'a, Result<()>> {
+        async move {
+            sqlx::query(
+                r#"
+                INSERT INTO sessions (id, host, session_id, project_path, project_name, status, created_at, updated_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $7)
+                ON CONFLICT (host, session_id) DO UPDATE SET
+                    project_path = excluded.project_path,
+                    project_name = excluded.project_name,
+                    status = excluded.status,
+                    updated_at = excluded.updated_at
+                "#,
+            )
+            .bind(Uuid::new_v4().to_string())
+            .bind(host)
+            .bind(session_id)
+            .bind(project_path)
+            .bind(project_name)
+            .bind(status)
+            .bind(Utc::now())
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        }
+        .instrument(debug_span!("upsert_session"))
+        .boxed()
+    }
+
+    fn upsert_agent<'a>(
+        &'a self,
+        host: &'a str,
+        session_id: &'a str,
+        agent_name: &'a str,
+        parent_session_id: Option<&'a str>,
+        status: &'a str,
+    ) -> BoxFuture<'a, Result<()>> {
+        async move {
+            sqlx::query(
+                r#"
+                INSERT INTO agents (id, host, session_id, agent_name, parent_session_id, status, created_at, updated_at)
+                VALUES ($1, $7, $2, $3, $4, $5, $6, $6)
+                ON CONFLICT (host, session_id, agent_name) DO UPDATE SET
+                    parent_session_id = excluded.parent_session_id,
+                    status = excluded.status,
+                    updated_at = excluded.updated_at
+                "#,
+            )
+            .bind(Uuid::new_v4().to_string())
+            .bind(session_id)
+            .bind(agent_name)
+            .bind(parent_session_id)
+            .bind(status)
+            .bind(Utc::now())
+            .bind(host)
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        }
+        .instrument(debug_span!("upsert_agent"))
+        .boxed()
+    }
+
+    fn cap_session_events<'a>(&'a self, sessions: &'a [(String, String)], keep: u32) -> BoxFuture<'a, Result<u64>> {
+        async move {
+            let mut pruned = 0;
+            for (host, session_id) in sessions {
+                // The newest event past the ones kept, if there are more than `keep`.
+                let last: Option<i64> = sqlx::query_scalar(
+                    "SELECT seq FROM events WHERE host = $3 AND session_id = $1 ORDER BY seq DESC LIMIT 1 OFFSET $2",
+                )
+                .bind(session_id)
+                .bind(i64::from(keep))
+                .bind(host)
+                .fetch_optional(&self.pool)
+                .await?;
+                let Some(last) = last else { continue };
+
+                let mut tx = self.pool.begin().await?;
+                sqlx::query(
+                    r#"
+                    INSERT INTO hourly_events (hour, host, events)
+                    SELECT date_trunc('hour', timestamp) AS hour, host, COUNT(*) FROM events
+                    WHERE host = $3 AND session_id = $1 AND seq <= $2
+                    GROUP BY 1, host
+                    ON CONFLICT (hour, host) DO UPDATE SET events = hourly_events.events + excluded.events
+                    "#,
+                )
+                .bind(session_id)
+                .bind(last)
+                .bind(host)
+                .execute(&mut *tx)
+                .await?;
+                let deleted = sqlx::query("DELETE FROM events WHERE host = $3 AND session_id = $1 AND seq <= $2")
+                    .bind(session_id)
+                    .bind(last)
+                    .bind(host)
+                    .execute(&mut *tx)
+                    .await?
+                    .rows_affected();
+                sqlx::query(
+                    "UPDATE sessions SET pruned_events = pruned_events + $1 WHERE host = $3 AND session_id = $2",
+                )
+                .bind(deleted as i64)
+                .bind(session_id)
+                .bind(host)
+                    .execute(&mut *tx)
+                    .await?;
+                tx.commit().await?;
+                pruned += deleted;
+            }
+            Ok(pruned)
+        }
+        .instrument(debug_span!("cap_session_events"))
+        .boxed()
+    }
+
+    fn insert_events<'a>(&'a self, events: &'a [NewEvent]) -> BoxFuture<'a, Result<()>> {
+        async move {
+            let mut tx = self.pool.begin().await?;
+            for event in events {
+                sqlx::query(
+                    r#"
+                    INSERT INTO events
+                        (id, host, session_id, agent_name, event_type, payload, timestamp, request_id, source)
+                    VALUES ($1, $2, $3, $4, $5, $6::JSONB, $7, $8, $9)
+                    "#,
+                )
+                .bind(&event.id)
+                .bind(&event.host)
+                .bind(&event.session_id)
+                .bind(&event.agent_name)
+                .bind(&event.event_type)
+                .bind(&event.payload)
+                .bind(event.timestamp)
+                .bind(&event.request_id)
+                .bind(&event.source)
+                .execute(&mut *tx)
+                .await?;
+            }
+            tx.commit().await?;
+            Ok(())
+        }
+        .instrument(debug_span!("insert_events"))
+        .boxed()
+    }
+
+    fn get_recent_events<'a>(
+        &'a self,
+        session_id: &'a str,
+        agent_name: Option<&'a str>,
+        limit: u32,
+    ) -> BoxFuture<'a, Result<Vec<SessionEvent>>> {
+        async move {
+            let events = sqlx::query_as(
+                r#"
+                SELECT id, agent_name, event_type, payload, timestamp, request_id, source
+                FROM events
+                WHERE session_id = $1 AND ($2::TEXT IS NULL OR agent_name = $2)
+                AND (host, session_id) IN (SELECT host, session_id FROM sessions)
+                ORDER BY timestamp DESC
+                LIMIT $3
+                "#,
+            )
+            .bind(session_id)
+            .bind(agent_name)
+            .bind(i64::from(limit))
+            .fetch_all(&self.pool)
+            .await?;
+            Ok(events)
+        }
+        .instrument(debug_span!("get_recent_events"))
+        .boxed()
+    }
+
+    fn latest_event_seq(&self) -> BoxFuture<'_, Result<i64>> {
+        async move {
+            let seq: Option<i64> = sqlx::query_scalar("SELECT MAX(seq) FROM events").fetch_one(&self.pool).await?;
+            Ok(seq.unwrap_or(0))
+        }
+        .instrument(debug_span!("latest_event_seq"))
+        .boxed()
+    }
+
+    fn get_events_after<'a>(
+        &'a self,
+        session_id: &'a str,
+        after: i64,
+    ) -> BoxFuture<'a, Result<Vec<(i64, SessionEvent)>>> {
+        async move {
+            let rows: Vec<EventAfterRow> = sqlx::query_as(
+                r#"
+                SELECT seq, id, agent_name, event_type, payload, timestamp, request_id, source
+                FROM events
+                WHERE session_id = $1 AND seq > $2
+                ORDER BY seq
+                "#,
+            )
+            .bind(session_id)
+            .bind(after)
+            .fetch_all(&self.pool)
+            .await?;
+            Ok(rows.into_iter().map(|row| (row.seq, row.event)).collect())
+        }
+        .instrument(debug_span!("get_events_after"))
+        .boxed()
+    }
+
+    fn search_events<'a>(
+        &'a self,
+        query: &'a str,
+        host: Option<&'a str>,
+        limit: u32,
+    ) -> BoxFuture<'a, Result<Vec<SearchHit>>> {
+        async move {
+            // `query` quotes each term, which websearch_to_tsquery reads as
+            // phrases that must all match, as FTS5 does.
+            let hits = sqlx::query_as(
+                r#"
+                SELECT e.id, e.host, e.session_id, s.project_name, s.status AS session_status, e.agent_name,
+                       e.event_type, e.payload, e.timestamp, e.request_id, e.source,
+                       ts_headline('simple', concat_ws(' ', e.payload->>'tool_name', e.payload->>'message'), q,
+                                   'StartSel="[", StopSel="]", MaxWords=12, MinWords=4') AS snippet
+                FROM events e
+                JOIN sessions s ON s.host = e.host AND s.session_id = e.session_id
+                CROSS JOIN websearch_to_tsquery('simple', $1) q
+                WHERE e.search @@ q AND ($2::TEXT IS NULL OR e.host = $2)
+                ORDER BY ts_rank(e.search, q) DESC
+                LIMIT $3
+                "#,
+            )
+            .bind(query)
+            .bind(host)
+            .bind(i64::from(limit))
+            .fetch_all(&self.pool)
+            .await?;
+            Ok(hits)
+        }
+        .instrument(debug_span!("search_events"))
+        .boxed()
+    }
+
+    fn start_tool_invocation<'a>(
+        &'a self,
+        host: &'a str,
+        session_id: &'a str,
+        agent_name: &'a str,
+        tool_name: &'a str,
+        tool_use_id: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<()>> {
+        async move {
+            sqlx::query(
+                r#"
+                INSERT INTO tool_invocations (id, host, session_id, agent_name, tool_name, tool_use_id, started_at)
+                VALUES ($1, $7, $2, $3, $4, $5, $6)
+                "#,
+            )
+            .bind(Uuid::new_v4().to_string())
+            .bind(session_id)
+            .bind(agent_name)
+            .bind(tool_name)
+            .bind(tool_use_id)
+            .bind(Utc::now())
+            .bind(host)
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        }
+        .instrument(debug_span!("start_tool_invocation"))
+        .boxed()
+    }
+
+    fn finish_tool_invocation<'a>(
+        &'a self,
+        host: &'a str,
+        session_id: &'a str,
+        agent_name: &'a str,
+        tool_name: &'a str,
+        tool_use_id: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<()>> {
+        async move {
+            sqlx::query(
+                r#"
+                UPDATE tool_invocations
+                SET ended_at = $1,
+                    duration_ms = FLOOR(EXTRACT(EPOCH FROM ($1 - started_at)) * 1000)::BIGINT
+                WHERE id = (
+                    SELECT id FROM tool_invocations
+                    WHERE host = $6 AND session_id = $2 AND agent_name = $3 AND ended_at IS NULL
+                    AND (tool_use_id = $4 OR ($4::TEXT IS NULL AND tool_name = $5))
+                    ORDER BY started_at DESC
+                    LIMIT 1
+                )
+                "#,
+            )
+            .bind(Utc::now())
+            .bind(session_id)
+            .bind(agent_name)
+            .bind(tool_use_id)
+            .bind(tool_name)
+            .bind(host)
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        }
+        .instrument(debug_span!("finish_tool_invocation"))
+        .boxed()
+    }
+
+    fn set_current_tool<'a>(
+        &'a self,
+        host: &'a str,
+        session_id: &'a str,
+        tool_name: &'a str,
+    ) -> BoxFuture<'a, Result<()>> {
+        async move {
+            sqlx::query("UPDATE sessions SET current_tool = $1 WHERE host = $2 AND session_id = $3")
+                .bind(tool_name)
+                .bind(host)
+                .bind(session_id)
+                .execute(&self.pool)
+                .await?;
+            Ok(())
+        }
+        .instrument(debug_span!("set_current_tool"))
+        .boxed()
+    }
+
+    fn clear_current_tool<'a>(
+        &'a self,
+        host: &'a str,
+        session_id: &'a str,
+        tool_name: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<()>> {
+        async move {
+            sqlx::query(
+                r#"
+                UPDATE sessions SET current_tool = NULL
+                WHERE host = $3 AND session_id = $1 AND ($2::TEXT IS NULL OR current_tool = $2)
+                "#,
+            )
+            .bind(session_id)
+            .bind(tool_name)
+            .bind(host)
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        }
+        .instrument(debug_span!("clear_current_tool"))
+        .boxed()
+    }
+
+    fn set_session_model<'a>(
+        &'a self,
+        host: &'a str,
+        session_id: &'a str,
+        model: &'a str,
+    ) -> BoxFuture<'a, Result<()>> {
+        async move {
+            sqlx::query("UPDATE sessions SET model = $1 WHERE host = $2 AND session_id = $3")
+                .bind(model)
+                .bind(host)
+                .bind(session_id)
+                .execute(&self.pool)
+                .await?;
+            Ok(())
+        }
+        .instrument(debug_span!("set_session_model"))
+        .boxed()
+    }
+
+    fn set_session_git_branch<'a>(
+        &'a self,
+        host: &'a str,
+        session_id: &'a str,
+        branch: &'a str,
+    ) -> BoxFuture<'a, Result<()>> {
+        async move {
+            sqlx::query("UPDATE sessions SET git_branch = $1 WHERE host = $2 AND session_id = $3")
+                .bind(branch)
+                .bind(host)
+                .bind(session_id)
+                .execute(&self.pool)
+                .await?;
+            Ok(())
+        }
+        .instrument(debug_span!("set_session_git_branch"))
+        .boxed()
+    }
+
+    fn set_last_message<'a>(
+        &'a self,
+        host: &'a str,
+        session_id: &'a str,
+        message: &'a str,
+    ) -> BoxFuture<'a, Result<()>> {
+        async move {
+            sqlx::query("UPDATE sessions SET last_message = $1 WHERE host = $2 AND session_id = $3")
+                .bind(message)
+                .bind(host)
+                .bind(session_id)
+                .execute(&self.pool)
+                .await?;
+            Ok(())
+        }
+        .instrument(debug_span!("set_last_message"))
+        .boxed()
+    }
+
+    fn set_transcript_summary<'a>(
+        &'a self,
+        host: &'a str,
+        session_id: &'a str,
+        assistant_text: Option<&'a str>,
+        tool_call: Option<&'a str>,
+        model: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<()>> {
+        async move {
+            sqlx::query(
+                r#"
+                UPDATE sessions SET
+                    last_assistant_text = COALESCE($1, last_assistant_text),
+                    last_tool_call = COALESCE($2, last_tool_call),
+                    model = COALESCE($3, model)
+                WHERE host = $5 AND session_id = $4
+                "#,
+            )
+            .bind(assistant_text)
+            .bind(tool_call)
+            .bind(model)
+            .bind(session_id)
+            .bind(host)
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        }
+        .instrument(debug_span!("set_transcript_summary"))
+        .boxed()
+    }
+
+    fn record_usage<'a>(
+        &'a self,
+        session_id: &'a str,
+        agent_name: &'a str,
+        message_id: Option<&'a str>,
+        model: Option<&'a str>,
+        usage: &'a TokenUsage,
+    ) -> BoxFuture<'a, Result<()>> {
+        async move {
+            sqlx::query(
+                r#"
+                INSERT INTO usage (id, session_id, agent_name, message_id, model, input_tokens, output_tokens,
+                                   cache_creation_input_tokens, cache_read_input_tokens, recorded_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                ON CONFLICT (message_id) DO UPDATE SET
+                    model = COALESCE(excluded.model, usage.model),
+                    input_tokens = GREATEST(usage.input_tokens, excluded.input_tokens),
+                    output_tokens = GREATEST(usage.output_tokens, excluded.output_tokens),
+                    cache_creation_input_tokens =
+                        GREATEST(usage.cache_creation_input_tokens, excluded.cache_creation_input_tokens),
+                    cache_read_input_tokens = GREATEST(usage.cache_read_input_tokens, excluded.cache_read_input_tokens)
+                "#,
+            )
+            .bind(Uuid::new_v4().to_string())
+            .bind(session_id)
+            .bind(agent_name)
+            .bind(message_id)
+            .bind(model)
+            .bind(usage.input_tokens)
+            .bind(usage.output_tokens)
+            .bind(usage.cache_creation_input_tokens)
+            .bind(usage.cache_read_input_tokens)
+            .bind(Utc::now())
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        }
+        .instrument(debug_span!("record_usage"))
+        .boxed()
+    }
+
+    fn get_session_usage<'a>(&'a self, session_id: &'a str) -> BoxFuture<'a, Result<SessionUsage>> {
+        self.session_usage(session_id).instrument(debug_span!("get_session_usage")).boxed()
+    }
+
+    fn get_usage_by_session_model(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'_, Result<Vec<ModelUsageRow>>> {
+        async move {
+            let rows = sqlx::query_as(&format!(
+                r#"
+                SELECT {MODEL_USAGE_COLUMNS}
+                FROM usage u
+                JOIN sessions s ON s.session_id = u.session_id
+                WHERE ($1::TIMESTAMPTZ IS NULL OR u.recorded_at >= $1)
+                AND ($2::TIMESTAMPTZ IS NULL OR u.recorded_at < $2)
+                GROUP BY s.host, u.session_id, s.project_name, u.model
+                ORDER BY MAX(u.recorded_at) DESC
+                "#
+            ))
+            .bind(from)
+            .bind(to)
+            .fetch_all(&self.pool)
+            .await?;
+            Ok(rows)
+        }
+        .instrument(debug_span!("get_usage_by_session_model"))
+        .boxed()
+    }
+
+    fn get_usage_for_sessions<'a>(&'a self, session_ids: &'a [String]) -> BoxFuture<'a, Result<Vec<ModelUsageRow>>> {
+        async move {
+            if session_ids.is_empty() {
+                return Ok(Vec::new());
+            }
+            let rows = sqlx::query_as(&format!(
+                r#"
+                SELECT {MODEL_USAGE_COLUMNS}
+                FROM usage u
+                JOIN sessions s ON s.session_id = u.session_id
+                WHERE u.session_id = ANY($1)
+                GROUP BY s.host, u.session_id, s.project_name, u.model
+                "#
+            ))
+            .bind(session_ids)
+            .fetch_all(&self.pool)
+            .await?;
+            Ok(rows)
+        }
+        .instrument(debug_span!("get_usage_for_sessions"))
+        .boxed()
+    }
+
+    fn get_active_usage_since(&self, since: DateTime<Utc>) -> BoxFuture<'_, Result<Vec<ModelUsageRow>>> {
+        async move {
+            let rows = sqlx::query_as(&format!(
+                r#"
+                SELECT {MODEL_USAGE_COLUMNS}
+                FROM usage u
+                JOIN sessions s ON s.session_id = u.session_id
+                WHERE s.status != 'completed' AND u.recorded_at >= $1
+                GROUP BY s.host, u.session_id, s.project_name, u.model
+                "#
+            ))
+            .bind(since)
+            .fetch_all(&self.pool)
+            .await?;
+            Ok(rows)
+        }
+        .instrument(debug_span!("get_active_usage_since"))
+        .boxed()
+    }
+
+    fn get_session_events_in_range(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'_, Result<Vec<EventRow>>> {
+        async move {
+            let rows = sqlx::query_as(&format!(
+                r#"
+                SELECT {EVENT_ROW_COLUMNS}
+                FROM events e
+                JOIN sessions s ON s.host = e.host AND s.session_id = e.session_id
+                WHERE ($2::TIMESTAMPTZ IS NULL OR e.timestamp < $2)
+                AND (e.host, e.session_id) IN (
+                    SELECT host, session_id FROM events
+                    WHERE ($1::TIMESTAMPTZ IS NULL OR timestamp >= $1)
+                    AND ($2::TIMESTAMPTZ IS NULL OR timestamp < $2)
+                )
+                ORDER BY e.host, e.session_id, e.timestamp
+                "#
+            ))
+            .bind(from)
+            .bind(to)
+            .fetch_all(&self.pool)
+            .await?;
+            Ok(rows)
+        }
+        .instrument(debug_span!("get_session_events_in_range"))
+        .boxed()
+    }
+
+    fn get_session_event_rows<'a>(&'a self, session_id: &'a str) -> BoxFuture<'a, Result<Vec<EventRow>>> {
+        async move {
+            let rows = sqlx::query_as(&format!(
+                r#"
+                SELECT {EVENT_ROW_COLUMNS}
+                FROM events e
+                JOIN sessions s ON s.session_id = e.session_id
+                WHERE e.session_id = $1
+                ORDER BY e.timestamp
+                "#
+            ))
+            .bind(session_id)
+            .fetch_all(&self.pool)
+            .await?;
+            Ok(rows)
+        }
+        .instrument(debug_span!("get_session_event_rows"))
+        .boxed()
+    }
+
+    fn get_tool_timeline<'a>(&'a self, session_id: &'a str) -> BoxFuture<'a, Result<Vec<TimelineEntry>>> {
+        async move {
+            let calls: Vec<ToolCallRow> = sqlx::query_as(
+                r#"
+                SELECT agent_name, tool_name, started_at, ended_at, duration_ms
+                FROM tool_invocations
+                WHERE session_id = $1
+                ORDER BY started_at
+                "#,
+            )
+            .bind(session_id)
+            .fetch_all(&self.pool)
+            .await?;
+            Ok(calls
+                .into_iter()
+                .map(|call| TimelineEntry::Tool {
+                    at: call.started_at,
+                    agent_name: call.agent_name,
+                    tool_name: call.tool_name,
+                    ended_at: call.ended_at,
+                    duration_ms: call.duration_ms,
+                })
+                .collect())
+        }
+        .instrument(debug_span!("get_tool_timeline"))
+        .boxed()
+    }
+
+    fn get_completed_sessions(&self) -> BoxFuture<'_, Result<Vec<CompletedSession>>> {
+        async move {
+            let sessions = sqlx::query_as(
+                "SELECT host, session_id, updated_at AS ended_at FROM sessions WHERE status = 'completed'",
+            )
+            .fetch_all(&self.pool)
+            .await?;
+            Ok(sessions)
+        }
+        .instrument(debug_span!("get_completed_sessions"))
+        .boxed()
+    }
+
+    fn get_session_durations(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'_, Result<Vec<SessionDuration>>> {
+        async move {
+            let durations: Vec<SessionDuration> = sqlx::query_as(
+                r#"
+                SELECT host, session_id, project_name, started_at, ended_at FROM (
+                    SELECT host, session_id, project_name, started_at, ended_at FROM session_durations
+                    UNION ALL
+                    SELECT host, session_id, project_name, created_at, updated_at FROM sessions
+                    WHERE status = 'completed'
+                ) d
+                WHERE ($1::TIMESTAMPTZ IS NULL OR ended_at >= $1)
+                AND ($2::TIMESTAMPTZ IS NULL OR ended_at < $2)
+                "#,
+            )
+            .bind(from)
+            .bind(to)
+            .fetch_all(&self.pool)
+            .await?;
+            Ok(durations
+                .into_iter()
+                .map(|d| SessionDuration { duration_seconds: (d.ended_at - d.started_at).num_seconds().max(0), ..d })
+                .collect())
+        }
+        .instrument(debug_span!("get_session_durations"))
+        .boxed()
+    }
+
+    fn get_hourly_event_counts(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'_, Result<Vec<HourlyEventCount>>> {
+        async move {
+            let counts = sqlx::query_as(
+                r#"
+                SELECT hour, host, SUM(events)::BIGINT AS events FROM (
+                    SELECT hour, host, events FROM hourly_events
+                    UNION ALL
+                    SELECT date_trunc('hour', timestamp), host, 1 FROM events
+                ) h
+                WHERE ($1::TIMESTAMPTZ IS NULL OR hour >= $1)
+                AND ($2::TIMESTAMPTZ IS NULL OR hour < $2)
+                GROUP BY hour, host
+                "#,
+            )
+            .bind(from)
+            .bind(to)
+            .fetch_all(&self.pool)
+            .await?;
+            Ok(counts)
+        }
+        .instrument(debug_span!("get_hourly_event_counts"))
+        .boxed()
+    }
+
+    fn get_archived_intervals(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> BoxFuture<'_, Result<Vec<ActiveInterval>>> {
+        async move {
+            let intervals = sqlx::query_as(
+                r#"
+                SELECT host, session_id, project_name, started_at AS start, ended_at AS "end"
+                FROM active_intervals
+                WHERE ended_at > $1 AND started_at < $2
+                ORDER BY started_at
+                "#,
+            )
+            .bind(from)
+            .bind(to)
+            .fetch_all(&self.pool)
+            .await?;
+            Ok(intervals)
+        }
+        .instrument(debug_span!("get_archived_intervals"))
+        .boxed()
+    }
+
+    fn get_daily_stats(&self) -> BoxFuture<'_, Result<DailyActivity>> {
+        async move {
+            let rows: Vec<(NaiveDate, String, i64, i64, i64)> =
+                sqlx::query_as("SELECT day, host, sessions, active_seconds, completions FROM daily_stats")
+                    .fetch_all(&self.pool)
+                    .await?;
+            Ok(rows
+                .into_iter()
+                .map(|(day, host, sessions, active_seconds, completions)| {
+                    ((day, host), DailyTotals { sessions, active_seconds, completions })
+                })
+                .collect())
+        }
+        .instrument(debug_span!("get_daily_stats"))
+        .boxed()
+    }
+
+    fn get_event_counts_since(&self, since: DateTime<Utc>) -> BoxFuture<'_, Result<Vec<EventCountRow>>> {
+        async move {
+            let counts = sqlx::query_as(
+                r#"
+                SELECT host, FLOOR(EXTRACT(EPOCH FROM (timestamp - $1)) / 60)::BIGINT AS minute, COUNT(*) AS events
+                FROM events
+                WHERE timestamp >= $1
+                GROUP BY host, minute
+                "#,
+            )
+            .bind(since)
+            .fetch_all(&self.pool)
+            .await?;
+            Ok(counts)
+        }
+        .instrument(debug_span!("get_event_counts_since"))
+        .boxed()
+    }
+
+    fn get_usage_since(&self, since: DateTime<Utc>) -> BoxFuture<'_, Result<WindowUsage>> {
+        async move {
+            let usage = sqlx::query_as(
+                r#"
+                SELECT COALESCE(SUM(input_tokens), 0)::BIGINT AS input_tokens,
+                       COALESCE(SUM(output_tokens), 0)::BIGINT AS output_tokens,
+                       COALESCE(SUM(cache_creation_input_tokens), 0)::BIGINT AS cache_creation_input_tokens,
+                       COALESCE(SUM(cache_read_input_tokens), 0)::BIGINT AS cache_read_input_tokens,
+                       COUNT(*) AS messages,
+                       MIN(recorded_at) AS oldest
+                FROM usage
+                WHERE recorded_at >= $1
+                "#,
+            )
+            .bind(since)
+            .fetch_one(&self.pool)
+            .await?;
+            Ok(usage)
+        }
+        .instrument(debug_span!("get_usage_since"))
+        .boxed()
+    }
+
+    fn get_tool_stats<'a>(&'a self, session_id: &'a str) -> BoxFuture<'a, Result<Vec<ToolStats>>> {
+        async move {
+            let stats = sqlx::query_as(
+                r#"
+                SELECT tool_name,
+                       COUNT(*) AS invocations,
+                       COUNT(duration_ms) AS completed,
+                       COUNT(*) - COUNT(duration_ms) AS in_flight,
+                       COALESCE(SUM(duration_ms), 0)::BIGINT AS total_ms,
+                       AVG(duration_ms)::DOUBLE PRECISION AS avg_ms,
+                       MIN(duration_ms) AS min_ms,
+                       MAX(duration_ms) AS max_ms
+                FROM tool_invocations
+                WHERE session_id = $1
+                GROUP BY tool_name
+                ORDER BY total_ms DESC
+                "#,
+            )
+            .bind(session_id)
+            .fetch_all(&self.pool)
+            .await?;
+            Ok(stats)
+        }
+        .instrument(debug_span!("get_tool_stats"))
+        .boxed()
+    }
+
+    fn get_active_sessions(&self) -> BoxFuture<'_, Result<Vec<SessionWithAgents>>> {
+        async move {
+            let mut sessions: Vec<SessionWithAgents> = sqlx::query_as(
+                r#"
+                SELECT id, host, session_id, project_path, project_name, status, model, git_branch, current_tool,
+                       last_message, last_assistant_text, last_tool_call, pinned, muted, hidden, notes, created_at,
+                       updated_at, pruned_events
+                FROM sessions
+                WHERE (status != 'completed' OR pinned) AND deleted_at IS NULL
+                ORDER BY pinned DESC, created_at DESC
+                "#,
+            )
+            .fetch_all(&self.pool)
+            .await?;
+            self.fill_sessions(&mut sessions).await?;
+            Ok(sessions)
+        }
+        .instrument(debug_span!("get_active_sessions"))
+        .boxed()
+    }
+
+    fn get_trash(&self) -> BoxFuture<'_, Result<Vec<SessionWithAgents>>> {
+        async move {
+            let mut sessions: Vec<SessionWithAgents> = sqlx::query_as(
+                r#"
+                SELECT id, host, session_id, project_path, project_name, status, model, git_branch, current_tool,
+                       last_message, last_assistant_text, last_tool_call, pinned, muted, hidden, notes, created_at,
+                       updated_at, pruned_events, deleted_at
+                FROM sessions
+                WHERE deleted_at IS NOT NULL
+                ORDER BY deleted_at DESC
+                "#,
+            )
+            .fetch_all(&self.pool)
+            .await?;
+            self.fill_sessions(&mut sessions).await?;
+            Ok(sessions)
+        }
+        .instrument(debug_span!("get_trash"))
+        .boxed()
+    }
+
+    fn get_session_page<'a>(
+        &'a self,
+        page: &'a SessionPage<'a>,
+    ) -> BoxFuture<'a, Result<(Vec<SessionWithAgents>, u64)>> {
+        async move {
+            let sql = page.sql(&DIALECT);
+            let select = format!(
+                r#"
+                SELECT s.id, s.host, s.session_id, s.project_path, s.project_name, s.status, s.model, s.git_branch,
+                       s.current_tool, s.last_message, s.last_assistant_text, s.last_tool_call, s.pinned, s.muted,
+                       s.hidden, s.notes, s.created_at, s.updated_at, s.pruned_events
+                {}{}
+                ORDER BY {}{}
+                "#,
+                sql.from, sql.after, sql.order_by, sql.limit
+            );
+            let query = bind_params(sqlx::query_as(&select), &sql.params);
+            let mut sessions: Vec<SessionWithAgents> =
+                bind_params(query, &sql.after_params).fetch_all(&self.pool).await?;
+            self.fill_sessions(&mut sessions).await?;
+            let count = format!("SELECT COUNT(*) {}", sql.from);
+            let (total,): (i64,) = bind_params(sqlx::query_as(&count), &sql.params).fetch_one(&self.pool).await?;
+            Ok((sessions, total as u64))
+        }
+        .instrument(debug_span!("get_session_page"))
+        .boxed()
+    }
+
+    fn get_workspace_sessions<'a>(&'a self, workspace: &'a str) -> BoxFuture<'a, Result<Vec<EditorSession>>> {
+        async move {
+            let (workspace, prefix) = super::workspace_prefix(workspace);
+            let sessions = sqlx::query_as(
+                r#"
+                SELECT s.host, s.session_id, s.project_name, s.project_path, s.status, s.updated_at,
+                       (SELECT COUNT(*) FROM agents a
+                        WHERE a.host = s.host AND a.session_id = s.session_id AND a.status != 'completed') AS active_agents
+                FROM sessions s
+                WHERE s.status != 'completed' AND s.deleted_at IS NULL
+                AND (s.project_path = $1 OR substr(s.project_path, 1, length($2)) = $2)
+                ORDER BY s.updated_at DESC
+                "#,
+            )
+            .bind(workspace)
+            .bind(&prefix)
+            .fetch_all(&self.pool)
+            .await?;
+            Ok(sessions)
+        }
+        .instrument(debug_span!("get_workspace_sessions"))
+        .boxed()
+    }
+
+    fn set_session_notes<'a>(
+        &'a self,
+        host: Option<&'a str>,
+        session_id: &'a str,
+        notes: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<bool>> {
+        async move {
+            let result =
+                sqlx::query("UPDATE sessions SET notes = $1 WHERE session_id = $2 AND ($3::TEXT IS NULL OR host = $3)")
+                    .bind(notes)
+                    .bind(session_id)
+                    .bind(host)
+                .execute(&self.pool)
+                .await?;
+            Ok(result.rows_affected() > 0)
+        }
+        .instrument(debug_span!("set_session_notes"))
+        .boxed()
+    }
+
+    fn get_session_tags<'a>(
+        &'a self,
+        host: Option<&'a str>,
+        session_id: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<String>>> {
+        self.session_tags(host, session_id).instrument(debug_span!("get_session_tags")).boxed()
+    }
+
+    fn add_session_tags<'a>(
+        &'a self,
+        host: Option<&'a str>,
+        session_id: &'a str,
+        tags: &'a [String],
+    ) -> BoxFuture<'a, Result<()>> {
+        async move {
+            let now = Utc::now();
+            for tag in tags {
+                sqlx::query(
+                    r#"
+                    INSERT INTO session_tags (host, session_id, tag, created_at)
+                    SELECT host, session_id, $2, $3 FROM sessions
+                    WHERE session_id = $1 AND ($4::TEXT IS NULL OR host = $4)
+                    ON CONFLICT DO NOTHING
+                    "#,
+                )
+                .bind(session_id)
+                .bind(tag)
+                .bind(now)
+                .bind(host)
+                .execute(&self.pool)
+                .await?;
+            }
+            Ok(())
+        }
+        .instrument(debug_span!("add_session_tags"))
+        .boxed()
+    }
+
+    fn remove_session_tags<'a>(
+        &'a self,
+        host: Option<&'a str>,
+        session_id: &'a str,
+        tags: &'a [String],
+    ) -> BoxFuture<'a, Result<()>> {
+        async move {
+            sqlx::query(
+                "DELETE FROM session_tags WHERE session_id = $1 AND ($3::TEXT IS NULL OR host = $3) AND tag = ANY($2)",
+            )
+            .bind(session_id)
+            .bind(tags)
+            .bind(host)
+                .execute(&self.pool)
+                .await?;
+            Ok(())
+        }
+        .instrument(debug_span!("remove_session_tags"))
+        .boxed()
+    }
+
+    fn session_exists<'a>(&'a self, host: Option<&'a str>, session_id: &'a str) -> BoxFuture<'a, Result<bool>> {
+        async move {
+            let exists = sqlx::query_scalar(
+                "SELECT EXISTS (SELECT 1 FROM sessions WHERE session_id = $1 AND ($2::TEXT IS NULL OR host = $2))",
+            )
+            .bind(session_id)
+            .bind(host)
+            .fetch_one(&self.pool)
+            .await?;
+            Ok(exists)
+        }
+        .instrument(debug_span!("session_exists"))
+        .boxed()
+    }
+
+    fn mark_session_completed<'a>(&'a self, host: &'a str, session_id: &'a str) -> BoxFuture<'a, Result<()>> {
+        async move {
+            self.set_status(Some(host), session_id, "completed", None).await?;
+            Ok(())
+        }
+        .instrument(debug_span!("mark_session_completed"))
+        .boxed()
+    }
+
+    fn mark_active_session_idle<'a>(&'a self, host: &'a str, session_id: &'a str) -> BoxFuture<'a, Result<()>> {
+        async move {
+            self.set_status(Some(host), session_id, "idle", Some(&["active"])).await?;
+            Ok(())
+        }
+        .instrument(debug_span!("mark_active_session_idle"))
+        .boxed()
+    }
+
+    fn acknowledge_session<'a>(&'a self, host: Option<&'a str>, session_id: &'a str) -> BoxFuture<'a, Result<bool>> {
+        self.set_status(host, session_id, "acknowledged", Some(&["waiting_input", "needs_permission"]))
+            .instrument(debug_span!("acknowledge_session"))
+            .boxed()
+    }
+
+    fn set_session_pinned<'a>(
+        &'a self,
+        host: Option<&'a str>,
+        session_id: &'a str,
+        pinned: bool,
+    ) -> BoxFuture<'a, Result<bool>> {
+        async move {
+            let result =
+                sqlx::query("UPDATE sessions SET pinned = $1 WHERE session_id = $2 AND ($3::TEXT IS NULL OR host = $3)")
+                    .bind(pinned)
+                    .bind(session_id)
+                    .bind(host)
+                .execute(&self.pool)
+                .await?;
+            Ok(result.rows_affected() > 0)
+        }
+        .instrument(debug_span!("set_session_pinned"))
+        .boxed()
+    }
+
+    fn trash_session<'a>(&'a self, host: Option<&'a str>, session_id: &'a str) -> BoxFuture<'a, Result<bool>> {
+        async move {
+            let result = sqlx::query(
+                r#"
+                UPDATE sessions SET deleted_at = COALESCE(deleted_at, $1)
+                WHERE session_id = $2 AND ($3::TEXT IS NULL OR host = $3)
+                "#,
+            )
+            .bind(Utc::now())
+            .bind(session_id)
+            .bind(host)
+            .execute(&self.pool)
+            .await?;
+            Ok(result.rows_affected() > 0)
+        }
+        .instrument(debug_span!("trash_session"))
+        .boxed()
+    }
+
+    fn restore_session<'a>(&'a self, host: Option<&'a str>, session_id: &'a str) -> BoxFuture<'a, Result<bool>> {
+        async move {
+            let result = sqlx::query(
+                r#"
+                UPDATE sessions SET deleted_at = NULL
+                WHERE session_id = $1 AND ($2::TEXT IS NULL OR host = $2) AND deleted_at IS NOT NULL
+                "#,
+            )
+            .bind(session_id)
+            .bind(host)
+            .execute(&self.pool)
+            .await?;
+            Ok(result.rows_affected() > 0)
+        }
+        .instrument(debug_span!("restore_session"))
+        .boxed()
+    }
+
+    fn set_session_muted<'a>(
+        &'a self,
+        host: Option<&'a str>,
+        session_id: &'a str,
+        muted: bool,
+        hidden: bool,
+    ) -> BoxFuture<'a, Result<bool>> {
+        async move {
+            let result = sqlx::query(
+                "UPDATE sessions SET muted = $1, hidden = $2 WHERE session_id = $3 AND ($4::TEXT IS NULL OR host = $4)",
+            )
+            .bind(muted)
+            .bind(muted && hidden)
+            .bind(session_id)
+            .bind(host)
+            .execute(&self.pool)
+            .await?;
+            Ok(result.rows_affected() > 0)
+        }
+        .instrument(debug_span!("set_session_muted"))
+        .boxed()
+    }
+
+    fn get_alert_rules(&self) -> BoxFuture<'_, Result<Vec<AlertRule>>> {
+        async move {
+            let rules = sqlx::query_as(
+                r#"
+                SELECT id, name, enabled, conditions, sinks, created_at, updated_at
+                FROM alert_rules
+                ORDER BY created_at
+                "#,
+            )
+            .fetch_all(&self.pool)
+            .await?;
+            Ok(rules)
+        }
+        .instrument(debug_span!("get_alert_rules"))
+        .boxed()
+    }
+
+    fn get_alert_rule<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<Option<AlertRule>>> {
+        self.alert_rule(id).instrument(debug_span!("get_alert_rule")).boxed()
+    }
+
+    fn insert_alert_rule<'a>(&'a self, rule: &'a AlertRuleInput) -> BoxFuture<'a, Result<AlertRule>> {
+        async move {
+            let id = Uuid::new_v4().to_string();
+            sqlx::query(
+                r#"
+                INSERT INTO alert_rules (id, name, enabled, conditions, sinks, created_at, updated_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $6)
+                "#,
+            )
+            .bind(&id)
+            .bind(&rule.name)
+            .bind(rule.enabled)
+            .bind(Json(&rule.conditions))
+            .bind(Json(&rule.sinks))
+            .bind(Utc::now())
+            .execute(&self.pool)
+            .await?;
+            self.alert_rule(&id).await?.context("inserted rule not found")
+        }
+        .instrument(debug_span!("insert_alert_rule"))
+        .boxed()
+    }
+
+    fn update_alert_rule<'a>(
+        &'a self,
+        id: &'a str,
+        rule: &'a AlertRuleInput,
+    ) -> BoxFuture<'a, Result<Option<AlertRule>>> {
+        async move {
+            let result = sqlx::query(
+                r#"
+                UPDATE alert_rules SET name = $1, enabled = $2, conditions = $3, sinks = $4, updated_at = $5
+                WHERE id = $6
+                "#,
+            )
+            .bind(&rule.name)
+            .bind(rule.enabled)
+            .bind(Json(&rule.conditions))
+            .bind(Json(&rule.sinks))
+            .bind(Utc::now())
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+            if result.rows_affected() == 0 {
+                return Ok(None);
+            }
+            self.alert_rule(id).await
+        }
+        .instrument(debug_span!("update_alert_rule"))
+        .boxed()
+    }
+
+    fn delete_alert_rule<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<bool>> {
+        async move {
+            let result = sqlx::query("DELETE FROM alert_rules WHERE id = $1").bind(id).execute(&self.pool).await?;
+            Ok(result.rows_affected() > 0)
+        }
+        .instrument(debug_span!("delete_alert_rule"))
+        .boxed()
+    }
+
+    fn get_project_aliases(&self) -> BoxFuture<'_, Result<Vec<ProjectAlias>>> {
+        async move {
+            let aliases = sqlx::query_as(
+                "SELECT project_path, display_name, created_at, updated_at FROM project_aliases ORDER BY display_name",
+            )
+            .fetch_all(&self.pool)
+            .await?;
+            Ok(aliases)
+        }
+        .instrument(debug_span!("get_project_aliases"))
+        .boxed()
+    }
+
+    fn set_project_alias<'a>(&'a self, project_path: &'a str, display_name: &'a str) -> BoxFuture<'a, Result<()>> {
+        async move {
+            sqlx::query(
+                r#"
+                INSERT INTO project_aliases (project_path, display_name, created_at, updated_at)
+                VALUES ($1, $2, $3, $3)
+                ON CONFLICT (project_path) DO UPDATE SET
+                    display_name = excluded.display_name,
+                    updated_at = excluded.updated_at
+                "#,
+            )
+            .bind(project_path)
+            .bind(display_name)
+            .bind(Utc::now())
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        }
+        .instrument(debug_span!("set_project_alias"))
+        .boxed()
+    }
+
+    fn delete_project_alias<'a>(&'a self, project_path: &'a str) -> BoxFuture<'a, Result<bool>> {
+        async move {
+            let result = sqlx::query("DELETE FROM project_aliases WHERE project_path = $1")
+                .bind(project_path)
+                .execute(&self.pool)
+                .await?;
+            Ok(result.rows_affected() > 0)
+        }
+        .instrument(debug_span!("delete_project_alias"))
+        .boxed()
+    }
+
+    fn get_setting<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<Option<String>>> {
+        async move {
+            let value = sqlx::query_scalar("SELECT value FROM settings WHERE key = $1")
+                .bind(key)
+                .fetch_optional(&self.pool)
+                .await?;
+            Ok(value)
+        }
+        .instrument(debug_span!("get_setting"))
+        .boxed()
+    }
+
+    fn set_setting<'a>(&'a self, key: &'a str, value: &'a str) -> BoxFuture<'a, Result<()>> {
+        async move {
+            sqlx::query(
+                r#"
+                INSERT INTO settings (key, value, updated_at)
+                VALUES ($1, $2, $3)
+                ON CONFLICT (key) DO UPDATE SET
+                    value = excluded.value,
+                    updated_at = excluded.updated_at
+                "#,
+            )
+            .bind(key)
+            .bind(value)
+            .bind(Utc::now())
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        }
+        .instrument(debug_span!("set_setting"))
+        .boxed()
+    }
+
+    fn insert_notification<'a>(&'a self, record: &'a NotificationRecord) -> BoxFuture<'a, Result<()>> {
+        async move {
+            sqlx::query(
+                r#"
+                INSERT INTO notifications
+                    (sink, kind, rule_id, session_id, project_name, status, message, result, error, created_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                "#,
+            )
+            .bind(&record.sink)
+            .bind(&record.kind)
+            .bind(&record.rule_id)
+            .bind(&record.session_id)
+            .bind(&record.project_name)
+            .bind(&record.status)
+            .bind(&record.message)
+            .bind(&record.result)
+            .bind(&record.error)
+            .bind(record.created_at)
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        }
+        .instrument(debug_span!("insert_notification"))
+        .boxed()
+    }
+
+    fn get_notifications(&self, limit: u32, offset: u32) -> BoxFuture<'_, Result<(Vec<NotificationRecord>, i64)>> {
+        async move {
+            let notifications = sqlx::query_as(
+                r#"
+                SELECT n.id, n.sink, n.kind, n.rule_id, r.name AS rule_name, n.session_id, n.project_name,
+                       n.status, n.message, n.result, n.error, n.created_at
+                FROM notifications n
+                LEFT JOIN alert_rules r ON r.id = n.rule_id
+                ORDER BY n.id DESC
+                LIMIT $1 OFFSET $2
+                "#,
+            )
+            .bind(i64::from(limit))
+            .bind(i64::from(offset))
+            .fetch_all(&self.pool)
+            .await?;
+            let total = sqlx::query_scalar("SELECT COUNT(*) FROM notifications").fetch_one(&self.pool).await?;
+            Ok((notifications, total))
+        }
+        .instrument(debug_span!("get_notifications"))
+        .boxed()
+    }
+
+    fn get_session_notifications<'a>(&'a self, session_id: &'a str) -> BoxFuture<'a, Result<Vec<NotificationRecord>>> {
+        async move {
+            let notifications = sqlx::query_as(
+                r#"
+                SELECT n.id, n.sink, n.kind, n.rule_id, r.name AS rule_name, n.session_id, n.project_name,
+                       n.status, n.message, n.result, n.error, n.created_at
+                FROM notifications n
+                LEFT JOIN alert_rules r ON r.id = n.rule_id
+                WHERE n.session_id = $1
+                ORDER BY n.id
+                "#,
+            )
+            .bind(session_id)
+            .fetch_all(&self.pool)
+            .await?;
+            Ok(notifications)
+        }
+        .instrument(debug_span!("get_session_notifications"))
+        .boxed()
+    }
+
+    fn prune_notifications(&self, retention: Duration) -> BoxFuture<'_, Result<()>> {
+        async move {
+            sqlx::query("DELETE FROM notifications WHERE created_at < $1")
+                .bind(Utc::now() - retention)
+                .execute(&self.pool)
+                .await?;
+            Ok(())
+        }
+        .instrument(debug_span!("prune_notifications"))
+        .boxed()
+    }
+
+    fn insert_dead_letter<'a>(
+        &'a self,
+        path: &'a str,
+        body: &'a str,
+        error: &'a str,
+        keep: u32,
+    ) -> BoxFuture<'a, Result<()>> {
+        async move {
+            let mut tx = self.pool.begin().await?;
+            sqlx::query("INSERT INTO dead_letters (path, body, error, received_at) VALUES ($1, $2, $3, $4)")
+                .bind(path)
+                .bind(body)
+                .bind(error)
+                .bind(Utc::now())
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query(
+                "DELETE FROM dead_letters WHERE id NOT IN (SELECT id FROM dead_letters ORDER BY id DESC LIMIT $1)",
+            )
+            .bind(i64::from(keep))
+            .execute(&mut *tx)
+            .await?;
+            tx.commit().await?;
+            Ok(())
+        }
+        .instrument(debug_span!("insert_dead_letter"))
+        .boxed()
+    }
+
+    fn get_dead_letters(&self, limit: u32) -> BoxFuture<'_, Result<Vec<DeadLetter>>> {
+        async move {
+            let letters = sqlx::query_as(
+                "SELECT id, path, body, error, received_at FROM dead_letters ORDER BY id DESC LIMIT $1",
+            )
+            .bind(i64::from(limit))
+            .fetch_all(&self.pool)
+            .await?;
+            Ok(letters)
+        }
+        .instrument(debug_span!("get_dead_letters"))
+        .boxed()
+    }
+
+    fn insert_api_key<'a>(
+        &'a self,
+        name: &'a str,
+        digest: &'a str,
+        admin: bool,
+    ) -> BoxFuture<'a, Result<Option<ApiKey>>> {
+        async move {
+            let created_at = Utc::now();
+            let result = sqlx::query(
+                "INSERT INTO api_keys (name, digest, created_at, admin) VALUES ($1, $2, $3, $4) ON CONFLICT DO NOTHING",
+            )
+            .bind(name)
+            .bind(digest)
+            .bind(created_at)
+            .bind(admin)
+            .execute(&self.pool)
+            .await?;
+            let key = ApiKey { name: name.to_string(), created_at, revoked_at: None, admin };
+            Ok((result.rows_affected() > 0).then_some(key))
+        }
+        .instrument(debug_span!("insert_api_key"))
+        .boxed()
+    }
+
+    fn get_api_keys(&self) -> BoxFuture<'_, Result<Vec<ApiKey>>> {
+        async move {
+            let keys =
+                sqlx::query_as("SELECT name, created_at, revoked_at, admin FROM api_keys ORDER BY created_at, name")
+                    .fetch_all(&self.pool)
+                    .await?;
+            Ok(keys)
+        }
+        .instrument(debug_span!("get_api_keys"))
+        .boxed()
+    }
+
+    fn get_live_api_keys(&self) -> BoxFuture<'_, Result<Vec<(String, String, bool)>>> {
+        async move {
+            let keys = sqlx::query_as("SELECT digest, name, admin FROM api_keys WHERE revoked_at IS NULL")
+                .fetch_all(&self.pool)
+                .await?;
+            Ok(keys)
+        }
+        .instrument(debug_span!("get_live_api_keys"))
+        .boxed()
+    }
+
+    fn revoke_api_key<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<bool>> {
+        async move {
+            let result = sqlx::query("UPDATE api_keys SET revoked_at = $1 WHERE name = $2 AND revoked_at IS NULL")
+                .bind(Utc::now())
+                .bind(name)
+                .execute(&self.pool)
+                .await?;
+            Ok(result.rows_affected() > 0)
+        }
+        .instrument(debug_span!("revoke_api_key"))
+        .boxed()
+    }
+
+    fn clear_all_sessions(&self) -> BoxFuture<'_, Result<()>> {
+        async move {
+            let mut tx = self.pool.begin().await?;
+            for table in SESSION_CHILD_TABLES {
+                sqlx::query(&format!("DELETE FROM {table}")).execute(&mut *tx).await?;
+            }
+            sqlx::query("DELETE FROM sessions").execute(&mut *tx).await?;
+            tx.commit().await?;
+            Ok(())
+        }
+        .instrument(debug_span!("clear_all_sessions"))
+        .boxed()
+    }
+
+    fn cleanup_old_completed(
+        &self,
+        completed_ttl: Option<Duration>,
+        usage_retention: Duration,
+        trash_retention: Duration,
+        keep_events: bool,
+    ) -> BoxFuture<'_, Result<CleanupReport>> {
+        self.cleanup(completed_ttl, usage_retention, trash_retention, keep_events)
+            .instrument(debug_span!("cleanup_old_completed"))
+            .boxed()
+    }
+
+    /// Plain VACUUM: dead rows become reusable space without locking tables,
+    /// so the database rarely shrinks, but stops growing.
+    fn maintain(&self) -> BoxFuture<'_, Result<MaintenanceReport>> {
+        async move {
+            let started = std::time::Instant::now();
+            let size = "SELECT pg_database_size(current_database())";
+            let size_before: i64 = sqlx::query_scalar(size).fetch_one(&self.pool).await?;
+            self.pool.execute("VACUUM (ANALYZE)").await?;
+            let size_after: i64 = sqlx::query_scalar(size).fetch_one(&self.pool).await?;
+            Ok(MaintenanceReport {
+                size_before: size_before as u64,
+                size_after: size_after as u64,
+                full_vacuum: false,
+                duration_ms: started.elapsed().as_secs_f64() * 1000.0,
+            })
+        }
+        .instrument(debug_span!("maintain"))
+        .boxed()
+    }
+
+    /// The server manages its own WAL.
+    fn checkpoint(&self) -> BoxFuture<'_, Result<()>> {
+        futures::future::ok(()).boxed()
+    }
+
+    fn wal_size(&self) -> Option<u64> {
+        None
+    }
+
+    fn file_size(&self) -> Option<u64> {
+        None
+    }
+
+    /// Postgres keeps the space deleted rows leave for reuse rather than
+    /// giving it back, so its size says nothing about what pruning would
+    /// gain; that's left to the database's own administration.
+    fn used_size(&self) -> BoxFuture<'_, Result<Option<u64>>> {
+        futures::future::ok(None).boxed()
+    }
+
+    fn prune_archived_events(&self, limit: u32) -> BoxFuture<'_, Result<u64>> {
+        async move {
+            let deleted: i64 = sqlx::query_scalar(
+                r#"
+                WITH pruned AS (
+                    DELETE FROM events WHERE seq IN (
+                        SELECT seq FROM events e
+                        WHERE NOT EXISTS (
+                            SELECT 1 FROM sessions s
+                            WHERE s.host = e.host AND s.session_id = e.session_id AND s.status != 'completed'
+                              AND s.deleted_at IS NULL
+                        )
+                        ORDER BY seq
+                        LIMIT $1
+                    )
+                    RETURNING host, session_id, timestamp
+                ), hourly AS (
+                    INSERT INTO hourly_events (hour, host, events)
+                    SELECT date_trunc('hour', timestamp) AS hour, host, COUNT(*) FROM pruned
+                    GROUP BY 1, host
+                    ON CONFLICT (hour, host) DO UPDATE SET events = hourly_events.events + excluded.events
+                ), counted AS (
+                    UPDATE sessions s SET pruned_events = s.pruned_events + p.events
+                    FROM (SELECT host, session_id, COUNT(*) AS events FROM pruned GROUP BY host, session_id) p
+                    WHERE s.host = p.host AND s.session_id = p.session_id
+                )
+                SELECT COUNT(*) FROM pruned
+                "#,
+            )
+            .bind(i64::from(limit))
+            .fetch_one(&self.pool)
+            .await?;
+            Ok(deleted as u64)
+        }
+        .instrument(debug_span!("prune_archived_events"))
+        .boxed()
+    }
+
+    fn export_sessions(&self) -> BoxFuture<'_, Result<Vec<ExportedSession>>> {
+        async move {
+            let mut sessions: Vec<ExportedSession> = sqlx::query_as(
+                r#"
+                SELECT id, host, session_id, project_path, project_name, status, created_at, updated_at,
+                       current_tool, last_message, last_assistant_text, last_tool_call, model, pinned, muted, hidden,
+                       notes, git_branch, deleted_at, pruned_events
+                FROM sessions
+                ORDER BY created_at
+                "#,
+            )
+            .fetch_all(&self.pool)
+            .await?;
+            let tags: Vec<(String, String, String)> =
+                sqlx::query_as("SELECT host, session_id, tag FROM session_tags ORDER BY created_at, tag")
+                    .fetch_all(&self.pool)
+                    .await?;
+            super::attach_tags(&mut sessions, tags);
+            Ok(sessions)
+        }
+        .instrument(debug_span!("export_sessions"))
+        .boxed()
+    }
+
+    fn export_agents(&self) -> BoxFuture<'_, Result<Vec<ExportedAgent>>> {
+        async move {
+            let agents = sqlx::query_as(
+                "SELECT id, host, session_id, agent_name, parent_session_id, status, created_at, updated_at FROM agents \
+                 ORDER BY created_at",
+            )
+            .fetch_all(&self.pool)
+            .await?;
+            Ok(agents)
+        }
+        .instrument(debug_span!("export_agents"))
+        .boxed()
+    }
+
+    fn export_events(
+        &self,
+        after: i64,
+        limit: u32,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'_, Result<Vec<(i64, ExportedEvent)>>> {
+        async move {
+            let rows: Vec<ExportEventRow> = sqlx::query_as(
+                r#"
+                SELECT seq, id, host, session_id, agent_name, event_type, payload, timestamp, request_id, source
+                FROM events
+                WHERE seq > $1
+                AND ($3::TIMESTAMPTZ IS NULL OR timestamp >= $3)
+                AND ($4::TIMESTAMPTZ IS NULL OR timestamp < $4)
+                ORDER BY seq
+                LIMIT $2
+                "#,
+            )
+            .bind(after)
+            .bind(i64::from(limit))
+            .bind(from)
+            .bind(to)
+            .fetch_all(&self.pool)
+            .await?;
+            Ok(rows.into_iter().map(|row| (row.seq, row.event)).collect())
+        }
+        .instrument(debug_span!("export_events"))
+        .boxed()
+    }
+
+    fn export_settings(&self) -> BoxFuture<'_, Result<Vec<ExportedSetting>>> {
+        async move {
+            let settings = sqlx::query_as("SELECT key, value, updated_at FROM settings ORDER BY key")
+                .fetch_all(&self.pool)
+                .await?;
+            Ok(settings)
+        }
+        .instrument(debug_span!("export_settings"))
+        .boxed()
+    }
+
+    fn import<'a>(&'a self, data: &'a Export, on_conflict: OnConflict) -> BoxFuture<'a, Result<ImportReport>> {
+        async move {
+            let mut report = ImportReport::default();
+            let mut tx = self.pool.begin().await?;
+
+            let sessions = format!(
+                r#"
+                INSERT INTO sessions (
+                    id, host, session_id, project_path, project_name, status, created_at, updated_at, current_tool,
+                    last_message, last_assistant_text, last_tool_call, model, pinned, muted, hidden, notes, git_branch,
+                    deleted_at, pruned_events
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20)
+                {}
+                "#,
+                super::import_conflict(on_conflict, "host, session_id", super::IMPORTED_SESSION_COLUMNS),
+            );
+            // Tags are listed in the order they were added; stagger their
+            // times so they keep it.
+            let tagged_at = Utc::now();
+            for s in &data.sessions {
+                let written = sqlx::query(&sessions)
+                    .bind(&s.id)
+                    .bind(&s.host)
+                    .bind(&s.session_id)
+                    .bind(&s.project_path)
+                    .bind(&s.project_name)
+                    .bind(&s.status)
+                    .bind(s.created_at)
+                    .bind(s.updated_at)
+                    .bind(&s.current_tool)
+                    .bind(&s.last_message)
+                    .bind(&s.last_assistant_text)
+                    .bind(&s.last_tool_call)
+                    .bind(&s.model)
+                    .bind(s.pinned)
+                    .bind(s.muted)
+                    .bind(s.hidden)
+                    .bind(&s.notes)
+                    .bind(&s.git_branch)
+                    .bind(s.deleted_at)
+                    .bind(s.pruned_events)
+                    .execute(&mut *tx)
+                    .await?
+                    .rows_affected()
+                    > 0;
+                super::count_import(&mut report.sessions, written);
+                if !written {
+                    continue;
+                }
+                sqlx::query("DELETE FROM session_tags WHERE host = $1 AND session_id = $2")
+                    .bind(&s.host)
+                    .bind(&s.session_id)
+                    .execute(&mut *tx)
+                    .await?;
+                for (i, tag) in s.tags.iter().enumerate() {
+                    sqlx::query(
+                        "INSERT INTO session_tags (host, session_id, tag, created_at) VALUES ($1, $2, $3, $4) \
+                         ON CONFLICT DO NOTHING",
+                    )
+                    .bind(&s.host)
+                    .bind(&s.session_id)
+                    .bind(tag)
+                    .bind(tagged_at + Duration::milliseconds(i as i64))
+                    .execute(&mut *tx)
+                    .await?;
+                }
+            }
+
+            let agents = format!(
+                r#"
+                INSERT INTO agents (id, host, session_id, agent_name, parent_session_id, status, created_at, updated_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                {}
+                "#,
+                super::import_conflict(on_conflict, "host, session_id, agent_name", super::IMPORTED_AGENT_COLUMNS),
+            );
+            for a in &data.agents {
+                let result = sqlx::query(&agents)
+                    .bind(&a.id)
+                    .bind(&a.host)
+                    .bind(&a.session_id)
+                    .bind(&a.agent_name)
+                    .bind(&a.parent_session_id)
+                    .bind(&a.status)
+                    .bind(a.created_at)
+                    .bind(a.updated_at)
+                    .execute(&mut *tx)
+                    .await?;
+                super::count_import(&mut report.agents, result.rows_affected() > 0);
+            }
+
+            for e in &data.events {
+                let result = sqlx::query(
+                    r#"
+                    INSERT INTO events
+                        (id, host, session_id, agent_name, event_type, payload, timestamp, request_id, source)
+                    VALUES ($1, $2, $3, $4, $5, $6::JSONB, $7, $8, $9)
+                    ON CONFLICT DO NOTHING
+                    "#,
+                )
+                .bind(&e.id)
+                .bind(&e.host)
+                .bind(&e.session_id)
+                .bind(&e.agent_name)
+                .bind(&e.event_type)
+                .bind(e.payload.to_string())
+                .bind(e.timestamp)
+                .bind(&e.request_id)
+                .bind(&e.source)
+                .execute(&mut *tx)
+                .await?;
+                super::count_import(&mut report.events, result.rows_affected() > 0);
+            }
+
+            let settings = format!(
+                "INSERT INTO settings (key, value, updated_at) VALUES ($1, $2, $3) {}",
+                super::import_conflict(on_conflict, "key", &["value", "updated_at"]),
+            );
+            for s in &data.settings {
+                let result = sqlx::query(&settings)
+                    .bind(&s.key)
+                    .bind(&s.value)
+                    .bind(s.updated_at)
+                    .execute(&mut *tx)
+                    .await?;
+                super::count_import(&mut report.settings, result.rows_affected() > 0);
+            }
+
+            tx.commit().await?;
+            Ok(report)
+        }
+        .instrument(debug_span!("import"))
+        .boxed()
+    }
+
+    fn backup<'a>(&'a self, _dest: &'a Path) -> BoxFuture<'a, Result<()>> {
+        futures::future::err(anyhow::anyhow!("Postgres databases are backed up with pg_dump")).boxed()
+    }
+}
@@ -0,0 +1,671 @@
+//! The default store: a SQLite file, through the queries in `db.rs`. Writes
+//! are retried while another connection holds the lock, see `db::retry`.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use futures::{future::BoxFuture, FutureExt};
+use sqlx::sqlite::{SqliteAutoVacuum, SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions};
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use tracing::error;
+
+use super::{SessionPage, Store};
+use crate::{
+    db::{self, retry},
+    models::{
+        ActiveInterval, AlertRule, AlertRuleInput, ApiKey, CleanupReport, CompletedSession, DailyActivity, DeadLetter,
+        EditorSession, EventCountRow, EventRow, Export, ExportedAgent, ExportedEvent, ExportedSession, ExportedSetting,
+        HourlyEventCount, ImportReport, MaintenanceReport, ModelUsageRow, NewEvent, NotificationRecord, OnConflict,
+        ProjectAlias, SearchHit, SessionDuration, SessionEvent, SessionUsage, SessionWithAgents, TimelineEntry,
+        TokenUsage, ToolStats, WindowUsage,
+    },
+};
+
+pub struct SqliteStore {
+    pool: SqlitePool,
+    location: String,
+    /// The database file; `None` in memory.
+    path: Option<PathBuf>,
+    /// The `-wal` file beside it.
+    wal_path: Option<PathBuf>,
+}
+
+impl SqliteStore {
+    /// Open or create the database at `path` and bring its schema up to date.
+    /// SQLite checkpoints the WAL itself once it reaches `wal_autocheckpoint`
+    /// pages; 0 leaves that to `checkpoint`.
+    ///
+    /// A corrupt file is moved aside to `<path>.corrupt-<timestamp>` and an
+    /// empty database started in its place, rather than failing every query
+    /// until someone deletes it.
+    pub async fn open(path: &Path, wal_autocheckpoint: u32) -> Result<Self> {
+        match Self::open_checked(path, wal_autocheckpoint).await {
+            Err(e) if db::is_corrupt(&e) => {
+                let moved = move_aside(path)?;
+                error!(
+                    "Database {} is corrupt ({e:#}); moved it to {} and starting with an empty one",
+                    path.display(),
+                    moved.display()
+                );
+                Self::open_checked(path, wal_autocheckpoint).await
+            }
+            result => result,
+        }
+    }
+
+    async fn open_checked(path: &Path, wal_autocheckpoint: u32) -> Result<Self> {
+        let connect_opts = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal)
+            // Takes effect only on a new file; `db::maintain` converts older ones.
+            .auto_vacuum(SqliteAutoVacuum::Incremental)
+            .foreign_keys(true)
+            // Hook bursts write from several connections at once; wait for the
+            // lock rather than failing with "database is locked".
+            .busy_timeout(std::time::Duration::from_secs(5))
+            .pragma("wal_autocheckpoint", wal_autocheckpoint.to_string());
+
+        let pool = SqlitePoolOptions::new()
+            .max_connections(5)
+            .connect_with(connect_opts)
+            .await
+            .context("failed to open SQLite database")?;
+
+        let ready = async {
+            db::integrity_check(&pool).await?;
+            db::init_db(&pool).await.context("failed to run schema migrations")
+        };
+        if let Err(e) = ready.await {
+            // Let go of the file before it is moved.
+            pool.close().await;
+            return Err(e);
+        }
+        let mut wal_path = path.as_os_str().to_owned();
+        wal_path.push("-wal");
+        Ok(Self {
+            pool,
+            location: path.display().to_string(),
+            path: Some(path.to_path_buf()),
+            wal_path: Some(wal_path.into()),
+        })
+    }
+
+    /// A fresh in-memory database, shared by every connection in the pool and
+    /// gone once the store is dropped. For demos and tests.
+    pub async fn memory() -> Result<Self> {
+        let connect_opts = SqliteConnectOptions::from_str("sqlite::memory:")?
+            .auto_vacuum(SqliteAutoVacuum::Incremental)
+            .foreign_keys(true);
+
+        // The database lives only as long as a connection to it, so keep one
+        // open for good.
+        let pool = SqlitePoolOptions::new()
+            .max_connections(5)
+            .min_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect_with(connect_opts)
+            .await
+            .context("failed to open in-memory SQLite database")?;
+
+        db::init_db(&pool).await.context("failed to run schema migrations")?;
+        Ok(Self { pool, location: "memory (discarded on exit)".to_string(), path: None, wal_path: None })
+    }
+}
+
+/// Rename `path` and its `-wal` and `-shm` files to
+/// `<path>.corrupt-<timestamp>`, keeping the set together so it can still be
+/// opened for salvage. Returns the new database path.
+fn move_aside(path: &Path) -> Result<PathBuf> {
+    let stamp = Utc::now().format("%Y%m%dT%H%M%SZ");
+    let mut moved = path.as_os_str().to_owned();
+    moved.push(format!(".corrupt-{stamp}"));
+    for suffix in ["", "-wal", "-shm"] {
+        let mut from = path.as_os_str().to_owned();
+        from.push(suffix);
+        let mut to = moved.clone();
+        to.push(suffix);
+        match std::fs::rename(&from, &to) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                return Err(e).with_context(|| format!("failed to move aside {}", Path::new(&from).display()));
+            }
+            _ => {}
+        }
+    }
+    Ok(moved.into())
+}
+
+impl Store for SqliteStore {
+    fn describe(&self) -> String {
+        self.location.clone()
+    }
+
+    fn pending_migrations(&self) -> BoxFuture<'_, Result<usize>> {
+        db::pending_migrations(&self.pool).boxed()
+    }
+
+    fn upsert_session<'a>(
+        &'a self,
+        host: &'a str,
+        session_id: &'a str,
+        project_path: &'a str,
+        project_name: &'a str,
+        status: &'a str,
+    ) -> BoxFuture<'a, Result<()>> {
+        retry(move || db::upsert_session(&self.pool, host, session_id, project_path, project_name, status)).boxed()
+    }
+
+    fn upsert_agent<'a>(
+        &'a self,
+        host: &'a str,
+        session_id: &'a str,
+        agent_name: &'a str,
+        parent_session_id: Option<&'a str>,
+        status: &'a str,
+    ) -> BoxFuture<'a, Result<()>> {
+        retry(move || db::upsert_agent(&self.pool, host, session_id, agent_name, parent_session_id, status)).boxed()
+    }
+
+    fn cap_session_events<'a>(&'a self, sessions: &'a [(String, String)], keep: u32) -> BoxFuture<'a, Result<u64>> {
+        retry(move || db::cap_session_events(&self.pool, sessions, keep)).boxed()
+    }
+
+    fn insert_events<'a>(&'a self, events: &'a [NewEvent]) -> BoxFuture<'a, Result<()>> {
+        retry(move || db::insert_events(&self.pool, events)).boxed()
+    }
+
+    fn get_recent_events<'a>(
+        &'a self,
+        session_id: &'a str,
+        agent_name: Option<&'a str>,
+        limit: u32,
+    ) -> BoxFuture<'a, Result<Vec<SessionEvent>>> {
+        db::get_recent_events(&self.pool, session_id, agent_name, limit).boxed()
+    }
+
+    fn latest_event_seq(&self) -> BoxFuture<'_, Result<i64>> {
+        db::latest_event_seq(&self.pool).boxed()
+    }
+
+    fn get_events_after<'a>(
+        &'a self,
+        session_id: &'a str,
+        after: i64,
+    ) -> BoxFuture<'a, Result<Vec<(i64, SessionEvent)>>> {
+        db::get_events_after(&self.pool, session_id, after).boxed()
+    }
+
+    fn search_events<'a>(
+        &'a self,
+        query: &'a str,
+        host: Option<&'a str>,
+        limit: u32,
+    ) -> BoxFuture<'a, Result<Vec<SearchHit>>> {
+        db::search_events(&self.pool, query, host, limit).boxed()
+    }
+
+    fn start_tool_invocation<'a>(
+        &'a self,
+        host: &'a str,
+        session_id: &'a str,
+        agent_name: &'a str,
+        tool_name: &'a str,
+        tool_use_id: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<()>> {
+        retry(move || db::start_tool_invocation(&self.pool, host, session_id, agent_name, tool_name, tool_use_id)).boxed()
+    }
+
+    fn finish_tool_invocation<'a>(
+        &'a self,
+        host: &'a str,
+        session_id: &'a str,
+        agent_name: &'a str,
+        tool_name: &'a str,
+        tool_use_id: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<()>> {
+        retry(move || db::finish_tool_invocation(&self.pool, host, session_id, agent_name, tool_name, tool_use_id)).boxed()
+    }
+
+    fn set_current_tool<'a>(
+        &'a self,
+        host: &'a str,
+        session_id: &'a str,
+        tool_name: &'a str,
+    ) -> BoxFuture<'a, Result<()>> {
+        retry(move || db::set_current_tool(&self.pool, host, session_id, tool_name)).boxed()
+    }
+
+    fn clear_current_tool<'a>(
+        &'a self,
+        host: &'a str,
+        session_id: &'a str,
+        tool_name: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<()>> {
+        retry(move || db::clear_current_tool(&self.pool, host, session_id, tool_name)).boxed()
+    }
+
+    fn set_session_model<'a>(
+        &'a self,
+        host: &'a str,
+        session_id: &'a str,
+        model: &'a str,
+    ) -> BoxFuture<'a, Result<()>> {
+        retry(move || db::set_session_model(&self.pool, host, session_id, model)).boxed()
+    }
+
+    fn set_session_git_branch<'a>(
+        &'a self,
+        host: &'a str,
+        session_id: &'a str,
+        branch: &'a str,
+    ) -> BoxFuture<'a, Result<()>> {
+        retry(move || db::set_session_git_branch(&self.pool, host, session_id, branch)).boxed()
+    }
+
+    fn set_last_message<'a>(
+        &'a self,
+        host: &'a str,
+        session_id: &'a str,
+        message: &'a str,
+    ) -> BoxFuture<'a, Result<()>> {
+        retry(move || db::set_last_message(&self.pool, host, session_id, message)).boxed()
+    }
+
+    fn set_transcript_summary<'a>(
+        &'a self,
+        host: &'a str,
+        session_id: &'a str,
+        assistant_text: Option<&'a str>,
+        tool_call: Option<&'a str>,
+        model: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<()>> {
+        retry(move || db::set_transcript_summary(&self.pool, host, session_id, assistant_text, tool_call, model)).boxed()
+    }
+
+    fn record_usage<'a>(
+        &'a self,
+        session_id: &'a str,
+        agent_name: &'a str,
+        message_id: Option<&'a str>,
+        model: Option<&'a str>,
+        usage: &'a TokenUsage,
+    ) -> BoxFuture<'a, Result<()>> {
+        retry(move || db::record_usage(&self.pool, session_id, agent_name, message_id, model, usage)).boxed()
+    }
+
+    fn get_session_usage<'a>(&'a self, session_id: &'a str) -> BoxFuture<'a, Result<SessionUsage>> {
+        db::get_session_usage(&self.pool, session_id).boxed()
+    }
+
+    fn get_usage_by_session_model(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'_, Result<Vec<ModelUsageRow>>> {
+        db::get_usage_by_session_model(&self.pool, from, to).boxed()
+    }
+
+    fn get_usage_for_sessions<'a>(&'a self, session_ids: &'a [String]) -> BoxFuture<'a, Result<Vec<ModelUsageRow>>> {
+        db::get_usage_for_sessions(&self.pool, session_ids).boxed()
+    }
+
+    fn get_active_usage_since(&self, since: DateTime<Utc>) -> BoxFuture<'_, Result<Vec<ModelUsageRow>>> {
+        db::get_active_usage_since(&self.pool, since).boxed()
+    }
+
+    fn get_session_events_in_range(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'_, Result<Vec<EventRow>>> {
+        db::get_session_events_in_range(&self.pool, from, to).boxed()
+    }
+
+    fn get_session_event_rows<'a>(&'a self, session_id: &'a str) -> BoxFuture<'a, Result<Vec<EventRow>>> {
+        db::get_session_event_rows(&self.pool, session_id).boxed()
+    }
+
+    fn get_tool_timeline<'a>(&'a self, session_id: &'a str) -> BoxFuture<'a, Result<Vec<TimelineEntry>>> {
+        db::get_tool_timeline(&self.pool, session_id).boxed()
+    }
+
+    fn get_completed_sessions(&self) -> BoxFuture<'_, Result<Vec<CompletedSession>>> {
+        db::get_completed_sessions(&self.pool).boxed()
+    }
+
+    fn get_session_durations(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'_, Result<Vec<SessionDuration>>> {
+        db::get_session_durations(&self.pool, from, to).boxed()
+    }
+
+    fn get_hourly_event_counts(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'_, Result<Vec<HourlyEventCount>>> {
+        db::get_hourly_event_counts(&self.pool, from, to).boxed()
+    }
+
+    fn get_archived_intervals(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> BoxFuture<'_, Result<Vec<ActiveInterval>>> {
+        db::get_archived_intervals(&self.pool, from, to).boxed()
+    }
+
+    fn get_daily_stats(&self) -> BoxFuture<'_, Result<DailyActivity>> {
+        db::get_daily_stats(&self.pool).boxed()
+    }
+
+    fn get_event_counts_since(&self, since: DateTime<Utc>) -> BoxFuture<'_, Result<Vec<EventCountRow>>> {
+        db::get_event_counts_since(&self.pool, since).boxed()
+    }
+
+    fn get_usage_since(&self, since: DateTime<Utc>) -> BoxFuture<'_, Result<WindowUsage>> {
+        db::get_usage_since(&self.pool, since).boxed()
+    }
+
+    fn get_tool_stats<'a>(&'a self, session_id: &'a str) -> BoxFuture<'a, Result<Vec<ToolStats>>> {
+        db::get_tool_stats(&self.pool, session_id).boxed()
+    }
+
+    fn get_active_sessions(&self) -> BoxFuture<'_, Result<Vec<SessionWithAgents>>> {
+        db::get_active_sessions(&self.pool).boxed()
+    }
+
+    fn get_session_page<'a>(
+        &'a self,
+        page: &'a SessionPage<'a>,
+    ) -> BoxFuture<'a, Result<(Vec<SessionWithAgents>, u64)>> {
+        db::get_session_page(&self.pool, page).boxed()
+    }
+
+    fn get_workspace_sessions<'a>(&'a self, workspace: &'a str) -> BoxFuture<'a, Result<Vec<EditorSession>>> {
+        db::get_workspace_sessions(&self.pool, workspace).boxed()
+    }
+
+    fn set_session_notes<'a>(
+        &'a self,
+        host: Option<&'a str>,
+        session_id: &'a str,
+        notes: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<bool>> {
+        retry(move || db::set_session_notes(&self.pool, host, session_id, notes)).boxed()
+    }
+
+    fn get_session_tags<'a>(
+        &'a self,
+        host: Option<&'a str>,
+        session_id: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<String>>> {
+        db::get_session_tags(&self.pool, host, session_id).boxed()
+    }
+
+    fn add_session_tags<'a>(
+        &'a self,
+        host: Option<&'a str>,
+        session_id: &'a str,
+        tags: &'a [String],
+    ) -> BoxFuture<'a, Result<()>> {
+        retry(move || db::add_session_tags(&self.pool, host, session_id, tags)).boxed()
+    }
+
+    fn remove_session_tags<'a>(
+        &'a self,
+        host: Option<&'a str>,
+        session_id: &'a str,
+        tags: &'a [String],
+    ) -> BoxFuture<'a, Result<()>> {
+        retry(move || db::remove_session_tags(&self.pool, host, session_id, tags)).boxed()
+    }
+
+    fn session_exists<'a>(&'a self, host: Option<&'a str>, session_id: &'a str) -> BoxFuture<'a, Result<bool>> {
+        db::session_exists(&self.pool, host, session_id).boxed()
+    }
+
+    fn mark_session_completed<'a>(&'a self, host: &'a str, session_id: &'a str) -> BoxFuture<'a, Result<()>> {
+        retry(move || db::mark_session_completed(&self.pool, host, session_id)).boxed()
+    }
+
+    fn mark_active_session_idle<'a>(&'a self, host: &'a str, session_id: &'a str) -> BoxFuture<'a, Result<()>> {
+        retry(move || db::mark_active_session_idle(&self.pool, host, session_id)).boxed()
+    }
+
+    fn acknowledge_session<'a>(&'a self, host: Option<&'a str>, session_id: &'a str) -> BoxFuture<'a, Result<bool>> {
+        retry(move || db::acknowledge_session(&self.pool, host, session_id)).boxed()
+    }
+
+    fn set_session_pinned<'a>(
+        &'a self,
+        host: Option<&'a str>,
+        session_id: &'a str,
+        pinned: bool,
+    ) -> BoxFuture<'a, Result<bool>> {
+        retry(move || db::set_session_pinned(&self.pool, host, session_id, pinned)).boxed()
+    }
+
+    fn trash_session<'a>(&'a self, host: Option<&'a str>, session_id: &'a str) -> BoxFuture<'a, Result<bool>> {
+        retry(move || db::trash_session(&self.pool, host, session_id)).boxed()
+    }
+
+    fn restore_session<'a>(&'a self, host: Option<&'a str>, session_id: &'a str) -> BoxFuture<'a, Result<bool>> {
+        retry(move || db::restore_session(&self.pool, host, session_id)).boxed()
+    }
+
+    fn get_trash(&self) -> BoxFuture<'_, Result<Vec<SessionWithAgents>>> {
+        db::get_trash(&self.pool).boxed()
+    }
+
+    fn set_session_muted<'a>(
+        &'a self,
+        host: Option<&'a str>,
+        session_id: &'a str,
+        muted: bool,
+        hidden: bool,
+    ) -> BoxFuture<'a, Result<bool>> {
+        retry(move || db::set_session_muted(&self.pool, host, session_id, muted, hidden)).boxed()
+    }
+
+    fn get_alert_rules(&self) -> BoxFuture<'_, Result<Vec<AlertRule>>> {
+        db::get_alert_rules(&self.pool).boxed()
+    }
+
+    fn get_alert_rule<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<Option<AlertRule>>> {
+        db::get_alert_rule(&self.pool, id).boxed()
+    }
+
+    fn insert_alert_rule<'a>(&'a self, rule: &'a AlertRuleInput) -> BoxFuture<'a, Result<AlertRule>> {
+        retry(move || db::insert_alert_rule(&self.pool, rule)).boxed()
+    }
+
+    fn update_alert_rule<'a>(
+        &'a self,
+        id: &'a str,
+        rule: &'a AlertRuleInput,
+    ) -> BoxFuture<'a, Result<Option<AlertRule>>> {
+        retry(move || db::update_alert_rule(&self.pool, id, rule)).boxed()
+    }
+
+    fn delete_alert_rule<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<bool>> {
+        retry(move || db::delete_alert_rule(&self.pool, id)).boxed()
+    }
+
+    fn get_project_aliases(&self) -> BoxFuture<'_, Result<Vec<ProjectAlias>>> {
+        db::get_project_aliases(&self.pool).boxed()
+    }
+
+    fn set_project_alias<'a>(&'a self, project_path: &'a str, display_name: &'a str) -> BoxFuture<'a, Result<()>> {
+        retry(move || db::set_project_alias(&self.pool, project_path, display_name)).boxed()
+    }
+
+    fn delete_project_alias<'a>(&'a self, project_path: &'a str) -> BoxFuture<'a, Result<bool>> {
+        retry(move || db::delete_project_alias(&self.pool, project_path)).boxed()
+    }
+
+    fn get_setting<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<Option<String>>> {
+        db::get_setting(&self.pool, key).boxed()
+    }
+
+    fn set_setting<'a>(&'a self, key: &'a str, value: &'a str) -> BoxFuture<'a, Result<()>> {
+        retry(move || db::set_setting(&self.pool, key, value)).boxed()
+    }
+
+    fn insert_notification<'a>(&'a self, record: &'a NotificationRecord) -> BoxFuture<'a, Result<()>> {
+        retry(move || db::insert_notification(&self.pool, record)).boxed()
+    }
+
+    fn get_notifications(&self, limit: u32, offset: u32) -> BoxFuture<'_, Result<(Vec<NotificationRecord>, i64)>> {
+        db::get_notifications(&self.pool, limit, offset).boxed()
+    }
+
+    fn get_session_notifications<'a>(&'a self, session_id: &'a str) -> BoxFuture<'a, Result<Vec<NotificationRecord>>> {
+        db::get_session_notifications(&self.pool, session_id).boxed()
+    }
+
+    fn prune_notifications(&self, retention: Duration) -> BoxFuture<'_, Result<()>> {
+        retry(move || db::prune_notifications(&self.pool, retention)).boxed()
+    }
+
+    fn insert_dead_letter<'a>(
+        &'a self,
+        path: &'a str,
+        body: &'a str,
+        error: &'a str,
+        keep: u32,
+    ) -> BoxFuture<'a, Result<()>> {
+        retry(move || db::insert_dead_letter(&self.pool, path, body, error, keep)).boxed()
+    }
+
+    fn get_dead_letters(&self, limit: u32) -> BoxFuture<'_, Result<Vec<DeadLetter>>> {
+        db::get_dead_letters(&self.pool, limit).boxed()
+    }
+
+    fn insert_api_key<'a>(
+        &'a self,
+        name: &'a str,
+        digest: &'a str,
+        admin: bool,
+    ) -> BoxFuture<'a, Result<Option<ApiKey>>> {
+        retry(move || db::insert_api_key(&self.pool, name, digest, admin)).boxed()
+    }
+
+    fn get_api_keys(&self) -> BoxFuture<'_, Result<Vec<ApiKey>>> {
+        db::get_api_keys(&self.pool).boxed()
+    }
+
+    fn get_live_api_keys(&self) -> BoxFuture<'_, Result<Vec<(String, String, bool)>>> {
+        db::get_live_api_keys(&self.pool).boxed()
+    }
+
+    fn revoke_api_key<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<bool>> {
+        retry(move || db::revoke_api_key(&self.pool, name)).boxed()
+    }
+
+    fn clear_all_sessions(&self) -> BoxFuture<'_, Result<()>> {
+        retry(move || db::clear_all_sessions(&self.pool)).boxed()
+    }
+
+    fn cleanup_old_completed(
+        &self,
+        completed_ttl: Option<Duration>,
+        usage_retention: Duration,
+        trash_retention: Duration,
+        keep_events: bool,
+    ) -> BoxFuture<'_, Result<CleanupReport>> {
+        retry(move || {
+            db::cleanup_old_completed(&self.pool, completed_ttl, usage_retention, trash_retention, keep_events)
+        })
+        .boxed()
+    }
+
+    fn maintain(&self) -> BoxFuture<'_, Result<MaintenanceReport>> {
+        retry(move || db::maintain(&self.pool)).boxed()
+    }
+
+    fn checkpoint(&self) -> BoxFuture<'_, Result<()>> {
+        db::checkpoint(&self.pool).boxed()
+    }
+
+    fn used_size(&self) -> BoxFuture<'_, Result<Option<u64>>> {
+        async move { Ok(Some(db::used_size(&self.pool).await?)) }.boxed()
+    }
+
+    fn prune_archived_events(&self, limit: u32) -> BoxFuture<'_, Result<u64>> {
+        retry(move || db::prune_archived_events(&self.pool, limit)).boxed()
+    }
+
+    fn backup<'a>(&'a self, dest: &'a Path) -> BoxFuture<'a, Result<()>> {
+        db::backup(&self.pool, dest).boxed()
+    }
+
+    fn export_sessions(&self) -> BoxFuture<'_, Result<Vec<ExportedSession>>> {
+        db::export_sessions(&self.pool).boxed()
+    }
+
+    fn export_agents(&self) -> BoxFuture<'_, Result<Vec<ExportedAgent>>> {
+        db::export_agents(&self.pool).boxed()
+    }
+
+    fn export_events(
+        &self,
+        after: i64,
+        limit: u32,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'_, Result<Vec<(i64, ExportedEvent)>>> {
+        db::export_events(&self.pool, after, limit, from, to).boxed()
+    }
+
+    fn export_settings(&self) -> BoxFuture<'_, Result<Vec<ExportedSetting>>> {
+        db::export_settings(&self.pool).boxed()
+    }
+
+    fn import<'a>(&'a self, data: &'a Export, on_conflict: OnConflict) -> BoxFuture<'a, Result<ImportReport>> {
+        retry(move || db::import(&self.pool, data, on_conflict)).boxed()
+    }
+
+    fn wal_size(&self) -> Option<u64> {
+        // No file until the first write of this run.
+        let path = self.wal_path.as_ref()?;
+        Some(std::fs::metadata(path).map_or(0, |meta| meta.len()))
+    }
+
+    fn file_size(&self) -> Option<u64> {
+        let path = self.path.as_ref()?;
+        std::fs::metadata(path).ok().map(|meta| meta.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn corrupt_file_is_moved_aside() {
+        let dir = std::env::temp_dir().join(format!("claude-monitor-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("sessions.db");
+        std::fs::write(&path, vec![0xa5; 8192]).unwrap();
+
+        let store = SqliteStore::open(&path, 1000).await.unwrap();
+        assert!(store.get_alert_rules().await.unwrap().is_empty());
+        let moved: Vec<_> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .filter(|name| name.starts_with("sessions.db.corrupt-"))
+            .collect();
+        assert_eq!(moved.len(), 1);
+        assert_eq!(std::fs::read(dir.join(&moved[0])).unwrap(), vec![0xa5; 8192]);
+
+        drop(store);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
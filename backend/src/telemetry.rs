@@ -0,0 +1,108 @@
+//! Spans for Jaeger, Tempo or any OpenTelemetry collector. Every request
+//! gets a span named after its route, with the database calls, event
+//! writes and broadcasts it leads to as children, so a slow request can be
+//! broken down. They are exported over OTLP/gRPC when an endpoint is set,
+//! in a build with the `otlp` feature:
+//!
+//! ```toml
+//! [telemetry]
+//! otlp_endpoint = "http://localhost:4317"
+//! service_name = "claude-monitor"
+//! filter = "claude_monitor=debug,tower_http=info"   # spans exported
+//! ```
+
+use axum::{extract::MatchedPath, http::Request};
+use serde::Deserialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::{field::Empty, info_span, Span};
+use tracing_subscriber::{Layer, Registry};
+
+use crate::request_id;
+
+/// `[telemetry]` section of config.toml.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TelemetryConfig {
+    /// Collector to send spans to; none are exported when unset.
+    pub otlp_endpoint: Option<String>,
+    /// `service.name` the spans are reported under.
+    pub service_name: String,
+    /// Which spans are exported, in `RUST_LOG` syntax. Database calls are
+    /// at debug level, below what the log shows by default.
+    pub filter: String,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            otlp_endpoint: None,
+            service_name: "claude-monitor".to_string(),
+            filter: "claude_monitor=debug,tower_http=info".to_string(),
+        }
+    }
+}
+
+/// Set once spans are being exported, to name request spans after their
+/// route for the collector; without one the log needn't repeat it.
+static EXPORTING: AtomicBool = AtomicBool::new(false);
+
+/// Span for one HTTP request, for `TraceLayer::make_span_with`.
+pub fn request_span<B>(request: &Request<B>) -> Span {
+    let route = request.extensions().get::<MatchedPath>().map_or(request.uri().path(), MatchedPath::as_str);
+    let request_id = request.headers().get(&request_id::HEADER).and_then(|id| id.to_str().ok()).unwrap_or_default();
+    let span = info_span!("request", method = %request.method(), route, request_id, otel.name = Empty);
+    if EXPORTING.load(Ordering::Relaxed) {
+        span.record("otel.name", format!("{} {route}", request.method()));
+    }
+    span
+}
+
+/// The layer exporting spans, when `otlp_endpoint` is set. Call it inside
+/// the runtime: the exporter sends its batches from a task.
+pub fn layer(config: &TelemetryConfig) -> anyhow::Result<Option<Box<dyn Layer<Registry> + Send + Sync>>> {
+    let Some(endpoint) = &config.otlp_endpoint else {
+        return Ok(None);
+    };
+    let layer = otlp::layer(config, endpoint)?;
+    EXPORTING.store(true, Ordering::Relaxed);
+    Ok(Some(layer))
+}
+
+#[cfg(feature = "otlp")]
+mod otlp {
+    use anyhow::{Context, Result};
+    use opentelemetry::{trace::TracerProvider as _, KeyValue};
+    use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+    use opentelemetry_sdk::{runtime, trace::TracerProvider, Resource};
+    use tracing_subscriber::{EnvFilter, Layer, Registry};
+
+    use super::TelemetryConfig;
+
+    pub fn layer(config: &TelemetryConfig, endpoint: &str) -> Result<Box<dyn Layer<Registry> + Send + Sync>> {
+        let filter = EnvFilter::try_new(&config.filter)
+            .with_context(|| format!("invalid telemetry filter {:?}", config.filter))?;
+        let exporter = SpanExporter::builder()
+            .with_tonic()
+            .with_endpoint(endpoint)
+            .build()
+            .with_context(|| format!("failed to set up span export to {endpoint}"))?;
+        let provider = TracerProvider::builder()
+            .with_batch_exporter(exporter, runtime::Tokio)
+            .with_resource(Resource::new([KeyValue::new("service.name", config.service_name.clone())]))
+            .build();
+        let tracer = provider.tracer(env!("CARGO_PKG_NAME"));
+        opentelemetry::global::set_tracer_provider(provider);
+        Ok(tracing_opentelemetry::layer().with_tracer(tracer).with_filter(filter).boxed())
+    }
+}
+
+#[cfg(not(feature = "otlp"))]
+mod otlp {
+    use tracing_subscriber::{Layer, Registry};
+
+    use super::TelemetryConfig;
+
+    pub fn layer(_: &TelemetryConfig, _: &str) -> anyhow::Result<Box<dyn Layer<Registry> + Send + Sync>> {
+        anyhow::bail!("this build has no OTLP support; rebuild with `--features otlp` or unset `otlp_endpoint`")
+    }
+}
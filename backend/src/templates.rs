@@ -0,0 +1,127 @@
+//! Catalog of user-facing strings (status lines now; notifications and reports
+//! as they are added), so phrasing can be localized or customized without
+//! patching source.
+//!
+//! The built-in catalog is English. Selecting `locale = "de"` in the
+//! `[templates]` config section loads `~/.claude-monitor/templates/de.toml`, a
+//! flat table of `"key" = "text"` overrides; anything it doesn't define falls
+//! back to English. Templates use `{name}` placeholders.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::{collections::HashMap, path::Path};
+
+const BUILTIN_EN: &[(&str, &str)] = &[
+    ("status.active", "Working"),
+    ("status.active_tool", "Running {tool}"),
+    ("status.waiting_input", "Waiting for your input"),
+    ("status.needs_permission", "Needs permission"),
+    ("status.needs_permission_tool", "Needs permission for {tool}"),
+    ("status.idle", "Idle"),
+    ("status.acknowledged", "Acknowledged"),
+    ("status.completed", "Finished"),
+    ("announce.started", "Project {project} started"),
+    ("announce.active", "Project {project} is working again"),
+    ("announce.waiting_input", "Project {project} is waiting for your input"),
+    ("announce.needs_permission", "Project {project} now needs permission"),
+    ("announce.needs_permission_tool", "Project {project} now needs permission for {tool}"),
+    ("announce.idle", "Project {project} finished its turn"),
+    ("announce.ended", "Project {project} ended"),
+    ("announce.tool", "Project {project} is running {tool}"),
+    ("notify.waiting_input.title", "{project} is waiting for you"),
+    ("notify.waiting_input.body", "Claude finished and is waiting for your input"),
+    ("notify.needs_permission.title", "{project} needs permission"),
+    ("notify.needs_permission.body", "Claude is waiting for your approval"),
+    ("notify.needs_permission.body_tool", "Claude wants to run {tool}"),
+    ("message.active", "Session {session} in project {project} is working"),
+    ("message.waiting_input", "Session {session} in project {project} is waiting for your input"),
+    ("message.needs_permission", "Session {session} in project {project} needs permission"),
+    ("message.needs_permission_tool", "Session {session} in project {project} needs permission for {tool}"),
+    ("message.idle", "Session {session} in project {project} finished its turn"),
+    ("message.acknowledged", "Session {session} in project {project} was acknowledged"),
+    ("message.completed", "Session {session} in project {project} ended"),
+    ("field.project", "Project"),
+    ("field.status", "Status"),
+    ("field.elapsed", "Running for"),
+    ("field.agents", "Agents"),
+    ("summary.title", "{count} alerts held back while notifications were paused"),
+    ("email.subject", "Claude sessions waiting on you ({count})"),
+    ("email.intro", "These sessions have been blocked on you for a while:"),
+    ("email.line", "- {project} ({session}): {status} for {duration}"),
+];
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TemplateConfig {
+    pub locale: String,
+}
+
+impl Default for TemplateConfig {
+    fn default() -> Self {
+        Self {
+            locale: "en".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Catalog {
+    pub locale: String,
+    strings: HashMap<String, String>,
+}
+
+impl Default for Catalog {
+    fn default() -> Self {
+        Self {
+            locale: "en".to_string(),
+            strings: BUILTIN_EN.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+        }
+    }
+}
+
+impl Catalog {
+    /// Built-in English overlaid with `<dir>/<locale>.toml` when it exists.
+    pub fn load(config: &TemplateConfig, dir: &Path) -> Result<Self> {
+        let mut catalog = Self {
+            locale: config.locale.clone(),
+            ..Self::default()
+        };
+
+        let path = dir.join(format!("{}.toml", config.locale));
+        if path.exists() {
+            let raw = std::fs::read_to_string(&path)
+                .with_context(|| format!("failed to read {}", path.display()))?;
+            let overrides: HashMap<String, String> =
+                toml::from_str(&raw).with_context(|| format!("failed to parse {}", path.display()))?;
+            catalog.strings.extend(overrides);
+        } else if config.locale != "en" {
+            tracing::warn!(
+                "No templates for locale '{}' at {}; using English",
+                config.locale,
+                path.display()
+            );
+        }
+
+        Ok(catalog)
+    }
+
+    /// Render `key`, substituting `{name}` placeholders from `args`.
+    /// Unknown keys render as the key itself so gaps are visible, not silent.
+    pub fn render(&self, key: &str, args: &[(&str, &str)]) -> String {
+        let Some(template) = self.strings.get(key) else {
+            return key.to_string();
+        };
+        args.iter().fold(template.clone(), |text, (name, value)| {
+            text.replace(&format!("{{{name}}}"), value)
+        })
+    }
+
+    /// One-line description of a session's state, e.g. "Running Bash".
+    pub fn status_text(&self, status: &str, tool: Option<&str>) -> String {
+        match (status, tool) {
+            ("active", Some(tool)) => self.render("status.active_tool", &[("tool", tool)]),
+            ("needs_permission", Some(tool)) => self.render("status.needs_permission_tool", &[("tool", tool)]),
+            _ => self.render(&format!("status.{status}"), &[]),
+        }
+    }
+}
@@ -0,0 +1,94 @@
+//! The whole server on a loopback port, backed by an in-memory database, for
+//! tests that drive it through `claude-monitor-client`. Its requests count
+//! as carrying a listener's token, so the `/api/admin` routes are open.
+
+use axum::Extension;
+use claude_monitor_client::Client;
+use std::{
+    sync::{atomic::Ordering, Arc},
+    time::Duration,
+};
+use tokio::{net::TcpListener, sync::broadcast};
+
+use crate::{
+    api::{self, AppState},
+    auth::Authenticated,
+    config::Config,
+    ingest::{self, EventWriter},
+    models::HookEvent,
+    store::SqliteStore,
+    templates::Catalog,
+};
+
+pub struct TestServer {
+    pub state: AppState,
+    pub client: Client,
+}
+
+/// Start a server with default config and an empty database. Notifications
+/// and stats aggregation are left off so tests don't reach the desktop or
+/// race the aggregator.
+pub async fn spawn() -> TestServer {
+    spawn_with(Config::default()).await
+}
+
+pub async fn spawn_with(config: Config) -> TestServer {
+    let store = SqliteStore::memory().await.expect("in-memory database");
+    let (tx, _rx) = broadcast::channel(100);
+    let (events, event_rx) = EventWriter::channel();
+    let state = AppState::new(
+        Arc::new(store),
+        tx,
+        Arc::new(config),
+        Arc::new(Catalog::default()),
+        None,
+        None,
+        events,
+    );
+    tokio::spawn(ingest::run(state.clone(), event_rx, std::future::pending()));
+    api::spawn_broadcaster(&state);
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind loopback port");
+    let addr = listener.local_addr().expect("bound address");
+    state.listening.store(true, Ordering::Relaxed);
+    let app = crate::router(state.clone()).layer(Extension(Authenticated));
+    tokio::spawn(async move { axum::serve(listener, app).await });
+
+    let client = Client::new(&format!("http://{addr}")).expect("client for test server");
+    TestServer { state, client }
+}
+
+impl TestServer {
+    /// Wait until every queued event has been written.
+    pub async fn flushed(&self) {
+        for _ in 0..200 {
+            let stats = self.state.events.stats();
+            if stats.written + stats.failed >= stats.queued {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("event writer did not flush within 2s");
+    }
+}
+
+/// A hook event for `session_id` in project `demo` with every optional field
+/// unset.
+pub fn event(event_type: &str, session_id: &str) -> HookEvent {
+    HookEvent {
+        event_type: event_type.to_string(),
+        session_id: session_id.to_string(),
+        host: None,
+        project_path: Some("/work/demo".to_string()),
+        project_name: Some("demo".to_string()),
+        agent_name: None,
+        parent_session_id: None,
+        needs_input: None,
+        tool_name: None,
+        transcript_path: None,
+        message: None,
+        tool_use_id: None,
+        model: None,
+        git_branch: None,
+    }
+}
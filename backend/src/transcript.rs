@@ -0,0 +1,395 @@
+//! Tails the Claude transcript JSONL files referenced by hook events and
+//! enriches session records with what the transcript reveals: the latest
+//! assistant text, the tools it called, and token usage.
+
+use anyhow::Result;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Deserialize;
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncSeekExt},
+    sync::mpsc,
+};
+use tracing::{debug, warn};
+
+use crate::{api::AppState, models::TokenUsage};
+
+/// Handle for registering transcripts with the tailing task.
+#[derive(Clone)]
+pub struct TranscriptTailer {
+    tx: mpsc::UnboundedSender<Command>,
+}
+
+enum Command {
+    Watch {
+        host: String,
+        session_id: String,
+        agent_name: String,
+        path: PathBuf,
+    },
+    /// On one host, or every host when `None`.
+    Unwatch {
+        host: Option<String>,
+        session_id: String,
+    },
+    /// Stop tailing the transcripts of every session, as (host, session_id),
+    /// not in the set.
+    Retain(HashSet<(String, String)>),
+}
+
+/// Most of a transcript read into memory at once.
+const CHUNK: u64 = 1 << 20;
+
+/// Longest line kept; longer ones are skipped whole.
+const MAX_LINE: usize = 8 << 20;
+
+/// How long a transcript may go unchanged before it stops being watched.
+/// Its position is kept, so the session's next event picks it up again.
+const IDLE: Duration = Duration::from_secs(30 * 60);
+
+impl TranscriptTailer {
+    /// Create the handle and the receiver that `run` consumes.
+    pub fn channel() -> (Self, TranscriptReceiver) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        (Self { tx }, TranscriptReceiver(rx))
+    }
+
+    /// Start tailing `path` for a session. Already-watched paths are ignored.
+    pub fn watch(&self, host: &str, session_id: &str, agent_name: &str, path: &str) {
+        let _ = self.tx.send(Command::Watch {
+            host: host.to_string(),
+            session_id: session_id.to_string(),
+            agent_name: agent_name.to_string(),
+            path: PathBuf::from(path),
+        });
+    }
+
+    /// Stop tailing every transcript belonging to a session, on `host` or
+    /// every host with that session id.
+    pub fn unwatch(&self, host: Option<&str>, session_id: &str) {
+        let _ = self.tx.send(Command::Unwatch {
+            host: host.map(str::to_string),
+            session_id: session_id.to_string(),
+        });
+    }
+
+    /// Stop tailing the transcripts of sessions no longer listed, such as
+    /// those cleanup removed. Sessions are given as (host, session_id).
+    pub fn retain(&self, sessions: HashSet<(String, String)>) {
+        let _ = self.tx.send(Command::Retain(sessions));
+    }
+}
+
+pub struct TranscriptReceiver(mpsc::UnboundedReceiver<Command>);
+
+/// Read position within one transcript file.
+struct Tail {
+    host: String,
+    session_id: String,
+    agent_name: String,
+    offset: u64,
+    /// Trailing bytes of a line that hasn't been fully written yet.
+    partial: Vec<u8>,
+    /// Set while the rest of a line over `MAX_LINE` is skipped.
+    skipping: bool,
+    /// Whether the file is watched, rather than idle past `IDLE`.
+    watching: bool,
+    last_read: Instant,
+}
+
+impl Tail {
+    fn new(host: String, session_id: String, agent_name: String) -> Self {
+        Self {
+            host,
+            session_id,
+            agent_name,
+            offset: 0,
+            partial: Vec::new(),
+            skipping: false,
+            watching: true,
+            last_read: Instant::now(),
+        }
+    }
+
+    /// Complete lines that `buf`, read next from the file, finishes.
+    /// Whether this is the transcript of the session with `session_id` on
+    /// `host`, or on any host when `None`.
+    fn belongs_to(&self, host: Option<&str>, session_id: &str) -> bool {
+        self.session_id == session_id && host.is_none_or(|host| self.host == host)
+    }
+
+    fn push(&mut self, buf: &[u8]) -> Vec<String> {
+        let mut lines = Vec::new();
+        let mut rest = buf;
+        while let Some(newline) = rest.iter().position(|&b| b == b'\n') {
+            if self.skipping {
+                self.skipping = false;
+            } else {
+                self.partial.extend_from_slice(&rest[..newline]);
+                let line = String::from_utf8_lossy(&self.partial);
+                if !line.trim().is_empty() {
+                    lines.push(line.trim_end_matches('\r').to_string());
+                }
+            }
+            self.partial.clear();
+            rest = &rest[newline + 1..];
+        }
+        if !self.skipping {
+            self.partial.extend_from_slice(rest);
+            if self.partial.len() > MAX_LINE {
+                warn!(session_id = %self.session_id, "Skipping a transcript line over {MAX_LINE} bytes");
+                self.partial = Vec::new();
+                self.skipping = true;
+            }
+        }
+        lines
+    }
+
+    fn reset(&mut self) {
+        self.offset = 0;
+        self.partial.clear();
+        self.skipping = false;
+    }
+}
+
+/// The parts of a transcript line the monitor cares about.
+#[derive(Debug, Default)]
+pub struct TranscriptEntry {
+    pub message_id: Option<String>,
+    pub model: Option<String>,
+    pub text: Option<String>,
+    pub tool_calls: Vec<String>,
+    pub usage: Option<TokenUsage>,
+}
+
+#[derive(Deserialize)]
+struct RawLine {
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    message: Option<RawMessage>,
+}
+
+#[derive(Deserialize)]
+struct RawMessage {
+    id: Option<String>,
+    model: Option<String>,
+    #[serde(default)]
+    content: serde_json::Value,
+    usage: Option<TokenUsage>,
+}
+
+/// Parse one JSONL line. Returns None for anything but assistant messages.
+pub fn parse_line(line: &str) -> Option<TranscriptEntry> {
+    let raw: RawLine = serde_json::from_str(line).ok()?;
+    if raw.kind.as_deref() != Some("assistant") {
+        return None;
+    }
+    let message = raw.message?;
+
+    let mut entry = TranscriptEntry {
+        message_id: message.id,
+        model: message.model,
+        usage: message.usage,
+        ..Default::default()
+    };
+
+    match &message.content {
+        serde_json::Value::String(text) => entry.text = Some(text.clone()),
+        serde_json::Value::Array(blocks) => {
+            for block in blocks {
+                match block.get("type").and_then(|t| t.as_str()) {
+                    Some("text") => {
+                        if let Some(text) = block.get("text").and_then(|t| t.as_str()) {
+                            entry.text = Some(text.to_string());
+                        }
+                    }
+                    Some("tool_use") => {
+                        if let Some(name) = block.get("name").and_then(|n| n.as_str()) {
+                            entry.tool_calls.push(name.to_string());
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        _ => {}
+    }
+
+    Some(entry)
+}
+
+/// Run the tailing loop until the handle side is dropped.
+pub async fn run(state: AppState, receiver: TranscriptReceiver) -> Result<()> {
+    let TranscriptReceiver(mut commands) = receiver;
+    let (fs_tx, mut fs_rx) = mpsc::unbounded_channel::<PathBuf>();
+
+    let mut watcher: RecommendedWatcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        match res {
+            Ok(event) if event.kind.is_modify() || event.kind.is_create() => {
+                for path in event.paths {
+                    let _ = fs_tx.send(path);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => warn!("transcript watcher error: {e}"),
+        }
+    })?;
+
+    let mut tails: HashMap<PathBuf, Tail> = HashMap::new();
+    // Paths that couldn't be watched, with their session, so they are
+    // neither retried nor logged again for it.
+    let mut failed: HashMap<PathBuf, (String, String)> = HashMap::new();
+    let mut sweep = tokio::time::interval(IDLE / 4);
+
+    loop {
+        let path = tokio::select! {
+            command = commands.recv() => match command {
+                Some(Command::Watch { host, session_id, agent_name, path }) => {
+                    if tails.get(&path).is_some_and(|tail| tail.watching) || failed.contains_key(&path) {
+                        continue;
+                    }
+                    if let Err(e) = watcher.watch(&path, RecursiveMode::NonRecursive) {
+                        warn!(session_id = %session_id, "Failed to watch transcript {}: {e}", path.display());
+                        failed.insert(path, (host, session_id));
+                        continue;
+                    }
+                    debug!(session_id = %session_id, "Tailing transcript {}", path.display());
+                    let tail = tails.entry(path.clone()).or_insert_with(|| Tail::new(host, session_id, agent_name));
+                    tail.watching = true;
+                    tail.last_read = Instant::now();
+                    // Catch up on whatever the file holds that wasn't read.
+                    path
+                }
+                Some(Command::Unwatch { host, session_id }) => {
+                    tails.retain(|path, tail| {
+                        if !tail.belongs_to(host.as_deref(), &session_id) {
+                            return true;
+                        }
+                        if tail.watching {
+                            let _ = watcher.unwatch(path);
+                        }
+                        false
+                    });
+                    failed.retain(|_, (failed_host, failed)| {
+                        *failed != session_id || host.as_deref().is_some_and(|host| failed_host != host)
+                    });
+                    continue;
+                }
+                Some(Command::Retain(sessions)) => {
+                    tails.retain(|path, tail| {
+                        if sessions.contains(&(tail.host.clone(), tail.session_id.clone())) {
+                            return true;
+                        }
+                        if tail.watching {
+                            let _ = watcher.unwatch(path);
+                        }
+                        false
+                    });
+                    failed.retain(|_, failed| sessions.contains(failed));
+                    continue;
+                }
+                None => return Ok(()),
+            },
+            Some(path) = fs_rx.recv() => path,
+            _ = sweep.tick() => {
+                for (path, tail) in &mut tails {
+                    if tail.watching && tail.last_read.elapsed() >= IDLE {
+                        debug!(session_id = %tail.session_id, "Transcript {} idle, no longer watched", path.display());
+                        let _ = watcher.unwatch(path);
+                        tail.watching = false;
+                    }
+                }
+                continue;
+            }
+        };
+
+        let Some(tail) = tails.get_mut(&path).filter(|tail| tail.watching) else {
+            continue;
+        };
+        tail.last_read = Instant::now();
+        if let Err(e) = read_new_lines(&state, &path, tail).await {
+            warn!("Failed to read transcript {}: {e}", path.display());
+        }
+    }
+}
+
+/// Read and apply the lines appended since the last call, advancing the
+/// tail, a chunk at a time.
+async fn read_new_lines(state: &AppState, path: &Path, tail: &mut Tail) -> Result<()> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let len = file.metadata().await?.len();
+    if len < tail.offset {
+        // Truncated or replaced: start over.
+        tail.reset();
+    }
+
+    file.seek(std::io::SeekFrom::Start(tail.offset)).await?;
+    let mut buf = Vec::new();
+    while tail.offset < len {
+        buf.clear();
+        (&mut file).take(CHUNK.min(len - tail.offset)).read_to_end(&mut buf).await?;
+        if buf.is_empty() {
+            break;
+        }
+        tail.offset += buf.len() as u64;
+        let entries: Vec<TranscriptEntry> = tail.push(&buf).iter().filter_map(|l| parse_line(l)).collect();
+        if !entries.is_empty() {
+            apply_entries(state, tail, &entries).await;
+        }
+    }
+    Ok(())
+}
+
+async fn apply_entries(state: &AppState, tail: &Tail, entries: &[TranscriptEntry]) {
+    let text = entries.iter().rev().find_map(|e| e.text.as_deref());
+    let tool = entries.iter().rev().find_map(|e| e.tool_calls.last().map(String::as_str));
+    let model = entries.iter().rev().find_map(|e| e.model.as_deref());
+
+    if let Err(e) = state.store.set_transcript_summary(&tail.host, &tail.session_id, text, tool, model).await {
+        warn!("set_transcript_summary error: {e}");
+        return;
+    }
+
+    for entry in entries {
+        if let Some(usage) = &entry.usage {
+            let message_id = entry.message_id.as_deref();
+            let model = entry.model.as_deref();
+            if let Err(e) =
+                state.store.record_usage(&tail.session_id, &tail.agent_name, message_id, model, usage).await
+            {
+                warn!("record_usage error: {e}");
+            }
+        }
+    }
+
+    state.broadcast_sessions();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Tail, MAX_LINE};
+
+    #[test]
+    fn lines_split_across_reads_are_joined() {
+        let mut tail = Tail::new("local".to_string(), "s1".to_string(), "main".to_string());
+        assert!(tail.push(b"{\"a\":").is_empty());
+        assert_eq!(tail.push(b"1}\n\n{\"b\""), ["{\"a\":1}"]);
+        assert_eq!(tail.push(b":2}\r\n"), ["{\"b\":2}"]);
+        assert!(tail.partial.is_empty());
+    }
+
+    #[test]
+    fn lines_over_the_limit_are_skipped_whole() {
+        let mut tail = Tail::new("local".to_string(), "s1".to_string(), "main".to_string());
+        assert_eq!(tail.push(b"first\n"), ["first"]);
+        let long = vec![b'x'; MAX_LINE + 1];
+        assert!(tail.push(&long).is_empty());
+        assert!(tail.partial.is_empty());
+        assert!(tail.push(b"xxx").is_empty());
+        assert_eq!(tail.push(b"x\nnext\n"), ["next"]);
+    }
+}
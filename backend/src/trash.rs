@@ -0,0 +1,128 @@
+//! Sessions dismissed with `DELETE /api/sessions/:id` go to the trash rather
+//! than straight to the reaper, so an accidental dismissal can be undone:
+//! `GET /api/trash` lists them and `POST /api/sessions/:id/restore` puts one
+//! back where it was. Each is erased, and archived into the stats, once it
+//! has been in the trash for `retention_hours`:
+//!
+//! ```toml
+//! [trash]
+//! retention_hours = 24
+//! ```
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::Duration;
+use serde::Deserialize;
+use serde_json::json;
+use tracing::warn;
+
+use crate::{
+    api::{AppState, HostFilter},
+    models::{ErrorResponse, SessionWithAgents},
+};
+
+/// `[trash]` section of config.toml.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TrashConfig {
+    /// Hours a dismissed session can still be restored.
+    pub retention_hours: u32,
+}
+
+impl Default for TrashConfig {
+    fn default() -> Self {
+        Self { retention_hours: 24 }
+    }
+}
+
+impl TrashConfig {
+    pub fn retention(&self) -> Duration {
+        Duration::hours(self.retention_hours.into())
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/trash",
+    tag = "sessions",
+    responses(
+        (status = 200, description = "Dismissed sessions, most recently dismissed first, each with `deleted_at`",
+         body = [SessionWithAgents]),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn get_trash(State(state): State<AppState>) -> impl IntoResponse {
+    let mut sessions = match state.store.get_trash().await {
+        Ok(sessions) => sessions,
+        Err(e) => {
+            warn!("get_trash error: {e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response();
+        }
+    };
+    if let Err(e) = state.fill_derived(&mut sessions).await {
+        warn!("get_trash error: {e}");
+        return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response();
+    }
+    Json(sessions).into_response()
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/sessions/{session_id}/restore",
+    tag = "sessions",
+    params(("session_id" = String, Path, description = "Claude session ID"), HostFilter),
+    responses(
+        (status = 200, description = "Session taken out of the trash"),
+        (status = 404, description = "The session is not in the trash", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse)
+    )
+)]
+pub async fn restore_session(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+    Query(hosts): Query<HostFilter>,
+) -> impl IntoResponse {
+    match state.store.restore_session(hosts.host.as_deref(), &session_id).await {
+        Ok(true) => {
+            state.broadcast_sessions();
+            StatusCode::OK.into_response()
+        }
+        Ok(false) => (StatusCode::NOT_FOUND, Json(json!({"error": "session not in the trash"}))).into_response(),
+        Err(e) => {
+            warn!("restore_session error: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::{self, event};
+
+    #[tokio::test]
+    async fn dismissed_sessions_can_be_restored() {
+        let server = test_support::spawn().await;
+        let client = &server.client;
+        client.post_event(&event("user_prompt_submit", "s1")).await.unwrap();
+        server.flushed().await;
+
+        client.dismiss_session("s1").await.unwrap();
+        assert!(client.sessions(&Default::default()).await.unwrap().is_empty());
+        let trash = client.trash().await.unwrap();
+        assert_eq!(trash.len(), 1);
+        assert_eq!(trash[0].status, "active");
+        assert!(trash[0].deleted_at.is_some());
+
+        client.restore_session("s1").await.unwrap();
+        let sessions = client.sessions(&Default::default()).await.unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].deleted_at, None);
+        assert!(client.trash().await.unwrap().is_empty());
+        assert!(client.restore_session("s1").await.is_err());
+        assert!(client.dismiss_session("nope").await.is_err());
+    }
+}
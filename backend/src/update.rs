@@ -0,0 +1,123 @@
+//! `claude-monitor self-update`: replace this binary with the latest GitHub
+//! release built for the same target, after checking its published SHA-256.
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::{
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    process::Command,
+    time::Duration,
+};
+
+use crate::{cli::SelfUpdateArgs, datadir};
+
+const LATEST_RELEASE_URL: &str = "https://api.github.com/repos/JinHedman/claude-session-monitor/releases/latest";
+
+#[derive(Debug, Deserialize)]
+struct Release {
+    tag_name: String,
+    assets: Vec<Asset>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+}
+
+impl Release {
+    fn asset(&self, name: &str) -> Result<&Asset> {
+        self.assets
+            .iter()
+            .find(|a| a.name == name)
+            .with_context(|| format!("release {} has no {name}", self.tag_name))
+    }
+}
+
+async fn download(client: &reqwest::Client, url: &str) -> Result<Vec<u8>> {
+    let response = client.get(url).send().await?.error_for_status()?;
+    Ok(response.bytes().await?.to_vec())
+}
+
+/// Unpack `archive` into `dir`; the release tarballs hold just the binary.
+fn extract(archive: &Path, dir: &Path) -> Result<PathBuf> {
+    let status = Command::new("tar")
+        .arg("-xzf")
+        .arg(archive)
+        .arg("-C")
+        .arg(dir)
+        .status()
+        .context("failed to run tar")?;
+    if !status.success() {
+        bail!("tar could not unpack {}", archive.display());
+    }
+    let binary = dir.join("claude-monitor");
+    if !binary.is_file() {
+        bail!("archive does not contain claude-monitor");
+    }
+    Ok(binary)
+}
+
+/// Unpack the archive in `staging` and move its binary over `exe`.
+fn install(staging: &Path, name: &str, archive: &[u8], exe: &Path) -> Result<()> {
+    let archive_path = staging.join(name);
+    std::fs::write(&archive_path, archive)?;
+    let binary = extract(&archive_path, staging)?;
+    std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755))?;
+    std::fs::rename(&binary, exe).with_context(|| format!("failed to replace {}", exe.display()))
+}
+
+pub async fn run(args: &SelfUpdateArgs) -> Result<()> {
+    let client = reqwest::Client::builder()
+        .user_agent(concat!("claude-monitor/", env!("CARGO_PKG_VERSION")))
+        .timeout(Duration::from_secs(120))
+        .build()?;
+    let release: Release = client
+        .get(LATEST_RELEASE_URL)
+        .send()
+        .await
+        .context("could not reach GitHub")?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    let current = env!("CARGO_PKG_VERSION");
+    let latest = release.tag_name.trim_start_matches('v');
+    if !datadir::version_newer(latest, current) {
+        println!("claude-monitor {current} is up to date");
+        return Ok(());
+    }
+    if args.check {
+        println!("claude-monitor {latest} is available (installed: {current})");
+        return Ok(());
+    }
+
+    let name = format!("claude-monitor-{}-{}.tar.gz", release.tag_name, env!("BUILD_TARGET"));
+    let archive = download(&client, &release.asset(&name)?.browser_download_url).await?;
+    let checksum = download(&client, &release.asset(&format!("{name}.sha256"))?.browser_download_url).await?;
+    // `shasum` format: the hex digest, then the file name.
+    let expected = String::from_utf8_lossy(&checksum)
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    let actual = hex::encode(Sha256::digest(&archive));
+    if actual != expected {
+        bail!("checksum mismatch for {name}: expected {expected}, got {actual}");
+    }
+
+    // Stage beside the executable so the final rename stays on one filesystem
+    // and is atomic; a running binary can be replaced this way on Unix.
+    let exe = std::env::current_exe()?.canonicalize()?;
+    let dir = exe.parent().context("executable has no parent directory")?;
+    let staging = dir.join(format!(".claude-monitor-update-{}", uuid::Uuid::new_v4().simple()));
+    std::fs::create_dir(&staging).with_context(|| format!("cannot write to {}", dir.display()))?;
+    let result = install(&staging, &name, &archive, &exe);
+    let _ = std::fs::remove_dir_all(&staging);
+    result?;
+
+    println!("Updated claude-monitor {current} → {latest}; restart the server to use it");
+    Ok(())
+}
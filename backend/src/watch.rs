@@ -0,0 +1,224 @@
+//! `claude-monitor watch`: a live terminal view of a server's sessions,
+//! driven by its `/ws` stream, for machines without the desktop overlay.
+//!
+//! Keys: ↑/↓ or j/k select, `a` acknowledges, `p` pins or unpins, `d`
+//! dismisses, `q` quits.
+
+use anyhow::Result;
+use chrono::Utc;
+use claude_monitor_client::{Client, SessionFilter};
+use futures::StreamExt;
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Cell, Row, Table, TableState},
+    DefaultTerminal, Frame,
+};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use crate::{cli::WatchArgs, listen::Target, models::SessionWithAgents, status};
+
+/// Wait before reconnecting after the stream drops.
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+
+enum Update {
+    Sessions(Vec<SessionWithAgents>),
+    Connected(bool),
+    Key(KeyEvent),
+}
+
+struct App {
+    server: String,
+    client: Client,
+    sessions: Vec<SessionWithAgents>,
+    table: TableState,
+    connected: bool,
+    /// Outcome of the last action, shown in the footer.
+    notice: Option<String>,
+}
+
+pub async fn run(args: &WatchArgs) -> Result<()> {
+    let target = Target::resolve(args.server.as_deref());
+    let client = Client::with_http(&target.url, target.http(Duration::from_secs(3)))?;
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    tokio::spawn(stream(client.clone(), tx.clone()));
+    std::thread::spawn(move || read_keys(tx));
+
+    let mut app = App {
+        client,
+        server: target.url.trim_end_matches('/').to_string(),
+        sessions: Vec::new(),
+        table: TableState::default(),
+        connected: false,
+        notice: None,
+    };
+    let mut terminal = ratatui::init();
+    let result = app.event_loop(&mut terminal, &mut rx).await;
+    ratatui::restore();
+    result
+}
+
+/// Follow the session stream, reconnecting whenever it drops.
+async fn stream(client: Client, tx: mpsc::UnboundedSender<Update>) {
+    let filter = SessionFilter { include_hidden: true, ..Default::default() };
+    loop {
+        if let Ok(mut snapshots) = client.subscribe(&filter).await {
+            let _ = tx.send(Update::Connected(true));
+            while let Some(Ok(sessions)) = snapshots.next().await {
+                if tx.send(Update::Sessions(sessions)).is_err() {
+                    return;
+                }
+            }
+        }
+        if tx.send(Update::Connected(false)).is_err() {
+            return;
+        }
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+/// crossterm's reader blocks, so it gets a thread of its own.
+fn read_keys(tx: mpsc::UnboundedSender<Update>) {
+    loop {
+        match event::poll(Duration::from_millis(250)) {
+            Ok(true) => {
+                if let Ok(Event::Key(key)) = event::read() {
+                    if tx.send(Update::Key(key)).is_err() {
+                        return;
+                    }
+                }
+            }
+            Ok(false) if tx.is_closed() => return,
+            Ok(false) => {}
+            Err(_) => return,
+        }
+    }
+}
+
+impl App {
+    async fn event_loop(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        rx: &mut mpsc::UnboundedReceiver<Update>,
+    ) -> Result<()> {
+        // Redraw every second so elapsed times keep counting.
+        let mut tick = tokio::time::interval(Duration::from_secs(1));
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            tokio::select! {
+                update = rx.recv() => match update {
+                    Some(Update::Sessions(sessions)) => self.set_sessions(sessions),
+                    Some(Update::Connected(connected)) => self.connected = connected,
+                    Some(Update::Key(key)) => {
+                        if key.kind == KeyEventKind::Press && !self.handle_key(key).await {
+                            return Ok(());
+                        }
+                    }
+                    None => return Ok(()),
+                },
+                _ = tick.tick() => {}
+            }
+        }
+    }
+
+    /// Replace the list, keeping the same session selected if it is still there.
+    fn set_sessions(&mut self, sessions: Vec<SessionWithAgents>) {
+        let selected = self.selected().map(|s| s.session_id.clone());
+        self.sessions = sessions;
+        let index = selected
+            .and_then(|id| self.sessions.iter().position(|s| s.session_id == id))
+            .or_else(|| self.table.selected().map(|i| i.min(self.sessions.len().saturating_sub(1))))
+            .or(Some(0));
+        self.table.select(index.filter(|_| !self.sessions.is_empty()));
+    }
+
+    fn selected(&self) -> Option<&SessionWithAgents> {
+        self.table.selected().and_then(|i| self.sessions.get(i))
+    }
+
+    /// Returns false to quit.
+    async fn handle_key(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return false,
+            KeyCode::Down | KeyCode::Char('j') => self.table.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => self.table.select_previous(),
+            KeyCode::Char('a') => self.act("ack").await,
+            KeyCode::Char('p') => {
+                let pinned = self.selected().is_some_and(|s| s.pinned);
+                self.act(if pinned { "unpin" } else { "pin" }).await;
+            }
+            KeyCode::Char('d') => self.act("dismiss").await,
+            _ => {}
+        }
+        true
+    }
+
+    /// Apply an action to the selected session through the HTTP API. The
+    /// change comes back over the stream like any other.
+    async fn act(&mut self, action: &str) {
+        let Some(session) = self.selected() else { return };
+        let session_id = session.session_id.clone();
+        let result = match action {
+            "ack" => self.client.ack_session(&session_id).await.map(drop),
+            "pin" => self.client.pin_session(&session_id).await,
+            "unpin" => self.client.unpin_session(&session_id).await,
+            _ => self.client.dismiss_session(&session_id).await,
+        };
+        self.notice = Some(match result {
+            Ok(()) => format!("{action}: {session_id}"),
+            Err(claude_monitor_client::Error::Http(e)) => format!("{action} failed: {}", e.without_url()),
+            Err(e) => format!("{action} failed: {e}"),
+        });
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [body, footer] = Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+        let now = Utc::now();
+
+        let rows = self.sessions.iter().map(|s| {
+            let pin = if s.pinned { "* " } else { "" };
+            let message = s.last_message.as_deref().unwrap_or("").replace('\n', " ");
+            Row::new([
+                Cell::from(format!("{pin}{}", s.project_name)),
+                Cell::from(s.status.clone()).style(Style::default().fg(status_color(&s.status))),
+                Cell::from(s.agents.len().max(1).to_string()),
+                Cell::from(status::elapsed(now - s.created_at)),
+                Cell::from(message.trim().to_string()),
+            ])
+        });
+        let widths = [
+            Constraint::Percentage(20),
+            Constraint::Length(17),
+            Constraint::Length(6),
+            Constraint::Length(8),
+            Constraint::Fill(1),
+        ];
+        let header = Row::new(["PROJECT", "STATUS", "AGENTS", "ELAPSED", "LAST MESSAGE"])
+            .style(Style::default().add_modifier(Modifier::BOLD));
+        let table = Table::new(rows, widths)
+            .header(header)
+            .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(table, body, &mut self.table);
+
+        let state = if self.connected { "connected" } else { "reconnecting…" };
+        let keys = "↑↓ select · a ack · p pin · d dismiss · q quit";
+        let text = match &self.notice {
+            Some(notice) => format!("{} {state} · {notice} · {keys}", self.server),
+            None => format!("{} {state} · {keys}", self.server),
+        };
+        frame.render_widget(Line::from(text).style(Style::default().fg(Color::DarkGray)), footer);
+    }
+}
+
+fn status_color(status: &str) -> Color {
+    match status {
+        "needs_permission" => Color::Red,
+        "waiting_input" => Color::Yellow,
+        "active" => Color::Green,
+        _ => Color::Gray,
+    }
+}
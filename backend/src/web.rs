@@ -0,0 +1,59 @@
+//! The web dashboard, embedded from `web/` at build time and served at `/`,
+//! so one binary provides both the API and the frontend.
+//!
+//! Paths that match no asset get `index.html`, letting a single-page app
+//! handle its own routes. It is given a `<base href>` of the path the
+//! client sees the server at, which its links are relative to.
+
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, StatusCode, Uri},
+    response::{IntoResponse, Response},
+    Json,
+};
+use rust_embed::RustEmbed;
+use serde_json::json;
+
+use crate::{api::AppState, proxy};
+
+#[derive(RustEmbed)]
+#[folder = "web/"]
+struct Assets;
+
+fn asset(path: &str) -> Option<Response> {
+    let file = Assets::get(path)?;
+    let mime = file.metadata.mimetype().to_string();
+    // Unhashed file names, so have browsers check back rather than cache blindly.
+    Some(([(header::CONTENT_TYPE, mime), (header::CACHE_CONTROL, "no-cache".to_string())], file.data).into_response())
+}
+
+/// `index.html` with a `<base href>` of `prefix`.
+fn index(prefix: &str) -> Response {
+    let Some(file) = Assets::get("index.html") else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let base = format!("<head>\n  <base href=\"{prefix}/\">");
+    let page = String::from_utf8_lossy(&file.data).replacen("<head>", &base, 1);
+    let headers = [(header::CONTENT_TYPE, "text/html"), (header::CACHE_CONTROL, "no-cache")];
+    (headers, page).into_response()
+}
+
+/// Router fallback: embedded assets, then `index.html` for app routes.
+pub async fn serve(State(state): State<AppState>, headers: HeaderMap, uri: Uri) -> Response {
+    let path = uri.path().trim_start_matches('/');
+    if path.starts_with("api/") {
+        return (StatusCode::NOT_FOUND, Json(json!({"error": "not found"}))).into_response();
+    }
+    let prefix = proxy::prefix(&headers, &proxy::base_path(&state.config.listen.base_path));
+    if path.is_empty() || path == "index.html" {
+        return index(&prefix);
+    }
+    if let Some(response) = asset(path) {
+        return response;
+    }
+    // A missing file with an extension is a broken asset link, not an app route.
+    if path.rsplit('/').next().is_some_and(|name| name.contains('.')) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    index(&prefix)
+}
@@ -1,60 +1,613 @@
 use axum::{
-    extract::{
-        ws::{Message, WebSocket},
-        State, WebSocketUpgrade,
+    body::Body,
+    extract::{Path, Query, Request, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use futures::{stream::SplitSink, SinkExt, StreamExt};
+use serde::Deserialize;
+use serde_json::json;
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
     },
-    response::Response,
 };
-use futures::{SinkExt, StreamExt};
 use tokio::sync::broadcast;
-use tracing::{info, warn};
+use tracing::{debug, info, info_span, warn, Instrument};
+use yawc::{Frame, HttpStream, OpCode, Options, WebSocket};
+
+use crate::{
+    announce::{self, Verbosity},
+    api::{self, AppState, SessionSnapshot},
+    metrics::Histogram,
+    models::{SequencedFrame, SessionKey, SessionSort, SessionStreamMessage, SessionWithAgents, SortOrder, WsStats},
+    paths,
+    proxy,
+    quota,
+};
+
+type Socket = WebSocket<HttpStream>;
+
+/// `[websocket]` section of config.toml.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct WebSocketConfig {
+    /// Offer permessage-deflate to clients that ask for it. Snapshots are
+    /// repetitive JSON, so this saves most of the bandwidth to remote
+    /// overlays at a little CPU.
+    pub compression: bool,
+}
+
+impl Default for WebSocketConfig {
+    fn default() -> Self {
+        Self { compression: true }
+    }
+}
+
+/// Clients of `/ws` and `/ws/sessions/:session_id`, and how the broadcasts
+/// reach them.
+#[derive(Clone, Default)]
+pub struct WsMetrics(Arc<Counters>);
+
+#[derive(Default)]
+struct Counters {
+    connected: AtomicUsize,
+    connections: AtomicU64,
+    lag_events: AtomicU64,
+    dropped: AtomicU64,
+    fanout: Histogram,
+}
+
+impl WsMetrics {
+    /// Clients connected now.
+    pub fn count(&self) -> usize {
+        self.0.connected.load(Ordering::Relaxed)
+    }
+
+    pub fn connections(&self) -> u64 {
+        self.0.connections.load(Ordering::Relaxed)
+    }
+
+    pub fn lag_events(&self) -> u64 {
+        self.0.lag_events.load(Ordering::Relaxed)
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.0.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Time from publishing each broadcast to having sent it to a client.
+    pub fn fanout(&self) -> &Histogram {
+        &self.0.fanout
+    }
+
+    /// Count one client until the returned guard is dropped.
+    fn connect(&self) -> Connected {
+        self.0.connected.fetch_add(1, Ordering::Relaxed);
+        self.0.connections.fetch_add(1, Ordering::Relaxed);
+        Connected(self.clone())
+    }
+
+    /// A client missed `n` broadcasts.
+    fn lagged(&self, n: u64) {
+        self.0.lag_events.fetch_add(1, Ordering::Relaxed);
+        self.0.dropped.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// A client has been sent `snapshot`.
+    fn sent(&self, snapshot: &SessionSnapshot) {
+        if let Some(published) = snapshot.published {
+            self.0.fanout.observe(published.elapsed());
+        }
+    }
+}
+
+struct Connected(WsMetrics);
+
+impl Drop for Connected {
+    fn drop(&mut self) {
+        self.0 .0.connected.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Connections, lag and broadcast latency of the WebSocket clients.
+#[utoipa::path(get, path = "/api/stats/ws", tag = "stats", responses((status = 200, body = WsStats)))]
+pub async fn get_stats(State(state): State<AppState>) -> impl IntoResponse {
+    let ws = &state.ws;
+    Json(WsStats {
+        connected: ws.count() as u64,
+        connections: ws.connections(),
+        lag_events: ws.lag_events(),
+        dropped_messages: ws.dropped(),
+        broadcasts: state.history.published(),
+        fanout: ws.fanout().summary(),
+    })
+}
 
-use crate::api::AppState;
+/// Client → server message narrowing the stream. `{"cwd": "/home/me/proj"}`
+/// keeps one workspace, `{"host": "devbox"}` one machine, and
+/// `{"tags": ["PROJ-12"]}` sessions carrying any of those tags; each message
+/// replaces the previous filter, so `{}` resets to all sessions.
+#[derive(Debug, Deserialize)]
+struct Subscription {
+    cwd: Option<String>,
+    host: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct WsParams {
+    /// `text` switches to plain-text announcements instead of JSON snapshots.
+    format: Option<String>,
+    verbosity: Option<Verbosity>,
+    /// Follow each snapshot with a `{"quota": {...}}` frame, see `quota`.
+    #[serde(default)]
+    quota: bool,
+    /// Include sessions muted with `hide`, which are left out by default.
+    #[serde(default)]
+    hidden: bool,
+    /// Initial tag filter, comma-separated, as in `Subscription::tags`.
+    tag: Option<String>,
+    /// Initial host filter, as in `Subscription::host`.
+    host: Option<String>,
+    /// Snapshot order; sessions needing attention come first by default.
+    sort: Option<SessionSort>,
+    order: Option<SortOrder>,
+    /// Last `seq` this client saw: it is sent the broadcasts since then as
+    /// deltas, or a snapshot if they are gone, and [`SequencedFrame`]s from
+    /// then on. `0` starts a numbered stream from scratch.
+    since_seq: Option<u64>,
+}
 
 pub async fn ws_handler(
-    ws: WebSocketUpgrade,
     State(state): State<AppState>,
+    Query(params): Query<WsParams>,
+    mut request: Request,
 ) -> Response {
-    ws.on_upgrade(move |socket| handle_socket(socket, state))
+    upgrade(&mut request, state.config.websocket.compression, state.ws.clone(), move |socket| async move {
+        if params.format.as_deref() == Some("text") {
+            let verbosity = params.verbosity.unwrap_or(state.config.text_stream.verbosity);
+            handle_text_socket(socket, state, verbosity).await;
+        } else {
+            handle_socket(socket, state, params).await;
+        }
+    })
 }
 
-async fn handle_socket(socket: WebSocket, state: AppState) {
-    let (mut sender, mut receiver) = socket.split();
+/// `/ws/sessions/:session_id`: one session's updates and the events recorded
+/// for it, as [`SessionStreamMessage`] frames, for a detail view.
+pub async fn session_ws_handler(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+    mut request: Request,
+) -> Response {
+    upgrade(&mut request, state.config.websocket.compression, state.ws.clone(), move |socket| {
+        handle_session_socket(socket, state, session_id)
+    })
+}
 
-    // Send current sessions immediately on connect.
-    match crate::db::get_active_sessions(&state.pool).await {
-        Ok(sessions) => {
-            if let Ok(json) = serde_json::to_string(&sessions) {
-                if sender.send(Message::Text(json)).await.is_err() {
-                    return;
+/// Answer the handshake and run `handle` on the socket once it is upgraded,
+/// counted among `clients` until it returns. What it logs names the client,
+/// as `X-Forwarded-For` gives it behind a proxy.
+fn upgrade<F, Fut>(request: &mut Request, compression: bool, clients: WsMetrics, handle: F) -> Response
+where
+    F: FnOnce(Socket) -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send,
+{
+    let options = if compression {
+        Options::default().with_balanced_compression()
+    } else {
+        Options::default().without_compression()
+    };
+    let client = proxy::client(request);
+    let (response, upgrade) = match WebSocket::upgrade_with_options(request, options) {
+        Ok(upgrade) => upgrade,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
+    let span = info_span!("websocket", client = client.as_deref().unwrap_or("unknown"));
+    tokio::spawn(
+        async move {
+            match upgrade.await {
+                Ok(socket) => {
+                    let _connected = clients.connect();
+                    handle(socket).await
                 }
+                Err(e) => warn!("WebSocket upgrade failed: {e}"),
             }
         }
-        Err(e) => warn!("Failed to fetch sessions for new WS client: {e}"),
+        .instrument(span),
+    );
+    response.map(Body::new)
+}
+
+/// Text mode: one announcement per frame, see `announce`.
+async fn handle_text_socket(socket: Socket, state: AppState, verbosity: Verbosity) {
+    let (mut sender, mut receiver) = socket.split();
+    let initial = announce::baseline(&state).await;
+    let lines = announce::announcements(state, initial, verbosity);
+    tokio::pin!(lines);
+
+    loop {
+        tokio::select! {
+            line = lines.next() => match line {
+                Some(line) => {
+                    if sender.send(Frame::text(line)).await.is_err() {
+                        break;
+                    }
+                }
+                None => break,
+            },
+            frame = receiver.next() => match frame {
+                Some(frame) if frame.opcode() != OpCode::Close => {}
+                _ => break,
+            },
+        }
     }
 
+    info!("WebSocket text client disconnected");
+}
+
+async fn handle_socket(socket: Socket, state: AppState, params: WsParams) {
+    let (mut sender, mut receiver) = socket.split();
+
+    let mut filter = Filter {
+        workspace: None,
+        tags: params.tag.as_deref().map(api::parse_tags).unwrap_or_default(),
+        host: params.host.clone(),
+        hidden: params.hidden,
+        sort: params.sort.unwrap_or(SessionSort::Attention),
+        order: params.order,
+        sequenced: params.since_seq.is_some(),
+    };
+
+    // Subscribe before the initial fetch so no update slips in between.
     let mut rx = state.tx.subscribe();
 
-    // Forward broadcast messages to the WebSocket client.
-    let send_task = tokio::spawn(async move {
-        loop {
-            match rx.recv().await {
-                Ok(msg) => {
-                    if sender.send(Message::Text(msg)).await.is_err() {
+    let mut latest = match params.since_seq.and_then(|seq| state.history.since(seq)) {
+        // A resuming client gets each broadcast it missed, as deltas.
+        Some(missed) => {
+            for pair in missed.windows(2) {
+                if send_delta(&mut sender, &pair[0], &pair[1], &filter).await.is_err() {
+                    return;
+                }
+            }
+            missed.last().cloned().unwrap_or_default()
+        }
+        // Anyone else gets the current sessions immediately on connect;
+        // numbered as the last broadcast if they care for numbers.
+        None => {
+            let latest = match state.history.latest().filter(|_| filter.sequenced) {
+                Some(latest) => latest,
+                None => match state.active_sessions().await {
+                    Ok(sessions) => sessions.into(),
+                    Err(e) => {
+                        warn!("Failed to fetch sessions for new WS client: {e}");
+                        SessionSnapshot::default()
+                    }
+                },
+            };
+            if send_sessions(&mut sender, &latest, &filter).await.is_err() {
+                return;
+            }
+            latest
+        }
+    };
+    if params.quota && send_quota(&mut sender, &state).await.is_err() {
+        return;
+    }
+
+    loop {
+        tokio::select! {
+            msg = rx.recv() => match msg {
+                // Already replayed from the history.
+                Ok(sessions) if sessions.seq <= latest.seq => {}
+                Ok(sessions) => {
+                    latest = sessions;
+                    if send_sessions(&mut sender, &latest, &filter).await.is_err() {
+                        break;
+                    }
+                    state.ws.sent(&latest);
+                    if params.quota && send_quota(&mut sender, &state).await.is_err() {
                         break;
                     }
                 }
                 Err(broadcast::error::RecvError::Lagged(n)) => {
                     warn!("WS client lagged by {n} messages");
+                    state.ws.lagged(n);
                 }
                 Err(broadcast::error::RecvError::Closed) => break,
-            }
+            },
+            frame = receiver.next() => match frame {
+                Some(frame) if frame.opcode() == OpCode::Text => match serde_json::from_slice::<Subscription>(
+                    frame.payload(),
+                ) {
+                    Ok(sub) => {
+                        filter.workspace = sub.cwd.as_deref().map(paths::normalize);
+                        filter.tags = sub.tags;
+                        filter.host = sub.host;
+                        debug!(workspace = ?filter.workspace, tags = ?filter.tags, "WS client updated subscription");
+                        // Re-send the last snapshot under the new filter.
+                        if send_sessions(&mut sender, &latest, &filter).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => debug!("Ignoring unrecognised WS message: {e}"),
+                },
+                // yawc answers pings itself; pong and binary frames need no handling.
+                Some(frame) if frame.opcode() != OpCode::Close => {}
+                _ => break,
+            },
         }
-    });
-
-    // Drain incoming frames (ping/pong/close) until the client disconnects.
-    while let Some(Ok(_)) = receiver.next().await {}
+    }
 
-    send_task.abort();
     info!("WebSocket client disconnected");
 }
+
+async fn handle_session_socket(socket: Socket, state: AppState, session_id: String) {
+    let (mut sender, mut receiver) = socket.split();
+
+    // Subscribe, then mark the event position, then fetch: nothing recorded
+    // after the initial frame can be missed. Earlier events are at
+    // `/api/sessions/:session_id/events`.
+    let mut rx = state.tx.subscribe();
+    let mut seq = match state.store.latest_event_seq().await {
+        Ok(seq) => seq,
+        Err(e) => {
+            warn!("Failed to read event position for WS client: {e}");
+            return;
+        }
+    };
+    let sessions = match state.active_sessions().await {
+        Ok(sessions) => sessions,
+        Err(e) => {
+            warn!("Failed to fetch sessions for new WS client: {e}");
+            Vec::new()
+        }
+    };
+    // Serialized form of the last session frame, so unchanged snapshots are skipped.
+    let mut sent = None;
+    if send_session(&mut sender, &sessions, &session_id, &mut sent).await.is_err() {
+        return;
+    }
+
+    loop {
+        tokio::select! {
+            msg = rx.recv() => {
+                let sessions = match msg {
+                    Ok(sessions) => sessions,
+                    // Events are read by position, so only the skipped snapshots are lost.
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        warn!("WS session client lagged by {n} messages");
+                        state.ws.lagged(n);
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                match state.store.get_events_after(&session_id, seq).await {
+                    Ok(events) => {
+                        for (event_seq, event) in events {
+                            seq = event_seq;
+                            if send_message(&mut sender, &SessionStreamMessage::Event(event)).await.is_err() {
+                                return;
+                            }
+                        }
+                    }
+                    Err(e) => warn!("Failed to fetch events for WS session client: {e}"),
+                }
+                if send_session(&mut sender, &sessions, &session_id, &mut sent).await.is_err() {
+                    break;
+                }
+                state.ws.sent(&sessions);
+            },
+            frame = receiver.next() => match frame {
+                Some(frame) if frame.opcode() != OpCode::Close => {}
+                _ => break,
+            },
+        }
+    }
+
+    info!(session_id, "WebSocket session client disconnected");
+}
+
+/// Send the session's current state from `sessions` unless it matches `sent`.
+async fn send_session(
+    sender: &mut SplitSink<Socket, Frame>,
+    sessions: &[SessionWithAgents],
+    session_id: &str,
+    sent: &mut Option<String>,
+) -> yawc::Result<()> {
+    let session = sessions.iter().find(|s| s.session_id == session_id).cloned().map(Box::new);
+    let json = match serde_json::to_string(&SessionStreamMessage::Session(session)) {
+        Ok(json) => json,
+        Err(e) => {
+            warn!("Failed to serialize session: {e}");
+            return Ok(());
+        }
+    };
+    if sent.as_ref() == Some(&json) {
+        return Ok(());
+    }
+    sender.send(Frame::text(json.clone())).await?;
+    *sent = Some(json);
+    Ok(())
+}
+
+async fn send_message(sender: &mut SplitSink<Socket, Frame>, message: &SessionStreamMessage) -> yawc::Result<()> {
+    match serde_json::to_string(message) {
+        Ok(json) => sender.send(Frame::text(json)).await,
+        Err(e) => {
+            warn!("Failed to serialize session event: {e}");
+            Ok(())
+        }
+    }
+}
+
+/// Which sessions a JSON client is sent, in what order and what form.
+struct Filter {
+    /// Only sessions inside this workspace, if set; normalized once when set,
+    /// as stored session paths are at ingestion.
+    workspace: Option<String>,
+    /// Only sessions carrying one of these tags, if any.
+    tags: Vec<String>,
+    /// Only sessions on this host, if set.
+    host: Option<String>,
+    /// Whether to include hidden sessions.
+    hidden: bool,
+    sort: SessionSort,
+    order: Option<SortOrder>,
+    /// Send [`SequencedFrame`]s rather than bare lists, see `?since_seq=`.
+    sequenced: bool,
+}
+
+impl Filter {
+    fn allows(&self, session: &SessionWithAgents) -> bool {
+        (self.hidden || !session.hidden)
+            && session.has_any_tag(&self.tags)
+            && self.host.as_ref().is_none_or(|h| *h == session.host)
+            && self
+                .workspace
+                .as_deref()
+                .is_none_or(|root| paths::is_within(&paths::fold_case(&session.project_path), root))
+    }
+}
+
+/// Serialize and send the sessions of a snapshot that `filter` allows.
+async fn send_sessions(
+    sender: &mut SplitSink<Socket, Frame>,
+    snapshot: &SessionSnapshot,
+    filter: &Filter,
+) -> yawc::Result<()> {
+    let mut scoped: Vec<&SessionWithAgents> = snapshot.iter().filter(|s| filter.allows(s)).collect();
+    api::sort_sessions(&mut scoped, filter.sort, filter.order);
+
+    let json = if filter.sequenced {
+        let sessions = scoped.into_iter().cloned().collect();
+        serde_json::to_string(&SequencedFrame::Snapshot { seq: snapshot.seq, sessions })
+    } else {
+        serde_json::to_string(&scoped)
+    };
+    match json {
+        Ok(json) => sender.send(Frame::text(json)).await,
+        Err(e) => {
+            warn!("Failed to serialize sessions: {e}");
+            Ok(())
+        }
+    }
+}
+
+/// Send what broadcast `next` changed since `prev`, as seen through `filter`.
+async fn send_delta(
+    sender: &mut SplitSink<Socket, Frame>,
+    prev: &SessionSnapshot,
+    next: &SessionSnapshot,
+    filter: &Filter,
+) -> yawc::Result<()> {
+    let key = |s: &SessionWithAgents| (s.host.clone(), s.session_id.clone());
+    // Compared serialized, as that is what the client holds.
+    let before: HashMap<_, _> = prev
+        .iter()
+        .filter(|s| filter.allows(s))
+        .map(|s| (key(s), serde_json::to_string(s).ok()))
+        .collect();
+    let after: HashSet<_> = next.iter().filter(|s| filter.allows(s)).map(key).collect();
+
+    let mut changed: Vec<&SessionWithAgents> = next
+        .iter()
+        .filter(|s| filter.allows(s) && before.get(&key(s)) != Some(&serde_json::to_string(s).ok()))
+        .collect();
+    api::sort_sessions(&mut changed, filter.sort, filter.order);
+    let removed = prev
+        .iter()
+        .filter(|s| filter.allows(s) && !after.contains(&key(s)))
+        .map(|s| SessionKey { host: s.host.clone(), session_id: s.session_id.clone() })
+        .collect();
+
+    let delta = SequencedFrame::Delta {
+        seq: next.seq,
+        changed: changed.into_iter().cloned().collect(),
+        removed,
+    };
+    match serde_json::to_string(&delta) {
+        Ok(json) => sender.send(Frame::text(json)).await,
+        Err(e) => {
+            warn!("Failed to serialize session delta: {e}");
+            Ok(())
+        }
+    }
+}
+
+/// Send the current quota window. Usage only changes alongside session
+/// updates, so this rides on each snapshot rather than its own timer.
+async fn send_quota(sender: &mut SplitSink<Socket, Frame>, state: &AppState) -> yawc::Result<()> {
+    let status = match quota::current(state.store.as_ref(), &state.config.quota).await {
+        Ok(status) => status,
+        Err(e) => {
+            warn!("Failed to compute quota for WS client: {e}");
+            return Ok(());
+        }
+    };
+    sender.send(Frame::text(json!({ "quota": status }).to_string())).await
+}
+
+#[cfg(test)]
+mod tests {
+    use claude_monitor_client::SessionFilter;
+    use futures::StreamExt;
+    use std::time::Duration;
+
+    use super::Filter;
+    use crate::{
+        paths,
+        test_support::{self, event},
+    };
+
+    #[tokio::test]
+    async fn workspaces_match_paths_as_the_filesystem_compares_them() {
+        let server = test_support::spawn().await;
+        let mut prompt = event("user_prompt_submit", "s1");
+        prompt.project_path = Some("/Work/Demo".to_string());
+        server.client.post_event(&prompt).await.unwrap();
+        let session = server.state.active_sessions().await.unwrap().remove(0);
+
+        let filter = |cwd: &str| Filter {
+            workspace: Some(paths::normalize(cwd)),
+            tags: Vec::new(),
+            host: None,
+            hidden: false,
+            sort: Default::default(),
+            order: None,
+            sequenced: false,
+        };
+        assert!(filter("/Work/Demo").allows(&session));
+        assert!(filter("/Work").allows(&session));
+        // Case-insensitive only where the filesystem is.
+        assert_eq!(filter("/work/demo").allows(&session), cfg!(target_os = "macos"));
+        assert!(!filter("/Work/Demo2").allows(&session));
+    }
+
+    #[tokio::test]
+    async fn subscribers_see_new_sessions() {
+        let server = test_support::spawn().await;
+        let mut updates = server.client.subscribe(&SessionFilter::default()).await.unwrap();
+        let first = updates.next().await.unwrap().unwrap();
+        assert!(first.is_empty());
+
+        server.client.post_event(&event("user_prompt_submit", "s1")).await.unwrap();
+        let next = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let sessions = updates.next().await.unwrap().unwrap();
+                if !sessions.is_empty() {
+                    return sessions;
+                }
+            }
+        })
+        .await
+        .expect("snapshot after the event");
+        assert_eq!(next[0].session_id, "s1");
+    }
+}
@@ -0,0 +1,49 @@
+#!/usr/bin/env bash
+# Regenerate backend/.sqlx, the query data sqlx's macros check SQLite queries
+# against when there is no database to ask (SQLX_OFFLINE=true, as in release
+# builds). Run it after changing a checked query or adding a migration.
+#
+#   scripts/sqlx-prepare.sh           rewrite backend/.sqlx
+#   scripts/sqlx-prepare.sh --check   fail if backend/.sqlx is out of date
+set -euo pipefail
+
+SCRIPT_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+PROJECT_ROOT="$(dirname "$SCRIPT_DIR")"
+BACKEND="$PROJECT_ROOT/backend"
+PREPARED="$BACKEND/.sqlx"
+
+CHECK=false
+if [ "${1:-}" = "--check" ]; then
+    CHECK=true
+fi
+
+WORK="$(mktemp -d)"
+trap 'rm -rf "$WORK"' EXIT
+
+echo "==> Creating a schema database..."
+# The same order the server applies them in: the base schema, then every
+# migration by number.
+cat "$BACKEND/migrations/schema.sql" "$BACKEND"/migrations/[0-9]*.sql | sqlite3 "$WORK/schema.db"
+
+echo "==> Describing queries..."
+mkdir "$WORK/sqlx"
+# The macros only describe queries when they run, so make cargo recompile the
+# files that hold them.
+grep -rlE 'sqlx::query(_as|_scalar)?!' "$BACKEND/src" | xargs touch
+(
+    cd "$BACKEND"
+    DATABASE_URL="sqlite://$WORK/schema.db" SQLX_OFFLINE=false SQLX_OFFLINE_DIR="$WORK/sqlx" \
+        cargo check --quiet --all-targets
+)
+
+if $CHECK; then
+    if ! diff -r "$PREPARED" "$WORK/sqlx"; then
+        echo "backend/.sqlx is out of date; run scripts/sqlx-prepare.sh and commit the result" >&2
+        exit 1
+    fi
+    echo "==> backend/.sqlx is up to date"
+else
+    rm -rf "$PREPARED"
+    mv "$WORK/sqlx" "$PREPARED"
+    echo "==> Wrote $(ls "$PREPARED" | wc -l | tr -d ' ') queries to backend/.sqlx"
+fi